@@ -12,6 +12,7 @@ pub enum RpcError {
     InvalidKeypair = -32104,
     InvalidAddressParam = -32105,
     DecryptionFailed = -32106,
+    ScanFailed = -32107,
 
     // Transaction-related errors
     TxBuildFail = -32110,
@@ -37,6 +38,7 @@ fn to_tuple(e: RpcError) -> (i64, String) {
         RpcError::InvalidKeypair => "Invalid keypair",
         RpcError::InvalidAddressParam => "Invalid address parameter",
         RpcError::DecryptionFailed => "Decryption failed",
+        RpcError::ScanFailed => "Failed scanning blocks",
         // Transaction-related errors
         RpcError::TxBuildFail => "Failed building transaction",
         RpcError::TxBroadcastFail => "Failed broadcasting transaction",
@@ -1,6 +1,10 @@
+use darkfi_sdk::crypto::Nullifier;
+use darkfi_serial::{deserialize, serialize};
+use log::{debug, error};
+
 use darkfi::{
     consensus::ValidatorState,
-    node::{state::StateUpdate, MemoryState},
+    node::{scanner::ScanResult, state::StateUpdate, MemoryState},
     tx::Transaction,
     Result,
 };
@@ -20,4 +24,70 @@ impl Darkfid {
 
         ValidatorState::validate_state_transitions(mem_state, &[tx.clone()])
     }
+
+    /// Scan the blocks finalized since the last scan for our wallet's coins.
+    /// With `rescan_from`, the blocks are scanned again from that slot.
+    pub async fn scan_wallet_from(&self, rescan_from: Option<u64>) -> Result<ScanResult> {
+        // Don't hold the validator state during the scan
+        let blockchain = self.validator_state.read().await.blockchain.clone();
+        let scanner = self.scanner.lock().await;
+        match rescan_from {
+            Some(slot) => scanner.rescan(&blockchain, slot).await,
+            None => scanner.scan(&blockchain).await,
+        }
+    }
+
+    pub async fn scan_wallet(&self) -> Result<ScanResult> {
+        self.scan_wallet_from(None).await
+    }
+
+    /// Process the blocks finalized since the last pass for our wallet:
+    /// scan them, revert our transactions not finalized within
+    /// `revert_after` slots, and broadcast the others again if a slot got
+    /// finalized without them.
+    pub async fn process_finalized(&self, revert_after: u64) -> Result<()> {
+        let height = self.scan_wallet().await?.height;
+
+        // Our transactions still in the mempool stay pending, and coins
+        // spent by finalized or other mempool transactions aren't reverted
+        let (nullifiers, mempool) = {
+            let validator_state = self.validator_state.read().await;
+            let mempool: Vec<(blake3::Hash, Vec<Nullifier>)> = validator_state
+                .unconfirmed_txs
+                .iter()
+                .map(|tx| {
+                    let nullifiers = tx.inputs.iter().map(|input| input.revealed.nullifier);
+                    (blake3::hash(&serialize(tx)), nullifiers.collect())
+                })
+                .collect();
+            (validator_state.blockchain.nullifiers.clone(), mempool)
+        };
+        let in_mempool =
+            |tx_hash: &blake3::Hash| -> bool { mempool.iter().any(|(hash, _)| hash == tx_hash) };
+        let is_spent = |nullifier: &Nullifier| -> Result<bool> {
+            Ok(mempool.iter().any(|(_, nfs)| nfs.contains(nullifier)) ||
+                nullifiers.contains(nullifier)?)
+        };
+        let pending_txs =
+            self.scanner.lock().await.update_pending(revert_after, &in_mempool, &is_spent).await?;
+
+        let sync_p2p = match &self.sync_p2p {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        for pending in pending_txs {
+            if pending.slot >= Some(height) {
+                continue
+            }
+
+            debug!("Broadcasting pending transaction {} again", pending.tx_hash.to_hex());
+            let tx: Transaction = deserialize(&pending.tx)?;
+            if let Err(e) = sync_p2p.announce(&tx).await {
+                error!("Failed broadcasting pending transaction: {}", e);
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -10,18 +10,18 @@ use darkfi::{
     async_daemonize, cli_desc,
     consensus::{
         proto::{
-            ProtocolKeepAlive, ProtocolParticipant, ProtocolProposal, ProtocolSync,
-            ProtocolSyncConsensus, ProtocolTx,
+            ProtocolKeepAlive, ProtocolParticipant, ProtocolProposal, ProtocolSnapshot,
+            ProtocolSync, ProtocolSyncConsensus, ProtocolTx,
         },
         state::ValidatorStatePtr,
-        task::{block_sync_task, proposal_task},
+        task::{block_sync_task, proposal_task, snapshot_sync_task},
         ValidatorState, MAINNET_GENESIS_HASH_BYTES, MAINNET_GENESIS_TIMESTAMP,
         TESTNET_GENESIS_HASH_BYTES, TESTNET_GENESIS_TIMESTAMP,
     },
     crypto::{address::Address, keypair::PublicKey},
     net,
     net::P2pPtr,
-    node::Client,
+    node::{Client, WalletScanner},
     rpc::{
         clock_sync::check_clock,
         jsonrpc::{
@@ -30,7 +30,7 @@ use darkfi::{
         },
         server::{listen_and_serve, RequestHandler},
     },
-    util::path::expand_path,
+    util::{async_util::sleep, path::expand_path},
     wallet::walletdb::init_wallet,
     Error, Result,
 };
@@ -145,10 +145,26 @@ struct Args {
     /// Whitelisted faucet address (repeatable flag)
     faucet_pub: Vec<String>,
 
+    #[structopt(long)]
+    /// Sync the canonical state from the latest snapshot before syncing blocks
+    snapshot_sync: bool,
+
+    #[structopt(long)]
+    /// Address of a validator whose state snapshots are trusted (repeatable flag)
+    snapshot_trusted_pub: Vec<String>,
+
     #[structopt(long)]
     /// Verify system clock is correct
     clock_sync: bool,
 
+    #[structopt(long, default_value = "10")]
+    /// Seconds between scans of finalized blocks for wallet coins
+    wallet_scan_interval: u64,
+
+    #[structopt(long, default_value = "30")]
+    /// Finalized slots after which an unconfirmed transaction is reverted
+    pending_revert_slots: u64,
+
     #[structopt(short, parse(from_occurrences))]
     /// Increase verbosity (-vvv supported)
     verbose: u8,
@@ -156,10 +172,11 @@ struct Args {
 
 pub struct Darkfid {
     synced: Mutex<bool>, // AtomicBool is weird in Arc
-    _consensus_p2p: Option<P2pPtr>,
+    consensus_p2p: Option<P2pPtr>,
     sync_p2p: Option<P2pPtr>,
     client: Arc<Client>,
     validator_state: ValidatorStatePtr,
+    scanner: Mutex<WalletScanner>,
 }
 
 // JSON-RPC methods
@@ -186,6 +203,7 @@ impl RequestHandler for Darkfid {
             // =====================
             Some("ping") => return self.misc_pong(req.id, params).await,
             Some("clock") => return self.misc_clock(req.id, params).await,
+            Some("get_metrics") => return self.misc_get_metrics(req.id, params).await,
 
             // ==================
             // Blockchain methods
@@ -194,11 +212,21 @@ impl RequestHandler for Darkfid {
             Some("blockchain.merkle_roots") => {
                 return self.blockchain_merkle_roots(req.id, params).await
             }
+            Some("blockchain.last_slot") => return self.blockchain_last_slot(req.id, params).await,
+            Some("blockchain.get_blocks_after") => {
+                return self.blockchain_get_blocks_after(req.id, params).await
+            }
+            Some("blockchain.snapshot_slot") => {
+                return self.blockchain_snapshot_slot(req.id, params).await
+            }
 
             // ===================
             // Transaction methods
             // ===================
             Some("tx.transfer") => return self.tx_transfer(req.id, params).await,
+            Some("tx.transfer_many") => return self.tx_transfer_many(req.id, params).await,
+            Some("tx.plan_transfer") => return self.tx_plan_transfer(req.id, params).await,
+            Some("tx.build_unsigned") => return self.tx_build_unsigned(req.id, params).await,
             Some("tx.broadcast") => return self.tx_broadcast(req.id, params).await,
 
             // ==============
@@ -223,6 +251,18 @@ impl RequestHandler for Darkfid {
                 return self.wallet_get_merkle_path(req.id, params).await
             }
             Some("wallet.decrypt_note") => return self.wallet_decrypt_note(req.id, params).await,
+            Some("wallet.export_view_key") => {
+                return self.wallet_export_view_key(req.id, params).await
+            }
+            Some("wallet.import_view_key") => {
+                return self.wallet_import_view_key(req.id, params).await
+            }
+            Some("wallet.get_viewed_coins") => {
+                return self.wallet_get_viewed_coins(req.id, params).await
+            }
+            Some("wallet.scan_height") => return self.wallet_scan_height(req.id, params).await,
+            Some("wallet.rescan") => return self.wallet_rescan(req.id, params).await,
+            Some("wallet.history") => return self.wallet_history(req.id, params).await,
 
             // ==============
             // Invalid method
@@ -242,16 +282,30 @@ impl Darkfid {
         let client = validator_state.read().await.client.clone();
         debug!("Released validator state lock");
 
+        let scanner = Mutex::new(WalletScanner::new(client.wallet.clone()));
+
         Ok(Self {
             synced: Mutex::new(false),
-            _consensus_p2p: consensus_p2p,
+            consensus_p2p,
             sync_p2p,
             client,
             validator_state,
+            scanner,
         })
     }
 }
 
+/// Process the finalized blocks for our wallet every `interval` seconds,
+/// see [`Darkfid::process_finalized`].
+async fn wallet_scan_task(darkfid: Arc<Darkfid>, interval: u64, revert_after: u64) {
+    loop {
+        if let Err(e) = darkfid.process_finalized(revert_after).await {
+            error!("Failed processing finalized blocks for the wallet: {}", e);
+        }
+        sleep(interval).await;
+    }
+}
+
 async_daemonize!(realmain);
 async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
     if args.consensus && args.clock_sync {
@@ -321,6 +375,14 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
         faucet_pubkeys.push(pk);
     }
 
+    // Parse trusted snapshot producer addresses
+    let mut snapshot_pubkeys = vec![];
+    for i in args.snapshot_trusted_pub {
+        let addr = Address::from_str(&i)?;
+        let pk = PublicKey::try_from(addr)?;
+        snapshot_pubkeys.push(pk);
+    }
+
     // Initialize validator state
     let state = ValidatorState::new(
         &sled_db,
@@ -369,6 +431,14 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
             })
             .await;
 
+        let _state = state.clone();
+        registry
+            .register(net::SESSION_ALL, move |channel, p2p| {
+                let state = _state.clone();
+                async move { ProtocolSnapshot::init(channel, state, p2p).await.unwrap() }
+            })
+            .await;
+
         Some(p2p)
     };
 
@@ -438,6 +508,14 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
     info!("Starting JSON-RPC server");
     ex.spawn(listen_and_serve(args.rpc_listen, darkfid.clone())).detach();
 
+    info!("Starting wallet scan task");
+    ex.spawn(wallet_scan_task(
+        darkfid.clone(),
+        args.wallet_scan_interval,
+        args.pending_revert_slots,
+    ))
+    .detach();
+
     info!("Starting sync P2P network");
     sync_p2p.clone().unwrap().start(ex.clone()).await?;
     let _ex = ex.clone();
@@ -452,6 +530,14 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
     info!("Waiting for sync P2P outbound connections");
     sync_p2p.clone().unwrap().wait_for_outbound(ex.clone()).await?;
 
+    if args.snapshot_sync {
+        if let Err(e) =
+            snapshot_sync_task(sync_p2p.clone().unwrap(), state.clone(), &snapshot_pubkeys).await
+        {
+            error!("Failed syncing state snapshot: {}", e);
+        }
+    }
+
     match block_sync_task(sync_p2p.clone().unwrap(), state.clone()).await {
         Ok(()) => *darkfid.synced.lock().await = true,
         Err(e) => error!("Failed syncing blockchain: {}", e),
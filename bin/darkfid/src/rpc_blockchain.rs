@@ -1,5 +1,6 @@
 use darkfi_sdk::crypto::MerkleNode;
-use log::{debug, error};
+use darkfi_serial::serialize;
+use log::error;
 use serde_json::{json, Value};
 
 use darkfi::rpc::jsonrpc::{
@@ -10,13 +11,16 @@ use darkfi::rpc::jsonrpc::{
 use super::Darkfid;
 use crate::{server_error, RpcError};
 
+/// Maximum number of blocks returned by `blockchain.get_blocks_after`
+const MAX_BLOCKS_AFTER: u64 = 100;
+
 impl Darkfid {
     // RPCAPI:
     // Queries the blockchain database for a block in the given slot.
-    // Returns a readable block upon success.
+    // Returns the base58 encoded serialized block upon success.
     //
     // --> {"jsonrpc": "2.0", "method": "blockchain.get_slot", "params": [0], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": {...}, "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": "ABCD...", "id": 1}
     pub async fn blockchain_get_slot(&self, id: Value, params: &[Value]) -> JsonResult {
         if params.len() != 1 || !params[0].is_u64() {
             return JsonError::new(InvalidParams, None, id).into()
@@ -40,9 +44,7 @@ impl Darkfid {
             return server_error(RpcError::UnknownSlot, id, None)
         }
 
-        // TODO: Return block as JSON
-        debug!("{:#?}", blocks[0]);
-        JsonResponse::new(json!(true), id).into()
+        JsonResponse::new(json!(bs58::encode(serialize(&blocks[0])).into_string()), id).into()
     }
 
     // RPCAPI:
@@ -72,4 +74,90 @@ impl Darkfid {
 
         JsonResponse::new(json!(roots), id).into()
     }
+
+    // RPCAPI:
+    // Queries the blockchain database for the slot of the last finalized block.
+    //
+    // --> {"jsonrpc": "2.0", "method": "blockchain.last_slot", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": 42, "id": 1}
+    pub async fn blockchain_last_slot(&self, id: Value, params: &[Value]) -> JsonResult {
+        if !params.is_empty() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let validator_state = self.validator_state.read().await;
+
+        let (slot, _) = match validator_state.blockchain.last() {
+            Ok(v) => {
+                drop(validator_state);
+                v
+            }
+            Err(e) => {
+                error!("[RPC] blockchain.last_slot: Failed fetching last block: {}", e);
+                return JsonError::new(InternalError, None, id).into()
+            }
+        };
+
+        JsonResponse::new(json!(slot), id).into()
+    }
+
+    // RPCAPI:
+    // Queries the blockchain database for up to `n` finalized blocks after
+    // the given slot, so wallets can scan them for their coins. At most 100
+    // blocks are returned at once.
+    // Returns the base58 encoded serialized blocks upon success.
+    //
+    // --> {"jsonrpc": "2.0", "method": "blockchain.get_blocks_after", "params": [0, 10], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": "ABCD...", "id": 1}
+    pub async fn blockchain_get_blocks_after(&self, id: Value, params: &[Value]) -> JsonResult {
+        if params.len() != 2 || !params[0].is_u64() || !params[1].is_u64() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let slot = params[0].as_u64().unwrap();
+        let n = params[1].as_u64().unwrap().min(MAX_BLOCKS_AFTER);
+        let validator_state = self.validator_state.read().await;
+
+        let blocks = match validator_state.blockchain.get_blocks_after(slot, n) {
+            Ok(v) => {
+                drop(validator_state);
+                v
+            }
+            Err(e) => {
+                error!("[RPC] blockchain.get_blocks_after: Failed fetching blocks: {}", e);
+                return JsonError::new(InternalError, None, id).into()
+            }
+        };
+
+        JsonResponse::new(json!(bs58::encode(serialize(&blocks)).into_string()), id).into()
+    }
+
+    // RPCAPI:
+    // Queries the blockchain database for the slot of the snapshot it was
+    // synced from. Wallets can't scan the blocks before it, which the node
+    // doesn't have.
+    // Returns the slot, or `null` if the node synced the whole chain.
+    //
+    // --> {"jsonrpc": "2.0", "method": "blockchain.snapshot_slot", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": 42, "id": 1}
+    pub async fn blockchain_snapshot_slot(&self, id: Value, params: &[Value]) -> JsonResult {
+        if !params.is_empty() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let validator_state = self.validator_state.read().await;
+
+        let slot = match validator_state.blockchain.snapshots.get_synced_from() {
+            Ok(v) => {
+                drop(validator_state);
+                v
+            }
+            Err(e) => {
+                error!("[RPC] blockchain.snapshot_slot: Failed fetching snapshot slot: {}", e);
+                return JsonError::new(InternalError, None, id).into()
+            }
+        };
+
+        JsonResponse::new(json!(slot), id).into()
+    }
 }
@@ -25,4 +25,24 @@ impl Darkfid {
     pub async fn misc_clock(&self, id: Value, _params: &[Value]) -> JsonResult {
         JsonResponse::new(json!(Timestamp::current_time()), id).into()
     }
+
+    // RPCAPI:
+    // Returns the metrics of the sync and consensus P2P networks, or `null`
+    // for a network that isn't running.
+    //
+    // --> {"jsonrpc": "2.0", "method": "get_metrics", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": {"sync": {...}, "consensus": {...}}, "id": 1}
+    pub async fn misc_get_metrics(&self, id: Value, _params: &[Value]) -> JsonResult {
+        let sync = match &self.sync_p2p {
+            Some(p2p) => p2p.get_metrics().await,
+            None => Value::Null,
+        };
+
+        let consensus = match &self.consensus_p2p {
+            Some(p2p) => p2p.get_metrics().await,
+            None => Value::Null,
+        };
+
+        JsonResponse::new(json!({"sync": sync, "consensus": consensus}), id).into()
+    }
 }
@@ -2,10 +2,15 @@ use std::str::FromStr;
 
 use darkfi_serial::{deserialize, serialize};
 use log::{error, warn};
+use pasta_curves::group::ff::PrimeField;
 use serde_json::{json, Value};
 
 use darkfi::{
-    crypto::{address::Address, keypair::PublicKey, token_id},
+    crypto::{
+        address::{parse_recipient, Address},
+        token_id,
+    },
+    node::{CoinSelection, Payment},
     rpc::jsonrpc::{ErrorCode::InvalidParams, JsonError, JsonResponse, JsonResult},
     tx::Transaction,
 };
@@ -18,7 +23,7 @@ impl Darkfid {
     // Transfer a given amount of some token to the given address.
     // Returns a transaction ID upon success.
     //
-    // * `dest_addr` -> Recipient's DarkFi address
+    // * `dest_addr` -> Recipient's DarkFi address, or viewable address
     // * `token_id` -> ID of the token to send
     // * `12345` -> Amount in `u64` of the funds to send
     //
@@ -42,7 +47,7 @@ impl Darkfid {
         let token = params[1].as_str().unwrap();
         let amount = params[2].as_u64().unwrap();
 
-        let address = match Address::from_str(address) {
+        let (public, view_public) = match parse_recipient(address) {
             Ok(v) => v,
             Err(e) => {
                 error!("[RPC] tx.transfer: Failed parsing address from string: {}", e);
@@ -50,47 +55,44 @@ impl Darkfid {
             }
         };
 
-        let pubkey = match PublicKey::try_from(address) {
+        let token_id = match token_id::parse_b58(token) {
             Ok(v) => v,
             Err(e) => {
-                error!("[RPC] tx.transfer: Failed parsing PublicKey from Address: {}", e);
+                error!("[RPC] tx.transfer: Failed parsing Token ID from string: {}", e);
                 return server_error(RpcError::ParseError, id, None)
             }
         };
 
-        let token_id = match token_id::parse_b58(token) {
-            Ok(v) => v,
-            Err(e) => {
-                error!("[RPC] tx.transfer: Failed parsing Token ID from string: {}", e);
-                return server_error(RpcError::ParseError, id, None)
+        let sync_p2p = match &self.sync_p2p {
+            Some(v) => v,
+            None => {
+                warn!("[RPC] tx.transfer: No sync P2P network, not broadcasting transaction.");
+                return server_error(RpcError::TxBroadcastFail, id, None)
             }
         };
 
+        let payment = Payment { public, view_public, value: amount, token_id };
         let tx = match self
             .client
-            .build_transaction(
-                pubkey,
-                amount,
-                token_id,
-                false,
+            .transfer(
+                &[payment],
+                CoinSelection::default(),
                 self.validator_state.read().await.state_machine.clone(),
             )
             .await
         {
-            Ok(v) => v,
+            Ok(mut v) => v.remove(0),
             Err(e) => {
                 error!("tx.transfer: Failed building transaction: {}", e);
                 return server_error(RpcError::TxBuildFail, id, None)
             }
         };
 
-        if let Some(sync_p2p) = &self.sync_p2p {
-            if let Err(e) = sync_p2p.broadcast(tx.clone()).await {
-                error!("[RPC] tx.transfer: Failed broadcasting transaction: {}", e);
-                return server_error(RpcError::TxBroadcastFail, id, None)
+        if let Err(e) = sync_p2p.announce(&tx).await {
+            error!("[RPC] tx.transfer: Failed broadcasting transaction: {}", e);
+            if let Err(e) = self.client.revert_transfer(&tx).await {
+                error!("[RPC] tx.transfer: Failed reverting transaction: {}", e);
             }
-        } else {
-            warn!("[RPC] tx.transfer: No sync P2P network, not broadcasting transaction.");
             return server_error(RpcError::TxBroadcastFail, id, None)
         }
 
@@ -98,6 +100,174 @@ impl Darkfid {
         JsonResponse::new(json!(tx_hash), id).into()
     }
 
+    // RPCAPI:
+    // Pay several recipients at once, selecting the spent coins with the given
+    // strategy (`largest-first`, `minimize-inputs` or `random`). Change is
+    // sent to a fresh address. A transaction can only move a single token, so
+    // one transaction is made per token. If one fails to broadcast, it and
+    // the following ones are reverted, and the error lists the broadcast ones.
+    // Returns the transaction IDs upon success.
+    //
+    // * `[["dest_addr", "token_id", 12345], ...]` -> Recipients, tokens and amounts
+    // * `"largest-first"` -> Coin selection strategy
+    //
+    // --> {"jsonrpc": "2.0", "method": "tx.transfer_many", "params": [[["dest_addr", "token_id", 12345]], "largest-first"], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": ["txID...", ...], "id": 1}
+    pub async fn tx_transfer_many(&self, id: Value, params: &[Value]) -> JsonResult {
+        if params.len() != 2 || !params[0].is_array() || !params[1].is_string() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        if !(*self.synced.lock().await) {
+            error!("[RPC] tx.transfer_many: Blockchain is not synced");
+            return server_error(RpcError::NotSynced, id, None)
+        }
+
+        let (payments, selection) = match parse_transfer_params("tx.transfer_many", params) {
+            Some(v) => v,
+            None => return server_error(RpcError::ParseError, id, None),
+        };
+
+        let sync_p2p = match &self.sync_p2p {
+            Some(v) => v,
+            None => {
+                warn!(
+                    "[RPC] tx.transfer_many: No sync P2P network, not broadcasting transactions."
+                );
+                return server_error(RpcError::TxBroadcastFail, id, None)
+            }
+        };
+
+        let txs = match self
+            .client
+            .transfer(&payments, selection, self.validator_state.read().await.state_machine.clone())
+            .await
+        {
+            Ok(v) => v,
+            Err(e) => {
+                error!("[RPC] tx.transfer_many: Failed building transactions: {}", e);
+                return server_error(RpcError::TxBuildFail, id, Some(&e.to_string()))
+            }
+        };
+
+        let mut tx_hashes = vec![];
+        for (i, tx) in txs.iter().enumerate() {
+            if let Err(e) = sync_p2p.announce(tx).await {
+                error!("[RPC] tx.transfer_many: Failed broadcasting transaction: {}", e);
+
+                // The transactions already broadcast stay pending
+                for tx in &txs[i..] {
+                    if let Err(e) = self.client.revert_transfer(tx).await {
+                        error!("[RPC] tx.transfer_many: Failed reverting transaction: {}", e);
+                    }
+                }
+
+                let msg = format!("Broadcast transactions: {:?}", tx_hashes);
+                return server_error(RpcError::TxBroadcastFail, id, Some(&msg))
+            }
+
+            tx_hashes.push(blake3::hash(&serialize(tx)).to_hex().as_str().to_string());
+        }
+
+        JsonResponse::new(json!(tx_hashes), id).into()
+    }
+
+    // RPCAPI:
+    // Plan a transfer like `tx.transfer_many` without building or broadcasting
+    // anything, showing the coins that would be spent and the outputs made.
+    // The change address shown is not the one used by an actual transfer.
+    // Returns a plan per token upon success.
+    //
+    // --> {"jsonrpc": "2.0", "method": "tx.plan_transfer", "params": [[["dest_addr", "token_id", 12345]], "random"], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": [{"token_id": "...", "inputs": [...], "outputs": [...], "change": {...}}], "id": 1}
+    pub async fn tx_plan_transfer(&self, id: Value, params: &[Value]) -> JsonResult {
+        if params.len() != 2 || !params[0].is_array() || !params[1].is_string() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let (payments, selection) = match parse_transfer_params("tx.plan_transfer", params) {
+            Some(v) => v,
+            None => return server_error(RpcError::ParseError, id, None),
+        };
+
+        let plans = match self.client.plan_transfer(&payments, selection).await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("[RPC] tx.plan_transfer: Failed planning transfer: {}", e);
+                return server_error(RpcError::TxBuildFail, id, Some(&e.to_string()))
+            }
+        };
+
+        let ret: Vec<Value> = plans
+            .iter()
+            .map(|plan| {
+                let inputs: Vec<Value> = plan
+                    .inputs
+                    .iter()
+                    .map(|c| {
+                        json!({
+                            "coin": bs58::encode(serialize(&c.coin)).into_string(),
+                            "value": c.note.value,
+                        })
+                    })
+                    .collect();
+
+                let outputs: Vec<Value> = plan
+                    .outputs
+                    .iter()
+                    .map(|p| json!({"address": Address::from(p.public).to_string(), "value": p.value}))
+                    .collect();
+
+                let change = plan.change.map(|(kp, value)| {
+                    json!({"address": Address::from(kp.public).to_string(), "value": value})
+                });
+
+                json!({
+                    "token_id": bs58::encode(plan.token_id.to_repr()).into_string(),
+                    "inputs": inputs,
+                    "outputs": outputs,
+                    "change": change,
+                })
+            })
+            .collect();
+
+        JsonResponse::new(json!(ret), id).into()
+    }
+
+    // RPCAPI:
+    // Build unsigned transactions like `tx.transfer_many`, spending the coins
+    // found with the wallet's view keys. No spending key is used: the owners
+    // of the coins prove and sign their inputs elsewhere, e.g. with `drk
+    // unsigned sign`, before the transactions can be broadcast. The change is
+    // sent back to the owner of the first spent coin.
+    // Returns a base58 encoded unsigned transaction per token upon success.
+    //
+    // --> {"jsonrpc": "2.0", "method": "tx.build_unsigned", "params": [[["dest_addr", "token_id", 12345]], "largest-first"], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": ["base58_encoded_unsigned_tx", ...], "id": 1}
+    pub async fn tx_build_unsigned(&self, id: Value, params: &[Value]) -> JsonResult {
+        if params.len() != 2 || !params[0].is_array() || !params[1].is_string() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let (payments, selection) = match parse_transfer_params("tx.build_unsigned", params) {
+            Some(v) => v,
+            None => return server_error(RpcError::ParseError, id, None),
+        };
+
+        let txs = match self.client.build_unsigned(&payments, selection).await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("[RPC] tx.build_unsigned: Failed building transactions: {}", e);
+                return server_error(RpcError::TxBuildFail, id, Some(&e.to_string()))
+            }
+        };
+
+        let ret: Vec<String> =
+            txs.iter().map(|tx| bs58::encode(serialize(tx)).into_string()).collect();
+
+        JsonResponse::new(json!(ret), id).into()
+    }
+
     // RPCAPI:
     // Simulate a network state transition with the given transaction.
     // Returns `true` if the transaction is valid, otherwise, a corresponding
@@ -184,7 +354,7 @@ impl Darkfid {
 
         // TODO: Should we apply the state transition locally before broadcasting it?
         if let Some(sync_p2p) = &self.sync_p2p {
-            if let Err(e) = sync_p2p.broadcast(tx.clone()).await {
+            if let Err(e) = sync_p2p.announce(&tx).await {
                 error!("[RPC] tx.broadcast: Failed broadcasting transaction: {}", e);
                 return server_error(RpcError::TxBroadcastFail, id, None)
             }
@@ -199,3 +369,47 @@ impl Darkfid {
         JsonResponse::new(json!(tx_hash), id).into()
     }
 }
+
+/// Parse the `[[address, token_id, amount], ...]` and coin selection params
+/// of a transfer. Errors are logged with the given method name.
+fn parse_transfer_params(method: &str, params: &[Value]) -> Option<(Vec<Payment>, CoinSelection)> {
+    let selection = match CoinSelection::from_str(params[1].as_str().unwrap()) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("[RPC] {}: Failed parsing coin selection: {}", method, e);
+            return None
+        }
+    };
+
+    let mut payments = vec![];
+    for (i, elem) in params[0].as_array().unwrap().iter().enumerate() {
+        let payment = match elem.as_array() {
+            Some(v) if v.len() == 3 && v[0].is_string() && v[1].is_string() && v[2].is_u64() => v,
+            _ => {
+                error!("[RPC] {}: Payment {} is not [address, token_id, amount]", method, i);
+                return None
+            }
+        };
+
+        let (public, view_public) = match parse_recipient(payment[0].as_str().unwrap()) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("[RPC] {}: Failed parsing address from string: {}", method, e);
+                return None
+            }
+        };
+
+        let token_id = match token_id::parse_b58(payment[1].as_str().unwrap()) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("[RPC] {}: Failed parsing Token ID from string: {}", method, e);
+                return None
+            }
+        };
+
+        let value = payment[2].as_u64().unwrap();
+        payments.push(Payment { public, view_public, value, token_id });
+    }
+
+    Some((payments, selection))
+}
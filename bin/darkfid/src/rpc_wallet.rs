@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use darkfi_serial::{deserialize, serialize};
 use fxhash::FxHashMap;
 use incrementalmerkletree::Tree;
@@ -7,8 +9,9 @@ use serde_json::{json, Value};
 
 use darkfi::{
     crypto::{
-        address::Address,
-        keypair::{Keypair, PublicKey, SecretKey},
+        address::{Address, ViewableAddress},
+        coin::Coin,
+        keypair::{Keypair, PublicKey, SecretKey, ViewKey},
         token_id,
     },
     node::State,
@@ -278,7 +281,7 @@ impl Darkfid {
     }
 
     // RPCAPI:
-    // Query the state merkle tree for the merkle path of a given leaf position.
+    // Query the wallet merkle tree for the merkle path of a given leaf position.
     //
     // --> {"jsonrpc": "2.0", "method": "wallet.get_merkle_path", "params": [3], "id": 1}
     // <-- {"jsonrpc": "2.0", "result": ["f091uf1...", "081ff0h10w1h0...", ...], "id": 1}
@@ -290,12 +293,20 @@ impl Darkfid {
         let leaf_pos: incrementalmerkletree::Position =
             ((params[0].as_u64().unwrap() as u64) as usize).into();
 
-        let validator_state = self.validator_state.read().await;
-        let state = validator_state.state_machine.lock().await;
-        let root = state.tree.root(0).unwrap();
-        let merkle_path = state.tree.authentication_path(leaf_pos, &root).unwrap();
-        drop(state);
-        drop(validator_state);
+        // The wallet's tree witnesses our coins
+        let tree = match self.client.get_tree().await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("[RPC] wallet.get_merkle_path: Failed fetching merkle tree: {}", e);
+                return JsonError::new(InternalError, None, id).into()
+            }
+        };
+
+        let root = tree.root(0).unwrap();
+        let merkle_path = match tree.authentication_path(leaf_pos, &root) {
+            Some(v) => v,
+            None => return JsonError::new(InvalidParams, None, id).into(),
+        };
 
         let ret: Vec<String> =
             merkle_path.iter().map(|x| bs58::encode(serialize(x)).into_string()).collect();
@@ -337,13 +348,202 @@ impl Darkfid {
             }
         };
 
+        // Notes sent to a viewable address are encrypted to the view key
         for kp in keypairs {
-            if let Some(note) = State::try_decrypt_note(&enc_note, kp.secret) {
-                let s = bs58::encode(&serialize(&note)).into_string();
-                return JsonResponse::new(json!(s), id).into()
+            for secret in [kp.secret, ViewKey::from(kp).secret] {
+                if let Some(note) = State::try_decrypt_note(&enc_note, secret) {
+                    let s = bs58::encode(&serialize(&note)).into_string();
+                    return JsonResponse::new(json!(s), id).into()
+                }
             }
         }
 
         server_error(RpcError::DecryptionFailed, id, None)
     }
+
+    // RPCAPI:
+    // Exports the view key of the given keypair index. It can decrypt the
+    // notes of coins sent to the returned viewable address, but can't spend
+    // them. Returns the encoded view key and its viewable address upon success.
+    //
+    // --> {"jsonrpc": "2.0", "method": "wallet.export_view_key", "params": [0], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": ["viewkey...", "viewable_address..."], "id": 1}
+    pub async fn wallet_export_view_key(&self, id: Value, params: &[Value]) -> JsonResult {
+        if params.len() != 1 || !params[0].is_u64() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let keypairs = match self.client.get_keypairs().await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("[RPC] wallet.export_view_key: Failed fetching keypairs: {}", e);
+                return server_error(RpcError::KeypairFetch, id, None)
+            }
+        };
+
+        if let Some(kp) = keypairs.get(params[0].as_u64().unwrap() as usize) {
+            let view_key = ViewKey::from(*kp);
+            let address = ViewableAddress::from(view_key).to_string();
+            return JsonResponse::new(json!([view_key.to_string(), address]), id).into()
+        }
+
+        server_error(RpcError::KeypairNotFound, id, None)
+    }
+
+    // RPCAPI:
+    // Imports a given view key, as exported by `wallet.export_view_key`.
+    // Coins sent to its viewable address are recorded when scanning, but
+    // never spent by the wallet. Returns the viewable address upon success.
+    //
+    // --> {"jsonrpc": "2.0", "method": "wallet.import_view_key", "params": ["viewkey..."], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": "viewable_address...", "id": 1}
+    pub async fn wallet_import_view_key(&self, id: Value, params: &[Value]) -> JsonResult {
+        if params.len() != 1 || !params[0].is_string() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let view_key = match ViewKey::from_str(params[0].as_str().unwrap()) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("[RPC] wallet.import_view_key: Failed parsing view key from string: {}", e);
+                return server_error(RpcError::InvalidKeypair, id, None)
+            }
+        };
+
+        let address = ViewableAddress::from(view_key).to_string();
+
+        if let Err(e) = self.client.wallet.put_view_key(&view_key).await {
+            error!("[RPC] wallet.import_view_key: Failed inserting view key into wallet: {}", e);
+            return JsonError::new(InternalError, None, id).into()
+        }
+
+        JsonResponse::new(json!(address), id).into()
+    }
+
+    // RPCAPI:
+    // Queries the wallet for the coins found with its view keys.
+    // Returns a list of the coins' address, token ID, value, slot and whether
+    // they were spent.
+    //
+    // --> {"jsonrpc": "2.0", "method": "wallet.get_viewed_coins", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": [{"address": "1DarkFi...", "token_id": "F00b4r...", "value": 100, "slot": 42, "spent": false}, ...], "id": 1}
+    pub async fn wallet_get_viewed_coins(&self, id: Value, params: &[Value]) -> JsonResult {
+        if !params.is_empty() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let viewed_coins = match self.client.wallet.get_viewed_coins().await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("[RPC] wallet.get_viewed_coins: Failed fetching from wallet: {}", e);
+                return JsonError::new(InternalError, None, id).into()
+            }
+        };
+
+        let ret: Vec<Value> = viewed_coins
+            .iter()
+            .map(|x| {
+                json!({
+                    "address": Address::from(x.public).to_string(),
+                    "token_id": bs58::encode(x.note.token_id.to_repr()).into_string(),
+                    "value": x.note.value,
+                    "slot": x.slot,
+                    "spent": x.is_spent,
+                })
+            })
+            .collect();
+
+        JsonResponse::new(json!(ret), id).into()
+    }
+
+    // RPCAPI:
+    // Queries the wallet for the slot of the last block it scanned.
+    //
+    // --> {"jsonrpc": "2.0", "method": "wallet.scan_height", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": 42, "id": 1}
+    pub async fn wallet_scan_height(&self, id: Value, params: &[Value]) -> JsonResult {
+        if !params.is_empty() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        match self.client.wallet.get_scan_height().await {
+            Ok(v) => JsonResponse::new(json!(v.unwrap_or(0)), id).into(),
+            Err(e) => {
+                error!("[RPC] wallet.scan_height: Failed fetching from wallet: {}", e);
+                JsonError::new(InternalError, None, id).into()
+            }
+        }
+    }
+
+    // RPCAPI:
+    // Scans the finalized blocks again for the wallet's coins, from the given
+    // slot. Useful after importing keys which already received coins.
+    // Returns the slot of the last scanned block upon success.
+    //
+    // --> {"jsonrpc": "2.0", "method": "wallet.rescan", "params": [0], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": 42, "id": 1}
+    pub async fn wallet_rescan(&self, id: Value, params: &[Value]) -> JsonResult {
+        if params.len() != 1 || !params[0].is_u64() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let slot = params[0].as_u64().unwrap();
+
+        match self.scan_wallet_from(Some(slot)).await {
+            Ok(v) => JsonResponse::new(json!(v.height), id).into(),
+            Err(e) => {
+                error!("[RPC] wallet.rescan: Failed scanning blocks: {}", e);
+                server_error(RpcError::ScanFailed, id, None)
+            }
+        }
+    }
+
+    // RPCAPI:
+    // Queries the wallet for its transaction history, most recent first.
+    // Returns a list of transactions with their direction, value, token ID,
+    // counterparties, slot, memo, status, timestamp and linked coins.
+    //
+    // --> {"jsonrpc": "2.0", "method": "wallet.history", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": [{"tx_hash": "...", "direction": "sent", ...}, ...], "id": 1}
+    pub async fn wallet_history(&self, id: Value, params: &[Value]) -> JsonResult {
+        if !params.is_empty() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let history = match self.client.wallet.get_tx_history().await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("[RPC] wallet.history: Failed fetching history from wallet: {}", e);
+                return JsonError::new(InternalError, None, id).into()
+            }
+        };
+
+        let encode_coins = |coins: &[Coin]| -> Vec<String> {
+            coins.iter().map(|x| bs58::encode(serialize(x)).into_string()).collect()
+        };
+
+        let ret: Vec<Value> = history
+            .iter()
+            .map(|x| {
+                let counterparties: Vec<String> =
+                    x.counterparties.iter().map(|p| Address::from(*p).to_string()).collect();
+
+                json!({
+                    "tx_hash": x.tx_hash.to_hex().as_str(),
+                    "direction": x.direction.to_string(),
+                    "value": x.value,
+                    "token_id": bs58::encode(x.token_id.to_repr()).into_string(),
+                    "counterparties": counterparties,
+                    "slot": x.slot,
+                    "memo": String::from_utf8_lossy(&x.memo),
+                    "status": x.status.to_string(),
+                    "timestamp": x.timestamp.0,
+                    "spent_coins": encode_coins(&x.spent_coins),
+                    "received_coins": encode_coins(&x.received_coins),
+                })
+            })
+            .collect();
+
+        JsonResponse::new(json!(ret), id).into()
+    }
 }
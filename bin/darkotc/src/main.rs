@@ -13,10 +13,10 @@ use darkfi::{
     cli_desc,
     crypto::{
         burn_proof::{create_burn_proof, verify_burn_proof},
+        keycache::KeyCache,
         keypair::{PublicKey, SecretKey},
         mint_proof::{create_mint_proof, verify_mint_proof},
         note::{EncryptedNote, Note},
-        proof::{ProvingKey, VerifyingKey},
         schnorr,
         schnorr::SchnorrSecret,
         token_id,
@@ -178,11 +178,13 @@ async fn init_swap(
 
     // Build ZK proving keys
     let pb = progress_bar("Building proving key for the Mint contract");
-    let mint_pk = ProvingKey::build(11, &MintContract::default());
+    let circuit_id = KeyCache::native_circuit_id::<MintContract>();
+    let mint_pk = KeyCache::default().proving_key(&circuit_id, 11, &MintContract::default());
     pb.finish();
 
     let pb = progress_bar("Building proving key for the Burn contract");
-    let burn_pk = ProvingKey::build(11, &BurnContract::default());
+    let circuit_id = KeyCache::native_circuit_id::<BurnContract>();
+    let burn_pk = KeyCache::default().proving_key(&circuit_id, 11, &BurnContract::default());
     pb.finish();
 
     // The coin we want to receive
@@ -301,11 +303,13 @@ fn inspect_partial(data: &str) -> Result<()> {
 
     // Build ZK verifying keys
     let pb = progress_bar("Building verifying key for the Mint contract");
-    let mint_vk = VerifyingKey::build(11, &MintContract::default());
+    let circuit_id = KeyCache::native_circuit_id::<MintContract>();
+    let mint_vk = KeyCache::default().verifying_key(&circuit_id, 11, &MintContract::default());
     pb.finish();
 
     let pb = progress_bar("Building verifying key for the Burn contract");
-    let burn_vk = VerifyingKey::build(11, &BurnContract::default());
+    let circuit_id = KeyCache::native_circuit_id::<BurnContract>();
+    let burn_vk = KeyCache::default().verifying_key(&circuit_id, 11, &BurnContract::default());
     pb.finish();
 
     let pb = progress_bar("Verifying Burn proof");
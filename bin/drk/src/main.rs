@@ -14,12 +14,18 @@ use url::Url;
 
 use darkfi::{
     cli_desc,
-    crypto::{address::Address, token_id},
+    crypto::{
+        address::{parse_recipient, Address},
+        proof::VerifyingKey,
+        token_id,
+    },
+    node::Client,
     rpc::{client::RpcClient, jsonrpc::JsonRequest},
+    tx::unsigned::UnsignedTransaction,
     util::{
         cli::{fg_red, get_log_config, get_log_level, progress_bar},
         net_name::NetworkName,
-        parse::encode_base10,
+        parse::{decode_base10, encode_base10},
     },
     Result,
 };
@@ -27,6 +33,15 @@ use darkfi::{
 mod deploy_contract;
 use deploy_contract::create_deploy_data;
 
+mod scan;
+use scan::scan_wallet;
+
+mod unsigned;
+use unsigned::{
+    combine_unsigned, decode_unsigned, encode_unsigned, finalize_unsigned, inspect_unsigned,
+    read_secret, sign_unsigned,
+};
+
 #[derive(Parser)]
 #[clap(name = "drk", about = cli_desc!(), version)]
 #[clap(arg_required_else_help(true))]
@@ -84,16 +99,19 @@ enum Subcmd {
         #[clap(long)]
         /// Get all addresses in the wallet
         all_addresses: bool,
+
+        #[clap(long)]
+        /// Show the wallet's transaction history
+        history: bool,
     },
 
     /// Transfer of value
     Transfer {
-        /// Recipient address
-        #[clap(parse(try_from_str))]
-        recipient: Address,
+        /// Recipient address, or viewable address
+        recipient: String,
 
         /// Amount to transfer
-        amount: f64,
+        amount: String,
 
         /// Coin network
         #[clap(short, long, default_value = "darkfi", parse(try_from_str))]
@@ -102,11 +120,52 @@ enum Subcmd {
         /// Token ID
         #[clap(short, long)]
         token_id: String,
+
+        /// Additional payment in the same transfer, as `address:amount:token_id`
+        #[clap(long, multiple_occurrences(true))]
+        pay: Vec<String>,
+
+        /// Strategy selecting the coins to spend
+        #[clap(
+            long,
+            default_value = "largest-first",
+            possible_values = &["largest-first", "minimize-inputs", "random"]
+        )]
+        selection: String,
+
+        /// Only show the planned inputs and outputs, without sending anything
+        #[clap(long)]
+        dry_run: bool,
     },
 
     /// Broadcast a given transaction from stdin
     Broadcast,
 
+    /// Scan the blocks finalized by darkfid for the coins of a local wallet
+    Scan {
+        #[clap(long, default_value = "~/.config/darkfi/drk_wallet.db")]
+        /// Path to the local wallet database
+        wallet_path: String,
+
+        #[clap(long, default_value = "changeme")]
+        /// Password for the local wallet database
+        wallet_pass: String,
+
+        #[clap(long, multiple_occurrences(true))]
+        /// View key to import into the wallet before scanning
+        view_key: Vec<String>,
+
+        #[clap(long)]
+        /// Scan the blocks again, looking for coins from the given slot
+        rescan: Option<u64>,
+    },
+
+    /// Unsigned transactions, proven and signed offline by the coins' owners
+    Unsigned {
+        #[clap(subcommand)]
+        command: UnsignedSubcmd,
+    },
+
     /// Deploy a smart contract in the current directory or a given path.
     DeployContract {
         #[clap(long, default_value = ".")]
@@ -114,6 +173,47 @@ enum Subcmd {
     },
 }
 
+#[derive(Subcommand)]
+enum UnsignedSubcmd {
+    /// Build unsigned transactions spending the coins of darkfid's view keys,
+    /// one per line and per token
+    Create {
+        /// Payments, as `address:amount:token_id`
+        #[clap(required = true)]
+        payments: Vec<String>,
+
+        /// Strategy selecting the coins to spend
+        #[clap(
+            long,
+            default_value = "largest-first",
+            possible_values = &["largest-first", "minimize-inputs", "random"]
+        )]
+        selection: String,
+    },
+
+    /// Show the inputs of an unsigned transaction from stdin
+    Inspect,
+
+    /// Prove and sign the inputs of an unsigned transaction from stdin owned
+    /// by the given key. With several owners, each signs again after combining.
+    Sign {
+        /// File containing the base58 encoded secret key
+        #[clap(long)]
+        secret_file: PathBuf,
+    },
+
+    /// Combine copies of an unsigned transaction signed by different owners
+    Combine {
+        /// Files containing the copies
+        #[clap(min_values = 2, required = true)]
+        files: Vec<PathBuf>,
+    },
+
+    /// Turn a complete unsigned transaction from stdin into a transaction
+    /// for `drk broadcast`
+    Finalize,
+}
+
 struct Drk {
     pub rpc_client: RpcClient,
 }
@@ -223,23 +323,138 @@ impl Drk {
         Ok(())
     }
 
+    async fn wallet_history(&self) -> Result<()> {
+        let req = JsonRequest::new("wallet.history", json!([]));
+        let rep = self.rpc_client.request(req).await?;
+
+        let history = match rep.as_array() {
+            Some(v) => v,
+            None => {
+                eprintln!("Invalid history data received from darkfid RPC endpoint.");
+                exit(1);
+            }
+        };
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.set_titles(row!["Transaction", "Direction", "Amount", "Token ID", "Slot", "Status"]);
+
+        for tx in history {
+            let slot = match tx["slot"].as_u64() {
+                Some(v) => v.to_string(),
+                None => "-".to_string(),
+            };
+
+            table.add_row(row![
+                tx["tx_hash"].as_str().unwrap_or("?"),
+                tx["direction"].as_str().unwrap_or("?"),
+                encode_base10(tx["value"].as_u64().unwrap_or(0), 8),
+                tx["token_id"].as_str().unwrap_or("?"),
+                slot,
+                tx["status"].as_str().unwrap_or("?")
+            ]);
+        }
+
+        if table.is_empty() {
+            println!("No transactions.");
+        } else {
+            println!("{}", table);
+        }
+
+        Ok(())
+    }
+
     async fn tx_transfer(
         &self,
-        network: NetworkName,
-        token_id: String,
-        recipient: Address,
-        amount: f64,
+        payments: Vec<(String, String, u64)>,
+        selection: String,
+    ) -> Result<()> {
+        for (recipient, _, amount) in &payments {
+            println!(
+                "Attempting to transfer {} tokens to {}",
+                encode_base10(*amount, 8),
+                recipient
+            );
+        }
+
+        let payments: Vec<_> = payments.iter().map(|(a, t, v)| json!([a, t, v])).collect();
+        let req = JsonRequest::new("tx.transfer_many", json!([payments, selection]));
+        let rep = self.rpc_client.request(req).await?;
+
+        for tx_id in rep.as_array().unwrap_or(&vec![]) {
+            println!("Success! Transaction ID: {}", tx_id);
+        }
+
+        Ok(())
+    }
+
+    async fn tx_plan_transfer(
+        &self,
+        payments: Vec<(String, String, u64)>,
+        selection: String,
     ) -> Result<()> {
-        println!("Attempting to transfer {} tokens to {}", amount, recipient);
+        let payments: Vec<_> = payments.iter().map(|(a, t, v)| json!([a, t, v])).collect();
+        let req = JsonRequest::new("tx.plan_transfer", json!([payments, selection]));
+        let rep = self.rpc_client.request(req).await?;
+
+        let plans = match rep.as_array() {
+            Some(v) => v,
+            None => {
+                eprintln!("Invalid transfer plan received from darkfid RPC endpoint.");
+                exit(1);
+            }
+        };
+
+        for plan in plans {
+            println!("Transaction for token {}", plan["token_id"].as_str().unwrap_or("?"));
+
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+            table.set_titles(row!["", "Coin/Address", "Amount"]);
 
-        let req = JsonRequest::new(
-            "tx.transfer",
-            json!([network.to_string(), token_id, recipient.to_string(), amount]),
-        );
+            let value = |v: &serde_json::Value| encode_base10(v["value"].as_u64().unwrap_or(0), 8);
+
+            for input in plan["inputs"].as_array().unwrap_or(&vec![]) {
+                table.add_row(row!["Input", input["coin"].as_str().unwrap_or("?"), value(input)]);
+            }
+
+            for output in plan["outputs"].as_array().unwrap_or(&vec![]) {
+                table.add_row(row![
+                    "Output",
+                    output["address"].as_str().unwrap_or("?"),
+                    value(output)
+                ]);
+            }
+
+            if plan["change"].is_object() {
+                let change = &plan["change"];
+                table.add_row(row![
+                    "Change",
+                    format!("{} (fresh)", change["address"].as_str().unwrap_or("?")),
+                    value(change)
+                ]);
+            }
+
+            println!("{}", table);
+        }
+
+        println!("Dry run, nothing was sent.");
+        Ok(())
+    }
 
+    async fn tx_build_unsigned(
+        &self,
+        payments: Vec<(String, String, u64)>,
+        selection: String,
+    ) -> Result<()> {
+        let payments: Vec<_> = payments.iter().map(|(a, t, v)| json!([a, t, v])).collect();
+        let req = JsonRequest::new("tx.build_unsigned", json!([payments, selection]));
         let rep = self.rpc_client.request(req).await?;
 
-        println!("Success! Transaction ID: {}", rep);
+        for tx in rep.as_array().unwrap_or(&vec![]) {
+            println!("{}", tx.as_str().unwrap_or("?"));
+        }
+
         Ok(())
     }
 
@@ -252,6 +467,54 @@ impl Drk {
     }
 }
 
+/// Parse a payment given on the command line, exiting on invalid input.
+fn parse_payment(address: &str, amount: &str, token: &str) -> (String, String, u64) {
+    if let Err(e) = parse_recipient(address) {
+        eprintln!("Error: Invalid address \"{}\": {}", address, e);
+        exit(1);
+    }
+
+    let amount = match decode_base10(amount, 8, true) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: Invalid amount \"{}\": {}", amount, e);
+            exit(1);
+        }
+    };
+
+    if token_id::parse_b58(token).is_err() {
+        eprintln!("Error: Invalid Token ID \"{}\"", token);
+        exit(1);
+    }
+
+    (address.to_string(), token.to_string(), amount)
+}
+
+/// Parse a payment given as `address:amount:token_id`, exiting on invalid input.
+fn parse_payment_str(payment: &str) -> (String, String, u64) {
+    let parts: Vec<&str> = payment.split(':').collect();
+    if parts.len() != 3 {
+        eprintln!("Error: Payment \"{}\" is not address:amount:token_id", payment);
+        exit(1);
+    }
+    parse_payment(parts[0], parts[1], parts[2])
+}
+
+/// Read a base58 encoded unsigned transaction from stdin, exiting on
+/// invalid input.
+fn read_unsigned_stdin(mint_vk: &VerifyingKey) -> Result<UnsignedTransaction> {
+    let mut buf = String::new();
+    stdin().read_to_string(&mut buf)?;
+
+    match decode_unsigned(&buf, mint_vk) {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            eprintln!("Error: Invalid unsigned transaction: {}", e);
+            exit(1);
+        }
+    }
+}
+
 #[async_std::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
@@ -274,7 +537,7 @@ async fn main() -> Result<()> {
             drk.airdrop(address, faucet_endpoint, amount, token_id).await
         }
 
-        Subcmd::Wallet { keygen, balance, address, all_addresses } => {
+        Subcmd::Wallet { keygen, balance, address, all_addresses, history } => {
             let rpc_client = RpcClient::new(args.endpoint).await?;
             let drk = Drk { rpc_client };
 
@@ -294,15 +557,31 @@ async fn main() -> Result<()> {
                 return drk.wallet_all_addresses().await
             }
 
+            if history {
+                return drk.wallet_history().await
+            }
+
             eprintln!("Run 'drk wallet -h' to see the subcommand usage.");
             exit(2);
         }
 
-        Subcmd::Transfer { recipient, amount, network, token_id } => {
+        Subcmd::Transfer { recipient, amount, network, token_id, pay, selection, dry_run } => {
+            if network != NetworkName::DarkFi {
+                eprintln!("Error: Only transfers on the DarkFi network are supported.");
+                exit(1);
+            }
+
+            let mut payments = vec![parse_payment(&recipient, &amount, &token_id)];
+            payments.extend(pay.iter().map(|p| parse_payment_str(p)));
+
             let rpc_client = RpcClient::new(args.endpoint).await?;
             let drk = Drk { rpc_client };
 
-            drk.tx_transfer(network, token_id, recipient, amount).await
+            if dry_run {
+                return drk.tx_plan_transfer(payments, selection).await
+            }
+
+            drk.tx_transfer(payments, selection).await
         }
 
         Subcmd::Broadcast => {
@@ -315,6 +594,70 @@ async fn main() -> Result<()> {
             drk.tx_broadcast(buf).await
         }
 
+        Subcmd::Scan { wallet_path, wallet_pass, view_key, rescan } => {
+            if let Err(e) =
+                scan_wallet(args.endpoint, &wallet_path, &wallet_pass, &view_key, rescan).await
+            {
+                eprintln!("{} {}", fg_red("Error:"), e);
+                exit(1);
+            }
+            Ok(())
+        }
+
+        Subcmd::Unsigned { command } => match command {
+            UnsignedSubcmd::Create { payments, selection } => {
+                let payments: Vec<_> = payments.iter().map(|p| parse_payment_str(p)).collect();
+
+                let rpc_client = RpcClient::new(args.endpoint).await?;
+                let drk = Drk { rpc_client };
+
+                drk.tx_build_unsigned(payments, selection).await
+            }
+
+            UnsignedSubcmd::Inspect => {
+                let tx = read_unsigned_stdin(&Client::build_mint_vk())?;
+                inspect_unsigned(&tx);
+                Ok(())
+            }
+
+            UnsignedSubcmd::Sign { secret_file } => {
+                let secret = read_secret(&secret_file)?;
+                let mint_vk = Client::build_mint_vk();
+                let mut tx = read_unsigned_stdin(&mint_vk)?;
+
+                let changed = sign_unsigned(&mut tx, &mint_vk, secret)?;
+                if changed == 0 {
+                    eprintln!("Nothing to prove or sign with this key.");
+                } else if !tx.is_complete() {
+                    eprintln!(
+                        "Inputs updated: {}. Not complete yet, combine and sign again.",
+                        changed
+                    );
+                }
+
+                println!("{}", encode_unsigned(&tx));
+                Ok(())
+            }
+
+            UnsignedSubcmd::Combine { files } => {
+                let tx = combine_unsigned(&files, &Client::build_mint_vk())?;
+                println!("{}", encode_unsigned(&tx));
+                Ok(())
+            }
+
+            UnsignedSubcmd::Finalize => {
+                let tx = read_unsigned_stdin(&Client::build_mint_vk())?;
+                match finalize_unsigned(tx) {
+                    Ok(v) => println!("{}", v),
+                    Err(e) => {
+                        eprintln!("{} {}", fg_red("Error:"), e);
+                        exit(1);
+                    }
+                }
+                Ok(())
+            }
+        },
+
         Subcmd::DeployContract { path } => {
             eprintln!("Trying to deploy the smart contract in {:?}", path);
             let deploy_data = match create_deploy_data(&path) {
@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+use pasta_curves::group::ff::PrimeField;
+use prettytable::{format, row, Table};
+use url::Url;
+
+use darkfi::{
+    crypto::{
+        address::Address,
+        keypair::{PublicKey, ViewKey},
+    },
+    node::{scanner::RpcBlockSource, Client, WalletScanner},
+    rpc::client::RpcClient,
+    util::parse::encode_base10,
+    wallet::walletdb::init_wallet,
+    Result,
+};
+
+/// Scan the blocks finalized by the darkfid node at `endpoint` for the coins
+/// of a local wallet, importing the given view keys first. With `rescan`,
+/// the blocks are scanned again for coins from that slot.
+pub async fn scan_wallet(
+    endpoint: Url,
+    wallet_path: &str,
+    wallet_pass: &str,
+    view_keys: &[String],
+    rescan: Option<u64>,
+) -> Result<()> {
+    let wallet = init_wallet(wallet_path, wallet_pass).await?;
+    let client = Client::new(wallet).await?;
+
+    for view_key in view_keys {
+        client.wallet.put_view_key(&ViewKey::from_str(view_key)?).await?;
+    }
+
+    let source = RpcBlockSource::new(RpcClient::new(endpoint).await?);
+    let scanner = WalletScanner::new(client.wallet.clone());
+    let result = match rescan {
+        Some(slot) => scanner.rescan(&source, slot).await?,
+        None => scanner.scan(&source).await?,
+    };
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(row!["Owner", "Amount", "Token ID", "Viewed"]);
+
+    for own_coin in &result.own_coins {
+        table.add_row(row![
+            Address::from(PublicKey::from_secret(own_coin.secret)).to_string(),
+            encode_base10(own_coin.note.value, 8),
+            bs58::encode(own_coin.note.token_id.to_repr()).into_string(),
+            false
+        ]);
+    }
+
+    for viewed_coin in &result.viewed_coins {
+        table.add_row(row![
+            Address::from(viewed_coin.public).to_string(),
+            encode_base10(viewed_coin.note.value, 8),
+            bs58::encode(viewed_coin.note.token_id.to_repr()).into_string(),
+            true
+        ]);
+    }
+
+    if table.is_empty() {
+        println!("No new coins.");
+    } else {
+        println!("{}", table);
+    }
+
+    println!("Scanned up to slot {}", result.height);
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use darkfi_serial::{deserialize, serialize};
+use pasta_curves::group::ff::PrimeField;
+use prettytable::{format, row, Table};
+
+use darkfi::{
+    crypto::{
+        address::{Address, ViewableAddress},
+        keypair::SecretKey,
+        proof::VerifyingKey,
+    },
+    node::Client,
+    tx::unsigned::UnsignedTransaction,
+    util::parse::encode_base10,
+    Result,
+};
+
+/// Decode a base58 encoded unsigned transaction, checking that its outputs
+/// match their plaintext and are proven.
+pub fn decode_unsigned(encoded: &str, mint_vk: &VerifyingKey) -> Result<UnsignedTransaction> {
+    let bytes = bs58::decode(encoded.trim()).into_vec()?;
+    let tx: UnsignedTransaction = deserialize(&bytes)?;
+    tx.check(mint_vk)?;
+    Ok(tx)
+}
+
+/// Encode an unsigned transaction as base58.
+pub fn encode_unsigned(tx: &UnsignedTransaction) -> String {
+    bs58::encode(serialize(tx)).into_string()
+}
+
+/// Print the inputs and outputs of an unsigned transaction.
+pub fn inspect_unsigned(tx: &UnsignedTransaction) {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(row!["Input", "Owner", "Amount", "Token ID", "Proven", "Signed"]);
+
+    for (i, input) in tx.inputs.iter().enumerate() {
+        table.add_row(row![
+            i,
+            Address::from(input.public).to_string(),
+            encode_base10(input.note.value, 8),
+            bs58::encode(input.note.token_id.to_repr()).into_string(),
+            input.proof.is_some(),
+            input.signature.is_some()
+        ]);
+    }
+
+    println!("{}", table);
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(row!["Output", "Recipient", "Amount", "Token ID"]);
+
+    for (i, output) in tx.outputs.iter().enumerate() {
+        let recipient = match output.view_public {
+            Some(view_public) => ViewableAddress::new(output.public, view_public).to_string(),
+            None => Address::from(output.public).to_string(),
+        };
+
+        table.add_row(row![
+            i,
+            recipient,
+            encode_base10(output.note.value, 8),
+            bs58::encode(output.note.token_id.to_repr()).into_string()
+        ]);
+    }
+
+    println!("{}", table);
+
+    let total: u64 = tx.inputs.iter().map(|x| x.note.value).sum();
+    println!("Total spent: {}", encode_base10(total, 8));
+
+    if tx.is_complete() {
+        println!("Complete, ready to be finalized.");
+    } else {
+        println!("Incomplete, needs more proofs or signatures.");
+    }
+}
+
+/// Read a base58 encoded secret key from a file.
+pub fn read_secret(path: &Path) -> Result<SecretKey> {
+    let contents = read_to_string(path)?;
+    SecretKey::from_str(contents.trim())
+}
+
+/// Prove and sign the inputs of `tx` owned by `secret`, returning the
+/// number of inputs proven or signed.
+pub fn sign_unsigned(
+    tx: &mut UnsignedTransaction,
+    mint_vk: &VerifyingKey,
+    secret: SecretKey,
+) -> Result<usize> {
+    let burn_pk = Client::build_burn_pk();
+    tx.sign(mint_vk, &burn_pk, secret)
+}
+
+/// Combine the copies of an unsigned transaction found in `files`.
+pub fn combine_unsigned(files: &[PathBuf], mint_vk: &VerifyingKey) -> Result<UnsignedTransaction> {
+    let mut txs = vec![];
+    for file in files {
+        txs.push(decode_unsigned(&read_to_string(file)?, mint_vk)?);
+    }
+
+    let mut tx = txs.remove(0);
+    for other in &txs {
+        tx.combine(other)?;
+    }
+
+    Ok(tx)
+}
+
+/// Finalize a complete unsigned transaction, returning the base58 encoded
+/// transaction to broadcast.
+pub fn finalize_unsigned(tx: UnsignedTransaction) -> Result<String> {
+    let tx = tx.finalize()?;
+    Ok(bs58::encode(serialize(&tx)).into_string())
+}
@@ -1,13 +1,17 @@
 use clap::{Parser, Subcommand};
+use futures::{select, FutureExt};
 use log::info;
-use serde_json::json;
+use serde_json::{json, Value};
 use simplelog::{ColorChoice, TermLogger, TerminalMode};
 use url::Url;
 
 use darkfi::{
     cli_desc,
     rpc::{client::RpcClient, jsonrpc::JsonRequest},
-    util::cli::{get_log_config, get_log_level},
+    util::{
+        async_util::sleep,
+        cli::{get_log_config, get_log_level},
+    },
     Result,
 };
 
@@ -35,16 +39,37 @@ enum Subcmd {
     /// Sync fud folder contents and signal network for record changes
     Sync,
 
-    /// Retrieve provided file name from the fud network
+    /// Retrieve provided file hash from the fud network
     Get {
         #[clap(short, long)]
-        /// File name
+        /// File hash
+        file: String,
+    },
+
+    /// List active file transfers
+    Transfers,
+
+    /// Pause provided file hash transfer, it resumes by retrieving the file again
+    Pause {
+        #[clap(short, long)]
+        /// File hash
         file: String,
     },
+
+    /// Cancel provided file hash transfer
+    Cancel {
+        #[clap(short, long)]
+        /// File hash
+        file: String,
+    },
+
+    /// List seeded files and the peers they were served to
+    Seeding,
 }
 
 struct Fu {
     pub rpc_client: RpcClient,
+    pub endpoint: Url,
 }
 
 impl Fu {
@@ -57,7 +82,7 @@ impl Fu {
         let rep = self.rpc_client.request(req).await?;
 
         // Extract response
-        let content = rep[0].as_array().unwrap();
+        let content = rep[0].as_object().unwrap();
         let new = rep[1].as_array().unwrap();
         let deleted = rep[2].as_array().unwrap();
 
@@ -66,8 +91,8 @@ impl Fu {
         if content.is_empty() {
             info!("No file records exists in DHT.");
         } else {
-            for name in content {
-                info!("\t{}", name.as_str().unwrap());
+            for (name, hash) in content {
+                info!("\t{} {}", hash.as_str().unwrap(), name);
             }
         }
         info!("------------------------------");
@@ -103,14 +128,108 @@ impl Fu {
     }
 
     async fn get(&self, file: String) -> Result<()> {
+        // Progress is polled on a separate connection, while the
+        // get request blocks until the file is retrieved.
+        let progress_client = RpcClient::new(self.endpoint.clone()).await?;
         let req = JsonRequest::new("get", json!([file]));
+
+        let rep = select! {
+            rep = self.rpc_client.request(req).fuse() => rep,
+            rep = print_progress(&progress_client, &file).fuse() => rep,
+        };
+        progress_client.close().await?;
+
+        let path = rep?;
+        info!("File waits you at: {}", path.as_str().unwrap());
+        Ok(())
+    }
+
+    async fn transfers(&self) -> Result<()> {
+        let req = JsonRequest::new("transfers", json!([]));
+        let rep = self.rpc_client.request(req).await?;
+        let transfers = rep.as_array().unwrap();
+
+        info!("----------Transfers-----------");
+        if transfers.is_empty() {
+            info!("No active transfers.");
+        } else {
+            for transfer in transfers {
+                info!("\t{}", transfer_info(transfer));
+            }
+        }
+        info!("------------------------------");
+
+        Ok(())
+    }
+
+    async fn pause(&self, file: String) -> Result<()> {
+        let req = JsonRequest::new("pause", json!([file]));
+        self.rpc_client.request(req).await?;
+        info!("Transfer paused!");
+        Ok(())
+    }
+
+    async fn cancel(&self, file: String) -> Result<()> {
+        let req = JsonRequest::new("cancel", json!([file]));
+        self.rpc_client.request(req).await?;
+        info!("Transfer cancelled!");
+        Ok(())
+    }
+
+    async fn seeding(&self) -> Result<()> {
+        let req = JsonRequest::new("seeding", json!([]));
         let rep = self.rpc_client.request(req).await?;
-        let path = rep.as_str().unwrap();
-        info!("File waits you at: {}", path);
+        let files = rep.as_array().unwrap();
+
+        info!("----------Seeding-------------");
+        if files.is_empty() {
+            info!("No files are being seeded.");
+        } else {
+            for file in files {
+                info!(
+                    "\t{} {}: served {} chunks to {} peers, {} other seeders",
+                    file["hash"].as_str().unwrap(),
+                    file["name"].as_str().unwrap(),
+                    file["chunks_served"],
+                    file["peers"],
+                    file["seeders"],
+                );
+            }
+        }
+        info!("------------------------------");
+
         Ok(())
     }
 }
 
+/// Periodically print the progress of the given file transfer.
+/// Only returns if polling fails.
+async fn print_progress(rpc_client: &RpcClient, file: &str) -> Result<Value> {
+    loop {
+        sleep(1).await;
+        let req = JsonRequest::new("transfers", json!([]));
+        let rep = rpc_client.request(req).await?;
+        for transfer in rep.as_array().unwrap() {
+            if transfer["hash"].as_str() == Some(file) {
+                info!("{}", transfer_info(transfer));
+            }
+        }
+    }
+}
+
+fn transfer_info(transfer: &Value) -> String {
+    format!(
+        "{} {} [{}]: {}/{} chunks from {} peers, {:.1} KiB/s",
+        transfer["hash"].as_str().unwrap(),
+        transfer["name"].as_str().unwrap(),
+        transfer["state"].as_str().unwrap(),
+        transfer["chunks_done"],
+        transfer["chunks_total"],
+        transfer["peers"],
+        transfer["throughput"].as_f64().unwrap() / 1024.0,
+    )
+}
+
 #[async_std::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
@@ -119,13 +238,17 @@ async fn main() -> Result<()> {
     let log_config = get_log_config();
     TermLogger::init(log_level, log_config, TerminalMode::Mixed, ColorChoice::Auto)?;
 
-    let rpc_client = RpcClient::new(args.endpoint).await?;
-    let fu = Fu { rpc_client };
+    let rpc_client = RpcClient::new(args.endpoint.clone()).await?;
+    let fu = Fu { rpc_client, endpoint: args.endpoint };
 
     match args.command {
         Subcmd::List => fu.list().await,
         Subcmd::Sync => fu.sync().await,
         Subcmd::Get { file } => fu.get(file).await,
+        Subcmd::Transfers => fu.transfers().await,
+        Subcmd::Pause { file } => fu.pause(file).await,
+        Subcmd::Cancel { file } => fu.cancel(file).await,
+        Subcmd::Seeding => fu.seeding().await,
     }?;
 
     fu.close_connection().await
@@ -9,6 +9,11 @@ pub enum RpcError {
     KeyRemoveFail = -35111,
     WaitingNetworkError = -35112,
     FileGenerationFail = -35113,
+    FolderReadFail = -35114,
+    InvalidManifest = -35115,
+    FileFetchFail = -35116,
+    UnknownTransfer = -35117,
+    TransferStopped = -35118,
 }
 
 fn to_tuple(e: RpcError) -> (i64, String) {
@@ -19,6 +24,11 @@ fn to_tuple(e: RpcError) -> (i64, String) {
         RpcError::KeyRemoveFail => "Failed to remove key",
         RpcError::WaitingNetworkError => "Error while waiting network response.",
         RpcError::FileGenerationFail => "Failed to generate file for key",
+        RpcError::FolderReadFail => "Failed to read contents folder",
+        RpcError::InvalidManifest => "Received invalid file manifest",
+        RpcError::FileFetchFail => "Failed to retrieve file chunks",
+        RpcError::UnknownTransfer => "Did not find transfer",
+        RpcError::TransferStopped => "Transfer was paused or cancelled",
     };
 
     (e as i64, msg.to_string())
@@ -1,8 +1,18 @@
-use std::{collections::HashSet, fs, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use async_std::sync::Arc;
+use async_std::{
+    future::timeout,
+    sync::{Arc, Mutex, RwLock},
+};
 use async_trait::async_trait;
-use darkfi_serial::serialize;
+use darkfi_serial::{deserialize, serialize};
+use futures::future::join_all;
 use log::{debug, error, info, warn};
 use serde_json::{json, Value};
 use structopt_toml::{serde::Deserialize, structopt::StructOpt, StructOptToml};
@@ -12,6 +22,7 @@ use darkfi::{
     async_daemonize, cli_desc,
     dht::{waiting_for_response, Dht, DhtPtr},
     net,
+    net::{ChannelPtr, MessageSubscription},
     rpc::{
         jsonrpc::{
             ErrorCode::{InvalidParams, MethodNotFound},
@@ -20,15 +31,29 @@ use darkfi::{
         server::{listen_and_serve, RequestHandler},
     },
     util::path::expand_path,
-    Result,
+    Error, Result,
 };
 
 mod error;
 use error::{server_error, RpcError};
 
+mod manifest;
+use manifest::{parse_hash, Manifest};
+
+mod proto;
+use proto::{ChunkReply, ChunkRequest, ProtocolFud};
+
+mod transfer;
+use transfer::{SeedStats, Transfer, TransferState};
+
 const CONFIG_FILE: &str = "fud_config.toml";
 const CONFIG_FILE_CONTENTS: &str = include_str!("../fud_config.toml");
 
+/// Directory inside the contents folder holding partial downloads
+const PARTIAL_DIR: &str = ".partial";
+/// Seconds to wait for a peer to reply to a chunk request
+const CHUNK_TIMEOUT: u64 = 30;
+
 #[derive(Clone, Debug, Deserialize, StructOpt, StructOptToml)]
 #[serde(default)]
 #[structopt(name = "fud", about = cli_desc!())]
@@ -89,11 +114,26 @@ pub struct Fud {
 
     /// Path to the contents directory
     folder: PathBuf,
+
+    /// Manifests of the files we share, keyed by file hash
+    files: RwLock<HashMap<blake3::Hash, Manifest>>,
+
+    /// Files being retrieved from the network, keyed by file hash
+    transfers: Mutex<HashMap<blake3::Hash, Transfer>>,
+
+    /// Statistics of the files we serve, keyed by file hash
+    seeding: Mutex<HashMap<blake3::Hash, SeedStats>>,
 }
 
 impl Fud {
     pub async fn new(dht: DhtPtr, folder: PathBuf) -> Result<Self> {
-        Ok(Self { dht, folder })
+        Ok(Self {
+            dht,
+            folder,
+            files: RwLock::new(HashMap::new()),
+            transfers: Mutex::new(HashMap::new()),
+            seeding: Mutex::new(HashMap::new()),
+        })
     }
 
     /// Initialize fud dht state by reading the contents folder and generating
@@ -105,24 +145,14 @@ impl Fud {
             fs::create_dir_all(&self.folder)?;
         }
 
-        let entries = fs::read_dir(&self.folder).unwrap();
-        {
-            let mut lock = self.dht.write().await;
-
-            // Sync lookup map with network
-            if let Err(e) = lock.sync_lookup_map().await {
-                error!("Failed to sync lookup map: {}", e);
-            }
+        // Sync lookup map with network
+        if let Err(e) = self.dht.write().await.sync_lookup_map().await {
+            error!("Failed to sync lookup map: {}", e);
+        }
 
-            for entry in entries {
-                let e = entry.unwrap();
-                let name = String::from(e.file_name().to_str().unwrap());
-                info!("Entry: {}", name);
-                let key_hash = blake3::hash(&serialize(&name));
-                let value: Vec<u8> = std::fs::read(e.path()).unwrap();
-                if let Err(e) = lock.insert(key_hash, value).await {
-                    error!("Failed to insert key: {}", e);
-                }
+        for path in self.folder_entries()? {
+            if let Err(e) = self.add_file(&path).await {
+                error!("Failed to insert key: {}", e);
             }
         }
 
@@ -157,39 +187,269 @@ impl Fud {
         Ok(())
     }
 
+    /// Retrieve the paths of the regular files in the contents folder.
+    /// Directories, including the partial downloads one, are skipped.
+    fn folder_entries(&self) -> Result<Vec<PathBuf>> {
+        let mut entries = vec![];
+        for entry in fs::read_dir(&self.folder)? {
+            let path = entry?.path();
+            if path.is_file() {
+                entries.push(path);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Build the manifest of the given file, store it in the dht under
+    /// the file hash, and start serving its chunks.
+    async fn add_file(&self, path: &Path) -> Result<blake3::Hash> {
+        let manifest = Manifest::from_path(path)?;
+        let file_hash = manifest.file_hash();
+        info!("Entry: {} ({})", manifest.name, file_hash);
+
+        self.dht.write().await.insert(file_hash, serialize(&manifest)).await?;
+        self.files.write().await.insert(file_hash, manifest);
+        Ok(file_hash)
+    }
+
+    /// Stop serving the file with the given hash and remove its dht record.
+    async fn remove_file(&self, file_hash: &blake3::Hash) -> Result<()> {
+        self.files.write().await.remove(file_hash);
+        self.seeding.lock().await.remove(file_hash);
+        match self.dht.write().await.remove(*file_hash).await? {
+            Some(k) => debug!("Hash key removed: {}", k),
+            None => warn!("Did not find key: {}", file_hash),
+        }
+
+        Ok(())
+    }
+
+    /// Retrieve the given file from our peers, tracking its progress in the
+    /// transfers map. Returns `None` if the transfer was paused or cancelled.
+    /// Paused transfers stay in the map until they are resumed by retrieving
+    /// the file again, or cancelled.
+    async fn fetch_file(
+        &self,
+        file_hash: &blake3::Hash,
+        manifest: &Manifest,
+    ) -> Result<Option<PathBuf>> {
+        {
+            let mut transfers = self.transfers.lock().await;
+            match transfers.get_mut(file_hash) {
+                Some(t) if t.state == TransferState::Paused && !t.running => t.resume(),
+                Some(_) => {
+                    return Err(Error::Custom(format!(
+                        "File {} is already being retrieved",
+                        file_hash
+                    )))
+                }
+                None => {
+                    transfers.insert(*file_hash, Transfer::new(manifest));
+                }
+            }
+        }
+
+        let result = self.download(file_hash, manifest).await;
+
+        let mut transfers = self.transfers.lock().await;
+        match transfers.get_mut(file_hash) {
+            Some(t) if t.state == TransferState::Paused && matches!(result, Ok(None)) => {
+                t.running = false
+            }
+            _ => {
+                transfers.remove(file_hash);
+            }
+        }
+
+        result
+    }
+
+    /// Retrieve the chunks of the given file from our peers into a partial
+    /// file, and move it into the contents folder once complete. Chunks are
+    /// split between peers and fetched in parallel, and each one is verified
+    /// against the manifest. Chunks already present in the partial file are
+    /// kept, so interrupted or paused downloads are resumed.
+    async fn download(
+        &self,
+        file_hash: &blake3::Hash,
+        manifest: &Manifest,
+    ) -> Result<Option<PathBuf>> {
+        let partial_dir = self.folder.join(PARTIAL_DIR);
+        fs::create_dir_all(&partial_dir)?;
+        let partial = partial_dir.join(file_hash.to_hex().as_str());
+
+        let file = OpenOptions::new().write(true).create(true).open(&partial)?;
+        file.set_len(manifest.size)?;
+
+        let mut missing = vec![];
+        for index in 0..manifest.chunks.len() {
+            match manifest.read_chunk(&partial, index) {
+                Ok(data) if manifest.verify_chunk(index, &data) => {}
+                _ => missing.push(index),
+            }
+        }
+
+        info!(
+            "Retrieving {} of {} chunks for file {}",
+            missing.len(),
+            manifest.chunks.len(),
+            file_hash
+        );
+
+        if let Some(transfer) = self.transfers.lock().await.get_mut(file_hash) {
+            transfer.chunks_done = manifest.chunks.len() - missing.len();
+        }
+
+        let p2p = self.dht.read().await.p2p.clone();
+        let channels: Vec<ChannelPtr> = p2p.channels().lock().await.values().cloned().collect();
+        if channels.is_empty() && !missing.is_empty() {
+            return Err(Error::NetworkNotConnected)
+        }
+
+        let mut peers = vec![];
+        for channel in channels {
+            let reply_sub = channel.subscribe_msg::<ChunkReply>().await?;
+            peers.push((channel, reply_sub));
+        }
+
+        // Each round, a chunk is requested from a different peer, so every
+        // peer gets asked for a chunk before we give up on it.
+        for round in 0..peers.len() {
+            if missing.is_empty() || self.transfer_state(file_hash).await != TransferState::Active {
+                break
+            }
+
+            let mut assigned = vec![vec![]; peers.len()];
+            for index in missing {
+                assigned[(index + round) % peers.len()].push(index);
+            }
+
+            let jobs = peers.iter().zip(assigned).map(|((channel, reply_sub), indexes)| {
+                self.fetch_chunks(channel, reply_sub, file_hash, manifest, &partial, indexes)
+            });
+            missing = join_all(jobs).await.into_iter().flatten().collect();
+        }
+
+        for (_, reply_sub) in peers {
+            reply_sub.unsubscribe().await;
+        }
+
+        match self.transfer_state(file_hash).await {
+            TransferState::Active => {}
+            TransferState::Paused => {
+                info!("Transfer of file {} paused", file_hash);
+                return Ok(None)
+            }
+            TransferState::Cancelled => {
+                info!("Transfer of file {} cancelled", file_hash);
+                fs::remove_file(&partial)?;
+                return Ok(None)
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(Error::Custom(format!("Failed to retrieve {} chunks", missing.len())))
+        }
+
+        let path = self.folder.join(&manifest.name);
+        fs::rename(&partial, &path)?;
+        Ok(Some(path))
+    }
+
+    /// Retrieve the given chunks from a peer, writing the valid ones into the
+    /// partial file. Returns the indexes of the chunks that weren't retrieved.
+    async fn fetch_chunks(
+        &self,
+        channel: &ChannelPtr,
+        reply_sub: &MessageSubscription<ChunkReply>,
+        file_hash: &blake3::Hash,
+        manifest: &Manifest,
+        partial: &Path,
+        indexes: Vec<usize>,
+    ) -> Vec<usize> {
+        let mut failed = vec![];
+        for (i, index) in indexes.iter().enumerate() {
+            if self.transfer_state(file_hash).await != TransferState::Active {
+                failed.extend_from_slice(&indexes[i..]);
+                break
+            }
+
+            let data = match fetch_chunk(channel, reply_sub, file_hash, *index).await {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("Failed to retrieve chunk {} from {}: {}", index, channel.address(), e);
+                    failed.push(*index);
+                    continue
+                }
+            };
+
+            if !manifest.verify_chunk(*index, &data) {
+                warn!("Peer {} sent an invalid chunk {}", channel.address(), index);
+                failed.push(*index);
+                continue
+            }
+
+            if let Err(e) = manifest.write_chunk(partial, *index, &data) {
+                error!("Failed to write chunk {}: {}", index, e);
+                failed.push(*index);
+                continue
+            }
+
+            debug!("Retrieved chunk {}/{}", index + 1, manifest.chunks.len());
+            if let Some(transfer) = self.transfers.lock().await.get_mut(file_hash) {
+                transfer.chunk_done(channel.address(), data.len());
+            }
+        }
+
+        failed
+    }
+
+    /// State of the transfer of the given file. Transfers that are no
+    /// longer tracked are reported as cancelled.
+    async fn transfer_state(&self, file_hash: &blake3::Hash) -> TransferState {
+        match self.transfers.lock().await.get(file_hash) {
+            Some(transfer) => transfer.state,
+            None => TransferState::Cancelled,
+        }
+    }
+
     // RPCAPI:
     // Returns all folder contents, with file changes.
+    // Shared files are returned as a map of file names to file hashes.
     // --> {"jsonrpc": "2.0", "method": "list", "params": [], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "[[files],[new],[deleted]", "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": "[{files},[new],[deleted]", "id": 1}
     pub async fn list(&self, id: Value, _params: &[Value]) -> JsonResult {
-        let mut content = HashSet::new();
+        let mut content = HashMap::new();
         let mut new = HashSet::new();
         let mut deleted = HashSet::new();
 
-        let entries = fs::read_dir(&self.folder).unwrap();
-        let records = self.dht.read().await.map.clone();
-        let mut entries_hashes = HashSet::new();
+        let entries = match self.folder_entries() {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Failed to read contents folder: {}", e);
+                return server_error(RpcError::FolderReadFail, id)
+            }
+        };
+
+        let files = self.files.read().await.clone();
+        let names: HashSet<&String> = files.values().map(|m| &m.name).collect();
 
         // We iterate files for new records
-        for entry in entries {
-            let e = entry.unwrap();
-            let name = String::from(e.file_name().to_str().unwrap());
-            let key_hash = blake3::hash(&serialize(&name));
-            entries_hashes.insert(key_hash);
-
-            if records.contains_key(&key_hash) {
-                content.insert(name.clone());
-            } else {
+        for path in entries {
+            let name = String::from(path.file_name().unwrap().to_str().unwrap());
+            if !names.contains(&name) {
                 new.insert(name);
             }
         }
 
         // We check records for removed files
-        for key in records.keys() {
-            if entries_hashes.contains(key) {
-                continue
+        for (file_hash, manifest) in &files {
+            if self.folder.join(&manifest.name).is_file() {
+                content.insert(manifest.name.clone(), file_hash.to_string());
+            } else {
+                deleted.insert(file_hash.to_string());
             }
-            deleted.insert(key.to_string());
         }
 
         JsonResponse::new(json!((content, new, deleted)), id).into()
@@ -202,53 +462,42 @@ impl Fud {
     pub async fn sync(&self, id: Value, _params: &[Value]) -> JsonResult {
         info!("Sync process started");
 
-        let entries = fs::read_dir(&self.folder).unwrap();
-        {
-            let mut lock = self.dht.write().await;
-            let records = lock.map.clone();
-            let mut entries_hashes = HashSet::new();
-
-            // We iterate files for new records
-            for entry in entries {
-                let e = entry.unwrap();
-                let name = String::from(e.file_name().to_str().unwrap());
-                info!("Entry: {}", name);
-                let key_hash = blake3::hash(&serialize(&name));
-                entries_hashes.insert(key_hash);
-
-                if records.contains_key(&key_hash) {
+        let entries = match self.folder_entries() {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Failed to read contents folder: {}", e);
+                return server_error(RpcError::FolderReadFail, id)
+            }
+        };
+
+        // We check records for removed or modified files
+        let files = self.files.read().await.clone();
+        let mut known = HashSet::new();
+        for (file_hash, manifest) in files {
+            match fs::metadata(self.folder.join(&manifest.name)) {
+                Ok(m) if m.is_file() && m.len() == manifest.size => {
+                    known.insert(manifest.name);
                     continue
                 }
+                _ => {}
+            }
 
-                let value: Vec<u8> = std::fs::read(e.path()).unwrap();
-                if let Err(e) = lock.insert(key_hash, value).await {
-                    error!("Failed to insert key: {}", e);
-                    return server_error(RpcError::KeyInsertFail, id)
-                }
+            if let Err(e) = self.remove_file(&file_hash).await {
+                error!("Failed to remove key: {}", e);
+                return server_error(RpcError::KeyRemoveFail, id)
             }
+        }
 
-            // We check records for removed files
-            let records = lock.map.clone();
-            for key in records.keys() {
-                if entries_hashes.contains(key) {
-                    continue
-                }
+        // We iterate files for new records
+        for path in entries {
+            let name = String::from(path.file_name().unwrap().to_str().unwrap());
+            if known.contains(&name) {
+                continue
+            }
 
-                let result = lock.remove(*key).await;
-                match result {
-                    Ok(option) => match option {
-                        Some(k) => {
-                            debug!("Hash key removed: {}", k);
-                        }
-                        None => {
-                            warn!("Did not find key: {}", key);
-                        }
-                    },
-                    Err(e) => {
-                        error!("Failed to remove key: {}", e);
-                        return server_error(RpcError::KeyRemoveFail, id)
-                    }
-                }
+            if let Err(e) = self.add_file(&path).await {
+                error!("Failed to insert key: {}", e);
+                return server_error(RpcError::KeyInsertFail, id)
             }
         }
 
@@ -256,76 +505,179 @@ impl Fud {
     }
 
     // RPCAPI:
-    // Checks if provided key exists and retrieve it from the local map or queries the network.
-    // Returns key or not found message.
-    // --> {"jsonrpc": "2.0", "method": "get", "params": ["name"], "id": 1}
+    // Retrieves the file with the provided hash, from the contents folder or from the network.
+    // Returns the file path or not found message.
+    // --> {"jsonrpc": "2.0", "method": "get", "params": ["file_hash"], "id": 1}
     // <-- {"jsonrpc": "2.0", "result": "path", "id": 1}
     async fn get(&self, id: Value, params: &[Value]) -> JsonResult {
         if params.len() != 1 || !params[0].is_string() {
             return JsonError::new(InvalidParams, None, id).into()
         }
 
-        let key = params[0].as_str().unwrap().to_string();
-        let key_hash = blake3::hash(&serialize(&key));
+        let file_hash = match parse_hash(params[0].as_str().unwrap()) {
+            Some(v) => v,
+            None => return JsonError::new(InvalidParams, None, id).into(),
+        };
 
-        // We execute this sequence to prevent lock races between threads
-        // Verify key exists
-        let exists = self.dht.read().await.contains_key(key_hash);
-        if exists.is_none() {
-            info!("Did not find key: {}", key);
-            return server_error(RpcError::UnknownKey, id)
+        if let Some(manifest) = self.files.read().await.get(&file_hash) {
+            return JsonResponse::new(json!(self.folder.join(&manifest.name)), id).into()
         }
 
-        // Check if key is local or should query network
-        let path = self.folder.join(key.clone());
-        let local = exists.unwrap();
-        if local {
-            match self.dht.read().await.get(key_hash) {
-                Some(_) => return JsonResponse::new(json!(path), id).into(),
-                None => {
-                    info!("Did not find key: {}", key);
-                    return server_error(RpcError::UnknownKey, id)
-                }
-            }
+        // Verify key exists
+        if self.dht.read().await.contains_key(file_hash).is_none() {
+            info!("Did not find key: {}", file_hash);
+            return server_error(RpcError::UnknownKey, id)
         }
 
-        info!("Key doesn't exist locally, querring network...");
-        if let Err(e) = self.dht.read().await.request_key(key_hash).await {
+        info!("File doesn't exist locally, querying network for its manifest...");
+        if let Err(e) = self.dht.read().await.request_key(file_hash).await {
             error!("Failed to query key: {}", e);
             return server_error(RpcError::QueryFailed, id)
         }
 
         info!("Waiting response...");
-        match waiting_for_response(self.dht.clone()).await {
-            Ok(response) => {
-                match response {
-                    Some(resp) => {
-                        info!("Key found!");
-                        // Optionally, we insert the key to our local map
-                        if let Err(e) =
-                            self.dht.write().await.insert(resp.key, resp.value.clone()).await
-                        {
-                            error!("Failed to insert key: {}", e);
-                            return server_error(RpcError::KeyInsertFail, id)
-                        }
-
-                        if let Err(e) = std::fs::write(path.clone(), resp.value) {
-                            error!("Failed to generate file for key: {}", e);
-                            return server_error(RpcError::FileGenerationFail, id)
-                        }
-                        JsonResponse::new(json!(path), id).into()
-                    }
-                    None => {
-                        info!("Did not find key: {}", key);
-                        server_error(RpcError::UnknownKey, id)
-                    }
-                }
+        let resp = match waiting_for_response(self.dht.clone()).await {
+            Ok(Some(resp)) if resp.key == file_hash => resp,
+            Ok(_) => {
+                info!("Did not find key: {}", file_hash);
+                return server_error(RpcError::UnknownKey, id)
             }
             Err(e) => {
                 error!("Error while waiting network response: {}", e);
-                server_error(RpcError::WaitingNetworkError, id)
+                return server_error(RpcError::WaitingNetworkError, id)
+            }
+        };
+
+        let manifest: Manifest = match deserialize(&resp.value) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Failed to deserialize manifest: {}", e);
+                return server_error(RpcError::InvalidManifest, id)
+            }
+        };
+
+        if !manifest.is_valid() || manifest.file_hash() != file_hash {
+            error!("Received manifest doesn't match file hash {}", file_hash);
+            return server_error(RpcError::InvalidManifest, id)
+        }
+
+        // We don't overwrite existing files
+        if self.folder.join(&manifest.name).exists() {
+            error!("File {} already exists in the contents folder", manifest.name);
+            return server_error(RpcError::FileGenerationFail, id)
+        }
+
+        let path = match self.fetch_file(&file_hash, &manifest).await {
+            Ok(Some(v)) => v,
+            Ok(None) => return server_error(RpcError::TransferStopped, id),
+            Err(e) => {
+                error!("Failed to retrieve file {}: {}", file_hash, e);
+                return server_error(RpcError::FileFetchFail, id)
+            }
+        };
+
+        info!("File retrieved!");
+        // We start serving the file ourselves
+        if let Err(e) = self.dht.write().await.insert(file_hash, resp.value).await {
+            error!("Failed to insert key: {}", e);
+            return server_error(RpcError::KeyInsertFail, id)
+        }
+        self.files.write().await.insert(file_hash, manifest);
+
+        JsonResponse::new(json!(path), id).into()
+    }
+
+    // RPCAPI:
+    // Returns the state of the files being retrieved from the network.
+    // --> {"jsonrpc": "2.0", "method": "transfers", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": [{"hash": "...", "name": "...", "state": "active", ...}], "id": 1}
+    async fn transfers(&self, id: Value, _params: &[Value]) -> JsonResult {
+        let transfers: Vec<Value> =
+            self.transfers.lock().await.iter().map(|(k, v)| v.to_json(k)).collect();
+        JsonResponse::new(json!(transfers), id).into()
+    }
+
+    // RPCAPI:
+    // Pauses the transfer of the file with the provided hash.
+    // The transfer is resumed by retrieving the file again.
+    // --> {"jsonrpc": "2.0", "method": "pause", "params": ["file_hash"], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
+    async fn pause(&self, id: Value, params: &[Value]) -> JsonResult {
+        self.set_transfer_state(id, params, TransferState::Paused).await
+    }
+
+    // RPCAPI:
+    // Cancels the transfer of the file with the provided hash,
+    // removing its partially retrieved data.
+    // --> {"jsonrpc": "2.0", "method": "cancel", "params": ["file_hash"], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
+    async fn cancel(&self, id: Value, params: &[Value]) -> JsonResult {
+        self.set_transfer_state(id, params, TransferState::Cancelled).await
+    }
+
+    async fn set_transfer_state(
+        &self,
+        id: Value,
+        params: &[Value],
+        state: TransferState,
+    ) -> JsonResult {
+        if params.len() != 1 || !params[0].is_string() {
+            return JsonError::new(InvalidParams, None, id).into()
+        }
+
+        let file_hash = match parse_hash(params[0].as_str().unwrap()) {
+            Some(v) => v,
+            None => return JsonError::new(InvalidParams, None, id).into(),
+        };
+
+        let mut transfers = self.transfers.lock().await;
+        match transfers.get_mut(&file_hash) {
+            // A stopped transfer has no task left to clean up after itself
+            Some(transfer) if !transfer.running && state == TransferState::Cancelled => {
+                transfers.remove(&file_hash);
+                let partial = self.folder.join(PARTIAL_DIR).join(file_hash.to_hex().as_str());
+                if let Err(e) = fs::remove_file(&partial) {
+                    warn!("Failed to remove partial file {:?}: {}", partial, e);
+                }
             }
+            Some(transfer) => transfer.state = state,
+            None => return server_error(RpcError::UnknownTransfer, id),
+        }
+
+        JsonResponse::new(json!(true), id).into()
+    }
+
+    // RPCAPI:
+    // Returns the files we are serving, along with how many peers retrieved
+    // chunks of them from us, and how many other nodes hold them.
+    // --> {"jsonrpc": "2.0", "method": "seeding", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": [{"hash": "...", "name": "...", "peers": 2, ...}], "id": 1}
+    async fn seeding(&self, id: Value, _params: &[Value]) -> JsonResult {
+        let files = self.files.read().await.clone();
+        let seeding = self.seeding.lock().await;
+        let dht = self.dht.read().await;
+
+        let mut result = vec![];
+        for (file_hash, manifest) in files {
+            let (peers, chunks, bytes) = match seeding.get(&file_hash) {
+                Some(s) => (s.peers.len(), s.chunks, s.bytes),
+                None => (0, 0, 0),
+            };
+
+            // The lookup map includes ourselves
+            let seeders = dht.lookup.get(&file_hash).map_or(0, |s| s.len().saturating_sub(1));
+
+            result.push(json!({
+                "hash": file_hash.to_string(),
+                "name": manifest.name,
+                "peers": peers,
+                "chunks_served": chunks,
+                "bytes_served": bytes,
+                "seeders": seeders,
+            }));
         }
+
+        JsonResponse::new(json!(result), id).into()
     }
 
     // RPCAPI:
@@ -359,6 +711,10 @@ impl RequestHandler for Fud {
             Some("list") => return self.list(req.id, params).await,
             Some("sync") => return self.sync(req.id, params).await,
             Some("get") => return self.get(req.id, params).await,
+            Some("transfers") => return self.transfers(req.id, params).await,
+            Some("pause") => return self.pause(req.id, params).await,
+            Some("cancel") => return self.cancel(req.id, params).await,
+            Some("seeding") => return self.seeding(req.id, params).await,
             Some("ping") => return self.pong(req.id, params).await,
             Some("get_info") => return self.get_info(req.id, params).await,
             Some(_) | None => return JsonError::new(MethodNotFound, None, req.id).into(),
@@ -366,6 +722,24 @@ impl RequestHandler for Fud {
     }
 }
 
+/// Request a chunk from a peer and wait for its reply.
+async fn fetch_chunk(
+    channel: &ChannelPtr,
+    reply_sub: &MessageSubscription<ChunkReply>,
+    file_hash: &blake3::Hash,
+    index: usize,
+) -> Result<Vec<u8>> {
+    channel.send(ChunkRequest { file: *file_hash, index: index as u64 }).await?;
+
+    // Replies to other downloads on the same channel are skipped
+    loop {
+        let reply = timeout(Duration::from_secs(CHUNK_TIMEOUT), reply_sub.receive()).await??;
+        if reply.file == *file_hash && reply.index == index as u64 {
+            return Ok(reply.data.clone())
+        }
+    }
+}
+
 async_daemonize!(realmain);
 async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
     // We use this handler to block this function after detaching all
@@ -400,6 +774,16 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
     let fud = Fud::new(dht.clone(), folder).await?;
     let fud = Arc::new(fud);
 
+    // Register fud protocol, serving file chunks to our peers
+    let registry = p2p.protocol_registry();
+    let _fud = fud.clone();
+    registry
+        .register(net::SESSION_ALL, move |channel, p2p| {
+            let fud = _fud.clone();
+            async move { ProtocolFud::init(channel, fud, p2p).await.unwrap() }
+        })
+        .await;
+
     // JSON-RPC server
     info!("Starting JSON-RPC server");
     ex.spawn(listen_and_serve(args.rpc_listen, fud.clone())).detach();
@@ -0,0 +1,233 @@
+use std::{
+    ffi::OsStr,
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use darkfi_serial::{SerialDecodable, SerialEncodable};
+
+use darkfi::{Error, Result};
+
+/// Size of a file chunk, in bytes
+pub const CHUNK_SIZE: usize = 256 * 1024;
+
+/// A `Manifest` describes a shared file as an ordered list of chunk hashes.
+/// Files are addressed by [`Manifest::file_hash`], which commits to the file
+/// size and to the Merkle root of its chunks, so each chunk retrieved from
+/// the network can be verified on its own.
+#[derive(Clone, Debug, SerialEncodable, SerialDecodable)]
+pub struct Manifest {
+    /// File name
+    pub name: String,
+    /// File size, in bytes
+    pub size: u64,
+    /// Ordered chunk hashes
+    pub chunks: Vec<blake3::Hash>,
+}
+
+impl Manifest {
+    /// Build the manifest of the file at the given path. The file is read
+    /// chunk by chunk, so it never has to fit in memory.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let name = match path.file_name().and_then(OsStr::to_str) {
+            Some(v) => v.to_string(),
+            None => return Err(Error::Custom(format!("Invalid file name: {:?}", path))),
+        };
+
+        let mut file = File::open(path)?;
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut size = 0;
+        let mut chunks = vec![];
+        loop {
+            let n = read_full(&mut file, &mut buf)?;
+            if n == 0 {
+                break
+            }
+
+            size += n as u64;
+            chunks.push(blake3::hash(&buf[..n]));
+        }
+
+        Ok(Self { name, size, chunks })
+    }
+
+    /// Content address of the file, committing to its size and the Merkle
+    /// root of its chunk hashes.
+    pub fn file_hash(&self) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.size.to_le_bytes());
+        hasher.update(merkle_root(&self.chunks).as_bytes());
+        hasher.finalize()
+    }
+
+    /// Check that the manifest is well formed: the chunk count must match
+    /// the file size, and the name must be a plain, non-hidden file name.
+    pub fn is_valid(&self) -> bool {
+        // `size` comes from the network, so it must not overflow here
+        let expected_chunks = self.size.div_ceil(CHUNK_SIZE as u64);
+        if self.chunks.len() as u64 != expected_chunks {
+            return false
+        }
+
+        !self.name.starts_with('.') &&
+            Path::new(&self.name).file_name() == Some(OsStr::new(&self.name))
+    }
+
+    /// Byte offset of the chunk with the given index in the file.
+    /// Fails if the index is out of range.
+    fn chunk_offset(&self, index: usize) -> Result<u64> {
+        if index >= self.chunks.len() {
+            return Err(Error::Custom(format!(
+                "Chunk index {} out of range ({} chunks)",
+                index,
+                self.chunks.len()
+            )))
+        }
+
+        match (index as u64).checked_mul(CHUNK_SIZE as u64) {
+            Some(v) if v < self.size => Ok(v),
+            _ => Err(Error::Custom(format!("Chunk index {} past the end of the file", index))),
+        }
+    }
+
+    /// Expected size of the chunk with the given index.
+    /// Fails if the index is out of range.
+    pub fn chunk_len(&self, index: usize) -> Result<usize> {
+        let offset = self.chunk_offset(index)?;
+        if index + 1 < self.chunks.len() {
+            return Ok(CHUNK_SIZE)
+        }
+
+        Ok((self.size - offset).min(CHUNK_SIZE as u64) as usize)
+    }
+
+    /// Verify the given data against the chunk hash with the given index.
+    pub fn verify_chunk(&self, index: usize, data: &[u8]) -> bool {
+        match self.chunk_len(index) {
+            Ok(len) => data.len() == len && blake3::hash(data) == self.chunks[index],
+            Err(_) => false,
+        }
+    }
+
+    /// Read the chunk with the given index from the file at the given path.
+    pub fn read_chunk(&self, path: &Path, index: usize) -> Result<Vec<u8>> {
+        let offset = self.chunk_offset(index)?;
+        let mut data = vec![0u8; self.chunk_len(index)?];
+
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    /// Write the chunk with the given index into the file at the given path.
+    pub fn write_chunk(&self, path: &Path, index: usize, data: &[u8]) -> Result<()> {
+        let offset = self.chunk_offset(index)?;
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(data)?;
+        Ok(())
+    }
+}
+
+/// Parse a hex encoded file hash.
+pub fn parse_hash(s: &str) -> Option<blake3::Hash> {
+    let bytes: [u8; 32] = hex::decode(s).ok()?.try_into().ok()?;
+    Some(blake3::Hash::from(bytes))
+}
+
+/// Compute the Merkle root of the given leaves. Odd nodes on a level are
+/// carried over to the next one.
+fn merkle_root(leaves: &[blake3::Hash]) -> blake3::Hash {
+    if leaves.is_empty() {
+        return blake3::hash(&[])
+    }
+
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = blake3::Hasher::new();
+                    hasher.update(left.as_bytes());
+                    hasher.update(right.as_bytes());
+                    hasher.finalize()
+                }
+                _ => pair[0],
+            })
+            .collect();
+    }
+
+    level[0]
+}
+
+/// Fill the buffer from the reader, stopping early only at EOF.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..])? {
+            0 => break,
+            read => n += read,
+        }
+    }
+
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() -> Result<()> {
+        let path = std::env::temp_dir().join("fud_test_manifest");
+        let content: Vec<u8> = (0..CHUNK_SIZE * 2 + 42).map(|i| i as u8).collect();
+        std::fs::write(&path, &content)?;
+
+        let manifest = Manifest::from_path(&path)?;
+        assert_eq!(manifest.size, content.len() as u64);
+        assert_eq!(manifest.chunks.len(), 3);
+        assert!(manifest.is_valid());
+        assert_eq!(manifest.chunk_len(2)?, 42);
+
+        for index in 0..manifest.chunks.len() {
+            let chunk = manifest.read_chunk(&path, index)?;
+            assert!(manifest.verify_chunk(index, &chunk));
+        }
+
+        let mut chunk = manifest.read_chunk(&path, 1)?;
+        chunk[0] ^= 1;
+        assert!(!manifest.verify_chunk(1, &chunk));
+        assert!(!manifest.verify_chunk(0, &chunk[..CHUNK_SIZE - 1]));
+
+        // The file hash is content addressed and doesn't depend on the name
+        let mut renamed = manifest.clone();
+        renamed.name = "other".to_string();
+        assert_eq!(renamed.file_hash(), manifest.file_hash());
+        assert_eq!(parse_hash(&manifest.file_hash().to_hex()), Some(manifest.file_hash()));
+
+        renamed.name = "../other".to_string();
+        assert!(!renamed.is_valid());
+
+        // Out of range indexes are rejected instead of panicking
+        assert!(manifest.chunk_len(3).is_err());
+        assert!(manifest.chunk_len(usize::MAX).is_err());
+        assert!(manifest.read_chunk(&path, 3).is_err());
+        assert!(manifest.read_chunk(&path, usize::MAX).is_err());
+        assert!(manifest.write_chunk(&path, usize::MAX, &[0]).is_err());
+        assert!(!manifest.verify_chunk(usize::MAX, &[]));
+
+        // A hostile size doesn't overflow, and a size inconsistent with
+        // the chunks doesn't underflow
+        let mut hostile = manifest.clone();
+        hostile.size = u64::MAX;
+        assert!(!hostile.is_valid());
+        hostile.size = 1;
+        assert!(hostile.chunk_len(2).is_err());
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
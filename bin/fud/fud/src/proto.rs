@@ -0,0 +1,171 @@
+use std::path::Path;
+
+use async_std::sync::Arc;
+use async_trait::async_trait;
+use darkfi_serial::{SerialDecodable, SerialEncodable};
+use log::{debug, error};
+use smol::Executor;
+
+use darkfi::{
+    net,
+    net::{
+        ChannelPtr, MessageSubscription, P2pPtr, ProtocolBase, ProtocolBasePtr,
+        ProtocolJobsManager, ProtocolJobsManagerPtr,
+    },
+    Result,
+};
+
+use crate::{manifest::Manifest, Fud};
+
+/// Request for a chunk of a file, addressed by its file hash
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct ChunkRequest {
+    /// File hash
+    pub file: blake3::Hash,
+    /// Chunk index
+    pub index: u64,
+}
+
+impl net::Message for ChunkRequest {
+    fn name() -> &'static str {
+        "chunkrequest"
+    }
+}
+
+/// Response to a [`ChunkRequest`]. Data is empty if the peer doesn't
+/// hold the requested chunk.
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct ChunkReply {
+    /// File hash
+    pub file: blake3::Hash,
+    /// Chunk index
+    pub index: u64,
+    /// Chunk data
+    pub data: Vec<u8>,
+}
+
+impl net::Message for ChunkReply {
+    fn name() -> &'static str {
+        "chunkreply"
+    }
+
+    fn decode_payload(payload: Vec<u8>) -> Result<Self> {
+        let ((file, index), data) = net::decode_blob_payload(payload, Self::decode_limits())?;
+        Ok(Self { file, index, data })
+    }
+}
+
+pub struct ProtocolFud {
+    channel: ChannelPtr,
+    request_sub: MessageSubscription<ChunkRequest>,
+    jobsman: ProtocolJobsManagerPtr,
+    fud: Arc<Fud>,
+}
+
+impl ProtocolFud {
+    pub async fn init(channel: ChannelPtr, fud: Arc<Fud>, _p2p: P2pPtr) -> Result<ProtocolBasePtr> {
+        debug!("Adding ProtocolFud to the protocol registry");
+        let msg_subsystem = channel.get_message_subsystem();
+        msg_subsystem.add_dispatch::<ChunkRequest>().await;
+        // Replies are consumed by the downloads subscribing to them
+        msg_subsystem.add_dispatch::<ChunkReply>().await;
+
+        let request_sub = channel.subscribe_msg::<ChunkRequest>().await?;
+
+        Ok(Arc::new(Self {
+            channel: channel.clone(),
+            request_sub,
+            jobsman: ProtocolJobsManager::new("FudProtocol", channel),
+            fud,
+        }))
+    }
+
+    async fn handle_receive_request(self: Arc<Self>) -> Result<()> {
+        debug!("ProtocolFud::handle_receive_request() [START]");
+        loop {
+            let request = match self.request_sub.receive().await {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("ProtocolFud::handle_receive_request(): recv fail: {}", e);
+                    continue
+                }
+            };
+
+            debug!("ProtocolFud::handle_receive_request() received {:?}", request);
+
+            let manifest = self.fud.files.read().await.get(&request.file).cloned();
+            let data = match manifest {
+                Some(m) => serve_chunk(&m, &self.fud.folder, request.index),
+                None => vec![],
+            };
+
+            if !data.is_empty() {
+                let mut seeding = self.fud.seeding.lock().await;
+                let stats = seeding.entry(request.file).or_default();
+                stats.chunk_served(self.channel.address(), data.len());
+            }
+
+            let response = ChunkReply { file: request.file, index: request.index, data };
+            if let Err(e) = self.channel.send(response).await {
+                error!("ProtocolFud::handle_receive_request(): channel send fail: {}", e)
+            };
+        }
+    }
+}
+
+/// Read the requested chunk of the file described by `manifest` from
+/// `folder`. Returns empty data if the index is out of range or the chunk
+/// can't be read. Chunks are verified before being served, in case the
+/// file changed on disk since we indexed it.
+fn serve_chunk(manifest: &Manifest, folder: &Path, index: u64) -> Vec<u8> {
+    let index = match usize::try_from(index) {
+        Ok(v) if v < manifest.chunks.len() => v,
+        _ => return vec![],
+    };
+
+    match manifest.read_chunk(&folder.join(&manifest.name), index) {
+        Ok(v) if manifest.verify_chunk(index, &v) => v,
+        _ => vec![],
+    }
+}
+
+#[async_trait]
+impl ProtocolBase for ProtocolFud {
+    async fn start(self: Arc<Self>, executor: Arc<Executor<'_>>) -> Result<()> {
+        debug!("ProtocolFud::start() [START]");
+        self.jobsman.clone().start(executor.clone());
+        self.jobsman.clone().spawn(self.clone().handle_receive_request(), executor.clone()).await;
+        debug!("ProtocolFud::start() [END]");
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "ProtocolFud"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::CHUNK_SIZE;
+
+    #[test]
+    fn test_serve_chunk() -> Result<()> {
+        let folder = std::env::temp_dir().join("fud_test_serve_chunk");
+        std::fs::create_dir_all(&folder)?;
+        let path = folder.join("file");
+        let content: Vec<u8> = (0..CHUNK_SIZE + 42).map(|i| i as u8).collect();
+        std::fs::write(&path, &content)?;
+
+        let manifest = Manifest::from_path(&path)?;
+        assert_eq!(serve_chunk(&manifest, &folder, 0), content[..CHUNK_SIZE]);
+        assert_eq!(serve_chunk(&manifest, &folder, 1), content[CHUNK_SIZE..]);
+
+        // Out of range indexes sent by a peer get an empty reply
+        assert!(serve_chunk(&manifest, &folder, 2).is_empty());
+        assert!(serve_chunk(&manifest, &folder, u64::MAX).is_empty());
+
+        std::fs::remove_dir_all(&folder)?;
+        Ok(())
+    }
+}
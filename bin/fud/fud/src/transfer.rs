@@ -0,0 +1,121 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::manifest::Manifest;
+
+/// State of a file transfer, controlled through the RPC interface
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferState {
+    Active,
+    Paused,
+    Cancelled,
+}
+
+impl TransferState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Paused => "paused",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Progress of a file being retrieved from the network
+pub struct Transfer {
+    /// File name
+    pub name: String,
+    /// Number of chunks of the file
+    pub chunks_total: usize,
+    /// Number of chunks we hold so far
+    pub chunks_done: usize,
+    /// Chunks retrieved from each peer serving the file
+    pub peers: HashMap<Url, usize>,
+    /// Bytes retrieved since the transfer started
+    pub bytes: u64,
+    /// Time the transfer started
+    pub started: Instant,
+    /// Current transfer state
+    pub state: TransferState,
+    /// Whether chunks are currently being retrieved. Paused transfers
+    /// stop running once their in-flight requests complete.
+    pub running: bool,
+}
+
+impl Transfer {
+    pub fn new(manifest: &Manifest) -> Self {
+        Self {
+            name: manifest.name.clone(),
+            chunks_total: manifest.chunks.len(),
+            chunks_done: 0,
+            peers: HashMap::new(),
+            bytes: 0,
+            started: Instant::now(),
+            state: TransferState::Active,
+            running: true,
+        }
+    }
+
+    /// Resume a paused transfer that stopped running. Throughput is
+    /// measured from the time it was resumed.
+    pub fn resume(&mut self) {
+        self.bytes = 0;
+        self.started = Instant::now();
+        self.state = TransferState::Active;
+        self.running = true;
+    }
+
+    /// Record a chunk retrieved from the given peer.
+    pub fn chunk_done(&mut self, peer: Url, len: usize) {
+        self.chunks_done += 1;
+        self.bytes += len as u64;
+        *self.peers.entry(peer).or_insert(0) += 1;
+    }
+
+    /// Average throughput since the transfer started, in bytes per second.
+    pub fn throughput(&self) -> f64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0
+        }
+
+        self.bytes as f64 / elapsed
+    }
+
+    pub fn to_json(&self, file_hash: &blake3::Hash) -> Value {
+        json!({
+            "hash": file_hash.to_string(),
+            "name": self.name,
+            "state": self.state.as_str(),
+            "chunks_done": self.chunks_done,
+            "chunks_total": self.chunks_total,
+            "peers": self.peers.len(),
+            "throughput": self.throughput(),
+        })
+    }
+}
+
+/// Statistics of a local file being served to the network
+#[derive(Default)]
+pub struct SeedStats {
+    /// Peers that retrieved chunks of the file from us
+    pub peers: HashSet<Url>,
+    /// Number of chunks served
+    pub chunks: u64,
+    /// Number of bytes served
+    pub bytes: u64,
+}
+
+impl SeedStats {
+    /// Record a chunk served to the given peer.
+    pub fn chunk_served(&mut self, peer: Url, len: usize) {
+        self.peers.insert(peer);
+        self.chunks += 1;
+        self.bytes += len as u64;
+    }
+}
@@ -28,6 +28,7 @@ impl RequestHandler for JsonRpcInterface {
         match req.method.as_str() {
             Some("ping") => self.pong(req.id, req.params).await,
             Some("get_info") => self.get_info(req.id, req.params).await,
+            Some("get_metrics") => self.get_metrics(req.id, req.params).await,
             Some(_) | None => JsonError::new(ErrorCode::MethodNotFound, None, req.id).into(),
         }
     }
@@ -50,4 +51,13 @@ impl JsonRpcInterface {
         let resp = self.p2p.get_info().await;
         JsonResponse::new(resp, id).into()
     }
+
+    // RPCAPI:
+    // Retrieves P2P network and channel metrics.
+    // --> {"jsonrpc": "2.0", "method": "get_metrics", "params": [], "id": 42}
+    // <-- {"jsonrpc": "2.0", result": {"sent": {}, "received": {}, "channels": [], ...}, "id": 42}
+    async fn get_metrics(&self, id: Value, _params: Value) -> JsonResult {
+        let resp = self.p2p.get_metrics().await;
+        JsonResponse::new(resp, id).into()
+    }
 }
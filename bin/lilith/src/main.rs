@@ -53,6 +53,7 @@ impl Spawn {
             "name": self.name.clone(),
             "urls": addr_vec,
             "hosts": self.addresses().await,
+            "metrics": self.p2p.get_metrics().await,
         })
     }
 }
@@ -0,0 +1,238 @@
+use darkfi::zkas::{Analyzer, Diagnostic, Lexer, Opcode, Parser, VarType};
+
+/// Kind of a symbol defined in a zkas source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    Constant,
+    Witness,
+    Variable,
+}
+
+impl SymbolKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Constant => "constant",
+            Self::Witness => "witness",
+            Self::Variable => "variable",
+        }
+    }
+}
+
+/// A symbol defined in a zkas source, with the position of its name.
+/// Positions are 0-based, as used in the LSP protocol.
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub typ: VarType,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Result of running a source through the zkas lexer, parser, and analyzer
+#[derive(Clone, Debug, Default)]
+pub struct Analysis {
+    /// Diagnostics emitted by all the stages that ran
+    pub diagnostics: Vec<Diagnostic>,
+    /// Symbols defined in the source. Empty if the source couldn't be parsed.
+    pub symbols: Vec<Symbol>,
+}
+
+impl Analysis {
+    pub fn new(filename: &str, source: &str) -> Self {
+        let mut analysis = Self::default();
+
+        let lexer = Lexer::new(filename, source.chars());
+        let tokens = match lexer.lex() {
+            Ok(v) => v,
+            Err(e) => {
+                analysis.diagnostics = e;
+                return analysis
+            }
+        };
+        analysis.diagnostics = lexer.diagnostics();
+
+        let parser = Parser::new(filename, source.chars(), tokens);
+        let (_, constants, witnesses, statements) = match parser.parse() {
+            Ok(v) => v,
+            Err(e) => {
+                analysis.diagnostics.extend(e);
+                return analysis
+            }
+        };
+        analysis.diagnostics.extend(parser.diagnostics());
+
+        // The analyzer keeps going on type errors, so we're still able
+        // to collect the symbols if it fails.
+        let mut analyzer =
+            Analyzer::new(filename, source.chars(), constants, witnesses, statements);
+        match analyzer.analyze_types() {
+            Ok(()) => analysis.diagnostics.extend(analyzer.diagnostics()),
+            Err(e) => analysis.diagnostics.extend(e),
+        }
+
+        let lines: Vec<&str> = source.lines().collect();
+
+        // Constants are positioned at their type, which precedes the name
+        for c in &analyzer.constants {
+            let (line, column) = locate(&lines, &c.name, c.line, c.column);
+            analysis.symbols.push(Symbol {
+                name: c.name.clone(),
+                kind: SymbolKind::Constant,
+                typ: c.typ,
+                line,
+                column,
+            });
+        }
+
+        for w in &analyzer.witnesses {
+            let (line, column) = locate(&lines, &w.name, w.line, w.column);
+            analysis.symbols.push(Symbol {
+                name: w.name.clone(),
+                kind: SymbolKind::Witness,
+                typ: w.typ,
+                line,
+                column,
+            });
+        }
+
+        // Nested calls and the locals of expanded functions are pushed on
+        // the stack with generated names
+        let generated = |name: &str| name.starts_with("_op_inner_") || name.starts_with("_fn_");
+        for v in analyzer.stack.iter().filter(|v| !generated(&v.name)) {
+            let (line, column) = locate(&lines, &v.name, v.line, v.column);
+            analysis.symbols.push(Symbol {
+                name: v.name.clone(),
+                kind: SymbolKind::Variable,
+                typ: v.typ,
+                line,
+                column,
+            });
+        }
+
+        analysis
+    }
+
+    /// Find the first definition of the given name.
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.name == name)
+    }
+
+    /// Markdown hover text for the given word.
+    pub fn hover(&self, word: &str) -> Option<String> {
+        if let Some(opcode) = Opcode::from_name(word) {
+            return Some(format!("```\n{}\n```", signature(&opcode)))
+        }
+
+        let symbol = self.lookup(word)?;
+        Some(format!("```\n{}: {:?}\n```\n{}", symbol.name, symbol.typ, symbol.kind.as_str()))
+    }
+}
+
+/// Signature of an opcode, e.g. `base_add(Base, Base) -> Base`
+pub fn signature(opcode: &Opcode) -> String {
+    let (return_types, arg_types) = opcode.arg_types();
+    let args: Vec<String> = arg_types.iter().map(|t| format!("{:?}", t)).collect();
+    let mut sig = format!("{}({})", opcode.name(), args.join(", "));
+    if let Some(ret) = return_types.first() {
+        sig.push_str(&format!(" -> {:?}", ret));
+    }
+
+    sig
+}
+
+/// All opcodes available in the language
+pub fn opcodes() -> Vec<Opcode> {
+    (0..=u8::MAX).filter_map(Opcode::from_repr).collect()
+}
+
+/// Find the word at the given 0-based position in the source.
+pub fn word_at(source: &str, line: u32, character: u32) -> Option<String> {
+    let chars: Vec<char> = source.lines().nth(line as usize)?.chars().collect();
+    let is_word = |c: &char| c.is_ascii_alphanumeric() || *c == '_';
+
+    let pos = (character as usize).min(chars.len());
+    let start = chars[..pos].iter().rposition(|c| !is_word(c)).map_or(0, |i| i + 1);
+    let end = chars[pos..].iter().position(|c| !is_word(c)).map_or(chars.len(), |i| pos + i);
+
+    if start == end {
+        return None
+    }
+
+    Some(chars[start..end].iter().collect())
+}
+
+/// Convert a 1-based zkas position to a 0-based one pointing at the
+/// given name, searching for it from the position onwards.
+fn locate(lines: &[&str], name: &str, line: usize, column: usize) -> (u32, u32) {
+    let line0 = line.saturating_sub(1);
+    let column0 = column.saturating_sub(1);
+
+    let is_word = |c: Option<char>| c.map_or(false, |c| c.is_ascii_alphanumeric() || c == '_');
+
+    let chars: Vec<char> = lines.get(line0).map_or(vec![], |l| l.chars().collect());
+    let name: Vec<char> = name.chars().collect();
+    let found = (column0..chars.len()).find(|&i| {
+        chars[i..].starts_with(&name) &&
+            !is_word(i.checked_sub(1).map(|j| chars[j])) &&
+            !is_word(chars.get(i + name.len()).copied())
+    });
+
+    (line0 as u32, found.unwrap_or(column0) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"constant "Test" {
+    EcFixedPointBase NULLIFIER_K,
+}
+
+contract "Test" {
+    Base a,
+    Base b,
+}
+
+circuit "Test" {
+    c = base_add(a, b);
+    d = ec_mul_base(c, NULLIFIER_K);
+    constrain_instance(ec_get_x(d));
+}
+"#;
+
+    #[test]
+    fn test_analysis() {
+        let analysis = Analysis::new("test.zk", SOURCE);
+        assert!(analysis.diagnostics.is_empty());
+
+        let k = analysis.lookup("NULLIFIER_K").unwrap();
+        assert_eq!(
+            (k.kind, k.typ, k.line, k.column),
+            (SymbolKind::Constant, VarType::EcFixedPointBase, 1, 21)
+        );
+
+        let a = analysis.lookup("a").unwrap();
+        assert_eq!((a.kind, a.typ, a.line, a.column), (SymbolKind::Witness, VarType::Base, 5, 9));
+
+        let d = analysis.lookup("d").unwrap();
+        assert_eq!(
+            (d.kind, d.typ, d.line, d.column),
+            (SymbolKind::Variable, VarType::EcPoint, 11, 4)
+        );
+        assert_eq!(analysis.symbols.len(), 5);
+
+        assert_eq!(word_at(SOURCE, 10, 10), Some("base_add".to_string()));
+        assert_eq!(word_at(SOURCE, 10, 17), Some("a".to_string()));
+        assert_eq!(word_at(SOURCE, 10, 2), None);
+        assert_eq!(analysis.hover("base_add").unwrap(), "```\nbase_add(Base, Base) -> Base\n```");
+        assert!(analysis.hover("c").unwrap().contains("c: Base"));
+
+        // Type errors still leave the symbols available
+        let source = SOURCE.replace("base_add(a, b)", "base_add(a, NULLIFIER_K)");
+        let analysis = Analysis::new("test.zk", &source);
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert_eq!(analysis.diagnostics[0].line, 11);
+        assert!(analysis.lookup("d").is_some());
+    }
+}
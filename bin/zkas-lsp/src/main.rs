@@ -0,0 +1,266 @@
+use std::{collections::HashMap, error::Error};
+
+use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as NotificationTrait, PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, Request as RequestTrait},
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    DiagnosticSeverity, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+
+use darkfi::zkas::{error::Severity, Diagnostic};
+
+mod analysis;
+use analysis::{opcodes, signature, word_at, Analysis, SymbolKind};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Sync + Send>>;
+
+/// An open document, along with the last analysis run on it
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+struct Server {
+    connection: Connection,
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    fn run(&mut self) -> Result<()> {
+        while let Ok(msg) = self.connection.receiver.recv() {
+            match msg {
+                Message::Request(req) => {
+                    if self.connection.handle_shutdown(&req)? {
+                        return Ok(())
+                    }
+                    self.handle_request(req)?;
+                }
+                Message::Notification(not) => self.handle_notification(not)?,
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&self, req: Request) -> Result<()> {
+        let (id, result) = match req.method.as_str() {
+            HoverRequest::METHOD => {
+                let (id, params) = extract::<HoverRequest>(req)?;
+                (id, serde_json::to_value(self.hover(params))?)
+            }
+            GotoDefinition::METHOD => {
+                let (id, params) = extract::<GotoDefinition>(req)?;
+                (id, serde_json::to_value(self.definition(params))?)
+            }
+            Completion::METHOD => {
+                let (id, params) = extract::<Completion>(req)?;
+                (id, serde_json::to_value(self.completion(params))?)
+            }
+            _ => return Ok(()),
+        };
+
+        self.connection.sender.send(Message::Response(Response::new_ok(id, result)))?;
+        Ok(())
+    }
+
+    fn handle_notification(&mut self, not: Notification) -> Result<()> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = not.extract::<<DidOpenTextDocument as NotificationTrait>::Params>(
+                    DidOpenTextDocument::METHOD,
+                )?;
+                let doc = params.text_document;
+                self.update(doc.uri, doc.text, Some(doc.version))?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = not.extract::<<DidChangeTextDocument as NotificationTrait>::Params>(
+                    DidChangeTextDocument::METHOD,
+                )?;
+                // We only advertise full sync, so the last change holds the whole text
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    let doc = params.text_document;
+                    self.update(doc.uri, change.text, Some(doc.version))?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = not.extract::<<DidCloseTextDocument as NotificationTrait>::Params>(
+                    DidCloseTextDocument::METHOD,
+                )?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.publish(uri, vec![], None)?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Analyze the new text of a document, and publish its diagnostics.
+    fn update(&mut self, uri: Url, text: String, version: Option<i32>) -> Result<()> {
+        // The full path is needed to resolve imports
+        let filename = match uri.to_file_path() {
+            Ok(v) => v.to_string_lossy().to_string(),
+            Err(()) => uri.path().to_string(),
+        };
+        let mut analysis = Analysis::new(&filename, &text);
+
+        // Keep the symbols around while the source doesn't parse, so
+        // navigation keeps working during edits.
+        if analysis.symbols.is_empty() {
+            if let Some(old) = self.documents.get(&uri) {
+                analysis.symbols = old.analysis.symbols.clone();
+            }
+        }
+
+        let diagnostics =
+            analysis.diagnostics.iter().map(|x| to_lsp_diagnostic(&filename, x)).collect();
+        self.documents.insert(uri.clone(), Document { text, analysis });
+        self.publish(uri, diagnostics, version)
+    }
+
+    fn publish(
+        &self,
+        uri: Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+        version: Option<i32>,
+    ) -> Result<()> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, version);
+        let not = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(Message::Notification(not))?;
+        Ok(())
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let pos = params.text_document_position_params;
+        let doc = self.documents.get(&pos.text_document.uri)?;
+        let word = word_at(&doc.text, pos.position.line, pos.position.character)?;
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: doc.analysis.hover(&word)?,
+            }),
+            range: None,
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let pos = params.text_document_position_params;
+        let doc = self.documents.get(&pos.text_document.uri)?;
+        let word = word_at(&doc.text, pos.position.line, pos.position.character)?;
+        let symbol = doc.analysis.lookup(&word)?;
+
+        let start = Position::new(symbol.line, symbol.column);
+        let end = Position::new(symbol.line, symbol.column + symbol.name.chars().count() as u32);
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            pos.text_document.uri,
+            Range::new(start, end),
+        )))
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let mut items: Vec<CompletionItem> = opcodes()
+            .iter()
+            .map(|opcode| CompletionItem {
+                label: opcode.name().to_string(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some(signature(opcode)),
+                ..Default::default()
+            })
+            .collect();
+
+        if let Some(doc) = self.documents.get(&params.text_document_position.text_document.uri) {
+            items.extend(doc.analysis.symbols.iter().map(|symbol| CompletionItem {
+                label: symbol.name.clone(),
+                kind: Some(match symbol.kind {
+                    SymbolKind::Constant => CompletionItemKind::CONSTANT,
+                    SymbolKind::Witness | SymbolKind::Variable => CompletionItemKind::VARIABLE,
+                }),
+                detail: Some(format!("{:?}", symbol.typ)),
+                ..Default::default()
+            }));
+        }
+
+        Some(CompletionResponse::Array(items))
+    }
+}
+
+fn extract<R: RequestTrait>(req: Request) -> Result<(RequestId, R::Params)> {
+    match req.extract::<R::Params>(R::METHOD) {
+        Ok(v) => Ok(v),
+        Err(ExtractError::JsonError { method, error }) => {
+            Err(format!("Invalid params for {}: {}", method, error).into())
+        }
+        Err(ExtractError::MethodMismatch(req)) => {
+            Err(format!("Unexpected method {}", req.method).into())
+        }
+    }
+}
+
+/// Convert a zkas diagnostic, using 1-based positions, into an LSP one.
+/// Diagnostics found in imported files are shown at the top of the file.
+fn to_lsp_diagnostic(filename: &str, diagnostic: &Diagnostic) -> lsp_types::Diagnostic {
+    let mut message = diagnostic.message.clone();
+
+    let range = if diagnostic.file != filename {
+        message = format!(
+            "{}:{}:{}: {}",
+            diagnostic.file, diagnostic.line, diagnostic.column, diagnostic.message
+        );
+        Range::default()
+    } else if diagnostic.line == 0 {
+        Range::default()
+    } else {
+        let line = (diagnostic.line - 1) as u32;
+        let column = diagnostic.column.saturating_sub(1) as u32;
+        Range::new(
+            Position::new(line, column),
+            Position::new(line, column + diagnostic.span as u32),
+        )
+    };
+
+    let severity = match diagnostic.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+    };
+
+    lsp_types::Diagnostic {
+        range,
+        severity: Some(severity),
+        source: Some(format!("zkas {}", diagnostic.namespace.to_lowercase())),
+        message,
+        ..Default::default()
+    }
+}
+
+fn main() -> Result<()> {
+    // stdout is used for the protocol, so no logging there
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server { connection, documents: HashMap::new() };
+    server.run()?;
+
+    // Drop the connection so the IO threads are able to finish
+    drop(server);
+    io_threads.join()?;
+
+    Ok(())
+}
@@ -8,12 +8,22 @@ use clap::Parser as ClapParser;
 
 use darkfi::{
     cli_desc,
-    zkas::{Analyzer, Compiler, Lexer, Parser, ZkBinary},
+    zk::CircuitStats,
+    zkas::{
+        ast, error::emit_diagnostics, Analyzer, Compiler, Diagnostic, DiagnosticResult, Lexer,
+        Parser, ZkBinary,
+    },
 };
 
+mod prover;
+mod witness;
+
 #[derive(clap::Parser)]
-#[clap(name = "zkas", about = cli_desc!(), version)]
+#[clap(name = "zkas", about = cli_desc!(), version, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Subcommand>,
+
     /// Place the output into <FILE>
     #[clap(short = 'o', value_name = "FILE")]
     output: Option<String>,
@@ -34,14 +44,133 @@ struct Args {
     #[clap(short = 'e')]
     examine: bool,
 
+    /// Print circuit size statistics
+    #[clap(long)]
+    stats: bool,
+
     /// ZK script to compile
-    input: String,
+    input: Option<String>,
+}
+
+#[derive(clap::Subcommand)]
+enum Subcommand {
+    /// Check the circuit against a witness file using halo2's MockProver
+    Mock {
+        /// ZK script of the circuit
+        input: String,
+
+        /// JSON file with the witnesses and public inputs
+        witnesses: String,
+    },
+
+    /// Create a proof of the circuit from a witness file
+    Prove {
+        /// ZK script of the circuit
+        input: String,
+
+        /// JSON file with the witnesses and public inputs
+        witnesses: String,
+
+        /// Place the proof into <FILE>
+        #[clap(short = 'o', value_name = "FILE")]
+        output: Option<String>,
+    },
+
+    /// Verify a proof created with `zkas prove`
+    Verify {
+        /// ZK script of the circuit
+        input: String,
+
+        /// Proof file
+        proof: String,
+    },
 }
 
 fn main() {
     let args = Args::parse();
 
-    let filename = args.input.as_str();
+    if let Some(command) = args.command {
+        let result = match command {
+            Subcommand::Mock { input, witnesses } => {
+                let (_, zkbin, declared) = compile_circuit(&input);
+                prover::mock(zkbin, &declared, &witnesses)
+            }
+            Subcommand::Prove { input, witnesses, output } => {
+                let (bincode, zkbin, declared) = compile_circuit(&input);
+                let output = output.unwrap_or_else(|| format!("{}.proof", witnesses));
+                prover::prove(zkbin, &bincode, &declared, &witnesses, &output)
+            }
+            Subcommand::Verify { input, proof } => {
+                let (bincode, zkbin, _) = compile_circuit(&input);
+                prover::verify(zkbin, &bincode, &proof)
+            }
+        };
+
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            exit(1);
+        }
+
+        return
+    }
+
+    let input = match args.input {
+        Some(v) => v,
+        None => {
+            eprintln!("Error: Missing input file. See --help for usage.");
+            exit(1);
+        }
+    };
+
+    let (bincode, _) = compile(&input, !args.strip, args.interactive, args.evaluate);
+
+    let output = match args.output {
+        Some(o) => o,
+        None => format!("{}.bin", input),
+    };
+
+    let mut file = match File::create(&output) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: Failed to create \"{}\". {}", output, e);
+            exit(1);
+        }
+    };
+
+    if let Err(e) = file.write_all(&bincode) {
+        eprintln!("Error: Failed to write bincode to \"{}\". {}", output, e);
+        exit(1);
+    };
+
+    println!("Wrote output to {}", &output);
+
+    if args.examine {
+        let zkbin = ZkBinary::decode(&bincode).unwrap();
+        println!("{:#?}", zkbin);
+    }
+
+    if args.stats {
+        let zkbin = ZkBinary::decode(&bincode).unwrap();
+        print_stats(&zkbin);
+    }
+}
+
+/// Compile the circuit in memory, for the subcommands working on it.
+/// Returns the bincode, along with the decoded binary and its witnesses.
+fn compile_circuit(filename: &str) -> (Vec<u8>, ZkBinary, Vec<ast::Witness>) {
+    let (bincode, witnesses) = compile(filename, false, false, false);
+    let zkbin = ZkBinary::decode(&bincode).unwrap();
+    (bincode, zkbin, witnesses)
+}
+
+/// Compile the given file, and return the bincode along with the
+/// witnesses declared in the circuit.
+fn compile(
+    filename: &str,
+    debug: bool,
+    interactive: bool,
+    evaluate: bool,
+) -> (Vec<u8>, Vec<ast::Witness>) {
     let source = match read_to_string(filename) {
         Ok(v) => v,
         Err(e) => {
@@ -57,25 +186,26 @@ fn main() {
     // The lexer goes over the input file and separates its content into
     // tokens that get fed into a parser.
     let lexer = Lexer::new(filename, source.chars());
-    let tokens = lexer.lex();
+    let tokens = unwrap_stage(filename, &source, lexer.lex(), lexer.diagnostics());
 
     // The parser goes over the tokens provided by the lexer and builds
     // the initial AST, not caring much about the semantics, just enforcing
     // syntax and general structure.
     let parser = Parser::new(filename, source.chars(), tokens);
-    let (namespace, constants, witnesses, statements) = parser.parse();
+    let (namespace, constants, witnesses, statements) =
+        unwrap_stage(filename, &source, parser.parse(), parser.diagnostics());
 
     // The analyzer goes through the initial AST provided by the parser and
     // converts return and variable types to their correct forms, and also
     // checks that the semantics of the ZK script are correct.
     let mut analyzer = Analyzer::new(filename, source.chars(), constants, witnesses, statements);
-    analyzer.analyze_types();
+    unwrap_stage(filename, &source, analyzer.analyze_types(), analyzer.diagnostics());
 
-    if args.interactive {
-        analyzer.analyze_semantic();
+    if interactive {
+        unwrap_stage(filename, &source, analyzer.analyze_semantic(), vec![]);
     }
 
-    if args.evaluate {
+    if evaluate {
         println!("{:#?}", analyzer.constants);
         println!("{:#?}", analyzer.witnesses);
         println!("{:#?}", analyzer.statements);
@@ -83,6 +213,7 @@ fn main() {
         exit(0);
     }
 
+    let witnesses = analyzer.witnesses.clone();
     let compiler = Compiler::new(
         filename,
         source.chars(),
@@ -91,34 +222,66 @@ fn main() {
         analyzer.witnesses,
         analyzer.statements,
         analyzer.literals,
-        !args.strip,
+        debug,
     );
 
-    let bincode = compiler.compile();
+    let mut bincode = unwrap_stage(filename, &source, compiler.compile(), compiler.diagnostics());
     // ANCHOR_END: zkas
 
-    let output = match args.output {
-        Some(o) => o,
-        None => format!("{}.bin", args.input),
-    };
-
-    let mut file = match File::create(&output) {
+    // The circuit's k is measured by laying out the compiled circuit
+    // with the zkvm, and written into the binary.
+    let zkbin = ZkBinary::decode(&bincode).unwrap();
+    let stats = match CircuitStats::measure(&zkbin) {
         Ok(v) => v,
         Err(e) => {
-            eprintln!("Error: Failed to create \"{}\". {}", output, e);
+            eprintln!("Error: Failed measuring the circuit of \"{}\". {}", filename, e);
             exit(1);
         }
     };
+    Compiler::write_k(&mut bincode, stats.k);
 
-    if let Err(e) = file.write_all(&bincode) {
-        eprintln!("Error: Failed to write bincode to \"{}\". {}", output, e);
-        exit(1);
-    };
+    (bincode, witnesses)
+}
 
-    println!("Wrote output to {}", &output);
+/// Print the diagnostics of a compilation stage, and exit if it failed.
+fn unwrap_stage<T>(
+    filename: &str,
+    source: &str,
+    result: DiagnosticResult<T>,
+    diagnostics: Vec<Diagnostic>,
+) -> T {
+    match result {
+        Ok(v) => {
+            emit_diagnostics(filename, source, &diagnostics);
+            v
+        }
+        Err(diagnostics) => {
+            emit_diagnostics(filename, source, &diagnostics);
+            exit(1);
+        }
+    }
+}
 
-    if args.examine {
-        let zkbin = ZkBinary::decode(&bincode).unwrap();
-        println!("{:#?}", zkbin);
+fn print_stats(zkbin: &ZkBinary) {
+    let stats = CircuitStats::measure(zkbin).unwrap();
+
+    println!("Circuit statistics for \"{}\":", zkbin.namespace);
+    println!("{:>4}  {:<24}{:>8}{:>10}{:>8}", "#", "opcode", "rows", "advices", "copies");
+    println!("{:>4}  {:<24}{:>8}", "", "setup", stats.setup_rows);
+    for (i, op) in stats.opcodes.iter().enumerate() {
+        println!(
+            "{:>4}  {:<24}{:>8}{:>10}{:>8}",
+            i,
+            op.opcode.name(),
+            op.rows,
+            op.advices,
+            op.copies
+        );
     }
+
+    println!();
+    println!("Rows: {} (plus {} reserved rows)", stats.rows, stats.reserved_rows);
+    println!("Advice columns: {}", stats.advices);
+    println!("Copy constraints: {}", stats.copies);
+    println!("Minimal k: {}", stats.k);
 }
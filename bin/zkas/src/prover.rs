@@ -0,0 +1,140 @@
+use std::{
+    fs::{read, read_to_string, File},
+    io::Write,
+    time::Instant,
+};
+
+use darkfi::{
+    crypto::{keycache::KeyCache, Proof},
+    zk::{vm::ZkCircuit, vm_stack::empty_witnesses},
+    zkas::{ast, ZkBinary},
+};
+use darkfi_serial::{deserialize, serialize, SerialDecodable, SerialEncodable};
+use halo2_proofs::dev::MockProver;
+use pasta_curves::pallas;
+use rand::rngs::OsRng;
+
+use crate::witness::WitnessFile;
+
+/// A proof along with the public inputs it was created for
+#[derive(SerialEncodable, SerialDecodable)]
+struct ProofFile {
+    /// Namespace of the circuit the proof was created for
+    namespace: String,
+    public_inputs: Vec<pallas::Base>,
+    proof: Proof,
+}
+
+fn read_witnesses(path: &str, declared: &[ast::Witness]) -> Result<WitnessFile, String> {
+    let json = match read_to_string(path) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Failed reading from \"{}\". {}", path, e)),
+    };
+
+    WitnessFile::parse(&json, declared).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Run the circuit with the given witnesses through halo2's `MockProver`,
+/// and print the constraints that are not satisfied.
+pub fn mock(zkbin: ZkBinary, declared: &[ast::Witness], witnesses: &str) -> Result<(), String> {
+    let file = read_witnesses(witnesses, declared)?;
+    let k = zkbin.k;
+    let circuit = ZkCircuit::new(file.witnesses, zkbin);
+
+    let prover = match MockProver::run(k, &circuit, vec![file.public_inputs]) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Failed to synthesize circuit: {}", e)),
+    };
+
+    match prover.verify() {
+        Ok(()) => {
+            println!("All constraints are satisfied");
+            Ok(())
+        }
+        Err(failures) => {
+            for failure in &failures {
+                eprintln!("{}", failure);
+            }
+            Err(format!("{} constraint failures", failures.len()))
+        }
+    }
+}
+
+/// Create a proof for the circuit with the given witnesses, and write it
+/// along with its public inputs to `output`. The proving key is taken
+/// from the key cache, keyed by the hash of the circuit's `bincode`.
+pub fn prove(
+    zkbin: ZkBinary,
+    bincode: &[u8],
+    declared: &[ast::Witness],
+    witnesses: &str,
+    output: &str,
+) -> Result<(), String> {
+    let file = read_witnesses(witnesses, declared)?;
+    let namespace = zkbin.namespace.clone();
+    let k = zkbin.k;
+    let circuit = ZkCircuit::new(file.witnesses, zkbin);
+
+    let now = Instant::now();
+    let circuit_id = KeyCache::zkas_circuit_id(bincode);
+    let proving_key = KeyCache::default().proving_key(&circuit_id, k, &circuit);
+    println!("ProvingKey loaded [{} s]", now.elapsed().as_secs_f64());
+
+    let now = Instant::now();
+    let proof = match Proof::create(&proving_key, &[circuit], &file.public_inputs, &mut OsRng) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Failed to create proof: {}", e)),
+    };
+    println!("Proof created [{} s]", now.elapsed().as_secs_f64());
+
+    let proof_file = ProofFile { namespace, public_inputs: file.public_inputs, proof };
+    let written = File::create(output).and_then(|mut f| f.write_all(&serialize(&proof_file)));
+    if let Err(e) = written {
+        return Err(format!("Failed to write proof to \"{}\". {}", output, e))
+    }
+
+    println!("Wrote proof to {}", output);
+    Ok(())
+}
+
+/// Verify a proof written by [`prove`] against the circuit. The verifying
+/// key is taken from the key cache, like the proving key.
+pub fn verify(zkbin: ZkBinary, bincode: &[u8], proof: &str) -> Result<(), String> {
+    let bytes = match read(proof) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Failed reading from \"{}\". {}", proof, e)),
+    };
+
+    let proof_file: ProofFile = match deserialize(&bytes) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Failed decoding proof \"{}\". {}", proof, e)),
+    };
+
+    if proof_file.namespace != zkbin.namespace {
+        return Err(format!(
+            "Proof was created for circuit \"{}\", not \"{}\"",
+            proof_file.namespace, zkbin.namespace
+        ))
+    }
+
+    println!("Public inputs:");
+    for (i, input) in proof_file.public_inputs.iter().enumerate() {
+        println!("{:>4}  {:?}", i, input);
+    }
+
+    let k = zkbin.k;
+    let circuit = ZkCircuit::new(empty_witnesses(&zkbin), zkbin);
+
+    let now = Instant::now();
+    let circuit_id = KeyCache::zkas_circuit_id(bincode);
+    let verifying_key = KeyCache::default().verifying_key(&circuit_id, k, &circuit);
+    println!("VerifyingKey loaded [{} s]", now.elapsed().as_secs_f64());
+
+    let now = Instant::now();
+    if let Err(e) = proof_file.proof.verify(&verifying_key, &proof_file.public_inputs) {
+        return Err(format!("Proof verification failed: {}", e))
+    }
+    println!("Proof verified [{} s]", now.elapsed().as_secs_f64());
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use darkfi::{
+    zk::vm::Witness,
+    zkas::{ast, VarType},
+};
+use darkfi_sdk::crypto::MerkleNode;
+use halo2_proofs::circuit::Value;
+use pasta_curves::{group::ff::PrimeField, pallas};
+use serde_json::Value as JsonValue;
+
+/// Witness values and public inputs for a circuit, read from a JSON
+/// file holding a value for every witness of the `contract` section:
+///
+/// ```json
+/// {
+///     "witnesses": {
+///         "value": 42,
+///         "value_blind": "0x1cb1a0d1bf4e04c1e58f0b3a4e82ee3ba06bd2ac0c6a52e8f5ab0cc04f2ac1a1",
+///         "leaf_pos": 3,
+///         "path": ["0x...", "0x...", ...]
+///     },
+///     "public_inputs": ["0x...", "0x..."]
+/// }
+/// ```
+///
+/// `Base` and `Scalar` values are given as integers, or as big-endian
+/// hex strings prefixed with `0x`, the way field elements are printed.
+/// `MerklePath` values are arrays of 32 `Base` values.
+pub struct WitnessFile {
+    /// Witnesses, in the order they are declared in the circuit
+    pub witnesses: Vec<Witness>,
+    /// Public inputs, in the order the circuit constrains them
+    pub public_inputs: Vec<pallas::Base>,
+}
+
+impl WitnessFile {
+    /// Parse a witness file for a circuit with the given witnesses.
+    pub fn parse(json: &str, declared: &[ast::Witness]) -> Result<Self, String> {
+        let json: JsonValue = match serde_json::from_str(json) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("Invalid JSON: {}", e)),
+        };
+
+        let values = match json.get("witnesses").and_then(|x| x.as_object()) {
+            Some(v) => v,
+            None => return Err("Missing \"witnesses\" object".to_string()),
+        };
+
+        for name in values.keys() {
+            if !declared.iter().any(|x| &x.name == name) {
+                return Err(format!("Unknown witness \"{}\"", name))
+            }
+        }
+
+        let mut witnesses = Vec::with_capacity(declared.len());
+        for w in declared {
+            let value = match values.get(&w.name) {
+                Some(v) => v,
+                None => return Err(format!("Missing value for witness \"{}\"", w.name)),
+            };
+
+            match parse_witness(w.typ, value) {
+                Ok(v) => witnesses.push(v),
+                Err(e) => return Err(format!("Witness \"{}\": {}", w.name, e)),
+            }
+        }
+
+        let public_inputs = match json.get("public_inputs").and_then(|x| x.as_array()) {
+            Some(v) => v,
+            None => return Err("Missing \"public_inputs\" array".to_string()),
+        };
+
+        let public_inputs = public_inputs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| parse_field(x).map_err(|e| format!("Public input {}: {}", i, e)))
+            .collect::<Result<Vec<pallas::Base>, String>>()?;
+
+        Ok(Self { witnesses, public_inputs })
+    }
+}
+
+fn parse_witness(typ: VarType, value: &JsonValue) -> Result<Witness, String> {
+    let witness = match typ {
+        VarType::Base => Witness::Base(Value::known(parse_field(value)?)),
+        VarType::Scalar => Witness::Scalar(Value::known(parse_field(value)?)),
+
+        VarType::MerklePath => {
+            let nodes = match value.as_array() {
+                Some(v) => v,
+                None => return Err("Expected an array of 32 Base values".to_string()),
+            };
+
+            let nodes: Vec<MerkleNode> = nodes
+                .iter()
+                .map(|x| parse_field::<pallas::Base>(x).map(MerkleNode::from))
+                .collect::<Result<_, _>>()?;
+
+            match nodes.try_into() {
+                Ok(v) => Witness::MerklePath(Value::known(v)),
+                Err(v) => return Err(format!("Expected 32 path elements, got {}", v.len())),
+            }
+        }
+
+        VarType::Uint32 => match value.as_u64().and_then(|x| u32::try_from(x).ok()) {
+            Some(v) => Witness::Uint32(Value::known(v)),
+            None => return Err("Expected an unsigned 32-bit integer".to_string()),
+        },
+
+        VarType::Uint64 => match value.as_u64() {
+            Some(v) => Witness::Uint64(Value::known(v)),
+            None => return Err("Expected an unsigned 64-bit integer".to_string()),
+        },
+
+        x => return Err(format!("Unsupported witness type {:?}", x)),
+    };
+
+    Ok(witness)
+}
+
+/// Parse a field element given as an integer, a decimal string, or a
+/// big-endian hex string prefixed with `0x`.
+fn parse_field<F: PrimeField<Repr = [u8; 32]>>(value: &JsonValue) -> Result<F, String> {
+    if let Some(v) = value.as_u64() {
+        return Ok(F::from(v))
+    }
+
+    let s = match value.as_str() {
+        Some(v) => v,
+        None => return Err("Expected an integer or a hex string".to_string()),
+    };
+
+    let hex = match s.strip_prefix("0x") {
+        Some(v) => v,
+        None => match s.parse::<u64>() {
+            Ok(v) => return Ok(F::from(v)),
+            Err(_) => return Err(format!("Invalid field element \"{}\"", s)),
+        },
+    };
+
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(format!("Expected 64 hex digits, got \"{}\"", s))
+    }
+
+    let mut repr = [0u8; 32];
+    for (i, byte) in repr.iter_mut().rev().enumerate() {
+        *byte = match u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16) {
+            Ok(v) => v,
+            Err(_) => return Err(format!("Invalid hex string \"{}\"", s)),
+        };
+    }
+
+    match Option::from(F::from_repr(repr)) {
+        Some(v) => Ok(v),
+        None => Err(format!("\"{}\" is not a canonical field element", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_field() {
+        let x = pallas::Base::from(0xdeadbeef);
+        let hex = format!("{:?}", x);
+        assert_eq!(parse_field::<pallas::Base>(&JsonValue::from(hex)).unwrap(), x);
+        assert_eq!(parse_field::<pallas::Base>(&JsonValue::from(0xdeadbeef_u64)).unwrap(), x);
+        assert_eq!(parse_field::<pallas::Base>(&JsonValue::from("3735928559")).unwrap(), x);
+
+        // The modulus itself is not canonical
+        let p = "0x40000000000000000000000000000000224698fc094cf91b992d30ed00000001";
+        assert!(parse_field::<pallas::Base>(&JsonValue::from(p)).is_err());
+        assert!(parse_field::<pallas::Scalar>(&JsonValue::from("0x1234")).is_err());
+        assert!(parse_field::<pallas::Scalar>(&JsonValue::from(-1)).is_err());
+    }
+
+    #[test]
+    fn test_witness_file() {
+        let declared = vec![
+            ast::Witness { name: "value".to_string(), typ: VarType::Base, line: 1, column: 1 },
+            ast::Witness { name: "pos".to_string(), typ: VarType::Uint32, line: 2, column: 1 },
+        ];
+
+        let json = r#"{"witnesses": {"pos": 3, "value": "0x01"}, "public_inputs": []}"#;
+        assert!(WitnessFile::parse(json, &declared).err().unwrap().contains("64 hex digits"));
+
+        let json = r#"{"witnesses": {"value": 1}, "public_inputs": []}"#;
+        assert!(WitnessFile::parse(json, &declared).err().unwrap().contains("\"pos\""));
+
+        let json = r#"{"witnesses": {"pos": 3, "value": 1, "x": 2}, "public_inputs": []}"#;
+        assert!(WitnessFile::parse(json, &declared).err().unwrap().contains("Unknown"));
+
+        let json = r#"{"witnesses": {"pos": 3, "value": 1}, "public_inputs": [1, "2"]}"#;
+        let file = WitnessFile::parse(json, &declared).unwrap();
+        assert_eq!(file.witnesses.len(), 2);
+        assert_eq!(file.public_inputs, vec![pallas::Base::from(1), pallas::Base::from(2)]);
+    }
+}
@@ -10,6 +10,7 @@ use rand::rngs::OsRng;
 
 use darkfi::{
     crypto::{
+        keycache::KeyCache,
         keypair::{PublicKey, SecretKey},
         proof::{ProvingKey, VerifyingKey},
         schnorr::{SchnorrPublic, SchnorrSecret, Signature},
@@ -89,14 +90,18 @@ impl ZkContractTable {
         Self { table: HashMap::new() }
     }
 
-    pub fn add_contract(&mut self, key: String, bincode: ZkBinary, k_param: u32) {
-        let witnesses = empty_witnesses(&bincode);
-        let circuit = ZkCircuit::new(witnesses, bincode.clone());
-        let proving_key = ProvingKey::build(k_param, &circuit);
-        let verifying_key = VerifyingKey::build(k_param, &circuit);
+    /// Add a zkas circuit, decoded from `bincode`. Its keys are taken from
+    /// the key cache, keyed by the hash of `bincode`.
+    pub fn add_contract(&mut self, key: String, zkbin: ZkBinary, bincode: &[u8]) {
+        let k_param = zkbin.k;
+        let witnesses = empty_witnesses(&zkbin);
+        let circuit = ZkCircuit::new(witnesses, zkbin.clone());
+        let circuit_id = KeyCache::zkas_circuit_id(bincode);
+        let proving_key = KeyCache::default().proving_key(&circuit_id, k_param, &circuit);
+        let verifying_key = KeyCache::default().verifying_key(&circuit_id, k_param, &circuit);
         let info = ZkContractInfo::Binary(ZkBinaryContractInfo {
             k_param,
-            bincode,
+            bincode: zkbin,
             proving_key,
             verifying_key,
         });
@@ -153,6 +153,7 @@ fn main() -> Result<()> {
             value: 110,
             token_id,
             public: keypair.public,
+            view_public: None,
         }],
     };
 
@@ -186,6 +187,7 @@ fn main() -> Result<()> {
             value: 110,
             token_id,
             public: keypair.public,
+            view_public: None,
         }],
     };
 
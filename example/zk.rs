@@ -29,8 +29,8 @@ fn main() -> Result<()> {
     // Prover
     // ======
     // Bigger k = more rows, but slower circuit
-    // Number of rows is 2^k
-    let k = 13;
+    // Number of rows is 2^k, and the compiler embeds the k the circuit needs
+    let k = zkbin.k;
 
     // Witness values
     let value = 42;
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS invalid_notes(
+	coin BLOB PRIMARY KEY NOT NULL,
+	public BLOB NOT NULL,
+	note BLOB NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS pending_txs(
+	tx_hash BLOB PRIMARY KEY NOT NULL,
+	tx BLOB NOT NULL,
+	slot INTEGER
+);
@@ -0,0 +1,3 @@
+CREATE TABLE IF NOT EXISTS scan_state(
+	height INTEGER NOT NULL
+);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS transactions(
+	tx_hash BLOB PRIMARY KEY NOT NULL,
+	direction INTEGER NOT NULL,
+	value BLOB NOT NULL,
+	token_id BLOB NOT NULL,
+	counterparties BLOB NOT NULL,
+	slot INTEGER,
+	memo BLOB,
+	status INTEGER NOT NULL,
+	timestamp INTEGER NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS tx_coins(
+	tx_hash BLOB NOT NULL,
+	coin BLOB NOT NULL,
+	is_spent BOOLEAN NOT NULL,
+	PRIMARY KEY (tx_hash, coin)
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS view_keys(
+	public BLOB PRIMARY KEY NOT NULL,
+	secret BLOB NOT NULL
+);
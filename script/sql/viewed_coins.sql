@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS viewed_coins(
+	coin BLOB PRIMARY KEY NOT NULL,
+	public BLOB NOT NULL,
+	note BLOB NOT NULL,
+	slot INTEGER NOT NULL,
+	leaf_position BLOB NOT NULL,
+	is_spent BOOLEAN NOT NULL
+);
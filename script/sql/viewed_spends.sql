@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS viewed_spends(
+	coin BLOB NOT NULL,
+	output BLOB NOT NULL,
+	slot INTEGER,
+	is_reverted BOOLEAN NOT NULL,
+	PRIMARY KEY (coin, output)
+);
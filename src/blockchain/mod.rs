@@ -18,13 +18,21 @@ pub use nfstore::NullifierStore;
 pub mod rootstore;
 pub use rootstore::RootStore;
 
+pub mod snapshotstore;
+pub use snapshotstore::SnapshotStore;
+
 pub mod statestore;
 pub use statestore::StateStore;
 
+pub mod treestore;
+pub use treestore::MerkleTreeStore;
+
 pub mod txstore;
 pub use txstore::TxStore;
 
 /// Structure holding all sled trees that comprise the concept of Blockchain.
+/// Clones share the same trees.
+#[derive(Clone)]
 pub struct Blockchain {
     /// Headers sled tree
     pub headers: HeaderStore,
@@ -40,6 +48,10 @@ pub struct Blockchain {
     pub nullifiers: NullifierStore,
     /// Merkle roots sled tree
     pub merkle_roots: RootStore,
+    /// Merkle tree of the canonical state sled tree
+    pub merkle_tree: MerkleTreeStore,
+    /// State snapshots sled tree
+    pub snapshots: SnapshotStore,
 }
 
 impl Blockchain {
@@ -54,8 +66,20 @@ impl Blockchain {
         let transactions = TxStore::new(db)?;
         let nullifiers = NullifierStore::new(db)?;
         let merkle_roots = RootStore::new(db)?;
-
-        Ok(Self { headers, blocks, order, transactions, metadata, nullifiers, merkle_roots })
+        let merkle_tree = MerkleTreeStore::new(db)?;
+        let snapshots = SnapshotStore::new(db)?;
+
+        Ok(Self {
+            headers,
+            blocks,
+            order,
+            transactions,
+            metadata,
+            nullifiers,
+            merkle_roots,
+            merkle_tree,
+            snapshots,
+        })
     }
 
     /// Insert a given slice of [`BlockInfo`] into the blockchain database.
@@ -0,0 +1,83 @@
+use darkfi_serial::{deserialize, serialize};
+
+use crate::{consensus::snapshot::Snapshot, Error, Result};
+
+const SLED_SNAPSHOT_TREE: &[u8] = b"_snapshots";
+const SLED_SNAPSHOT_SYNC_TREE: &[u8] = b"_snapshot_sync";
+const SLED_SNAPSHOT_SYNC_KEY: &[u8] = b"slot";
+
+/// The `SnapshotStore` is a `sled` tree storing signed snapshots of the
+/// canonical state, produced at finalized slots. The key is the snapshot
+/// slot, while the value is the serialized [`Snapshot`].
+/// A second tree records the snapshot the ledger was synced from, if any.
+#[derive(Clone)]
+pub struct SnapshotStore(sled::Tree, sled::Tree);
+
+impl SnapshotStore {
+    /// Opens a new or existing `SnapshotStore` on the given sled database.
+    pub fn new(db: &sled::Db) -> Result<Self> {
+        let tree = db.open_tree(SLED_SNAPSHOT_TREE)?;
+        let sync_tree = db.open_tree(SLED_SNAPSHOT_SYNC_TREE)?;
+        Ok(Self(tree, sync_tree))
+    }
+
+    /// Record that the ledger was synced from the snapshot at the given
+    /// slot, so it lacks the blocks between genesis and it.
+    pub fn set_synced_from(&self, slot: u64) -> Result<()> {
+        self.1.insert(SLED_SNAPSHOT_SYNC_KEY, &slot.to_be_bytes())?;
+        Ok(())
+    }
+
+    /// Retrieve the slot of the snapshot the ledger was synced from, if any.
+    pub fn get_synced_from(&self) -> Result<Option<u64>> {
+        match self.1.get(SLED_SNAPSHOT_SYNC_KEY)? {
+            Some(found) => {
+                let slot_bytes: [u8; 8] = found.as_ref().try_into().unwrap();
+                Ok(Some(u64::from_be_bytes(slot_bytes)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Insert a [`Snapshot`] into the store, replacing any existing
+    /// snapshot for the same slot.
+    pub fn insert(&self, snapshot: &Snapshot) -> Result<()> {
+        self.0.insert(snapshot.header.slot.to_be_bytes(), serialize(snapshot))?;
+        Ok(())
+    }
+
+    /// Check if the `SnapshotStore` contains a snapshot for the given slot.
+    pub fn contains(&self, slot: u64) -> Result<bool> {
+        Ok(self.0.contains_key(slot.to_be_bytes())?)
+    }
+
+    /// Retrieve the [`Snapshot`] for the given slot. Fails if it is not found.
+    pub fn get(&self, slot: u64) -> Result<Snapshot> {
+        match self.0.get(slot.to_be_bytes())? {
+            Some(found) => Ok(deserialize(&found)?),
+            None => Err(Error::SnapshotNotFound(slot)),
+        }
+    }
+
+    /// Retrieve the most recent [`Snapshot`] in the store, if any.
+    pub fn get_last(&self) -> Result<Option<Snapshot>> {
+        match self.0.last()? {
+            Some(found) => Ok(Some(deserialize(&found.1)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove all snapshots except the `n` most recent ones.
+    pub fn prune(&self, n: usize) -> Result<()> {
+        let keys: Vec<sled::IVec> =
+            self.0.iter().keys().rev().skip(n).collect::<sled::Result<_>>()?;
+
+        let mut batch = sled::Batch::default();
+        for key in keys {
+            batch.remove(key);
+        }
+
+        self.0.apply_batch(batch)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use darkfi_sdk::crypto::{constants::MERKLE_DEPTH, MerkleNode};
+use darkfi_serial::{deserialize, serialize};
+use incrementalmerkletree::bridgetree::BridgeTree;
+
+use crate::Result;
+
+const SLED_MERKLE_TREE_TREE: &[u8] = b"_merkletree";
+const SLED_MERKLE_TREE_KEY: &[u8] = b"tree";
+
+/// The `MerkleTreeStore` is a `sled` tree storing the Merkle tree of all
+/// the coins in the canonical state, so it survives restarts. Only the
+/// latest tree is kept.
+#[derive(Clone)]
+pub struct MerkleTreeStore(sled::Tree);
+
+impl MerkleTreeStore {
+    /// Opens a new or existing `MerkleTreeStore` on the given sled database.
+    pub fn new(db: &sled::Db) -> Result<Self> {
+        let tree = db.open_tree(SLED_MERKLE_TREE_TREE)?;
+        Ok(Self(tree))
+    }
+
+    /// Insert a Merkle tree into the store, replacing the previous one.
+    pub fn insert(&self, tree: &BridgeTree<MerkleNode, MERKLE_DEPTH>) -> Result<()> {
+        self.0.insert(SLED_MERKLE_TREE_KEY, serialize(tree))?;
+        Ok(())
+    }
+
+    /// Retrieve the stored Merkle tree, if any.
+    pub fn get(&self) -> Result<Option<BridgeTree<MerkleNode, MERKLE_DEPTH>>> {
+        match self.0.get(SLED_MERKLE_TREE_KEY)? {
+            Some(found) => Ok(Some(deserialize(&found)?)),
+            None => Ok(None),
+        }
+    }
+}
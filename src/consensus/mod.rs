@@ -14,6 +14,10 @@ pub use participant::{KeepAlive, Participant};
 pub mod state;
 pub use state::{ValidatorState, ValidatorStatePtr};
 
+/// Canonical state snapshots
+pub mod snapshot;
+pub use snapshot::{Snapshot, SnapshotHeader, StateSnapshot};
+
 /// Utility functions and types
 use crate::util::time::Timestamp;
 
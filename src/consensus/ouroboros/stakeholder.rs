@@ -440,6 +440,7 @@ impl Stakeholder {
                 value: coin.value.unwrap(),
                 token_id,
                 public: keypair.public,
+                view_public: None,
             }],
         };
         let tx = builder.build(&self.mint_pk, &self.burn_pk).unwrap();
@@ -21,3 +21,7 @@ pub use protocol_sync::ProtocolSync;
 /// Validator consensus sync protocol
 mod protocol_sync_consensus;
 pub use protocol_sync_consensus::ProtocolSyncConsensus;
+
+/// Canonical state snapshot sync protocol
+mod protocol_snapshot;
+pub use protocol_snapshot::ProtocolSnapshot;
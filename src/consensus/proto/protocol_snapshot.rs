@@ -0,0 +1,126 @@
+use async_std::sync::Arc;
+use async_trait::async_trait;
+use log::{debug, error};
+use smol::Executor;
+
+use crate::{
+    consensus::{
+        snapshot::{SnapshotChunk, SnapshotChunkRequest, SnapshotRequest, SnapshotResponse},
+        ValidatorStatePtr,
+    },
+    net::{
+        ChannelPtr, MessageSubscription, P2pPtr, ProtocolBase, ProtocolBasePtr,
+        ProtocolJobsManager, ProtocolJobsManagerPtr,
+    },
+    Result,
+};
+
+pub struct ProtocolSnapshot {
+    channel: ChannelPtr,
+    request_sub: MessageSubscription<SnapshotRequest>,
+    chunk_sub: MessageSubscription<SnapshotChunkRequest>,
+    jobsman: ProtocolJobsManagerPtr,
+    state: ValidatorStatePtr,
+}
+
+impl ProtocolSnapshot {
+    pub async fn init(
+        channel: ChannelPtr,
+        state: ValidatorStatePtr,
+        _p2p: P2pPtr,
+    ) -> Result<ProtocolBasePtr> {
+        let msg_subsystem = channel.get_message_subsystem();
+        msg_subsystem.add_dispatch::<SnapshotRequest>().await;
+        msg_subsystem.add_dispatch::<SnapshotChunkRequest>().await;
+
+        let request_sub = channel.subscribe_msg::<SnapshotRequest>().await?;
+        let chunk_sub = channel.subscribe_msg::<SnapshotChunkRequest>().await?;
+
+        Ok(Arc::new(Self {
+            channel: channel.clone(),
+            request_sub,
+            chunk_sub,
+            jobsman: ProtocolJobsManager::new("SnapshotProtocol", channel),
+            state,
+        }))
+    }
+
+    async fn handle_receive_request(self: Arc<Self>) -> Result<()> {
+        debug!("ProtocolSnapshot::handle_receive_request() [START]");
+        loop {
+            let request = match self.request_sub.receive().await {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("ProtocolSnapshot::handle_receive_request(): recv fail: {}", e);
+                    continue
+                }
+            };
+
+            debug!("ProtocolSnapshot::handle_receive_request() received {:?}", request);
+
+            let header = match self.state.read().await.blockchain.snapshots.get_last() {
+                Ok(Some(v)) if v.header.slot > request.after => Some(v.header),
+                Ok(_) => None,
+                Err(e) => {
+                    error!("ProtocolSnapshot::handle_receive_request(): get_last fail: {}", e);
+                    None
+                }
+            };
+
+            let response = SnapshotResponse { header };
+            if let Err(e) = self.channel.send(response).await {
+                error!("ProtocolSnapshot::handle_receive_request(): channel send fail: {}", e)
+            };
+        }
+    }
+
+    async fn handle_receive_chunk_request(self: Arc<Self>) -> Result<()> {
+        debug!("ProtocolSnapshot::handle_receive_chunk_request() [START]");
+        loop {
+            let request = match self.chunk_sub.receive().await {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("ProtocolSnapshot::handle_receive_chunk_request(): recv fail: {}", e);
+                    continue
+                }
+            };
+
+            debug!("ProtocolSnapshot::handle_receive_chunk_request() received {:?}", request);
+
+            // An empty chunk is sent back if we can't serve the request,
+            // so the requesting node doesn't wait on us forever.
+            let snapshot = self.state.read().await.blockchain.snapshots.get(request.slot);
+            let data = match snapshot.and_then(|s| s.chunk(request.index)) {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("ProtocolSnapshot::handle_receive_chunk_request(): {}", e);
+                    vec![]
+                }
+            };
+
+            let response = SnapshotChunk { slot: request.slot, index: request.index, data };
+            if let Err(e) = self.channel.send(response).await {
+                error!("ProtocolSnapshot::handle_receive_chunk_request(): channel send fail: {}", e)
+            };
+        }
+    }
+}
+
+#[async_trait]
+impl ProtocolBase for ProtocolSnapshot {
+    async fn start(self: Arc<Self>, executor: Arc<Executor<'_>>) -> Result<()> {
+        debug!("ProtocolSnapshot::start() [START]");
+        self.jobsman.clone().start(executor.clone());
+        self.jobsman.clone().spawn(self.clone().handle_receive_request(), executor.clone()).await;
+        self.jobsman
+            .clone()
+            .spawn(self.clone().handle_receive_chunk_request(), executor.clone())
+            .await;
+        debug!("ProtocolSnapshot::start() [END]");
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "ProtocolSnapshot"
+    }
+}
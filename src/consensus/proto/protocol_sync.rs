@@ -1,5 +1,6 @@
 use async_std::sync::Arc;
 use async_trait::async_trait;
+use darkfi_serial::VecEncoder;
 use log::{debug, error, info};
 use smol::Executor;
 
@@ -67,23 +68,37 @@ impl ProtocolSync {
             debug!("ProtocolSync::handle_receive_request() received {:?}", order);
 
             // Extra validations can be added here
-            let key = order.slot;
-            let blocks = match self.state.read().await.blockchain.get_blocks_after(key, BATCH) {
+            let payload = match self.encode_response(order.slot).await {
                 Ok(v) => v,
                 Err(e) => {
-                    error!("ProtocolSync::handle_receive_request(): get_blocks_after fail: {}", e);
+                    error!("ProtocolSync::handle_receive_request(): encode_response fail: {}", e);
                     continue
                 }
             };
-            debug!("ProtocolSync::handle_receive_request(): Found {} blocks", blocks.len());
 
-            let response = BlockResponse { blocks };
-            if let Err(e) = self.channel.send(response).await {
+            if let Err(e) = self.channel.send_encoded::<BlockResponse>(payload).await {
                 error!("ProtocolSync::handle_receive_request(): channel send fail: {}", e)
             };
         }
     }
 
+    /// Encode the [`BlockResponse`] with the blocks after the given slot.
+    /// Blocks are streamed into it one at a time, so they aren't all held
+    /// decoded alongside the encoded response.
+    async fn encode_response(&self, slot: u64) -> Result<Vec<u8>> {
+        let state = self.state.read().await;
+        let hashes = state.blockchain.order.get_after(slot, BATCH)?;
+        debug!("ProtocolSync::handle_receive_request(): Found {} blocks", hashes.len());
+
+        let mut encoder = VecEncoder::new(vec![], hashes.len() as u64).await?;
+        for hash in hashes {
+            let block = state.blockchain.get_blocks_by_hash(&[hash])?;
+            encoder.write(&block[0]).await?;
+        }
+
+        Ok(encoder.finish().await?)
+    }
+
     async fn handle_receive_block(self: Arc<Self>) -> Result<()> {
         // Consensus-mode enabled nodes have already performed these steps,
         // during proposal finalization.
@@ -67,8 +67,8 @@ impl ProtocolTx {
 
             // Nodes use unconfirmed_txs vector as seen_txs pool.
             if self.state.write().await.append_tx(tx_copy.clone()).await {
-                if let Err(e) = self.p2p.broadcast_with_exclude(tx_copy, &exclude_list).await {
-                    error!("handle_receive_tx(): p2p broadcast fail: {}", e);
+                if let Err(e) = self.p2p.announce_with_exclude(&tx_copy, &exclude_list).await {
+                    error!("handle_receive_tx(): p2p announce fail: {}", e);
                 };
             }
         }
@@ -0,0 +1,230 @@
+use darkfi_sdk::crypto::{constants::MERKLE_DEPTH, MerkleNode, Nullifier};
+use darkfi_serial::{deserialize, serialize, SerialDecodable, SerialEncodable};
+use incrementalmerkletree::bridgetree::BridgeTree;
+
+use super::BlockInfo;
+use crate::{
+    crypto::{
+        keypair::{PublicKey, SecretKey},
+        schnorr::{SchnorrPublic, SchnorrSecret, Signature},
+    },
+    net, Error, Result,
+};
+
+/// Amount of finalized slots between two consecutive state snapshots
+pub const SNAPSHOT_INTERVAL: u64 = 100;
+/// Amount of snapshots a node keeps in its database
+pub const SNAPSHOTS_KEPT: usize = 2;
+/// Maximum size of a snapshot chunk, in bytes
+pub const SNAPSHOT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// This struct represents the canonical [`State`](crate::node::State)
+/// at a finalized slot, along with the block that was finalized in it.
+#[derive(Clone, SerialEncodable, SerialDecodable)]
+pub struct StateSnapshot {
+    /// Last finalized block included in the snapshot
+    pub block: BlockInfo,
+    /// The entire Merkle tree state
+    pub tree: BridgeTree<MerkleNode, MERKLE_DEPTH>,
+    /// All Merkle roots seen up to this block
+    pub merkle_roots: Vec<MerkleNode>,
+    /// All nullifiers seen up to this block
+    pub nullifiers: Vec<Nullifier>,
+}
+
+impl StateSnapshot {
+    /// Deserialize the snapshot data described by `header`, checking that
+    /// it is the state at the header's block. The chunks of the data should
+    /// be checked with [`SnapshotHeader::verify_chunk`] first.
+    pub fn from_data(header: &SnapshotHeader, data: &[u8]) -> Result<Self> {
+        let snapshot: Self = deserialize(data)?;
+        if snapshot.block.header.slot != header.slot ||
+            snapshot.block.header.headerhash() != header.block
+        {
+            return Err(Error::SnapshotVerifyFailed("snapshot block mismatch".to_string()))
+        }
+
+        Ok(snapshot)
+    }
+}
+
+/// This struct represents the signed description of a [`StateSnapshot`].
+/// The serialized snapshot is split into chunks of at most
+/// [`SNAPSHOT_CHUNK_SIZE`] bytes, which can be requested and verified
+/// independently using their hashes.
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct SnapshotHeader {
+    /// Slot of the snapshot's block
+    pub slot: u64,
+    /// Headerhash of the snapshot's block
+    pub block: blake3::Hash,
+    /// Hashes of the serialized snapshot chunks, in order
+    pub chunks: Vec<blake3::Hash>,
+    /// Public key of the snapshot producer
+    pub public: PublicKey,
+    /// Producer signature over the fields above
+    pub signature: Signature,
+}
+
+impl SnapshotHeader {
+    /// Create a signed header for the given snapshot bytes.
+    pub fn new(slot: u64, block: blake3::Hash, data: &[u8], secret: &SecretKey) -> Self {
+        let chunks: Vec<blake3::Hash> =
+            data.chunks(SNAPSHOT_CHUNK_SIZE).map(blake3::hash).collect();
+        let public = PublicKey::from_secret(*secret);
+        let signature = secret.sign(&Self::signing_hash(slot, &block, &chunks).as_bytes()[..]);
+        Self { slot, block, chunks, public, signature }
+    }
+
+    /// Hash of the header data covered by the producer signature.
+    fn signing_hash(slot: u64, block: &blake3::Hash, chunks: &[blake3::Hash]) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&slot.to_be_bytes());
+        hasher.update(block.as_bytes());
+        for chunk in chunks {
+            hasher.update(chunk.as_bytes());
+        }
+        hasher.finalize()
+    }
+
+    /// Verify the header signature and that it was produced by one of
+    /// the given trusted public keys.
+    pub fn verify(&self, trusted: &[PublicKey]) -> bool {
+        if !trusted.contains(&self.public) {
+            return false
+        }
+
+        let hash = Self::signing_hash(self.slot, &self.block, &self.chunks);
+        self.public.verify(&hash.as_bytes()[..], &self.signature)
+    }
+
+    /// Verify that a received chunk matches its announced hash.
+    pub fn verify_chunk(&self, index: usize, data: &[u8]) -> bool {
+        match self.chunks.get(index) {
+            Some(hash) => *hash == blake3::hash(data),
+            None => false,
+        }
+    }
+}
+
+/// Structure holding a signed snapshot along with its serialized data,
+/// as stored in the [`SnapshotStore`](crate::blockchain::SnapshotStore).
+#[derive(Clone, SerialEncodable, SerialDecodable)]
+pub struct Snapshot {
+    /// Signed snapshot header
+    pub header: SnapshotHeader,
+    /// Serialized [`StateSnapshot`]
+    pub data: Vec<u8>,
+}
+
+impl Snapshot {
+    /// Serialize and sign the given [`StateSnapshot`].
+    pub fn new(state: &StateSnapshot, secret: &SecretKey) -> Self {
+        let data = serialize(state);
+        let header = SnapshotHeader::new(
+            state.block.header.slot,
+            state.block.header.headerhash(),
+            &data,
+            secret,
+        );
+        Self { header, data }
+    }
+
+    /// Retrieve the chunk with the given index.
+    pub fn chunk(&self, index: u32) -> Result<Vec<u8>> {
+        match self.data.chunks(SNAPSHOT_CHUNK_SIZE).nth(index as usize) {
+            Some(chunk) => Ok(chunk.to_vec()),
+            None => Err(Error::SnapshotChunkNotFound(self.header.slot, index)),
+        }
+    }
+}
+
+/// Auxiliary structure used to request the latest snapshot a peer holds.
+#[derive(Debug, SerialEncodable, SerialDecodable)]
+pub struct SnapshotRequest {
+    /// Only snapshots after this slot are of interest
+    pub after: u64,
+}
+
+impl net::Message for SnapshotRequest {
+    fn name() -> &'static str {
+        "snapshotrequest"
+    }
+}
+
+/// Auxiliary structure used as a response to [`SnapshotRequest`].
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct SnapshotResponse {
+    /// Latest snapshot header, if one newer than requested exists
+    pub header: Option<SnapshotHeader>,
+}
+
+impl net::Message for SnapshotResponse {
+    fn name() -> &'static str {
+        "snapshotresponse"
+    }
+}
+
+/// Auxiliary structure used to request a snapshot chunk.
+#[derive(Debug, SerialEncodable, SerialDecodable)]
+pub struct SnapshotChunkRequest {
+    /// Slot of the requested snapshot
+    pub slot: u64,
+    /// Index of the requested chunk
+    pub index: u32,
+}
+
+impl net::Message for SnapshotChunkRequest {
+    fn name() -> &'static str {
+        "snapshotchunkrequest"
+    }
+}
+
+/// Auxiliary structure used as a response to [`SnapshotChunkRequest`].
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct SnapshotChunk {
+    /// Slot of the snapshot
+    pub slot: u64,
+    /// Index of the chunk
+    pub index: u32,
+    /// Chunk data, empty if the peer doesn't hold the snapshot
+    pub data: Vec<u8>,
+}
+
+impl net::Message for SnapshotChunk {
+    fn name() -> &'static str {
+        "snapshotchunk"
+    }
+
+    fn decode_payload(payload: Vec<u8>) -> Result<Self> {
+        let ((slot, index), data) = net::decode_blob_payload(payload, Self::decode_limits())?;
+        Ok(Self { slot, index, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn snapshot_header_verify() {
+        let secret = SecretKey::random(&mut OsRng);
+        let public = PublicKey::from_secret(secret);
+        let data = vec![42u8; SNAPSHOT_CHUNK_SIZE * 2 + 1];
+        let header = SnapshotHeader::new(100, blake3::hash(b"block"), &data, &secret);
+
+        assert_eq!(header.chunks.len(), 3);
+        assert!(header.verify(&[public]));
+        assert!(!header.verify(&[PublicKey::from_secret(SecretKey::random(&mut OsRng))]));
+
+        let mut chunks = data.chunks(SNAPSHOT_CHUNK_SIZE);
+        assert!(header.verify_chunk(0, chunks.next().unwrap()));
+        assert!(!header.verify_chunk(1, &[0u8; 10]));
+        assert!(!header.verify_chunk(3, &[]));
+
+        let mut forged = header.clone();
+        forged.slot = 101;
+        assert!(!forged.verify(&[public]));
+    }
+}
@@ -15,8 +15,9 @@ use log::{debug, error, info, warn};
 use rand::rngs::OsRng;
 
 use super::{
+    snapshot::{SNAPSHOTS_KEPT, SNAPSHOT_INTERVAL},
     Block, BlockInfo, BlockProposal, Header, KeepAlive, LeadProof, Metadata, Participant,
-    ProposalChain,
+    ProposalChain, Snapshot, StateSnapshot,
 };
 
 use crate::{
@@ -28,12 +29,12 @@ use crate::{
     },
     net,
     node::{
-        state::{state_transition, ProgramState, StateUpdate},
+        state::{check_state_transition, ProgramState, StateUpdate},
         Client, MemoryState, State,
     },
     tx::Transaction,
     util::time::Timestamp,
-    Result,
+    Result, VerifyFailed,
 };
 
 /// `2 * DELTA` represents slot time
@@ -144,8 +145,15 @@ impl ValidatorState {
         let participating = None;
 
         let address = client.wallet.get_default_address().await?;
+
+        // Nodes from before the tree had its own store kept it in the wallet
+        let tree = match blockchain.merkle_tree.get()? {
+            Some(tree) => tree,
+            None => client.get_tree().await?,
+        };
+
         let state_machine = Arc::new(Mutex::new(State {
-            tree: client.get_tree().await?,
+            tree,
             merkle_roots: blockchain.merkle_roots.clone(),
             nullifiers: blockchain.nullifiers.clone(),
             cashier_pubkeys,
@@ -555,6 +563,7 @@ impl ValidatorState {
 
         chain.proposals.drain(0..bound);
 
+        let (prev_slot, _) = self.blockchain.last()?;
         info!("consensus: Adding {} finalized block to canonical chain.", finalized.len());
         let blockhashes = match self.blockchain.add(&finalized) {
             Ok(v) => v,
@@ -571,13 +580,21 @@ impl ValidatorState {
             let canon_state_clone = self.state_machine.lock().await.clone();
             let mem_st = MemoryState::new(canon_state_clone);
             let state_updates = Self::validate_state_transitions(mem_st, &proposal.txs)?;
-            self.update_canon_state(state_updates, None).await?;
+            self.update_canon_state(state_updates).await?;
             self.remove_txs(proposal.txs.clone())?;
         }
 
         let last_block = *blockhashes.last().unwrap();
         let last_slot = finalized.last().unwrap().header.slot;
 
+        // A snapshot is produced every time finalization crosses a snapshot
+        // interval boundary. Failing to do so must not halt consensus.
+        if last_slot / SNAPSHOT_INTERVAL > prev_slot / SNAPSHOT_INTERVAL {
+            if let Err(e) = self.create_snapshot(finalized.last().unwrap()).await {
+                error!("consensus: Failed creating state snapshot: {}", e);
+            }
+        }
+
         let mut dropped = vec![];
         for chain in self.consensus.proposals.iter() {
             let first = chain.proposals.first().unwrap();
@@ -774,7 +791,7 @@ impl ValidatorState {
         debug!("receive_blocks(): All state transitions passed");
 
         debug!("receive_blocks(): Updating canon state");
-        self.update_canon_state(canon_updates, None).await?;
+        self.update_canon_state(canon_updates).await?;
 
         debug!("receive_blocks(): Appending blocks to ledger");
         self.blockchain.add(blocks)?;
@@ -838,8 +855,67 @@ impl ValidatorState {
         Ok(())
     }
 
+    /// Create a signed [`Snapshot`] of the canonical state at the given
+    /// finalized block, and store it in the snapshot store. Only the
+    /// `SNAPSHOTS_KEPT` most recent snapshots are kept.
+    pub async fn create_snapshot(&self, block: &BlockInfo) -> Result<()> {
+        info!("create_snapshot(): Creating state snapshot for slot {}", block.header.slot);
+        let tree = self.state_machine.lock().await.tree.clone();
+        let state = StateSnapshot {
+            block: block.clone(),
+            tree,
+            merkle_roots: self.blockchain.merkle_roots.get_all()?,
+            nullifiers: self.blockchain.nullifiers.get_all()?,
+        };
+
+        let keypair = self.client.wallet.get_default_keypair().await?;
+        let snapshot = Snapshot::new(&state, &keypair.secret);
+        debug!("create_snapshot(): Snapshot size: {} bytes", snapshot.data.len());
+
+        self.blockchain.snapshots.insert(&snapshot)?;
+        self.blockchain.snapshots.prune(SNAPSHOTS_KEPT)?;
+        Ok(())
+    }
+
+    /// Replace the canonical state with a verified [`StateSnapshot`] and
+    /// append its block to the ledger. The snapshot is also stored, so the
+    /// node can serve it to other peers.
+    /// Blocks before the snapshot can't be scanned, so the local wallet
+    /// continues scanning from the snapshot's tree and slot, and coins
+    /// received before it will not be found. The snapshot's slot is
+    /// recorded so other wallets scanning this ledger can be refused too.
+    pub async fn apply_snapshot(&mut self, state: StateSnapshot, snapshot: Snapshot) -> Result<()> {
+        let (last_slot, _) = self.blockchain.last()?;
+        if state.block.header.slot <= last_slot {
+            debug!("apply_snapshot(): Snapshot is older than our last block, skipping");
+            return Ok(())
+        }
+
+        debug!("apply_snapshot(): Acquiring state machine lock");
+        let mut state_machine = self.state_machine.lock().await;
+        state_machine.merkle_roots.insert(&state.merkle_roots)?;
+        state_machine.nullifiers.insert(&state.nullifiers)?;
+        state_machine.tree = state.tree;
+        self.blockchain.merkle_tree.insert(&state_machine.tree)?;
+        self.client.wallet.put_tree(&state_machine.tree).await?;
+        self.client.wallet.put_scan_height(state.block.header.slot).await?;
+        drop(state_machine);
+        debug!("apply_snapshot(): Dropped state machine lock");
+
+        debug!("apply_snapshot(): Appending snapshot block to ledger");
+        self.blockchain.add(&[state.block])?;
+        self.blockchain.snapshots.set_synced_from(snapshot.header.slot)?;
+
+        self.blockchain.snapshots.insert(&snapshot)?;
+        self.blockchain.snapshots.prune(SNAPSHOTS_KEPT)?;
+        Ok(())
+    }
+
     /// Validate state transitions for given transactions and state and
-    /// return a vector of [`StateUpdate`]
+    /// return a vector of [`StateUpdate`].
+    /// State checks are applied sequentially, since each transaction can
+    /// depend on the previous ones, while the transactions themselves are
+    /// verified in parallel, batching all their zk proofs together.
     pub fn validate_state_transitions(
         state: MemoryState,
         txs: &[Transaction],
@@ -848,36 +924,34 @@ impl ValidatorState {
         let mut st = state;
 
         for (i, tx) in txs.iter().enumerate() {
-            let update = match state_transition(&st, tx.clone()) {
+            let update = match check_state_transition(&st, tx) {
                 Ok(v) => v,
                 Err(e) => {
                     warn!("validate_state_transition(): Failed for tx {}: {}", i, e);
-                    return Err(e.into())
+                    return Err(VerifyFailed::Transaction(i, Box::new(e)).into())
                 }
             };
             st.apply(update.clone());
             ret.push(update);
         }
 
+        if let Err(e) = Transaction::verify_batch(txs, st.mint_vk(), st.burn_vk()) {
+            warn!("validate_state_transition(): Failed verifying transactions: {}", e);
+            return Err(e.into())
+        }
+
         Ok(ret)
     }
 
-    /// Apply a vector of [`StateUpdate`] to the canonical state.
-    pub async fn update_canon_state(
-        &self,
-        updates: Vec<StateUpdate>,
-        notify: Option<smol::channel::Sender<(PublicKey, u64)>>,
-    ) -> Result<()> {
-        let secret_keys: Vec<SecretKey> =
-            self.client.get_keypairs().await?.iter().map(|x| x.secret).collect();
-
+    /// Apply a vector of [`StateUpdate`] to the canonical state, and store
+    /// its updated Merkle tree.
+    pub async fn update_canon_state(&self, updates: Vec<StateUpdate>) -> Result<()> {
         debug!("update_canon_state(): Acquiring state machine lock");
         let mut state = self.state_machine.lock().await;
         for update in updates {
-            state
-                .apply(update, secret_keys.clone(), notify.clone(), self.client.wallet.clone())
-                .await?;
+            state.apply(update)?;
         }
+        self.blockchain.merkle_tree.insert(&state.tree)?;
         drop(state);
         debug!("update_canon_state(): Dropped state machine lock");
 
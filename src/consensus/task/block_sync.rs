@@ -1,3 +1,7 @@
+use std::time::Duration;
+
+use async_std::future::timeout;
+
 use crate::{
     consensus::{
         block::{BlockOrder, BlockResponse},
@@ -29,13 +33,29 @@ pub async fn block_sync_task(p2p: net::P2pPtr, state: ValidatorStatePtr) -> Resu
         let mut last = state.read().await.blockchain.last()?;
         info!("Last known block: {:?} - {:?}", last.0, last.1);
 
+        // Orders or responses can get lost, in which case the order is sent again
+        let response_timeout = Duration::from_secs(p2p.settings().connect_timeout_seconds.into());
+
         loop {
             // Node creates a `BlockOrder` and sends it
             let order = BlockOrder { slot: last.0, block: last.1 };
             channel.send(order).await?;
 
             // Node stores response data.
-            let resp = response_sub.receive().await?;
+            let resp = match timeout(response_timeout, response_sub.receive()).await {
+                Ok(v) => v?,
+                Err(_) => {
+                    warn!("block_sync_task(): No response to order for slot {}, retrying", last.0);
+                    continue
+                }
+            };
+
+            // A late response to an order sent again doesn't start after the
+            // last known block, and would look like the end of the chain.
+            if resp.blocks.first().map_or(false, |x| x.header.slot <= last.0) {
+                debug!("block_sync_task(): Skipping stale response");
+                continue
+            }
 
             // Verify and store retrieved blocks
             debug!("block_sync_task(): Processing received blocks");
@@ -57,3 +77,89 @@ pub async fn block_sync_task(p2p: net::P2pPtr, state: ValidatorStatePtr) -> Resu
     info!("Blockchain synced!");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use async_std::sync::Arc;
+    use darkfi_sdk::crypto::MerkleNode;
+    use pasta_curves::pallas;
+    use smol::Executor;
+
+    use super::*;
+    use crate::{
+        consensus::{block::Header, proto::ProtocolSync, BlockInfo, Metadata, ValidatorState},
+        net::{sim::Simulation, transport::LinkConditions},
+        node::Client,
+        util::time::Timestamp,
+        wallet::walletdb::WalletDb,
+    };
+
+    /// Steps the test allows for the sync to finish
+    const MAX_STEPS: usize = 5000;
+
+    async fn validator(genesis_ts: Timestamp, genesis_data: blake3::Hash) -> ValidatorStatePtr {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let wallet = WalletDb::new("sqlite::memory:", "darkfi").await.unwrap();
+        let client = Arc::new(Client::new(wallet).await.unwrap());
+        ValidatorState::new(&db, genesis_ts, genesis_data, client, vec![], vec![]).await.unwrap()
+    }
+
+    #[test]
+    fn block_sync_simulation_test() {
+        let executor = Arc::new(Executor::new());
+        let ex = executor.clone();
+
+        smol::block_on(executor.run(async move {
+            let genesis_ts = Timestamp::current_time();
+            let genesis_data = blake3::hash(b"block_sync_simulation");
+            let states = vec![
+                validator(genesis_ts, genesis_data).await,
+                validator(genesis_ts, genesis_data).await,
+            ];
+
+            // The first node has finalized a few batches of blocks
+            let (_, mut previous) = states[0].read().await.blockchain.last().unwrap();
+            for slot in 1..=25 {
+                let root = MerkleNode::from(pallas::Base::from(slot));
+                let header = Header::new(previous, 0, slot, Timestamp::current_time(), root);
+                previous = header.headerhash();
+                let block = BlockInfo::new(header, vec![], Metadata::default());
+                states[0].write().await.receive_blocks(&[block]).await.unwrap();
+            }
+
+            let sim = Simulation::new(2, 42, ex.clone()).await;
+            for (node, state) in sim.nodes().iter().zip(&states) {
+                let state = state.clone();
+                node.protocol_registry()
+                    .register(net::SESSION_ALL, move |channel, p2p| {
+                        let state = state.clone();
+                        async move { ProtocolSync::init(channel, state, p2p, false).await.unwrap() }
+                    })
+                    .await;
+            }
+
+            sim.start().await.unwrap();
+            sim.wait_for_channels(1, MAX_STEPS).await.unwrap();
+
+            // The second node syncs over a slow link losing some of the
+            // orders and responses.
+            sim.set_default_link(LinkConditions { latency: Duration::from_millis(50), loss: 0.2 });
+
+            let (result_send, result_recv) = smol::channel::bounded(1);
+            let (node, state) = (sim.node(1), states[1].clone());
+            ex.spawn(async move {
+                result_send.send(block_sync_task(node, state).await).await.unwrap();
+            })
+            .detach();
+
+            sim.run_until(MAX_STEPS, || async { !result_recv.is_empty() }).await.unwrap();
+            result_recv.recv().await.unwrap().unwrap();
+
+            let synced = states[1].read().await.blockchain.last().unwrap();
+            assert_eq!(synced, (25, previous));
+            assert_eq!(synced, states[0].read().await.blockchain.last().unwrap());
+
+            sim.stop().await;
+        }));
+    }
+}
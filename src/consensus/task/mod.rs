@@ -3,6 +3,9 @@
 mod block_sync;
 pub use block_sync::block_sync_task;
 
+mod snapshot_sync;
+pub use snapshot_sync::snapshot_sync_task;
+
 mod consensus_sync;
 pub use consensus_sync::consensus_sync_task;
 
@@ -0,0 +1,283 @@
+use std::{future::Future, time::Duration};
+
+use async_std::future::timeout;
+use log::{debug, info, warn};
+
+use crate::{
+    consensus::{
+        snapshot::{
+            Snapshot, SnapshotChunk, SnapshotChunkRequest, SnapshotHeader, SnapshotRequest,
+            SnapshotResponse, StateSnapshot,
+        },
+        ValidatorStatePtr,
+    },
+    crypto::keypair::PublicKey,
+    net::{ChannelPtr, MessageSubscription, P2pPtr},
+    Error, Result,
+};
+
+/// Seconds to wait for a peer to reply to a snapshot or chunk request
+const SNAPSHOT_REPLY_TIMEOUT: u64 = 10;
+
+/// async task used for syncing the canonical state from the latest snapshot
+/// held by our peers. Snapshots are only accepted if they are signed by one
+/// of the `trusted` public keys. After this task completes, the node should
+/// run [`block_sync_task`](super::block_sync_task) to retrieve the blocks
+/// finalized after the snapshot.
+pub async fn snapshot_sync_task(
+    p2p: P2pPtr,
+    state: ValidatorStatePtr,
+    trusted: &[PublicKey],
+) -> Result<()> {
+    info!("Starting snapshot sync...");
+    let channels: Vec<ChannelPtr> = p2p.channels().lock().await.values().cloned().collect();
+    if channels.is_empty() {
+        warn!("Node is not connected to other nodes");
+        return Ok(())
+    }
+
+    let (last_slot, _) = state.read().await.blockchain.last()?;
+    info!("Last known block slot: {}", last_slot);
+
+    // Node asks every peer for their latest snapshot and keeps the
+    // most recent valid one, along with the peers serving it.
+    // Peers not answering in time are skipped.
+    let mut best: Option<SnapshotHeader> = None;
+    let mut peers: Vec<(ChannelPtr, MessageSubscription<SnapshotChunk>)> = vec![];
+    for channel in channels {
+        let msg_subsystem = channel.get_message_subsystem();
+        msg_subsystem.add_dispatch::<SnapshotResponse>().await;
+        msg_subsystem.add_dispatch::<SnapshotChunk>().await;
+        let response_sub = channel.subscribe_msg::<SnapshotResponse>().await?;
+        let chunk_sub = channel.subscribe_msg::<SnapshotChunk>().await?;
+
+        let response = request_snapshot(&channel, &response_sub, last_slot).await;
+        response_sub.unsubscribe().await;
+
+        let header = match response {
+            Ok(Some(v)) => v,
+            Ok(None) => {
+                debug!("snapshot_sync_task(): Peer {} has no newer snapshot", channel.address());
+                chunk_sub.unsubscribe().await;
+                continue
+            }
+            Err(e) => {
+                warn!("snapshot_sync_task(): Peer {} didn't reply: {}", channel.address(), e);
+                chunk_sub.unsubscribe().await;
+                continue
+            }
+        };
+
+        if header.slot <= last_slot || !header.verify(trusted) {
+            warn!("snapshot_sync_task(): Peer {} sent an invalid snapshot", channel.address());
+            chunk_sub.unsubscribe().await;
+            continue
+        }
+
+        match &best {
+            Some(b) if b.slot > header.slot => {
+                chunk_sub.unsubscribe().await;
+                continue
+            }
+            Some(b) if b.slot == header.slot && b.chunks == header.chunks => {}
+            _ => {
+                best = Some(header);
+                for (_, sub) in peers.drain(..) {
+                    sub.unsubscribe().await;
+                }
+            }
+        }
+
+        peers.push((channel, chunk_sub));
+    }
+
+    let header = match best {
+        Some(v) => v,
+        None => {
+            info!("No newer snapshot found, falling back to full block sync");
+            return Ok(())
+        }
+    };
+
+    info!(
+        "Retrieving snapshot for slot {} ({} chunks) from {} peers",
+        header.slot,
+        header.chunks.len(),
+        peers.len()
+    );
+
+    let (slot, serving) = (header.slot, &peers);
+    let snapshot = retrieve_snapshot(&header, peers.len(), move |peer, index| {
+        let (channel, chunk_sub) = &serving[peer];
+        request_chunk(channel, chunk_sub, slot, index)
+    })
+    .await;
+
+    for (_, chunk_sub) in &peers {
+        chunk_sub.unsubscribe().await;
+    }
+
+    let (snapshot, data) = snapshot?;
+    state.write().await.apply_snapshot(snapshot, Snapshot { header, data }).await?;
+
+    info!("Snapshot synced!");
+    Ok(())
+}
+
+/// Ask a peer for its latest snapshot after the given slot.
+async fn request_snapshot(
+    channel: &ChannelPtr,
+    response_sub: &MessageSubscription<SnapshotResponse>,
+    after: u64,
+) -> Result<Option<SnapshotHeader>> {
+    channel.send(SnapshotRequest { after }).await?;
+    let response =
+        timeout(Duration::from_secs(SNAPSHOT_REPLY_TIMEOUT), response_sub.receive()).await??;
+    Ok(response.header.clone())
+}
+
+/// Ask a peer for a snapshot chunk.
+async fn request_chunk(
+    channel: &ChannelPtr,
+    chunk_sub: &MessageSubscription<SnapshotChunk>,
+    slot: u64,
+    index: u32,
+) -> Result<SnapshotChunk> {
+    channel.send(SnapshotChunkRequest { slot, index }).await?;
+    let chunk = timeout(Duration::from_secs(SNAPSHOT_REPLY_TIMEOUT), chunk_sub.receive()).await??;
+    Ok((*chunk).clone())
+}
+
+/// Retrieve the chunks of the snapshot described by `header` from `peers`
+/// serving it, using `fetch(peer, index)` to request a chunk from a peer.
+/// Chunks are requested in a round-robin fashion, and a chunk failing to
+/// arrive or to verify is requested again from the next peer.
+/// Returns the snapshot along with its serialized data.
+async fn retrieve_snapshot<F, Fut>(
+    header: &SnapshotHeader,
+    peers: usize,
+    fetch: F,
+) -> Result<(StateSnapshot, Vec<u8>)>
+where
+    F: Fn(usize, u32) -> Fut,
+    Fut: Future<Output = Result<SnapshotChunk>>,
+{
+    let mut data = vec![];
+    for index in 0..header.chunks.len() {
+        let mut retrieved = false;
+        for attempt in 0..peers {
+            let peer = (index + attempt) % peers;
+            let chunk = match fetch(peer, index as u32).await {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("snapshot_sync_task(): Failed retrieving chunk {}: {}", index, e);
+                    continue
+                }
+            };
+
+            if chunk.slot != header.slot ||
+                chunk.index as usize != index ||
+                !header.verify_chunk(index, &chunk.data)
+            {
+                warn!("snapshot_sync_task(): Peer sent an invalid chunk {}", index);
+                continue
+            }
+
+            data.extend_from_slice(&chunk.data);
+            retrieved = true;
+            break
+        }
+
+        if !retrieved {
+            return Err(Error::SnapshotChunkNotFound(header.slot, index as u32))
+        }
+        debug!("snapshot_sync_task(): Retrieved chunk {}/{}", index + 1, header.chunks.len());
+    }
+
+    let snapshot = StateSnapshot::from_data(header, &data)?;
+    Ok((snapshot, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::sync::Arc;
+    use darkfi_sdk::crypto::{MerkleNode, Nullifier};
+    use incrementalmerkletree::Tree;
+    use pasta_curves::{group::ff::Field, pallas};
+    use rand::rngs::OsRng;
+
+    use crate::{
+        consensus::{block::Header, BlockInfo, Metadata, ValidatorState},
+        node::Client,
+        util::time::Timestamp,
+        wallet::walletdb::WalletDb,
+    };
+
+    async fn validator(genesis_ts: Timestamp, genesis_data: blake3::Hash) -> ValidatorStatePtr {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let wallet = WalletDb::new("sqlite::memory:", "darkfi").await.unwrap();
+        let client = Arc::new(Client::new(wallet).await.unwrap());
+        ValidatorState::new(&db, genesis_ts, genesis_data, client, vec![], vec![]).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn snapshot_sync() -> Result<()> {
+        let genesis_ts = Timestamp::current_time();
+        let genesis_data = blake3::hash(b"snapshot_sync");
+        let alice = validator(genesis_ts, genesis_data).await;
+        let bob = validator(genesis_ts, genesis_data).await;
+
+        // Alice's state holds a coin and a nullifier
+        let nullifier = Nullifier::from(pallas::Base::random(&mut OsRng));
+        let alice_root = {
+            let alice = alice.read().await;
+            let mut state_machine = alice.state_machine.lock().await;
+            state_machine.tree.append(&MerkleNode::from(pallas::Base::random(&mut OsRng)));
+            let root = state_machine.tree.root(0).unwrap();
+            alice.blockchain.merkle_roots.insert(&[root])?;
+            alice.blockchain.nullifiers.insert(&[nullifier])?;
+            root
+        };
+
+        let (_, genesis) = alice.read().await.blockchain.last()?;
+        let header = Header::new(genesis, 0, 5, Timestamp::current_time(), alice_root);
+        let block = BlockInfo::new(header, vec![], Metadata::default());
+        alice.read().await.create_snapshot(&block).await?;
+
+        let snapshot = alice.read().await.blockchain.snapshots.get_last()?.unwrap();
+        let trusted = [alice.read().await.client.wallet.get_default_keypair().await?.public];
+        assert!(snapshot.header.verify(&trusted));
+
+        // The first peer serves garbage, so every chunk comes from the second
+        let (state, data) = retrieve_snapshot(&snapshot.header, 2, |peer, index| {
+            let chunk = SnapshotChunk {
+                slot: snapshot.header.slot,
+                index,
+                data: if peer == 0 { vec![0; 32] } else { snapshot.chunk(index).unwrap() },
+            };
+            async move { Ok(chunk) }
+        })
+        .await?;
+        assert_eq!(data, snapshot.data);
+
+        // No peer serving valid chunks
+        let failed = retrieve_snapshot(&snapshot.header, 1, |_, _| async {
+            Err::<SnapshotChunk, _>(Error::TimeoutError)
+        })
+        .await;
+        assert!(matches!(failed, Err(Error::SnapshotChunkNotFound(5, 0))));
+
+        let header = snapshot.header.clone();
+        bob.write().await.apply_snapshot(state, Snapshot { header, data }).await?;
+
+        let bob = bob.read().await;
+        assert_eq!(bob.blockchain.last()?, (5, block.header.headerhash()));
+        assert_eq!(bob.state_machine.lock().await.tree.root(0).unwrap(), alice_root);
+        assert!(bob.blockchain.nullifiers.contains(&nullifier)?);
+        assert!(bob.blockchain.merkle_roots.contains(&alice_root)?);
+        assert!(bob.blockchain.snapshots.contains(5)?);
+
+        Ok(())
+    }
+}
@@ -3,10 +3,14 @@ use std::str::FromStr;
 use darkfi_serial::{SerialDecodable, SerialEncodable};
 use sha2::Digest;
 
-use crate::{crypto::keypair::PublicKey, Error, Result};
+use crate::{
+    crypto::keypair::{PublicKey, ViewKey},
+    Error, Result,
+};
 
 enum AddressType {
     Payment = 0,
+    Viewable = 1,
 }
 
 #[derive(
@@ -80,12 +84,96 @@ impl From<PublicKey> for Address {
     }
 }
 
+/// Address of a keypair whose notes are encrypted to its view key, see
+/// [`ViewKey`]. It holds the spending public key the coins are minted to,
+/// and the view public key their notes are encrypted to. Wallets holding
+/// the view key then detect the coins paid to this address.
+#[derive(
+    Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash, SerialEncodable, SerialDecodable,
+)]
+pub struct ViewableAddress(pub [u8; 69]);
+
+impl ViewableAddress {
+    pub fn new(public: PublicKey, view_public: PublicKey) -> Self {
+        let mut address = vec![AddressType::Viewable as u8];
+        address.extend_from_slice(&public.to_bytes());
+        address.extend_from_slice(&view_public.to_bytes());
+
+        // add the 4 first bytes of the hash as checksum
+        let payload_hash = sha2::Sha256::digest(&address);
+        address.extend_from_slice(&payload_hash[..4]);
+
+        let mut viewable_address = [0u8; 69];
+        viewable_address.copy_from_slice(&address);
+        Self(viewable_address)
+    }
+
+    /// Spending public key, which the coins are minted to.
+    pub fn public(&self) -> Result<PublicKey> {
+        PublicKey::from_bytes(self.0[1..33].try_into().unwrap())
+    }
+
+    /// View public key, which the notes are encrypted to.
+    pub fn view_public(&self) -> Result<PublicKey> {
+        PublicKey::from_bytes(self.0[33..65].try_into().unwrap())
+    }
+
+    fn is_valid_address(address: &[u8]) -> bool {
+        if address.starts_with(&[AddressType::Viewable as u8]) && address.len() == 69 {
+            let payload_hash = sha2::Sha256::digest(&address[..65]);
+            payload_hash[..4] == address[65..]
+        } else {
+            false
+        }
+    }
+}
+
+impl std::fmt::Display for ViewableAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", bs58::encode(self.0).into_string())
+    }
+}
+
+impl FromStr for ViewableAddress {
+    type Err = Error;
+
+    fn from_str(address: &str) -> Result<Self> {
+        if let Ok(v) = bs58::decode(&address).into_vec() {
+            if Self::is_valid_address(&v) {
+                let mut bytes_arr = [0u8; 69];
+                bytes_arr.copy_from_slice(&v);
+                return Ok(Self(bytes_arr))
+            }
+        }
+
+        Err(Error::InvalidAddress)
+    }
+}
+
+impl From<ViewKey> for ViewableAddress {
+    fn from(view_key: ViewKey) -> Self {
+        Self::new(view_key.public, view_key.view_public())
+    }
+}
+
+/// Parse the address of a payment recipient, either an [`Address`] or a
+/// [`ViewableAddress`]. Returns the public key to mint the coin to, and
+/// the one to encrypt its note to if it isn't the same.
+pub fn parse_recipient(address: &str) -> Result<(PublicKey, Option<PublicKey>)> {
+    if let Ok(address) = Address::from_str(address) {
+        return Ok((PublicKey::try_from(address)?, None))
+    }
+
+    let address = ViewableAddress::from_str(address)?;
+    Ok((address.public()?, Some(address.view_public()?)))
+}
+
 #[cfg(test)]
 mod tests {
     use rand::rngs::OsRng;
 
     use super::*;
-    use crate::crypto::keypair::{Keypair, PublicKey};
+    use crate::crypto::keypair::{Keypair, PublicKey, ViewKey};
 
     #[test]
     fn test_address() -> Result<()> {
@@ -98,6 +186,20 @@ mod tests {
         let address_str = address.to_string();
         let from_str = Address::from_str(&address_str)?;
         assert_eq!(from_str, address);
+        assert_eq!(parse_recipient(&address_str)?, (keypair.public, None));
+
+        // Viewable addresses carry the view public key
+        let view_key = ViewKey::from(keypair);
+        assert_ne!(view_key.view_public(), keypair.public);
+        let viewable = ViewableAddress::from(view_key);
+        assert_eq!(viewable.public()?, keypair.public);
+        assert_eq!(viewable.view_public()?, view_key.view_public());
+
+        let viewable_str = viewable.to_string();
+        assert_eq!(ViewableAddress::from_str(&viewable_str)?, viewable);
+        assert!(Address::from_str(&viewable_str).is_err());
+        assert!(ViewableAddress::from_str(&address_str).is_err());
+        assert_eq!(parse_recipient(&viewable_str)?, (keypair.public, Some(view_key.view_public())));
 
         Ok(())
     }
@@ -1,8 +1,17 @@
 use darkfi_sdk::crypto::Nullifier;
 use darkfi_serial::{SerialDecodable, SerialEncodable};
-use pasta_curves::{group::ff::PrimeField, pallas};
+use pasta_curves::{
+    arithmetic::CurveAffine,
+    group::{ff::PrimeField, Curve},
+    pallas,
+};
 
-use super::{keypair::SecretKey, note::Note};
+use super::{
+    keypair::{PublicKey, SecretKey},
+    note::Note,
+    types::{DrkSpendHook, DrkUserData, DrkValue},
+    util::poseidon_hash,
+};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, SerialEncodable, SerialDecodable)]
 pub struct Coin(pub pallas::Base);
@@ -15,6 +24,24 @@ impl Coin {
     pub fn to_bytes(self) -> [u8; 32] {
         self.0.to_repr()
     }
+
+    /// Compute the coin a note is for, when sent to `public`. Notes don't
+    /// carry a spend hook nor user data, so these are zero, as in the coins
+    /// made by the transaction builder.
+    pub fn from_note(public: &PublicKey, note: &Note) -> Self {
+        let coords = public.0.to_affine().coordinates().unwrap();
+
+        Coin(poseidon_hash::<8>([
+            *coords.x(),
+            *coords.y(),
+            DrkValue::from(note.value),
+            note.token_id,
+            note.serial,
+            DrkSpendHook::from(0),
+            DrkUserData::from(0),
+            note.coin_blind,
+        ]))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, SerialEncodable, SerialDecodable)]
@@ -25,3 +52,50 @@ pub struct OwnCoin {
     pub nullifier: Nullifier,
     pub leaf_position: incrementalmerkletree::Position,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{
+        keypair::Keypair,
+        mint_proof::MintRevealedValues,
+        types::{DrkCoinBlind, DrkSerial, DrkTokenId, DrkValueBlind},
+    };
+    use pasta_curves::group::ff::Field;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_coin_from_note() {
+        let keypair = Keypair::random(&mut OsRng);
+        let note = Note {
+            serial: DrkSerial::random(&mut OsRng),
+            value: 110,
+            token_id: DrkTokenId::random(&mut OsRng),
+            coin_blind: DrkCoinBlind::random(&mut OsRng),
+            value_blind: DrkValueBlind::random(&mut OsRng),
+            token_blind: DrkValueBlind::random(&mut OsRng),
+            memo: vec![],
+        };
+
+        let revealed = MintRevealedValues::compute(
+            note.value,
+            note.token_id,
+            note.value_blind,
+            note.token_blind,
+            note.serial,
+            DrkSpendHook::from(0),
+            DrkUserData::from(0),
+            note.coin_blind,
+            keypair.public,
+        );
+        assert_eq!(Coin::from_note(&keypair.public, &note), revealed.coin);
+
+        // Any other value makes another coin
+        let mut forged = note.clone();
+        forged.value = 1000;
+        assert_ne!(Coin::from_note(&keypair.public, &forged), revealed.coin);
+
+        let other = Keypair::random(&mut OsRng);
+        assert_ne!(Coin::from_note(&other.public, &note), revealed.coin);
+    }
+}
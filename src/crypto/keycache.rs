@@ -0,0 +1,295 @@
+use std::{
+    fs,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use fxhash::FxHashMap;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+
+use crate::{
+    crypto::{
+        proof::{ProvingKey, VerifyingKey},
+        types::DrkCircuitField,
+    },
+    util::path::{expand_path, home_dir},
+    Error, Result,
+};
+
+/// Default path of the key cache directory, shared by all binaries
+pub const DEFAULT_KEY_CACHE_PATH: &str = "~/.cache/darkfi/zk_keys";
+
+lazy_static! {
+    /// Proving keys loaded or built by this process, by circuit hash and `k`
+    static ref PROVING_KEYS: Mutex<FxHashMap<(blake3::Hash, u32), ProvingKey>> =
+        Mutex::new(FxHashMap::default());
+
+    /// Verifying keys loaded or built by this process, by circuit hash and `k`
+    static ref VERIFYING_KEYS: Mutex<FxHashMap<(blake3::Hash, u32), VerifyingKey>> =
+        Mutex::new(FxHashMap::default());
+}
+
+/// On-disk cache of zk proving and verifying keys.
+///
+/// Entries are keyed by a circuit hash and the `k` parameter. For zkas
+/// circuits the hash is the hash of the compiled zkas binary, while native
+/// circuits use a hash of their configured constraint system, so changing
+/// a circuit never hits the entries of its older versions.
+///
+/// halo2 can't serialize or restore proving and verifying keys, so an
+/// entry holds the commitment params for `k` and the fingerprint of the
+/// key, followed by a checksum. The key itself is generated again from
+/// the params on load, and checked against the fingerprint. Invalid
+/// entries are rebuilt and overwritten.
+///
+/// Keys are also kept in memory once loaded or built, so asking for the
+/// same key again in the process skips keygen entirely.
+pub struct KeyCache {
+    /// Cache directory, or `None` if keys are never cached
+    path: Option<PathBuf>,
+}
+
+impl Default for KeyCache {
+    /// Cache in [`DEFAULT_KEY_CACHE_PATH`]. Without a home directory,
+    /// keys are built without being cached.
+    fn default() -> Self {
+        if home_dir().is_none() {
+            warn!("No home directory, zk keys will not be cached");
+            return Self { path: None }
+        }
+
+        match Self::new(DEFAULT_KEY_CACHE_PATH) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Invalid zk key cache path, keys will not be cached: {}", e);
+                Self { path: None }
+            }
+        }
+    }
+}
+
+impl KeyCache {
+    /// Create a key cache in the given directory.
+    pub fn new(path: &str) -> Result<Self> {
+        Ok(Self { path: Some(expand_path(path)?) })
+    }
+
+    /// Circuit hash for a compiled zkas binary.
+    pub fn zkas_circuit_id(bincode: &[u8]) -> blake3::Hash {
+        blake3::hash(bincode)
+    }
+
+    /// Circuit hash for a native circuit, covering its type name and its
+    /// configured constraint system: gates, lookups and column layout.
+    pub fn native_circuit_id<C: Circuit<DrkCircuitField>>() -> blake3::Hash {
+        let mut cs = ConstraintSystem::default();
+        let _ = C::configure(&mut cs);
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(std::any::type_name::<C>().as_bytes());
+        hasher.update(format!("{:?}", cs.pinned()).as_bytes());
+        hasher.finalize()
+    }
+
+    /// Load the proving key for the given circuit from the cache,
+    /// or build and store it if no valid entry exists.
+    pub fn proving_key(
+        &self,
+        circuit_id: &blake3::Hash,
+        k: u32,
+        c: &impl Circuit<DrkCircuitField>,
+    ) -> ProvingKey {
+        if let Some(pk) = PROVING_KEYS.lock().unwrap().get(&(*circuit_id, k)) {
+            return pk.clone()
+        }
+
+        let pk = self.load_proving_key(circuit_id, k, c);
+        PROVING_KEYS.lock().unwrap().insert((*circuit_id, k), pk.clone());
+        pk
+    }
+
+    /// Load the verifying key for the given circuit from the cache,
+    /// or build and store it if no valid entry exists.
+    pub fn verifying_key(
+        &self,
+        circuit_id: &blake3::Hash,
+        k: u32,
+        c: &impl Circuit<DrkCircuitField>,
+    ) -> VerifyingKey {
+        if let Some(vk) = VERIFYING_KEYS.lock().unwrap().get(&(*circuit_id, k)) {
+            return vk.clone()
+        }
+
+        // The proving key holds the verifying key already
+        if let Some(pk) = PROVING_KEYS.lock().unwrap().get(&(*circuit_id, k)) {
+            return VerifyingKey { params: pk.params.clone(), vk: pk.pk.get_vk().clone() }
+        }
+
+        let vk = self.load_verifying_key(circuit_id, k, c);
+        VERIFYING_KEYS.lock().unwrap().insert((*circuit_id, k), vk.clone());
+        vk
+    }
+
+    fn load_proving_key(
+        &self,
+        circuit_id: &blake3::Hash,
+        k: u32,
+        c: &impl Circuit<DrkCircuitField>,
+    ) -> ProvingKey {
+        let entry = match self.entry_path(circuit_id, k) {
+            Some(v) => v,
+            None => return ProvingKey::build(k, c),
+        };
+
+        match self.load(&entry).and_then(|data| ProvingKey::read(&mut Cursor::new(data), c)) {
+            Ok(pk) => {
+                debug!("Loaded proving key from {:?}", entry);
+                return pk
+            }
+            Err(e) => info!("Building proving key, no valid cache entry {:?}: {}", entry, e),
+        }
+
+        let pk = ProvingKey::build(k, c);
+        let mut data = vec![];
+        if let Err(e) = pk.write(&mut data).and_then(|_| self.store(&entry, &data)) {
+            warn!("Failed storing proving key to {:?}: {}", entry, e);
+        }
+
+        pk
+    }
+
+    fn load_verifying_key(
+        &self,
+        circuit_id: &blake3::Hash,
+        k: u32,
+        c: &impl Circuit<DrkCircuitField>,
+    ) -> VerifyingKey {
+        let entry = match self.entry_path(circuit_id, k) {
+            Some(v) => v,
+            None => return VerifyingKey::build(k, c),
+        };
+
+        match self.load(&entry).and_then(|data| VerifyingKey::read(&mut Cursor::new(data), c)) {
+            Ok(vk) => {
+                debug!("Loaded verifying key from {:?}", entry);
+                return vk
+            }
+            Err(e) => info!("Building verifying key, no valid cache entry {:?}: {}", entry, e),
+        }
+
+        let vk = VerifyingKey::build(k, c);
+        let mut data = vec![];
+        if let Err(e) = vk.write(&mut data).and_then(|_| self.store(&entry, &data)) {
+            warn!("Failed storing verifying key to {:?}: {}", entry, e);
+        }
+
+        vk
+    }
+
+    fn entry_path(&self, circuit_id: &blake3::Hash, k: u32) -> Option<PathBuf> {
+        Some(self.path.as_ref()?.join(format!("{}_{}", circuit_id.to_hex(), k)))
+    }
+
+    /// Read an entry and verify its trailing checksum.
+    fn load(&self, entry: &Path) -> Result<Vec<u8>> {
+        let mut data = fs::read(entry)?;
+        if data.len() < blake3::OUT_LEN {
+            return Err(Error::ZkKeyMismatch)
+        }
+
+        let checksum = data.split_off(data.len() - blake3::OUT_LEN);
+        if blake3::hash(&data).as_bytes()[..] != checksum[..] {
+            return Err(Error::ZkKeyMismatch)
+        }
+
+        Ok(data)
+    }
+
+    /// Write an entry along with its checksum. The entry is written to a
+    /// temporary file first, so concurrent readers never see partial data.
+    fn store(&self, entry: &Path, data: &[u8]) -> Result<()> {
+        if let Some(dir) = entry.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let tmp = entry.with_extension(format!("tmp{}", std::process::id()));
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.write_all(blake3::hash(data).as_bytes())?;
+        file.sync_all()?;
+        fs::rename(tmp, entry)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk,
+        plonk::{Advice, Column},
+    };
+
+    use super::*;
+
+    /// Number of times [`CountingCircuit`] was synthesized by keygen
+    static SYNTHESIZED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Default)]
+    struct CountingCircuit;
+
+    impl Circuit<DrkCircuitField> for CountingCircuit {
+        type Config = Column<Advice>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<DrkCircuitField>) -> Self::Config {
+            let advice = meta.advice_column();
+            meta.enable_equality(advice);
+            advice
+        }
+
+        fn synthesize(
+            &self,
+            _config: Self::Config,
+            _layouter: impl Layouter<DrkCircuitField>,
+        ) -> std::result::Result<(), plonk::Error> {
+            SYNTHESIZED.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn keygen_skipped_on_cache_hit() -> Result<()> {
+        let dir = std::env::temp_dir().join("darkfi_keycache_test");
+        let cache = KeyCache::new(dir.to_str().unwrap())?;
+        let circuit_id = KeyCache::native_circuit_id::<CountingCircuit>();
+
+        let pk = cache.proving_key(&circuit_id, 4, &CountingCircuit);
+        let built = SYNTHESIZED.load(Ordering::SeqCst);
+        assert!(built > 0);
+
+        // Neither key is generated again
+        let cached = cache.proving_key(&circuit_id, 4, &CountingCircuit);
+        assert_eq!(cached.fingerprint(), pk.fingerprint());
+        let vk = cache.verifying_key(&circuit_id, 4, &CountingCircuit);
+        assert_eq!(vk.fingerprint(), pk.fingerprint());
+        assert_eq!(SYNTHESIZED.load(Ordering::SeqCst), built);
+
+        // Another k is a different key
+        cache.verifying_key(&circuit_id, 5, &CountingCircuit);
+        assert!(SYNTHESIZED.load(Ordering::SeqCst) > built);
+
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
@@ -18,7 +18,10 @@ use pasta_curves::{
 use rand::RngCore;
 
 use crate::{
-    crypto::{address::Address, util::mod_r_p},
+    crypto::{
+        address::Address,
+        util::{mod_r_p, poseidon_hash},
+    },
     Error, Result,
 };
 
@@ -40,6 +43,72 @@ impl Keypair {
     }
 }
 
+/// Domain separator deriving view secrets from spending secrets
+const VIEW_KEY_DOMAIN: u64 = 2;
+
+/// Incoming view key of a keypair. Senders paying a [`ViewableAddress`]
+/// encrypt their notes to the view key instead of the spending key, so
+/// wallets holding it can detect the coins sent to the address without
+/// being able to spend them, or to tell when they are spent.
+///
+/// [`ViewableAddress`]: crate::crypto::address::ViewableAddress
+#[derive(Copy, Clone, PartialEq, Eq, Debug, SerialDecodable, SerialEncodable)]
+pub struct ViewKey {
+    /// Secret the notes are encrypted to
+    pub secret: SecretKey,
+    /// Spending public key, which the coins are minted to
+    pub public: PublicKey,
+}
+
+impl ViewKey {
+    /// Public key the notes are encrypted to.
+    pub fn view_public(&self) -> PublicKey {
+        PublicKey::from_secret(self.secret)
+    }
+
+    pub fn to_bytes(self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.secret.to_bytes());
+        bytes[32..].copy_from_slice(&self.public.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; 64]) -> Result<Self> {
+        let secret = SecretKey::from_bytes(bytes[..32].try_into().unwrap())?;
+        let public = PublicKey::from_bytes(bytes[32..].try_into().unwrap())?;
+        Ok(Self { secret, public })
+    }
+}
+
+impl From<Keypair> for ViewKey {
+    /// Derive the view key of a keypair. Its secret is a hash of the
+    /// spending secret, which can't be recovered from it.
+    fn from(keypair: Keypair) -> Self {
+        let domain = pallas::Base::from(VIEW_KEY_DOMAIN);
+        let secret = SecretKey(poseidon_hash::<2>([keypair.secret.0, domain]));
+        Self { secret, public: keypair.public }
+    }
+}
+
+impl FromStr for ViewKey {
+    type Err = crate::Error;
+
+    /// Tries to create a `ViewKey` instance from a base58 encoded string.
+    fn from_str(encoded: &str) -> core::result::Result<Self, crate::Error> {
+        let decoded = bs58::decode(encoded).into_vec()?;
+        if decoded.len() != 64 {
+            return Err(Error::ViewKeyFromStr)
+        }
+        Self::from_bytes(&decoded.try_into().unwrap())
+    }
+}
+
+impl std::fmt::Display for ViewKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", bs58::encode(self.to_bytes()).into_string())
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug, SerialDecodable, SerialEncodable)]
 pub struct SecretKey(pub pallas::Base);
 
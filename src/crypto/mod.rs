@@ -2,6 +2,7 @@ pub mod address;
 pub mod burn_proof;
 pub mod coin;
 pub mod diffie_hellman;
+pub mod keycache;
 pub mod keypair;
 pub mod mint_proof;
 pub mod note;
@@ -1,14 +1,16 @@
-use darkfi_serial::{SerialDecodable, SerialEncodable};
+use std::io;
+
+use darkfi_serial::{Decodable, Encodable, SerialDecodable, SerialEncodable};
 use halo2_proofs::{
     plonk,
-    plonk::{Circuit, SingleVerifier},
+    plonk::{BatchVerifier, Circuit, SingleVerifier},
     poly::commitment::Params,
-    transcript::{Blake2bRead, Blake2bWrite},
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, Transcript},
 };
-use pasta_curves::vesta;
+use pasta_curves::{group::ff::PrimeField, vesta};
 use rand::RngCore;
 
-use crate::crypto::types::DrkCircuitField;
+use crate::{crypto::types::DrkCircuitField, Error, Result};
 
 // TODO: this API needs rework. It's not very good.
 // keygen_pk() takes a VerifyingKey by value,
@@ -25,10 +27,42 @@ pub struct VerifyingKey {
 
 impl VerifyingKey {
     pub fn build(k: u32, c: &impl Circuit<DrkCircuitField>) -> Self {
-        let params = Params::new(k);
+        Self::build_with_params(Params::new(k), c)
+    }
+
+    /// Build the key using already generated commitment params.
+    pub fn build_with_params(
+        params: Params<vesta::Affine>,
+        c: &impl Circuit<DrkCircuitField>,
+    ) -> Self {
         let vk = plonk::keygen_vk(&params, c).unwrap();
         VerifyingKey { params, vk }
     }
+
+    /// Fingerprint of the verifying key, committing to the circuit and params.
+    pub fn fingerprint(&self) -> blake3::Hash {
+        vk_fingerprint(&self.vk)
+    }
+
+    /// Serialize the key. halo2 does not support serializing the keys
+    /// themselves, so we write the commitment params along with the key
+    /// fingerprint, and the key is generated again on read.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<()> {
+        write_key(writer, &self.params, &self.fingerprint())
+    }
+
+    /// Deserialize a key written with [`VerifyingKey::write`], rebuilding
+    /// it for the given circuit. Fails if the rebuilt key does not match
+    /// the serialized fingerprint.
+    pub fn read<R: io::Read>(reader: &mut R, c: &impl Circuit<DrkCircuitField>) -> Result<Self> {
+        let (params, fingerprint) = read_key(reader)?;
+        let vk = Self::build_with_params(params, c);
+        if vk.fingerprint() != fingerprint {
+            return Err(Error::ZkKeyMismatch)
+        }
+
+        Ok(vk)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -39,11 +73,70 @@ pub struct ProvingKey {
 
 impl ProvingKey {
     pub fn build(k: u32, c: &impl Circuit<DrkCircuitField>) -> Self {
-        let params = Params::new(k);
+        Self::build_with_params(Params::new(k), c)
+    }
+
+    /// Build the key using already generated commitment params.
+    pub fn build_with_params(
+        params: Params<vesta::Affine>,
+        c: &impl Circuit<DrkCircuitField>,
+    ) -> Self {
         let vk = plonk::keygen_vk(&params, c).unwrap();
         let pk = plonk::keygen_pk(&params, vk, c).unwrap();
         ProvingKey { params, pk }
     }
+
+    /// Fingerprint of the key's verifying key, committing to the circuit and params.
+    pub fn fingerprint(&self) -> blake3::Hash {
+        vk_fingerprint(self.pk.get_vk())
+    }
+
+    /// Serialize the key. The format is the same as [`VerifyingKey::write`],
+    /// so either key can be read from it.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<()> {
+        write_key(writer, &self.params, &self.fingerprint())
+    }
+
+    /// Deserialize a key written with [`ProvingKey::write`], rebuilding
+    /// it for the given circuit. Fails if the rebuilt key does not match
+    /// the serialized fingerprint.
+    pub fn read<R: io::Read>(reader: &mut R, c: &impl Circuit<DrkCircuitField>) -> Result<Self> {
+        let (params, fingerprint) = read_key(reader)?;
+        let pk = Self::build_with_params(params, c);
+        if pk.fingerprint() != fingerprint {
+            return Err(Error::ZkKeyMismatch)
+        }
+
+        Ok(pk)
+    }
+}
+
+/// The key's identity as halo2 commits to it in the proof transcripts,
+/// so two keys with the same fingerprint verify the same proofs.
+fn vk_fingerprint(vk: &plonk::VerifyingKey<vesta::Affine>) -> blake3::Hash {
+    let mut transcript = Blake2bWrite::<_, vesta::Affine, Challenge255<_>>::init(vec![]);
+    vk.hash_into(&mut transcript).unwrap();
+    let challenge = transcript.squeeze_challenge().get_scalar();
+    blake3::Hash::from(challenge.to_repr())
+}
+
+fn write_key<W: io::Write>(
+    writer: &mut W,
+    params: &Params<vesta::Affine>,
+    fingerprint: &blake3::Hash,
+) -> Result<()> {
+    let mut params_bytes = vec![];
+    params.write(&mut params_bytes)?;
+    params_bytes.encode(&mut *writer)?;
+    fingerprint.encode(writer)?;
+    Ok(())
+}
+
+fn read_key<R: io::Read>(reader: &mut R) -> Result<(Params<vesta::Affine>, blake3::Hash)> {
+    let params_bytes: Vec<u8> = Decodable::decode(&mut *reader)?;
+    let params = Params::read(&mut &params_bytes[..])?;
+    let fingerprint = Decodable::decode(reader)?;
+    Ok((params, fingerprint))
 }
 
 #[derive(Clone, Default, Debug, PartialEq, Eq, SerialEncodable, SerialDecodable)]
@@ -85,6 +178,19 @@ impl Proof {
         plonk::verify_proof(&vk.params, &vk.vk, strategy, &[&[instances]], &mut transcript)
     }
 
+    /// Verify a batch of proofs created for the same circuit, given as
+    /// tuples of (proof, public inputs). The batch verifier amortizes the
+    /// expensive multiscalar multiplication across all proofs, but only
+    /// tells us whether the entire batch is valid or not.
+    pub fn verify_batch(vk: &VerifyingKey, proofs: &[(&Proof, Vec<DrkCircuitField>)]) -> bool {
+        let mut batch = BatchVerifier::new();
+        for (proof, instances) in proofs {
+            batch.add_proof(vec![vec![instances.clone()]], proof.0.clone());
+        }
+
+        batch.finalize(&vk.params, &vk.vk)
+    }
+
     pub fn new(bytes: Vec<u8>) -> Self {
         Proof(bytes)
     }
@@ -101,7 +207,7 @@ mod tests {
                 DrkCoinBlind, DrkSerial, DrkSpendHook, DrkTokenId, DrkUserData, DrkValueBlind,
             },
         },
-        zk::circuit::MintContract,
+        zk::circuit::{BurnContract, MintContract},
         Result,
     };
     use darkfi_serial::{Decodable, Encodable};
@@ -141,4 +247,53 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_proof_batch_verification() -> Result<()> {
+        let pk = ProvingKey::build(11, &MintContract::default());
+        let vk = VerifyingKey::build(11, &MintContract::default());
+
+        let mut proofs = vec![];
+        for value in [42_u64, 69_u64] {
+            let (proof, revealed) = create_mint_proof(
+                &pk,
+                value,
+                DrkTokenId::random(&mut OsRng),
+                DrkValueBlind::random(&mut OsRng),
+                DrkValueBlind::random(&mut OsRng),
+                DrkSerial::random(&mut OsRng),
+                DrkSpendHook::random(&mut OsRng),
+                DrkUserData::random(&mut OsRng),
+                DrkCoinBlind::random(&mut OsRng),
+                PublicKey::random(&mut OsRng),
+            )?;
+            proofs.push((proof, revealed.make_outputs()));
+        }
+
+        let batch: Vec<(&Proof, Vec<DrkCircuitField>)> =
+            proofs.iter().map(|(p, i)| (p, i.clone())).collect();
+        assert!(Proof::verify_batch(&vk, &batch));
+
+        // Swapping the public inputs must invalidate the batch
+        let batch = vec![(&proofs[0].0, proofs[1].1.clone()), (&proofs[1].0, proofs[0].1.clone())];
+        assert!(!Proof::verify_batch(&vk, &batch));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_serialization() -> Result<()> {
+        let vk = VerifyingKey::build(11, &MintContract::default());
+        let mut buf = vec![];
+        vk.write(&mut buf)?;
+
+        let vk2 = VerifyingKey::read(&mut &buf[..], &MintContract::default())?;
+        assert_eq!(vk.fingerprint(), vk2.fingerprint());
+
+        // Loading the key for a different circuit must fail
+        let res = VerifyingKey::read(&mut &buf[..], &BurnContract::default());
+        assert!(matches!(res, Err(Error::ZkKeyMismatch)));
+
+        Ok(())
+    }
 }
@@ -317,3 +317,94 @@ async fn prune_seen_messages(dht: DhtPtr) {
         dht.write().await.seen = map;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::net::{sim::Simulation, transport::LinkConditions};
+
+    /// Steps the test allows for a change to spread over the network
+    const MAX_STEPS: usize = 5000;
+
+    async fn wait_for_key(sim: &Simulation, dht: &DhtPtr, key: blake3::Hash) {
+        sim.run_until(MAX_STEPS, || async { dht.read().await.contains_key(key).is_some() })
+            .await
+            .unwrap();
+    }
+
+    async fn wait_for_node_channels(sim: &Simulation, index: usize, count: usize) {
+        let node = sim.node(index);
+        sim.run_until(MAX_STEPS, || async { node.channels().lock().await.len() == count })
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn dht_simulation_test() {
+        let executor = Arc::new(Executor::new());
+        let ex = executor.clone();
+
+        smol::block_on(executor.run(async move {
+            // Nodes form a line, so node 1 has to relay between nodes 0 and 2
+            let sim = Simulation::with_settings(3, 42, ex.clone(), |i, settings| {
+                settings.peers = settings.peers.split_off(i.saturating_sub(1));
+            })
+            .await;
+            sim.set_default_link(LinkConditions { latency: Duration::from_millis(10), loss: 0.0 });
+
+            let (stop_send, stop_recv) = smol::channel::unbounded();
+            let mut dhts = vec![];
+            for node in sim.nodes() {
+                let dht = Dht::new(None, node.clone(), stop_recv.clone(), ex.clone()).await;
+                dhts.push(dht.unwrap());
+            }
+
+            sim.start().await.unwrap();
+            wait_for_node_channels(&sim, 0, 1).await;
+            wait_for_node_channels(&sim, 1, 2).await;
+            wait_for_node_channels(&sim, 2, 1).await;
+
+            // Inserts are announced to the whole network
+            let key = blake3::hash(b"key");
+            dhts[0].write().await.insert(key, b"value".to_vec()).await.unwrap();
+            wait_for_key(&sim, &dhts[2], key).await;
+            assert_eq!(dhts[2].read().await.contains_key(key), Some(false));
+
+            // Values are fetched from the node holding them. The response is
+            // awaited in the background, while the network is stepped.
+            dhts[2].read().await.request_key(key).await.unwrap();
+            let (response_send, response_recv) = smol::channel::bounded(1);
+            let dht = dhts[2].clone();
+            ex.spawn(async move {
+                response_send.send(waiting_for_response(dht).await).await.unwrap();
+            })
+            .detach();
+            sim.run_until(MAX_STEPS, || async { !response_recv.is_empty() }).await.unwrap();
+            let response = response_recv.recv().await.unwrap().unwrap().unwrap();
+            assert_eq!(response.key, key);
+            assert_eq!(response.value, b"value");
+
+            // A node cut off from the network reconnects once the partition heals,
+            // and gets the inserts made after that.
+            sim.partition(&[&[0], &[1, 2]]);
+            let channels: Vec<_> = sim.node(0).channels().lock().await.values().cloned().collect();
+            for channel in channels {
+                channel.stop().await;
+            }
+            wait_for_node_channels(&sim, 0, 0).await;
+
+            sim.heal();
+            wait_for_node_channels(&sim, 0, 1).await;
+            wait_for_node_channels(&sim, 1, 2).await;
+
+            let key = blake3::hash(b"other key");
+            dhts[0].write().await.insert(key, b"other value".to_vec()).await.unwrap();
+            wait_for_key(&sim, &dhts[2], key).await;
+
+            stop_send.close();
+            sim.stop().await;
+        }));
+    }
+}
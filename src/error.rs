@@ -149,6 +149,9 @@ pub enum Error {
     #[error("Unable to decrypt mint note")]
     NoteDecryptionFailed,
 
+    #[error("Deserialized zk key does not match its fingerprint")]
+    ZkKeyMismatch,
+
     #[error("No keypair file detected")]
     KeypairPathNotFound,
 
@@ -164,6 +167,9 @@ pub enum Error {
     #[error("Failed converting bs58 string to SecretKey")]
     SecretKeyFromStr,
 
+    #[error("Failed converting bs58 string to ViewKey")]
+    ViewKeyFromStr,
+
     #[error("Invalid DarkFi address")]
     InvalidAddress,
 
@@ -193,6 +199,21 @@ pub enum Error {
     #[error("JSON-RPC error: {0}")]
     JsonRpcError(String),
 
+    #[error("Snapshot verification failed: {0}")]
+    SnapshotVerifyFailed(String),
+
+    #[error("Unsigned transaction is missing proofs or signatures")]
+    UnsignedTxIncomplete,
+
+    #[error("Unsigned transactions do not spend and mint the same coins")]
+    UnsignedTxMismatch,
+
+    #[error("Unsigned transaction output {0} does not match its plaintext")]
+    UnsignedTxOutputMismatch(usize),
+
+    #[error("Unsigned transaction input {0} has an invalid Merkle path")]
+    UnsignedTxMerklePath(usize),
+
     // ===============
     // Database errors
     // ===============
@@ -219,6 +240,12 @@ pub enum Error {
     #[error("Block {0} metadata not found in database")]
     BlockMetadataNotFound(String),
 
+    #[error("Snapshot for slot {0} not found in database")]
+    SnapshotNotFound(u64),
+
+    #[error("Chunk {1} of snapshot for slot {0} not found")]
+    SnapshotChunkNotFound(u64, u32),
+
     // =============
     // Wallet errors
     // =============
@@ -231,6 +258,9 @@ pub enum Error {
     #[error("Wallet insufficient balance")]
     WalletInsufficientBalance,
 
+    #[error("Can't scan the wallet from slot {0}, the ledger only has blocks since its snapshot at slot {1}")]
+    WalletScanBeforeSnapshot(u64, u64),
+
     // ===================
     // wasm runtime errors
     // ===================
@@ -362,6 +392,9 @@ pub enum VerifyFailed {
     #[error("Failed verifying zk proofs: {0}")]
     ProofVerifyFailed(String),
 
+    #[error("Transaction {0} verification failure: {1}")]
+    Transaction(usize, Box<VerifyFailed>),
+
     #[error("Internal error: {0}")]
     InternalError(String),
 }
@@ -6,7 +6,9 @@ use smol::Executor;
 use url::Url;
 
 use super::{
-    transport::{TcpTransport, TorTransport, Transport, TransportListener, TransportName},
+    transport::{
+        MemoryTransport, TcpTransport, TorTransport, Transport, TransportListener, TransportName,
+    },
     Channel, ChannelPtr, SessionWeakPtr,
 };
 use crate::{
@@ -114,6 +116,16 @@ impl Acceptor {
 
                 accept!(listener, transport, upgrade);
             }
+            TransportName::Memory => {
+                let listener = MemoryTransport::new(None).listen(accept_url.clone()).await;
+
+                if let Err(err) = listener {
+                    error!("Bind listener to {} failed: {}", accept_url, err);
+                    return Err(Error::BindFailed(accept_url.as_str().into()))
+                }
+
+                self.accept(Box::new(listener?), executor);
+            }
             _ => unimplemented!(),
         }
         Ok(())
@@ -12,6 +12,7 @@ use url::Url;
 use super::{
     message,
     message_subscriber::{MessageSubscription, MessageSubsystem},
+    metrics::{ChannelMetrics, UNKNOWN_COMMAND},
     transport::TransportStream,
     Session, SessionBitflag, SessionWeakPtr,
 };
@@ -81,7 +82,10 @@ pub struct Channel {
     stop_subscriber: SubscriberPtr<Error>,
     receive_task: StoppableTaskPtr,
     stopped: Mutex<bool>,
+    /// Whether the peer accepts compressed payloads
+    compress: Mutex<bool>,
     info: Mutex<ChannelInfo>,
+    metrics: ChannelMetrics,
     session: SessionWeakPtr,
 }
 
@@ -101,7 +105,8 @@ impl Channel {
         let message_subsystem = MessageSubsystem::new();
         Self::setup_dispatchers(&message_subsystem).await;
 
-        let channel_log = session.upgrade().unwrap().p2p().settings().channel_log;
+        let p2p = session.upgrade().unwrap().p2p();
+        let channel_log = p2p.settings().channel_log;
 
         Arc::new(Self {
             reader,
@@ -111,7 +116,9 @@ impl Channel {
             stop_subscriber: Subscriber::new(),
             receive_task: StoppableTask::new(),
             stopped: Mutex::new(false),
+            compress: Mutex::new(false),
             info: Mutex::new(ChannelInfo::new(channel_log)),
+            metrics: ChannelMetrics::new(p2p.metrics()),
             session,
         })
     }
@@ -177,6 +184,17 @@ impl Channel {
     /// creates a new payload and sends it over the TCP connection as a
     /// packet. Returns an error if something goes wrong.
     pub async fn send<M: message::Message>(&self, message: M) -> Result<()> {
+        let mut payload = Vec::new();
+        message.encode(&mut payload)?;
+        self.send_encoded::<M>(payload).await
+    }
+
+    /// Sends a message of type `M` already encoded into `payload`. Large
+    /// messages can be streamed into it, e.g. with a [`VecEncoder`], rather
+    /// than held whole alongside their encoding.
+    ///
+    /// [`VecEncoder`]: darkfi_serial::VecEncoder
+    pub async fn send_encoded<M: message::Message>(&self, payload: Vec<u8>) -> Result<()> {
         debug!(target: "net",
          "Channel::send() [START, command={:?}, address={}]",
          M::name(),
@@ -191,7 +209,7 @@ impl Channel {
         }
 
         // Catch failure and stop channel, return a net error
-        let result = match self.send_message(message).await {
+        let result = match self.send_message(M::name(), payload).await {
             Ok(()) => Ok(()),
             Err(err) => {
                 error!("Channel send error for [{}]: {}", self.address(), err);
@@ -214,14 +232,11 @@ impl Channel {
         result
     }
 
-    /// Implements send message functionality. Creates a new payload and encodes
-    /// it. Then creates a message packet- the base type of the network- and
-    /// copies the payload into it. Then we send the packet over the TCP
-    /// stream.
-    async fn send_message<M: message::Message>(&self, message: M) -> Result<()> {
-        let mut payload = Vec::new();
-        message.encode(&mut payload)?;
-        let packet = message::Packet { command: String::from(M::name()), payload };
+    /// Implements send message functionality. Creates a message packet- the
+    /// base type of the network- holding the encoded payload. Then we send
+    /// the packet over the TCP stream.
+    async fn send_message(&self, command: &str, payload: Vec<u8>) -> Result<()> {
+        let packet = message::Packet { command: String::from(command), payload };
         let time = NanoTimestamp::current_time();
         //let time = time::unix_timestamp()?;
 
@@ -232,8 +247,11 @@ impl Channel {
             };
         }
 
+        self.metrics.record_sent(&packet.command, packet.payload.len()).await;
+
+        let compress = *self.compress.lock().await;
         let stream = &mut *self.writer.lock().await;
-        message::send_packet(stream, packet).await
+        message::send_packet(stream, packet, compress).await
     }
 
     /// Subscribe to a messages on the message subsystem.
@@ -264,6 +282,17 @@ impl Channel {
         self.info.lock().await.remote_node_id = remote_node_id;
     }
 
+    /// Counters of the messages sent and received on the channel.
+    pub fn metrics(&self) -> &ChannelMetrics {
+        &self.metrics
+    }
+
+    /// Enable compression of the payloads sent to the peer, once it
+    /// advertised support for it.
+    pub async fn set_compression(&self, compress: bool) {
+        *self.compress.lock().await = compress;
+    }
+
     /// End of file error. Triggered when unexpected end of file occurs.
     fn is_eof_error(err: Error) -> bool {
         match err {
@@ -281,6 +310,9 @@ impl Channel {
         message_subsystem.add_dispatch::<message::GetAddrsMessage>().await;
         message_subsystem.add_dispatch::<message::AddrsMessage>().await;
         message_subsystem.add_dispatch::<message::ExtAddrsMessage>().await;
+        message_subsystem.add_dispatch::<message::InvMessage>().await;
+        message_subsystem.add_dispatch::<message::GetDataMessage>().await;
+        message_subsystem.add_dispatch::<message::DataMessage>().await;
     }
 
     /// Convenience function that returns the Message Subsystem.
@@ -326,6 +358,14 @@ impl Channel {
                 };
             }
 
+            // Peers choose the command, so anything we don't handle is counted
+            // under one label to keep the metrics bounded.
+            let command = match self.message_subsystem.has_dispatch(&packet.command).await {
+                true => packet.command.as_str(),
+                false => UNKNOWN_COMMAND,
+            };
+            self.metrics.record_received(command, packet.payload.len()).await;
+
             // Send result to our subscribers
             self.message_subsystem.notify(&packet.command, packet.payload).await;
         }
@@ -5,7 +5,8 @@ use log::error;
 use url::Url;
 
 use super::{
-    transport::{TcpTransport, TorTransport, Transport, TransportName},
+    hosts::host_ip,
+    transport::{MemoryTransport, TcpTransport, TorTransport, Transport, TransportName},
     Channel, ChannelPtr, SessionWeakPtr, SettingsPtr,
 };
 use crate::{Error, Result};
@@ -72,6 +73,15 @@ impl Connector {
 
         match transport_name {
             TransportName::Tcp(upgrade) => {
+                // Dialing a hostname would resolve it with the system resolver
+                if self.settings.no_resolve && host_ip(&connect_url).is_none() {
+                    error!(
+                        "Not dialing {}: hostnames aren't resolved with no_resolve",
+                        connect_url
+                    );
+                    return Err(Error::ConnectFailed)
+                }
+
                 let transport = TcpTransport::new(None, 1024);
                 let stream = transport.dial(connect_url.clone(), Some(timeout));
                 connect!(stream, transport, upgrade)
@@ -88,6 +98,17 @@ impl Connector {
 
                 connect!(stream, transport, upgrade)
             }
+            TransportName::Memory => {
+                let transport = MemoryTransport::new(self.settings.inbound.first().cloned());
+                let stream = transport.dial(connect_url.clone()).await;
+
+                if let Err(err) = stream {
+                    error!("Connection to {}  failed: {}", connect_url, err);
+                    return Err(Error::ConnectFailed)
+                }
+
+                Ok(Channel::new(Box::new(stream?), connect_url, self.session.clone()).await)
+            }
             _ => unimplemented!(),
         }
     }
@@ -1,15 +1,25 @@
-use async_std::sync::{Arc, Mutex};
+use async_std::{
+    net::ToSocketAddrs,
+    sync::{Arc, Mutex},
+};
 use std::net::IpAddr;
 
 use fxhash::{FxHashMap, FxHashSet};
 use ipnet::{Ipv4Net, Ipv6Net};
 use iprange::IpRange;
-use log::{debug, error, warn};
-use url::Url;
-
-use super::constants::{IP4_PRIV_RANGES, IP6_PRIV_RANGES, LOCALNET};
+use log::{debug, error, info, warn};
+use rand::seq::SliceRandom;
+use url::{Host, Url};
+
+use super::{
+    constants::{IP4_PRIV_RANGES, IP6_PRIV_RANGES, LOCALNET},
+    transport::socket_addr_to_url,
+};
 use crate::util::encoding::base32;
 
+/// Most outbound connections made to a single network group, outside localnet
+pub const MAX_OUTBOUND_PER_GROUP: usize = 2;
+
 /// Pointer to hosts class.
 pub type HostsPtr = Arc<Hosts>;
 
@@ -17,13 +27,16 @@ pub type HostsPtr = Arc<Hosts>;
 pub struct Hosts {
     addrs: Mutex<FxHashSet<Url>>,
     localnet: bool,
+    /// Whether hostnames may be resolved with the system's DNS resolver
+    resolve: bool,
     ipv4_range: IpRange<Ipv4Net>,
     ipv6_range: IpRange<Ipv6Net>,
 }
 
 impl Hosts {
-    /// Create a new host list.
-    pub fn new(localnet: bool) -> Arc<Self> {
+    /// Create a new host list. With `resolve` unset, hostnames are never
+    /// resolved, and only IP addresses and onion domains can be verified.
+    pub fn new(localnet: bool, resolve: bool) -> Arc<Self> {
         // Initialize ipv4_range and ipv6_range if needed
         let mut ipv4_range: IpRange<Ipv4Net> =
             IP4_PRIV_RANGES.iter().map(|s| s.parse().unwrap()).collect();
@@ -34,7 +47,13 @@ impl Hosts {
         ipv4_range.simplify();
         ipv6_range.simplify();
 
-        Arc::new(Self { addrs: Mutex::new(FxHashSet::default()), localnet, ipv4_range, ipv6_range })
+        Arc::new(Self {
+            addrs: Mutex::new(FxHashSet::default()),
+            localnet,
+            resolve,
+            ipv4_range,
+            ipv6_range,
+        })
     }
 
     /// Add a new host to the host list, after filtering.
@@ -42,7 +61,8 @@ impl Hosts {
         debug!(target: "net", "hosts::store() [Start]");
         let addrs = if !self.localnet {
             let filtered = filter_localnet(input_addrs);
-            let filtered = filter_invalid(&self.ipv4_range, &self.ipv6_range, filtered);
+            let filtered =
+                filter_invalid(&self.ipv4_range, &self.ipv6_range, filtered, self.resolve);
             filtered.into_iter().map(|(k, _)| k).collect()
         } else {
             debug!(target: "net", "hosts::store() [Localnet mode, skipping filterring.]");
@@ -61,8 +81,9 @@ impl Hosts {
         debug!(target: "net", "hosts::store_ext() [Start]");
         let addrs = if !self.localnet {
             let filtered = filter_localnet(input_addrs);
-            let filtered = filter_invalid(&self.ipv4_range, &self.ipv6_range, filtered);
-            filter_non_resolving(connection_addr, filtered)
+            let filtered =
+                filter_invalid(&self.ipv4_range, &self.ipv6_range, filtered, self.resolve);
+            filter_non_resolving(connection_addr, filtered, self.resolve)
         } else {
             debug!(target: "net", "hosts::store_ext() [Localnet mode, skipping filterring.]");
             input_addrs
@@ -74,11 +95,62 @@ impl Hosts {
         debug!(target: "net", "hosts::store_ext() [End]");
     }
 
+    /// Resolve DNS seeds, hostnames pointing to many nodes, and add an
+    /// address with the seed's scheme and port for each of their IPs.
+    pub async fn store_dns_seeds(&self, seeds: &[Url]) {
+        if !self.resolve {
+            warn!(target: "net", "hosts::store_dns_seeds() [Hostname resolution disabled, skipping DNS seeds.]");
+            return
+        }
+
+        let mut addrs = vec![];
+        for seed in seeds {
+            let (host, port) = match (seed.host_str(), seed.port()) {
+                (Some(h), Some(p)) => (h, p),
+                _ => {
+                    warn!(target: "net", "hosts::store_dns_seeds() [DNS seed {} needs a host and port]", seed);
+                    continue
+                }
+            };
+
+            let socket_addrs = match (host, port).to_socket_addrs().await {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!(target: "net", "hosts::store_dns_seeds() [Failed resolving DNS seed {}: {}]", seed, e);
+                    continue
+                }
+            };
+
+            let mut count = 0;
+            for socket_addr in socket_addrs {
+                if let Ok(url) = socket_addr_to_url(socket_addr, seed.scheme()) {
+                    addrs.push(url);
+                    count += 1;
+                }
+            }
+            info!(target: "net", "DNS seed {} resolved to {} addresses", seed, count);
+        }
+
+        self.store(addrs).await;
+    }
+
     /// Return the list of hosts.
     pub async fn load_all(&self) -> Vec<Url> {
         self.addrs.lock().await.iter().cloned().collect()
     }
 
+    /// Return the list of hosts in the order outbound connections should try
+    /// them, given the addresses of our other outbound connections: shuffled,
+    /// with new network groups and the less used IP family first. Addresses
+    /// in a group that already has [`MAX_OUTBOUND_PER_GROUP`] connections
+    /// are left out, unless we're on localnet.
+    pub async fn load_outbound(&self, connected: &[Url]) -> Vec<Url> {
+        let mut addrs = self.load_all().await;
+        addrs.shuffle(&mut rand::thread_rng());
+        let group_limit = (!self.localnet).then_some(MAX_OUTBOUND_PER_GROUP);
+        order_outbound(addrs, connected, group_limit)
+    }
+
     /// Remove an Url from the list
     pub async fn remove(&self, url: &Url) -> bool {
         self.addrs.lock().await.remove(url)
@@ -90,6 +162,83 @@ impl Hosts {
     }
 }
 
+/// Network group of an IP address: the /16 subnet for IPv4, and the /32
+/// subnet for IPv6. Addresses in the same group are likely run by the
+/// same operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NetGroup {
+    Ipv4([u8; 2]),
+    Ipv6([u8; 4]),
+}
+
+impl NetGroup {
+    /// Network group of an URL with an IP address host. Hostnames aren't
+    /// resolved, so they have no group.
+    pub fn from_url(url: &Url) -> Option<Self> {
+        match host_ip(url)? {
+            IpAddr::V4(ip) => {
+                let o = ip.octets();
+                Some(Self::Ipv4([o[0], o[1]]))
+            }
+            IpAddr::V6(ip) => {
+                let o = ip.octets();
+                Some(Self::Ipv6([o[0], o[1], o[2], o[3]]))
+            }
+        }
+    }
+}
+
+/// IP address of an URL's host, if it is one. Our schemes aren't special
+/// to the url crate, so IPv4 hosts get parsed as domains.
+pub(super) fn host_ip(url: &Url) -> Option<IpAddr> {
+    match url.host()? {
+        Host::Ipv4(ip) => Some(IpAddr::V4(ip)),
+        Host::Ipv6(ip) => Some(IpAddr::V6(ip)),
+        Host::Domain(d) => d.parse().ok(),
+    }
+}
+
+/// Order candidate addresses for outbound connections, so the connections
+/// are spread across network groups and IP families, which makes it harder
+/// to eclipse us. Addresses in a network group we're already connected to
+/// go last. The others are ordered by how many connections we already have
+/// to their IP family, so IPv4 and IPv6 peers are balanced. The order is
+/// otherwise kept. Addresses in a group with `group_limit` connections or
+/// more are dropped.
+fn order_outbound(
+    mut candidates: Vec<Url>,
+    connected: &[Url],
+    group_limit: Option<usize>,
+) -> Vec<Url> {
+    let mut groups: FxHashMap<NetGroup, usize> = FxHashMap::default();
+    for group in connected.iter().filter_map(NetGroup::from_url) {
+        *groups.entry(group).or_default() += 1;
+    }
+
+    if let Some(limit) = group_limit {
+        candidates.retain(|url| match NetGroup::from_url(url) {
+            Some(group) => groups.get(&group).copied().unwrap_or(0) < limit,
+            None => true,
+        });
+    }
+
+    let family = |url: &Url| match host_ip(url) {
+        Some(IpAddr::V4(_)) => 0,
+        Some(IpAddr::V6(_)) => 1,
+        None => 2,
+    };
+    let mut family_count = [0usize; 3];
+    for url in connected {
+        family_count[family(url)] += 1;
+    }
+
+    candidates.sort_by_key(|url| {
+        let used = NetGroup::from_url(url).map(|x| groups.contains_key(&x)).unwrap_or(false);
+        (used, family_count[family(url)])
+    });
+    candidates
+}
+
 /// Auxiliary function to filter localnet hosts.
 fn filter_localnet(input_addrs: Vec<Url>) -> Vec<Url> {
     debug!(target: "net", "hosts::filter_localnet() [Input addresses: {:?}]", input_addrs);
@@ -111,15 +260,35 @@ fn filter_localnet(input_addrs: Vec<Url>) -> Vec<Url> {
     filtered
 }
 
+/// Check an IP address isn't in one of the private ranges.
+fn is_public_ip(ipv4_range: &IpRange<Ipv4Net>, ipv6_range: &IpRange<Ipv6Net>, ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(a) => !ipv4_range.contains(a),
+        IpAddr::V6(a) => !ipv6_range.contains(a),
+    }
+}
+
 /// Auxiliary function to filter invalid(unresolvable) hosts.
+/// With `resolve` unset, hostnames are kept without being resolved.
 fn filter_invalid(
     ipv4_range: &IpRange<Ipv4Net>,
     ipv6_range: &IpRange<Ipv6Net>,
     input_addrs: Vec<Url>,
+    resolve: bool,
 ) -> FxHashMap<Url, Vec<IpAddr>> {
     debug!(target: "net", "hosts::filter_invalid() [Input addresses: {:?}]", input_addrs);
     let mut filtered = FxHashMap::default();
     for addr in &input_addrs {
+        // Check IP addresses directly, without resolving
+        if let Some(ip) = host_ip(addr) {
+            if is_public_ip(ipv4_range, ipv6_range, &ip) {
+                filtered.insert(addr.clone(), vec![ip]);
+            } else {
+                debug!(target: "net", "hosts::filter_invalid() [Filtered private-range IP: {}]", ip);
+            }
+            continue
+        }
+
         // Discard domainless Urls
         let domain = match addr.domain() {
             Some(d) => d,
@@ -142,7 +311,13 @@ fn filter_invalid(
             continue
         }
 
-        // Validate Internet domains and IPs. socket_addrs() does a resolution
+        // Keep hostnames as they are when we may not resolve them
+        if !resolve {
+            filtered.insert(addr.clone(), vec![]);
+            continue
+        }
+
+        // Validate Internet domains. socket_addrs() does a resolution
         // with the local DNS resolver (i.e. /etc/resolv.conf), so the admin has
         // to take care of any DNS leaks by properly configuring their system for
         // DNS resolution.
@@ -192,10 +367,12 @@ fn filter_invalid(
 
 /// Filters `input_addrs` keys to whatever has at least one `IpAddr` that is
 /// the same as `connection_addr`'s IP address.
-/// Skips .onion domains.
+/// Skips .onion domains. With `resolve` unset, `connection_addr` is only
+/// used when its host is an IP address.
 fn filter_non_resolving(
     connection_addr: Url,
     input_addrs: FxHashMap<Url, Vec<IpAddr>>,
+    resolve: bool,
 ) -> Vec<Url> {
     debug!(target: "net", "hosts::filter_non_resolving() [Input addresses: {:?}]", input_addrs);
     debug!(target: "net", "hosts::filter_non_resolving() [Connection address: {}]", connection_addr);
@@ -204,7 +381,13 @@ fn filter_non_resolving(
     let mut ipv4_range = vec![];
     let mut ipv6_range = vec![];
 
-    match connection_addr.socket_addrs(|| None) {
+    let socket_addrs = match (resolve, host_ip(&connection_addr)) {
+        (true, _) => connection_addr.socket_addrs(|| None),
+        (false, Some(ip)) => Ok(vec![(ip, 0).into()]),
+        (false, None) => Ok(vec![]),
+    };
+
+    match socket_addrs {
         Ok(v) => {
             for i in v {
                 match i.ip() {
@@ -226,8 +409,7 @@ fn filter_non_resolving(
     for (addr, resolves) in &input_addrs {
         // Keep onion domains. It's assumed that the .onion addresses
         // have already been validated.
-        let addr_domain = addr.domain().unwrap();
-        if addr_domain.ends_with(".onion") {
+        if addr.domain().map(|x| x.ends_with(".onion")).unwrap_or(false) {
             filtered.push(addr.clone());
             continue
         }
@@ -292,7 +474,10 @@ mod tests {
 
     use crate::net::{
         constants::{IP4_PRIV_RANGES, IP6_PRIV_RANGES},
-        hosts::{filter_invalid, filter_localnet, filter_non_resolving, is_valid_onion},
+        hosts::{
+            filter_invalid, filter_localnet, filter_non_resolving, is_valid_onion, order_outbound,
+            NetGroup, MAX_OUTBOUND_PER_GROUP,
+        },
     };
 
     #[test]
@@ -375,11 +560,26 @@ mod tests {
         let output_addrs = FxHashSet::from_iter(output_addrs.iter());
 
         // Execute filtering for v4 addr
-        let filtered = filter_invalid(&ipv4_range, &ipv6_range, input_addrs);
+        let filtered = filter_invalid(&ipv4_range, &ipv6_range, input_addrs, true);
         let filtered: Vec<Url> = filtered.into_iter().map(|(k, _)| k).collect();
         let filtered = FxHashSet::from_iter(filtered.iter());
         // Validate filtered addresses
         assert_eq!(output_addrs, filtered);
+
+        // IP addresses are checked without resolving, and hostnames are kept
+        // unresolved when resolution is disabled
+        let public_v4 = Url::parse("tls://185.60.216.35:13333").unwrap();
+        let public_v6 = Url::parse("tls://[2a03:2880:f12d:83:face:b00c:0:25de]:13333").unwrap();
+        let private_v4 = Url::parse("tls://192.168.1.1:13333").unwrap();
+        let unresolvable = Url::parse("tls://facebookkk.com:13333").unwrap();
+        let input_addrs =
+            vec![public_v4.clone(), public_v6.clone(), private_v4, unresolvable.clone()];
+
+        let filtered = filter_invalid(&ipv4_range, &ipv6_range, input_addrs, false);
+        assert_eq!(filtered.len(), 3);
+        assert_eq!(filtered[&public_v4], vec![IpAddr::V4(Ipv4Addr::new(185, 60, 216, 35))]);
+        assert_eq!(filtered[&public_v6].len(), 1);
+        assert!(filtered[&unresolvable].is_empty());
     }
 
     #[test]
@@ -440,22 +640,64 @@ mod tests {
         let fake_output_addrs = FxHashSet::from_iter(fake_output_addrs.iter());
 
         // Execute filtering for v4 addr
-        let filtered = filter_non_resolving(connection_url_v4, input_addrs.clone());
+        let filtered = filter_non_resolving(connection_url_v4, input_addrs.clone(), true);
         let filtered = FxHashSet::from_iter(filtered.iter());
         // Validate filtered addresses
         assert_eq!(output_addrs, filtered);
 
         // Execute filtering for v6 addr
-        let filtered = filter_non_resolving(connection_url_v6, input_addrs.clone());
+        let filtered = filter_non_resolving(connection_url_v6, input_addrs.clone(), true);
         let filtered = FxHashSet::from_iter(filtered.iter());
         assert_eq!(output_addrs, filtered);
 
         // Execute filtering for fake addr
-        let filtered = filter_non_resolving(fake_connection_url, input_addrs);
+        let filtered = filter_non_resolving(fake_connection_url, input_addrs, true);
         let filtered = FxHashSet::from_iter(filtered.iter());
         assert_eq!(fake_output_addrs, filtered);
     }
 
+    #[test]
+    fn test_order_outbound() {
+        let v4_a = Url::parse("tcp://185.60.216.35:13333").unwrap();
+        let v4_a2 = Url::parse("tcp://185.60.1.1:13333").unwrap();
+        let v4_b = Url::parse("tcp://31.13.64.35:13333").unwrap();
+        let v6_a = Url::parse("tcp://[2a03:2880:f12d:83:face:b00c:0:25de]:13333").unwrap();
+        let v6_a2 = Url::parse("tcp://[2a03:2880::1]:13333").unwrap();
+        let onion = Url::parse(
+            "tor://facebookwkhpilnemxj7asaniu7vnjjbiltxjqhye3mhbshg7kx5tfyd.onion:13333",
+        )
+        .unwrap();
+
+        assert_eq!(NetGroup::from_url(&v4_a), Some(NetGroup::Ipv4([185, 60])));
+        assert_eq!(NetGroup::from_url(&v4_a), NetGroup::from_url(&v4_a2));
+        assert_eq!(NetGroup::from_url(&v6_a), Some(NetGroup::Ipv6([0x2a, 0x03, 0x28, 0x80])));
+        assert_eq!(NetGroup::from_url(&v6_a), NetGroup::from_url(&v6_a2));
+        assert_eq!(NetGroup::from_url(&onion), None);
+
+        // Nothing connected yet: the order is kept
+        let candidates = vec![v4_a2.clone(), v6_a.clone(), v4_b.clone(), onion.clone()];
+        assert_eq!(order_outbound(candidates.clone(), &[], None), candidates);
+
+        // Connected to 185.60/16: other IPv4 groups first, then the less used
+        // families, and the used group last
+        let ordered = order_outbound(candidates.clone(), &[v4_a.clone()], None);
+        assert_eq!(ordered, vec![v6_a.clone(), onion.clone(), v4_b.clone(), v4_a2]);
+
+        // Groups at the limit are left out
+        let ordered = order_outbound(candidates.clone(), &[v4_a.clone()], Some(1));
+        assert_eq!(ordered, vec![v6_a.clone(), onion.clone(), v4_b.clone()]);
+        let ordered = order_outbound(candidates, &[v4_a, v4_b], Some(1));
+        assert_eq!(ordered, vec![v6_a.clone(), onion.clone()]);
+
+        // Connected to 2a03:2880/32 twice over: IPv6 comes after the others
+        let candidates = vec![v6_a2.clone(), v6_a.clone(), onion.clone()];
+        let ordered = order_outbound(candidates.clone(), &[v6_a.clone(), v6_a.clone()], None);
+        assert_eq!(ordered, vec![onion.clone(), v6_a2, v6_a.clone()]);
+        let ordered =
+            order_outbound(candidates, &[v6_a.clone(), v6_a], Some(MAX_OUTBOUND_PER_GROUP));
+        assert_eq!(ordered, vec![onion]);
+    }
+
     #[test]
     fn test_is_valid_onion() {
         // Valid onion
@@ -0,0 +1,259 @@
+use async_std::sync::{Arc, Mutex};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use fxhash::{FxHashMap, FxHashSet};
+use log::debug;
+use url::Url;
+
+use super::message::DataMessage;
+
+/// Number of message hashes remembered as seen
+const SEEN_CAPACITY: usize = 65536;
+
+/// Maximum total size of the payloads kept to serve peer requests
+const MAX_STORED_BYTES: usize = 64 * 1024 * 1024;
+
+/// Time after which a hash requested from a peer that didn't reply
+/// is requested from another peer that announced it.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum number of hashes awaiting a reply at once
+const MAX_REQUESTED: usize = 65536;
+
+/// Maximum number of other announcers remembered per requested hash
+const MAX_ANNOUNCERS: usize = 8;
+
+/// Pointer to inventory class.
+pub type InventoryPtr = Arc<Inventory>;
+
+/// Hash identifying a message in the inventory.
+pub fn inventory_hash(command: &str, payload: &[u8]) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&(command.len() as u64).to_le_bytes());
+    hasher.update(command.as_bytes());
+    hasher.update(payload);
+    hasher.finalize()
+}
+
+/// A hash requested from a peer, along with the other peers that
+/// announced it, to fall back to if the peer doesn't reply.
+struct Request {
+    time: Instant,
+    announcers: VecDeque<Url>,
+}
+
+/// Cache of the message hashes seen on the network, shared by all the
+/// channels. Messages announced by us are kept, up to a size limit, so
+/// they can be sent to the peers that request them.
+pub struct Inventory {
+    seen: Mutex<SeenHashes>,
+    requested: Mutex<FxHashMap<blake3::Hash, Request>>,
+    store: Mutex<StoredMessages>,
+}
+
+impl Inventory {
+    /// Create a new empty inventory.
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            seen: Mutex::new(SeenHashes::default()),
+            requested: Mutex::new(FxHashMap::default()),
+            store: Mutex::new(StoredMessages::default()),
+        })
+    }
+
+    /// Check whether a message hash was already seen.
+    pub async fn is_seen(&self, hash: &blake3::Hash) -> bool {
+        self.seen.lock().await.contains(hash)
+    }
+
+    /// Mark a message hash as seen. Returns false if it was already seen.
+    pub async fn insert_seen(&self, hash: blake3::Hash) -> bool {
+        self.requested.lock().await.remove(&hash);
+        self.seen.lock().await.insert(hash)
+    }
+
+    /// Keep a message we announce, so it can be served to peers, and mark
+    /// it as seen. Returns its hash.
+    pub async fn store(&self, command: String, payload: Vec<u8>) -> blake3::Hash {
+        let hash = inventory_hash(&command, &payload);
+        self.insert_seen(hash).await;
+        self.store.lock().await.insert(hash, DataMessage { command, payload });
+        hash
+    }
+
+    /// Get a stored message by its hash.
+    pub async fn get(&self, hash: &blake3::Hash) -> Option<DataMessage> {
+        self.store.lock().await.messages.get(hash).cloned()
+    }
+
+    /// Filter hashes announced by `peer` down to the ones we still need,
+    /// and mark them as requested from it. Hashes already requested from
+    /// another peer aren't requested again, but `peer` is remembered as an
+    /// announcer to retry with if that peer doesn't reply.
+    pub async fn request(&self, peer: &Url, hashes: &[blake3::Hash]) -> Vec<blake3::Hash> {
+        let seen = self.seen.lock().await;
+        let mut requested = self.requested.lock().await;
+        let now = Instant::now();
+
+        // Timed out requests are left to other announcers for another
+        // timeout period, and forgotten after that
+        requested.retain(|_, req| {
+            let age = now.duration_since(req.time);
+            age < REQUEST_TIMEOUT || (age < REQUEST_TIMEOUT * 2 && !req.announcers.is_empty())
+        });
+
+        let mut wanted = vec![];
+        for hash in hashes {
+            if seen.contains(hash) {
+                continue
+            }
+
+            if let Some(req) = requested.get_mut(hash) {
+                if req.announcers.len() < MAX_ANNOUNCERS && !req.announcers.contains(peer) {
+                    req.announcers.push_back(peer.clone());
+                }
+                continue
+            }
+
+            if requested.len() >= MAX_REQUESTED {
+                debug!(target: "net", "Inventory::request() too many pending requests, skipping {}", hash);
+                continue
+            }
+
+            requested.insert(*hash, Request { time: now, announcers: VecDeque::new() });
+            wanted.push(*hash);
+        }
+
+        debug!(target: "net", "Inventory::request() {} of {} hashes unseen", wanted.len(), hashes.len());
+        wanted
+    }
+
+    /// Take over the timed out requests of hashes `peer` announced, so they
+    /// can be requested from it instead.
+    pub async fn retry(&self, peer: &Url) -> Vec<blake3::Hash> {
+        let mut requested = self.requested.lock().await;
+        let now = Instant::now();
+
+        let mut wanted = vec![];
+        for (hash, req) in requested.iter_mut() {
+            if now.duration_since(req.time) < REQUEST_TIMEOUT {
+                continue
+            }
+
+            if let Some(pos) = req.announcers.iter().position(|x| x == peer) {
+                req.announcers.remove(pos);
+                req.time = now;
+                wanted.push(*hash);
+            }
+        }
+
+        if !wanted.is_empty() {
+            debug!(target: "net", "Inventory::retry() requesting {} timed out hashes from {}", wanted.len(), peer);
+        }
+        wanted
+    }
+
+    #[cfg(test)]
+    async fn expire_requests(&self) {
+        for req in self.requested.lock().await.values_mut() {
+            req.time -= REQUEST_TIMEOUT;
+        }
+    }
+}
+
+/// Set of seen hashes, forgetting the oldest ones once full.
+#[derive(Default)]
+struct SeenHashes {
+    order: VecDeque<blake3::Hash>,
+    hashes: FxHashSet<blake3::Hash>,
+}
+
+impl SeenHashes {
+    fn contains(&self, hash: &blake3::Hash) -> bool {
+        self.hashes.contains(hash)
+    }
+
+    fn insert(&mut self, hash: blake3::Hash) -> bool {
+        if !self.hashes.insert(hash) {
+            return false
+        }
+
+        self.order.push_back(hash);
+        if self.order.len() > SEEN_CAPACITY {
+            let oldest = self.order.pop_front().unwrap();
+            self.hashes.remove(&oldest);
+        }
+        true
+    }
+}
+
+/// Stored messages, dropping the oldest ones once over the size limit.
+#[derive(Default)]
+struct StoredMessages {
+    order: VecDeque<blake3::Hash>,
+    messages: FxHashMap<blake3::Hash, DataMessage>,
+    bytes: usize,
+}
+
+impl StoredMessages {
+    fn insert(&mut self, hash: blake3::Hash, message: DataMessage) {
+        if self.messages.contains_key(&hash) {
+            return
+        }
+
+        self.bytes += message.payload.len();
+        self.order.push_back(hash);
+        self.messages.insert(hash, message);
+
+        while self.bytes > MAX_STORED_BYTES {
+            let oldest = self.order.pop_front().unwrap();
+            let message = self.messages.remove(&oldest).unwrap();
+            self.bytes -= message.payload.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn inventory_test() {
+        let inventory = Inventory::new();
+
+        let hash = inventory.store("foo".to_string(), vec![1, 2, 3]).await;
+        assert_eq!(hash, inventory_hash("foo", &[1, 2, 3]));
+        assert!(inventory.is_seen(&hash).await);
+        assert_eq!(inventory.get(&hash).await.unwrap().payload, vec![1, 2, 3]);
+
+        // Only unseen hashes are requested, and only once
+        let alice = Url::parse("tcp://alice:1").unwrap();
+        let bob = Url::parse("tcp://bob:1").unwrap();
+        let carol = Url::parse("tcp://carol:1").unwrap();
+        let other = inventory_hash("foo", &[4, 5, 6]);
+        assert_eq!(inventory.request(&alice, &[hash, other]).await, vec![other]);
+        assert!(inventory.request(&bob, &[other]).await.is_empty());
+        assert!(inventory.request(&carol, &[other]).await.is_empty());
+
+        // Once alice times out, the hash is requested from the other
+        // announcers, one at a time
+        assert!(inventory.retry(&bob).await.is_empty());
+        inventory.expire_requests().await;
+        assert!(inventory.retry(&alice).await.is_empty());
+        assert_eq!(inventory.retry(&carol).await, vec![other]);
+        assert!(inventory.retry(&bob).await.is_empty());
+        inventory.expire_requests().await;
+        assert_eq!(inventory.retry(&bob).await, vec![other]);
+
+        // Without other announcers, timed out requests are dropped
+        inventory.expire_requests().await;
+        assert_eq!(inventory.request(&alice, &[other]).await, vec![other]);
+
+        assert!(inventory.insert_seen(other).await);
+        assert!(!inventory.insert_seen(other).await);
+        assert!(inventory.get(&other).await.is_none());
+    }
+}
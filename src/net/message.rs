@@ -1,15 +1,83 @@
-use darkfi_serial::{Decodable, Encodable, SerialDecodable, SerialEncodable, VarInt};
+use darkfi_serial::{
+    decode_slice, decode_with_limits, deserialize_partial_with_limits, BlobDecoder, BlobEncoder,
+    Decodable, DecodeLimits, Encodable, SerialDecodable, SerialEncodable, VarInt,
+};
 use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use log::debug;
+use std::io::{Cursor, Read};
 use url::Url;
 
 use crate::{Error, Result};
 
 const MAGIC_BYTES: [u8; 4] = [0xd9, 0xef, 0xb6, 0x7d];
 
+/// Magic bytes of packets with a zstd compressed payload
+const ZSTD_MAGIC_BYTES: [u8; 4] = [0xd9, 0xef, 0xb6, 0x7e];
+
+/// Name of zstd compression, as advertised in the version message
+pub const COMPRESSION_ZSTD: &str = "zstd";
+
+/// Payloads smaller than this are always sent uncompressed
+const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Maximum length of a packet's command
+const MAX_COMMAND_LEN: usize = 256;
+
+/// Maximum size of a packet's payload, also after decompressing it
+pub const MAX_PAYLOAD_SIZE: usize = 32 * 1024 * 1024;
+
+/// Maximum number of hashes in a single inv or getdata message
+pub const MAX_INV_HASHES: usize = 4096;
+
 /// Generic message template.
 pub trait Message: 'static + Encodable + Decodable + Send + Sync {
     fn name() -> &'static str;
+
+    /// Limits applied when decoding this message from a peer's payload.
+    fn decode_limits() -> DecodeLimits {
+        DecodeLimits {
+            max_len: MAX_PAYLOAD_SIZE as u64,
+            max_bytes: 4 * MAX_PAYLOAD_SIZE as u64,
+            max_depth: 16,
+        }
+    }
+
+    /// Decode this message from a peer's payload. Messages ending with a
+    /// large byte blob should keep it in the payload's buffer rather than
+    /// copying it, see [`decode_blob_payload`].
+    fn decode_payload(payload: Vec<u8>) -> Result<Self> {
+        Ok(decode_with_limits(Cursor::new(payload), Self::decode_limits())?)
+    }
+}
+
+/// Decode a payload made of a header `H` followed by a byte blob, encoded
+/// the same way as a `Vec<u8>`. The blob is returned in the payload's own
+/// buffer, so it isn't copied.
+pub fn decode_blob_payload<H: Decodable>(
+    mut payload: Vec<u8>,
+    limits: DecodeLimits,
+) -> Result<(H, Vec<u8>)> {
+    let (header, header_len) = deserialize_partial_with_limits(&payload, limits)?;
+
+    let mut rest = &payload[header_len..];
+    let blob_len = decode_slice(&mut rest)?.len();
+    if !rest.is_empty() || blob_len as u64 > limits.max_len {
+        return Err(Error::MalformedPacket)
+    }
+
+    payload.drain(..payload.len() - blob_len);
+    Ok((header, payload))
+}
+
+/// Limits for the small messages exchanged in the version handshake
+fn handshake_limits() -> DecodeLimits {
+    DecodeLimits { max_len: 1024, max_bytes: 1024, max_depth: 2 }
+}
+
+/// Limits for the lists of hashes exchanged by the inventory protocol
+fn inv_limits() -> DecodeLimits {
+    let max_len = MAX_INV_HASHES as u64;
+    DecodeLimits { max_len, max_bytes: max_len * 32, max_depth: 1 }
 }
 
 /// Outbound keep-alive message.
@@ -42,9 +110,28 @@ pub struct ExtAddrsMessage {
 }
 
 /// Requests version information of outbound connection.
-#[derive(SerialEncodable, SerialDecodable)]
+#[derive(SerialEncodable)]
 pub struct VersionMessage {
     pub node_id: String,
+    /// Payload compression algorithms the node accepts
+    pub compression: Vec<String>,
+}
+
+// Nodes predating payload compression don't send `compression`. Messages
+// are decoded from their length delimited payload, so the end of the data
+// is the end of the message.
+impl Decodable for VersionMessage {
+    fn decode<D: Read>(mut d: D) -> std::result::Result<Self, std::io::Error> {
+        let node_id = Decodable::decode(&mut d)?;
+
+        let mut byte = [0u8; 1];
+        let compression = match d.read(&mut byte)? {
+            0 => vec![],
+            _ => Decodable::decode(Read::chain(&byte[..], d))?,
+        };
+
+        Ok(Self { node_id, compression })
+    }
 }
 
 /// Sends version information to inbound connection. Response to VersionMessage.
@@ -54,6 +141,26 @@ pub struct VerackMessage {
     pub app: String,
 }
 
+/// Announces the hashes of messages available from the sender.
+#[derive(SerialEncodable, SerialDecodable, Clone)]
+pub struct InvMessage {
+    pub hashes: Vec<blake3::Hash>,
+}
+
+/// Requests the announced messages we haven't seen. Response to InvMessage.
+#[derive(SerialEncodable, SerialDecodable)]
+pub struct GetDataMessage {
+    pub hashes: Vec<blake3::Hash>,
+}
+
+/// Carries a requested message as a command and payload, the way it
+/// would be sent in a packet. Response to GetDataMessage.
+#[derive(SerialEncodable, SerialDecodable, Clone)]
+pub struct DataMessage {
+    pub command: String,
+    pub payload: Vec<u8>,
+}
+
 impl Message for PingMessage {
     fn name() -> &'static str {
         "ping"
@@ -88,12 +195,46 @@ impl Message for VersionMessage {
     fn name() -> &'static str {
         "version"
     }
+
+    fn decode_limits() -> DecodeLimits {
+        handshake_limits()
+    }
 }
 
 impl Message for VerackMessage {
     fn name() -> &'static str {
         "verack"
     }
+
+    fn decode_limits() -> DecodeLimits {
+        handshake_limits()
+    }
+}
+
+impl Message for InvMessage {
+    fn name() -> &'static str {
+        "inv"
+    }
+
+    fn decode_limits() -> DecodeLimits {
+        inv_limits()
+    }
+}
+
+impl Message for GetDataMessage {
+    fn name() -> &'static str {
+        "getdata"
+    }
+
+    fn decode_limits() -> DecodeLimits {
+        inv_limits()
+    }
+}
+
+impl Message for DataMessage {
+    fn name() -> &'static str {
+        "data"
+    }
 }
 
 /// Packets are the base type read from the network. Converted to messages and
@@ -113,12 +254,17 @@ pub async fn read_packet<R: AsyncRead + Unpin + Sized>(stream: &mut R) -> Result
     stream.read_exact(&mut magic).await?;
 
     debug!(target: "net", "read magic {:?}", magic);
-    if magic != MAGIC_BYTES {
-        return Err(Error::MalformedPacket)
-    }
+    let compressed = match magic {
+        MAGIC_BYTES => false,
+        ZSTD_MAGIC_BYTES => true,
+        _ => return Err(Error::MalformedPacket),
+    };
 
     // The type of the message
     let command_len = VarInt::decode_async(stream).await?.0 as usize;
+    if command_len > MAX_COMMAND_LEN {
+        return Err(Error::MalformedPacket)
+    }
     let mut cmd = vec![0u8; command_len];
     if command_len > 0 {
         stream.read_exact(&mut cmd).await?;
@@ -126,25 +272,45 @@ pub async fn read_packet<R: AsyncRead + Unpin + Sized>(stream: &mut R) -> Result
     let cmd = String::from_utf8(cmd)?;
     debug!(target: "net", "read command: {}", cmd);
 
-    let payload_len = VarInt::decode_async(stream).await?.0 as usize;
+    // The message-dependent data (see message types). It's read in chunks
+    // so a peer can't make us allocate more than it actually sends, and
+    // handed over to the dispatchers, which decode it in place with
+    // `Message::decode_payload`.
+    let mut decoder = BlobDecoder::new(&mut *stream).await?;
+    if decoder.remaining() > MAX_PAYLOAD_SIZE as u64 {
+        return Err(Error::MalformedPacket)
+    }
+    let mut payload = decoder.read_to_vec().await?;
+    debug!(target: "net", "read payload {} bytes", payload.len());
 
-    // The message-dependent data (see message types)
-    let mut payload = vec![0u8; payload_len];
-    if payload_len > 0 {
-        stream.read_exact(&mut payload).await?;
+    if compressed {
+        payload = decompress_payload(&payload)?;
+        debug!(target: "net", "decompressed payload to {} bytes", payload.len());
     }
-    debug!(target: "net", "read payload {} bytes", payload_len);
 
     Ok(Packet { command: cmd, payload })
 }
 
-/// Sends an outbound packet by writing data to TCP stream.
+/// Sends an outbound packet by writing data to TCP stream. With `compress`
+/// set, payloads above a size threshold are compressed if it makes them
+/// smaller. Only set it for peers that advertised zstd support.
 pub async fn send_packet<W: AsyncWrite + Unpin + Sized>(
     stream: &mut W,
-    packet: Packet,
+    mut packet: Packet,
+    compress: bool,
 ) -> Result<()> {
+    let mut magic = MAGIC_BYTES;
+    if compress && packet.payload.len() >= COMPRESSION_THRESHOLD {
+        let compressed = zstd::stream::encode_all(&packet.payload[..], 0)?;
+        if compressed.len() < packet.payload.len() {
+            debug!(target: "net", "compressed payload from {} bytes", packet.payload.len());
+            packet.payload = compressed;
+            magic = ZSTD_MAGIC_BYTES;
+        }
+    }
+
     debug!(target: "net", "sending magic...");
-    stream.write_all(&MAGIC_BYTES).await?;
+    stream.write_all(&magic).await?;
     debug!(target: "net", "sent magic...");
 
     VarInt(packet.command.len() as u64).encode_async(stream).await?;
@@ -153,12 +319,108 @@ pub async fn send_packet<W: AsyncWrite + Unpin + Sized>(
     debug!(target: "net", "sent command: {}", packet.command);
 
     assert_eq!(std::mem::size_of::<usize>(), std::mem::size_of::<u64>());
-    VarInt(packet.payload.len() as u64).encode_async(stream).await?;
-
-    if !packet.payload.is_empty() {
-        stream.write_all(&packet.payload).await?;
-    }
+    let mut encoder = BlobEncoder::new(&mut *stream, packet.payload.len() as u64).await?;
+    encoder.write(&packet.payload).await?;
+    encoder.finish().await?;
+    stream.flush().await?;
     debug!(target: "net", "sent payload {} bytes", packet.payload.len() as u64);
 
     Ok(())
 }
+
+/// Decompress a zstd compressed payload, failing if it decompresses to
+/// more than [`MAX_PAYLOAD_SIZE`] bytes.
+fn decompress_payload(payload: &[u8]) -> Result<Vec<u8>> {
+    let decoder = zstd::stream::read::Decoder::new(payload)?;
+
+    // Read one byte past the limit, so we know when it's exceeded
+    let mut data = vec![];
+    decoder.take(MAX_PAYLOAD_SIZE as u64 + 1).read_to_end(&mut data)?;
+    if data.len() > MAX_PAYLOAD_SIZE {
+        return Err(Error::MalformedPacket)
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+
+    async fn roundtrip(payload: Vec<u8>, compress: bool) -> (Vec<u8>, Packet) {
+        let packet = Packet { command: "test".to_string(), payload };
+        let mut stream = Cursor::new(vec![]);
+        send_packet(&mut stream, packet, compress).await.unwrap();
+
+        let data = stream.into_inner();
+        let packet = read_packet(&mut Cursor::new(data.clone())).await.unwrap();
+        (data, packet)
+    }
+
+    #[async_std::test]
+    async fn packet_compression_test() {
+        let payload = vec![42u8; 64 * 1024];
+        let (data, packet) = roundtrip(payload.clone(), true).await;
+        assert_eq!(data[..4], ZSTD_MAGIC_BYTES);
+        assert!(data.len() < payload.len());
+        assert_eq!(packet.command, "test");
+        assert_eq!(packet.payload, payload);
+
+        let (data, packet) = roundtrip(payload.clone(), false).await;
+        assert_eq!(data[..4], MAGIC_BYTES);
+        assert_eq!(packet.payload, payload);
+
+        // Small payloads are sent as they are
+        let (data, packet) = roundtrip(vec![42u8; 16], true).await;
+        assert_eq!(data[..4], MAGIC_BYTES);
+        assert_eq!(packet.payload, vec![42u8; 16]);
+
+        // Payloads decompressing past the limit are rejected
+        let bomb = zstd::stream::encode_all(&vec![0u8; MAX_PAYLOAD_SIZE + 1][..], 0).unwrap();
+        let mut data = ZSTD_MAGIC_BYTES.to_vec();
+        data.extend_from_slice(&darkfi_serial::serialize(&"test".to_string()));
+        data.extend_from_slice(&darkfi_serial::serialize(&bomb));
+        let result = read_packet(&mut Cursor::new(data)).await;
+        assert!(matches!(result, Err(Error::MalformedPacket)));
+    }
+
+    #[test]
+    fn blob_payload_test() {
+        let blob = vec![42u8; 64 * 1024];
+        let payload = darkfi_serial::serialize(&(7u64, blob.clone()));
+        let ptr = payload.as_ptr();
+        let limits = DecodeLimits::default();
+        let (header, data) = decode_blob_payload::<u64>(payload, limits).unwrap();
+        assert_eq!(header, 7);
+        assert_eq!(data, blob);
+        // The blob is kept in the payload's buffer
+        assert_eq!(data.as_ptr(), ptr);
+
+        // Trailing data and blobs over the limits are rejected
+        let mut payload = darkfi_serial::serialize(&(7u64, blob.clone()));
+        payload.push(0);
+        assert!(decode_blob_payload::<u64>(payload, limits).is_err());
+        let limits = DecodeLimits { max_len: 1024, ..limits };
+        let payload = darkfi_serial::serialize(&(7u64, blob));
+        assert!(decode_blob_payload::<u64>(payload, limits).is_err());
+    }
+
+    #[test]
+    fn version_message_test() {
+        let msg = VersionMessage {
+            node_id: "node".to_string(),
+            compression: vec![COMPRESSION_ZSTD.to_string()],
+        };
+        let decoded: VersionMessage =
+            darkfi_serial::deserialize(&darkfi_serial::serialize(&msg)).unwrap();
+        assert_eq!(decoded.node_id, msg.node_id);
+        assert_eq!(decoded.compression, msg.compression);
+
+        // Sent by nodes predating payload compression
+        let old = darkfi_serial::serialize(&"node".to_string());
+        let decoded: VersionMessage = darkfi_serial::deserialize(&old).unwrap();
+        assert_eq!(decoded.node_id, "node");
+        assert!(decoded.compression.is_empty());
+    }
+}
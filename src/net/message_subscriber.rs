@@ -1,5 +1,5 @@
 use async_std::sync::Mutex;
-use std::{any::Any, io::Cursor, sync::Arc};
+use std::{any::Any, sync::Arc};
 
 use async_trait::async_trait;
 use fxhash::FxHashMap;
@@ -133,8 +133,7 @@ impl<M: Message> MessageDispatcherInterface for MessageDispatcher<M> {
     async fn trigger(&self, payload: Vec<u8>) {
         // deserialize data into type
         // send down the pipes
-        let cursor = Cursor::new(payload);
-        match M::decode(cursor) {
+        match M::decode_payload(payload) {
             Ok(message) => {
                 let message = Ok(Arc::new(message));
                 self._trigger_all(message).await
@@ -173,6 +172,11 @@ impl MessageSubsystem {
         self.dispatchers.lock().await.insert(M::name(), Arc::new(MessageDispatcher::<M>::new()));
     }
 
+    /// Check if a dispatcher was added for the given command.
+    pub async fn has_dispatch(&self, command: &str) -> bool {
+        self.dispatchers.lock().await.contains_key(command)
+    }
+
     /// Subscribes to a Message. Using the Message name, the method returns an the associated MessageDispatcher from the list of
     /// dispatchers and calls subscribe().
     pub async fn subscribe<M: Message>(&self) -> Result<MessageSubscription<M>> {
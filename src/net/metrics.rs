@@ -0,0 +1,356 @@
+use async_std::{
+    future::timeout,
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use futures::{AsyncReadExt, AsyncWriteExt};
+use fxhash::FxHashMap;
+use log::{debug, error, info};
+use serde_json::json;
+use smol::Executor;
+use url::Url;
+
+use crate::{Error, Result};
+
+use super::{ChannelPtr, P2pPtr};
+
+/// Largest HTTP request head read by the metrics endpoint
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// Time a metrics endpoint client gets to send its request head
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Command that received messages without a dispatcher are counted under
+pub const UNKNOWN_COMMAND: &str = "unknown";
+
+/// Pointer to network metrics.
+pub type MetricsPtr = Arc<Metrics>;
+
+/// Number of messages and payload bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counter {
+    pub messages: u64,
+    pub bytes: u64,
+}
+
+impl Counter {
+    fn add(&mut self, bytes: usize) {
+        self.messages += 1;
+        self.bytes += bytes as u64;
+    }
+}
+
+/// Messages sent and received, by command.
+#[derive(Clone, Default)]
+pub struct Traffic {
+    pub sent: FxHashMap<String, Counter>,
+    pub received: FxHashMap<String, Counter>,
+}
+
+impl Traffic {
+    fn to_json(&self) -> serde_json::Value {
+        let counters = |map: &FxHashMap<String, Counter>| -> serde_json::Value {
+            map.iter()
+                .map(|(k, v)| (k.clone(), json!({"messages": v.messages, "bytes": v.bytes})))
+                .collect::<serde_json::Map<_, _>>()
+                .into()
+        };
+
+        json!({"sent": counters(&self.sent), "received": counters(&self.received)})
+    }
+
+    fn total(map: &FxHashMap<String, Counter>) -> Counter {
+        map.values().fold(Counter::default(), |acc, x| Counter {
+            messages: acc.messages + x.messages,
+            bytes: acc.bytes + x.bytes,
+        })
+    }
+}
+
+/// Counters aggregated over all the channels of a p2p network.
+#[derive(Default)]
+pub struct Metrics {
+    traffic: Mutex<Traffic>,
+    handshake_failures: AtomicU64,
+    reconnects: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub async fn traffic(&self) -> Traffic {
+        self.traffic.lock().await.clone()
+    }
+
+    pub fn handshake_failures(&self) -> u64 {
+        self.handshake_failures.load(Ordering::Relaxed)
+    }
+
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    /// Count a channel that failed the version handshake.
+    pub fn record_handshake_failure(&self) {
+        self.handshake_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a connection made again after the previous one was lost.
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// JSON snapshot of the network and channel counters.
+    pub async fn to_json(&self, channels: &[ChannelPtr]) -> serde_json::Value {
+        let mut channel_metrics = vec![];
+        for channel in channels {
+            let metrics = channel.metrics();
+            let mut value = metrics.traffic.lock().await.to_json();
+            value["address"] = json!(channel.address().to_string());
+            value["ping_ms"] = json!(metrics.ping().await.map(|x| x.as_millis() as u64));
+            channel_metrics.push(value);
+        }
+
+        let mut value = self.traffic.lock().await.to_json();
+        value["handshake_failures"] = json!(self.handshake_failures());
+        value["reconnects"] = json!(self.reconnects());
+        value["channels"] = json!(channel_metrics);
+        value
+    }
+
+    /// Network and channel counters in the Prometheus text format.
+    pub async fn to_prometheus(&self, channels: &[ChannelPtr]) -> String {
+        let mut out = String::new();
+        let traffic = self.traffic.lock().await.clone();
+
+        let mut per_command = |name: &str, help: &str, map: &FxHashMap<String, Counter>, bytes| {
+            metric_header(&mut out, name, help, "counter");
+            let mut commands: Vec<_> = map.iter().collect();
+            commands.sort_by(|a, b| a.0.cmp(b.0));
+            for (command, counter) in commands {
+                let value = if bytes { counter.bytes } else { counter.messages };
+                let _ = writeln!(out, "{}{{command=\"{}\"}} {}", name, escape(command), value);
+            }
+        };
+
+        per_command("darkfi_net_messages_sent_total", "Messages sent", &traffic.sent, false);
+        per_command("darkfi_net_bytes_sent_total", "Payload bytes sent", &traffic.sent, true);
+        per_command(
+            "darkfi_net_messages_received_total",
+            "Messages received",
+            &traffic.received,
+            false,
+        );
+        per_command(
+            "darkfi_net_bytes_received_total",
+            "Payload bytes received",
+            &traffic.received,
+            true,
+        );
+
+        metric_header(
+            &mut out,
+            "darkfi_net_handshake_failures_total",
+            "Failed handshakes",
+            "counter",
+        );
+        let _ = writeln!(out, "darkfi_net_handshake_failures_total {}", self.handshake_failures());
+
+        metric_header(
+            &mut out,
+            "darkfi_net_reconnects_total",
+            "Connections made again after being lost",
+            "counter",
+        );
+        let _ = writeln!(out, "darkfi_net_reconnects_total {}", self.reconnects());
+
+        metric_header(&mut out, "darkfi_net_channels", "Connected channels", "gauge");
+        let _ = writeln!(out, "darkfi_net_channels {}", channels.len());
+
+        let mut lines = FxHashMap::<&str, Vec<String>>::default();
+        for channel in channels {
+            let address = escape(channel.address().as_str());
+            let metrics = channel.metrics();
+            let traffic = metrics.traffic.lock().await.clone();
+            let sent = Traffic::total(&traffic.sent);
+            let received = Traffic::total(&traffic.received);
+
+            let mut push = |name, value: String| {
+                lines
+                    .entry(name)
+                    .or_default()
+                    .push(format!("{}{{address=\"{}\"}} {}", name, address, value));
+            };
+            push("darkfi_net_channel_bytes_sent_total", sent.bytes.to_string());
+            push("darkfi_net_channel_bytes_received_total", received.bytes.to_string());
+            if let Some(ping) = metrics.ping().await {
+                push("darkfi_net_channel_ping_seconds", ping.as_secs_f64().to_string());
+            }
+        }
+
+        for (name, help, typ) in [
+            ("darkfi_net_channel_bytes_sent_total", "Payload bytes sent on the channel", "counter"),
+            (
+                "darkfi_net_channel_bytes_received_total",
+                "Payload bytes received on the channel",
+                "counter",
+            ),
+            (
+                "darkfi_net_channel_ping_seconds",
+                "Last ping round trip time of the channel",
+                "gauge",
+            ),
+        ] {
+            metric_header(&mut out, name, help, typ);
+            for line in lines.remove(name).unwrap_or_default() {
+                let _ = writeln!(out, "{}", line);
+            }
+        }
+
+        out
+    }
+}
+
+fn metric_header(out: &mut String, name: &str, help: &str, typ: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, typ);
+}
+
+/// Escape a Prometheus label value.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Counters of a single channel. Everything recorded here is also added
+/// to the network [`Metrics`].
+pub struct ChannelMetrics {
+    traffic: Mutex<Traffic>,
+    ping: Mutex<Option<Duration>>,
+    parent: MetricsPtr,
+}
+
+impl ChannelMetrics {
+    pub fn new(parent: MetricsPtr) -> Self {
+        Self { traffic: Mutex::new(Traffic::default()), ping: Mutex::new(None), parent }
+    }
+
+    pub async fn traffic(&self) -> Traffic {
+        self.traffic.lock().await.clone()
+    }
+
+    /// Round trip time of the last ping on the channel.
+    pub async fn ping(&self) -> Option<Duration> {
+        *self.ping.lock().await
+    }
+
+    pub async fn record_sent(&self, command: &str, bytes: usize) {
+        for traffic in [&self.traffic, &self.parent.traffic] {
+            traffic.lock().await.sent.entry(command.to_string()).or_default().add(bytes);
+        }
+    }
+
+    /// Count a received message. Commands without a dispatcher on the channel
+    /// must be passed as [`UNKNOWN_COMMAND`], since peers choose them freely.
+    pub async fn record_received(&self, command: &str, bytes: usize) {
+        for traffic in [&self.traffic, &self.parent.traffic] {
+            traffic.lock().await.received.entry(command.to_string()).or_default().add(bytes);
+        }
+    }
+
+    pub async fn record_ping(&self, duration: Duration) {
+        *self.ping.lock().await = Some(duration);
+    }
+}
+
+/// Serve the metrics of the network in the Prometheus text format, over
+/// HTTP on the given address.
+pub async fn serve_prometheus(p2p: P2pPtr, url: Url, executor: Arc<Executor<'_>>) -> Result<()> {
+    let socket_addr = *url.socket_addrs(|| None)?.first().ok_or(Error::NoUrlFound)?;
+    let listener = TcpListener::bind(socket_addr).await?;
+    info!(target: "net", "Serving metrics on {}", url);
+
+    loop {
+        let (mut stream, peer_addr) = match listener.accept().await {
+            Ok(v) => v,
+            Err(e) => {
+                error!(target: "net", "Metrics endpoint accept failed: {}", e);
+                continue
+            }
+        };
+
+        let p2p = p2p.clone();
+        executor
+            .spawn(async move {
+                // Read the request head. Any request gets the metrics.
+                let read_request = async {
+                    let mut request = vec![];
+                    let mut buf = [0u8; 1024];
+                    while !request.ends_with(b"\r\n\r\n") && request.len() < MAX_REQUEST_SIZE {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return false,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    true
+                };
+
+                match timeout(REQUEST_TIMEOUT, read_request).await {
+                    Ok(true) => {}
+                    Ok(false) => return,
+                    Err(_) => {
+                        debug!(target: "net", "Metrics endpoint request from {} timed out", peer_addr);
+                        return
+                    }
+                }
+
+                let body = p2p.get_metrics_prometheus().await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+
+                if let Err(e) = stream.write_all(response.as_bytes()).await {
+                    debug!(target: "net", "Metrics endpoint write to {} failed: {}", peer_addr, e);
+                }
+            })
+            .detach();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn metrics_test() {
+        let metrics = Metrics::new();
+        let channel = ChannelMetrics::new(metrics.clone());
+        channel.record_sent("ping", 4).await;
+        channel.record_sent("ping", 4).await;
+        channel.record_received("a\"b", 10).await;
+        metrics.record_handshake_failure();
+
+        assert_eq!(channel.traffic().await.sent["ping"], Counter { messages: 2, bytes: 8 });
+        assert_eq!(metrics.traffic().await.received["a\"b"], Counter { messages: 1, bytes: 10 });
+
+        let text = metrics.to_prometheus(&[]).await;
+        assert!(text.contains("darkfi_net_messages_sent_total{command=\"ping\"} 2\n"));
+        assert!(text.contains("darkfi_net_bytes_sent_total{command=\"ping\"} 8\n"));
+        assert!(text.contains("darkfi_net_messages_received_total{command=\"a\\\"b\"} 1\n"));
+        assert!(text.contains("darkfi_net_handshake_failures_total 1\n"));
+        assert!(text.contains("darkfi_net_channels 0\n"));
+
+        let json = metrics.to_json(&[]).await;
+        assert_eq!(json["sent"]["ping"]["bytes"], 8);
+        assert_eq!(json["reconnects"], 0);
+    }
+}
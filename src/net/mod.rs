@@ -20,6 +20,11 @@ pub mod connector;
 /// the host store until it finds ones to connect to.
 pub mod hosts;
 
+/// Inventory is a cache of the message hashes seen on the network, shared by
+/// all the channels. Used by the inventory protocol to request only the
+/// messages a node hasn't seen, and to serve the messages it announced.
+pub mod inventory;
+
 /// Generic publish/subscribe class that can dispatch any kind of message to a
 /// subscribed list of dispatchers. Dispatchers subscribe to a single
 /// message format of any type. This is a generalized version of the simple
@@ -48,6 +53,12 @@ pub mod message_subscriber;
 /// converted into messages and passed to an event loop.
 pub mod message;
 
+/// Counters of the messages sent and received on each channel, aggregated
+/// over the whole network, along with handshake failures, reconnects and
+/// ping latencies. Exported as JSON for RPC and in the Prometheus text
+/// format over HTTP.
+pub mod metrics;
+
 /// P2P provides all core functionality to interact with the peer-to-peer
 /// network.
 ///
@@ -82,6 +93,12 @@ pub mod protocol;
 /// which describes the common functions across all sessions.
 pub mod session;
 
+/// Simulation harness running a number of P2p instances in a single process,
+/// over the in-memory transport. Latency, packet loss and partitions can be
+/// configured for the links between nodes, to test protocols under adverse
+/// network conditions.
+pub mod sim;
+
 /// Network configuration settings.
 pub mod settings;
 
@@ -95,8 +112,10 @@ pub use acceptor::{Acceptor, AcceptorPtr};
 pub use channel::{Channel, ChannelPtr};
 pub use connector::Connector;
 pub use hosts::{Hosts, HostsPtr};
-pub use message::Message;
+pub use inventory::{Inventory, InventoryPtr};
+pub use message::{decode_blob_payload, Message};
 pub use message_subscriber::MessageSubscription;
+pub use metrics::{ChannelMetrics, Metrics, MetricsPtr};
 pub use p2p::{P2p, P2pPtr};
 pub use protocol::{ProtocolBase, ProtocolBasePtr, ProtocolJobsManager, ProtocolJobsManagerPtr};
 pub use session::{
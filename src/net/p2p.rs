@@ -10,16 +10,17 @@ use smol::Executor;
 use url::Url;
 
 use crate::{
-    system::{Subscriber, SubscriberPtr, Subscription},
+    system::{StoppableTask, Subscriber, SubscriberPtr, Subscription},
     util::async_util::sleep,
-    Result,
+    Error, Result,
 };
 
 use super::{
-    message::Message,
+    message::{InvMessage, Message},
+    metrics::{serve_prometheus, Metrics, MetricsPtr},
     protocol::{register_default_protocols, ProtocolRegistry},
     session::{InboundSession, ManualSession, OutboundSession, SeedSyncSession, Session},
-    Channel, ChannelPtr, Hosts, HostsPtr, Settings, SettingsPtr,
+    Channel, ChannelPtr, Hosts, HostsPtr, Inventory, InventoryPtr, Settings, SettingsPtr,
 };
 
 /// List of channels that are awaiting connection.
@@ -63,6 +64,8 @@ pub struct P2p {
     // Used both internally and externally
     stop_subscriber: SubscriberPtr<()>,
     hosts: HostsPtr,
+    inventory: InventoryPtr,
+    metrics: MetricsPtr,
     protocol_registry: ProtocolRegistry,
 
     // We keep a reference to the sessions used for get info
@@ -94,7 +97,9 @@ impl P2p {
             channels: Mutex::new(FxHashMap::default()),
             channel_subscriber: Subscriber::new(),
             stop_subscriber: Subscriber::new(),
-            hosts: Hosts::new(settings.localnet),
+            hosts: Hosts::new(settings.localnet, !settings.no_resolve),
+            inventory: Inventory::new(),
+            metrics: Metrics::new(),
             protocol_registry: ProtocolRegistry::new(),
             session_manual: Mutex::new(None),
             session_inbound: Mutex::new(None),
@@ -133,6 +138,18 @@ impl P2p {
     }
     // ANCHOR_END: get_info
 
+    /// JSON snapshot of the network and channel metrics.
+    pub async fn get_metrics(&self) -> serde_json::Value {
+        let channels: Vec<ChannelPtr> = self.channels.lock().await.values().cloned().collect();
+        self.metrics.to_json(&channels).await
+    }
+
+    /// Network and channel metrics in the Prometheus text format.
+    pub async fn get_metrics_prometheus(&self) -> String {
+        let channels: Vec<ChannelPtr> = self.channels.lock().await.values().cloned().collect();
+        self.metrics.to_prometheus(&channels).await
+    }
+
     /// Invoke startup and seeding sequence. Call from constructing thread.
     // ANCHOR: start
     pub async fn start(self: Arc<Self>, executor: Arc<Executor<'_>>) -> Result<()> {
@@ -181,6 +198,17 @@ impl P2p {
         let outbound = self.session_outbound().await;
         outbound.clone().start(executor.clone()).await?;
 
+        let metrics_task = StoppableTask::new();
+        if let Some(url) = &self.settings.metrics_addr {
+            metrics_task.clone().start(
+                serve_prometheus(self.clone(), url.clone(), executor.clone()),
+                // Ignore stop handler
+                |_| async {},
+                Error::NetworkServiceStopped,
+                executor.clone(),
+            );
+        }
+
         let stop_sub = self.subscribe_stop().await;
         // Wait for stop signal
         stop_sub.receive().await;
@@ -189,6 +217,7 @@ impl P2p {
         manual.stop().await;
         inbound.stop().await;
         outbound.stop().await;
+        metrics_task.stop().await;
 
         debug!(target: "net", "P2p::run() [END]");
         Ok(())
@@ -384,6 +413,27 @@ impl P2p {
         Ok(())
     }
 
+    /// Announces a message across all channels. Only its hash is sent, and
+    /// peers that haven't seen it yet request the full message.
+    pub async fn announce<M: Message>(&self, message: &M) -> Result<()> {
+        self.announce_with_exclude(message, &[]).await
+    }
+
+    /// Announces a message across all channels.
+    /// Excludes channels provided in `exclude_list`.
+    pub async fn announce_with_exclude<M: Message>(
+        &self,
+        message: &M,
+        exclude_list: &[Url],
+    ) -> Result<()> {
+        let mut payload = Vec::new();
+        message.encode(&mut payload)?;
+        let hash = self.inventory.store(M::name().to_string(), payload).await;
+
+        debug!(target: "net", "P2p::announce() [command={}, hash={}]", M::name(), hash);
+        self.broadcast_with_exclude(InvMessage { hashes: vec![hash] }, exclude_list).await
+    }
+
     /// Add channel address to the list of connected channels.
     pub async fn store(&self, channel: ChannelPtr) {
         self.channels.lock().await.insert(channel.address(), channel.clone());
@@ -439,6 +489,16 @@ impl P2p {
         self.hosts.clone()
     }
 
+    /// Return an atomic pointer to the network metrics.
+    pub fn metrics(&self) -> MetricsPtr {
+        self.metrics.clone()
+    }
+
+    /// Return an atomic pointer to the inventory of seen messages.
+    pub fn inventory(&self) -> InventoryPtr {
+        self.inventory.clone()
+    }
+
     pub fn protocol_registry(&self) -> &ProtocolRegistry {
         &self.protocol_registry
     }
@@ -9,6 +9,17 @@
 /// address information to their local store.
 pub mod protocol_address;
 
+/// Protocol for inventory, getdata and data messages. Implements how nodes
+/// relay messages without sending full payloads to peers that already have
+/// them.
+///
+/// Nodes announce the hashes of the messages they broadcast with an inventory
+/// message. Peers check the hashes against a seen-cache shared by all the
+/// channels, and request only the unseen messages with a getdata message. The
+/// requested messages are sent back in data messages, and passed on to the
+/// channel subscribers as if they were sent directly.
+pub mod protocol_inventory;
+
 /// Manages the tasks for the network protocol. Used by other connection
 /// protocols to handle asynchronous task execution across the network. Runs all
 /// tasks that are handed to it on an executor that has stopping functionality.
@@ -50,6 +61,7 @@ pub mod protocol_base;
 pub mod protocol_registry;
 
 pub use protocol_address::ProtocolAddress;
+pub use protocol_inventory::ProtocolInventory;
 pub use protocol_jobs_manager::{ProtocolJobsManager, ProtocolJobsManagerPtr};
 pub use protocol_ping::ProtocolPing;
 pub use protocol_seed::ProtocolSeed;
@@ -67,5 +79,6 @@ pub async fn register_default_protocols(p2p: P2pPtr) {
     let registry = p2p.protocol_registry();
     registry.register(SESSION_ALL, ProtocolPing::init).await;
     registry.register(!SESSION_SEED, ProtocolAddress::init).await;
+    registry.register(!SESSION_SEED, ProtocolInventory::init).await;
     registry.register(SESSION_SEED, ProtocolSeed::init).await;
 }
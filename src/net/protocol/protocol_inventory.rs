@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use log::{debug, warn};
+use smol::Executor;
+
+use crate::{util::async_util::sleep, Result};
+
+use super::{
+    super::{
+        inventory::{inventory_hash, InventoryPtr},
+        message,
+        message_subscriber::MessageSubscription,
+        ChannelPtr, Message, P2pPtr,
+    },
+    ProtocolBase, ProtocolBasePtr, ProtocolJobsManager, ProtocolJobsManagerPtr,
+};
+
+/// Seconds between checks for timed out requests to retry with this peer
+const RETRY_INTERVAL: u64 = 5;
+
+/// Defines inv, getdata and data messages.
+pub struct ProtocolInventory {
+    channel: ChannelPtr,
+    inv_sub: MessageSubscription<message::InvMessage>,
+    getdata_sub: MessageSubscription<message::GetDataMessage>,
+    data_sub: MessageSubscription<message::DataMessage>,
+    inventory: InventoryPtr,
+    jobsman: ProtocolJobsManagerPtr,
+}
+
+impl ProtocolInventory {
+    /// Create a new inventory protocol. Makes an inv, a getdata and a data
+    /// subscription and adds them to the inventory protocol instance.
+    pub async fn init(channel: ChannelPtr, p2p: P2pPtr) -> ProtocolBasePtr {
+        let inv_sub = channel
+            .clone()
+            .subscribe_msg::<message::InvMessage>()
+            .await
+            .expect("Missing inv dispatcher!");
+
+        let getdata_sub = channel
+            .clone()
+            .subscribe_msg::<message::GetDataMessage>()
+            .await
+            .expect("Missing getdata dispatcher!");
+
+        let data_sub = channel
+            .clone()
+            .subscribe_msg::<message::DataMessage>()
+            .await
+            .expect("Missing data dispatcher!");
+
+        Arc::new(Self {
+            channel: channel.clone(),
+            inv_sub,
+            getdata_sub,
+            data_sub,
+            inventory: p2p.inventory(),
+            jobsman: ProtocolJobsManager::new("ProtocolInventory", channel),
+        })
+    }
+
+    /// Handles receiving the inv message. Requests the announced messages
+    /// that weren't seen yet, nor requested from another peer.
+    async fn handle_receive_inv(self: Arc<Self>) -> Result<()> {
+        debug!(target: "net", "ProtocolInventory::handle_receive_inv() [START]");
+        loop {
+            let inv = self.inv_sub.receive().await?;
+            let hashes = self.inventory.request(&self.channel.address(), &inv.hashes).await;
+            if hashes.is_empty() {
+                continue
+            }
+
+            debug!(target: "net", "ProtocolInventory::handle_receive_inv() requesting {} messages", hashes.len());
+            self.channel.clone().send(message::GetDataMessage { hashes }).await?;
+        }
+    }
+
+    /// Periodically requests the hashes this peer announced that another
+    /// peer didn't send in time.
+    async fn retry_requests(self: Arc<Self>) -> Result<()> {
+        debug!(target: "net", "ProtocolInventory::retry_requests() [START]");
+        loop {
+            sleep(RETRY_INTERVAL).await;
+
+            let hashes = self.inventory.retry(&self.channel.address()).await;
+            if hashes.is_empty() {
+                continue
+            }
+
+            self.channel.clone().send(message::GetDataMessage { hashes }).await?;
+        }
+    }
+
+    /// Handles receiving the getdata message. Replies with a data message
+    /// for each requested message we still have.
+    async fn handle_receive_getdata(self: Arc<Self>) -> Result<()> {
+        debug!(target: "net", "ProtocolInventory::handle_receive_getdata() [START]");
+        loop {
+            let getdata = self.getdata_sub.receive().await?;
+            for hash in &getdata.hashes {
+                match self.inventory.get(hash).await {
+                    Some(data) => self.channel.clone().send(data).await?,
+                    None => {
+                        debug!(target: "net", "ProtocolInventory::handle_receive_getdata() unknown hash {}", hash)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles receiving the data message. Unseen messages are passed to
+    /// the channel's subscribers as if they were sent directly.
+    async fn handle_receive_data(self: Arc<Self>) -> Result<()> {
+        debug!(target: "net", "ProtocolInventory::handle_receive_data() [START]");
+        loop {
+            let data = self.data_sub.receive().await?;
+
+            // Inventory messages can't be wrapped in one another
+            let command = data.command.as_str();
+            if command == message::InvMessage::name() ||
+                command == message::GetDataMessage::name() ||
+                command == message::DataMessage::name()
+            {
+                warn!(
+                    "ProtocolInventory: {} sent a wrapped {} message",
+                    self.channel.address(),
+                    command
+                );
+                continue
+            }
+
+            let hash = inventory_hash(command, &data.payload);
+            if !self.inventory.insert_seen(hash).await {
+                debug!(target: "net", "ProtocolInventory::handle_receive_data() already seen {}", hash);
+                continue
+            }
+
+            self.channel.get_message_subsystem().notify(command, data.payload.clone()).await;
+        }
+    }
+}
+
+#[async_trait]
+impl ProtocolBase for ProtocolInventory {
+    /// Starts the inventory protocol. Runs the receive inv, getdata and data
+    /// protocols, and the request retries, on the protocol task manager.
+    async fn start(self: Arc<Self>, executor: Arc<Executor<'_>>) -> Result<()> {
+        debug!(target: "net", "ProtocolInventory::start() [START]");
+        self.jobsman.clone().start(executor.clone());
+        self.jobsman.clone().spawn(self.clone().handle_receive_inv(), executor.clone()).await;
+        self.jobsman.clone().spawn(self.clone().handle_receive_getdata(), executor.clone()).await;
+        self.jobsman.clone().spawn(self.clone().handle_receive_data(), executor.clone()).await;
+        self.jobsman.clone().spawn(self.clone().retry_requests(), executor).await;
+        debug!(target: "net", "ProtocolInventory::start() [END]");
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "ProtocolInventory"
+    }
+}
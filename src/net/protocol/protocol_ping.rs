@@ -77,9 +77,10 @@ impl ProtocolPing {
                 self.channel.stop().await;
                 return Err(Error::ChannelStopped)
             }
-            let duration = start.elapsed().as_millis();
+            let duration = start.elapsed();
+            self.channel.metrics().record_ping(duration).await;
             debug!(target: "net", "Received Pong message {}ms from [{:?}]",
-                   duration, self.channel.address());
+                   duration.as_millis(), self.channel.address());
         }
     }
 
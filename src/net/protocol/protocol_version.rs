@@ -84,7 +84,12 @@ impl ProtocolVersion {
     async fn send_version(self: Arc<Self>) -> Result<()> {
         debug!(target: "net", "ProtocolVersion::send_version() [START]");
 
-        let version = message::VersionMessage { node_id: self.settings.node_id.clone() };
+        let compression = match self.settings.compression {
+            true => vec![message::COMPRESSION_ZSTD.to_string()],
+            false => vec![],
+        };
+        let version =
+            message::VersionMessage { node_id: self.settings.node_id.clone(), compression };
 
         self.channel.clone().send(version).await?;
 
@@ -139,6 +144,13 @@ impl ProtocolVersion {
         let version = self.version_sub.receive().await?;
         self.channel.set_remote_node_id(version.node_id.clone()).await;
 
+        // Compress what we send if both sides want it
+        if self.settings.compression &&
+            version.compression.iter().any(|x| x == message::COMPRESSION_ZSTD)
+        {
+            self.channel.set_compression(true).await;
+        }
+
         // Send version acknowledgement
         let verack = message::VerackMessage {
             app: self.settings.app_version.clone().unwrap_or_else(|| "".to_string()),
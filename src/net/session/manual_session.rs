@@ -87,6 +87,8 @@ impl ManualSession {
             outbound_transports.clone()
         };
 
+        let mut connected = false;
+
         loop {
             // Loop forever if attempts is 0
             // Otherwise loop attempts number of times
@@ -114,6 +116,11 @@ impl ManualSession {
 
                         self.clone().register_channel(channel.clone(), executor.clone()).await?;
 
+                        if connected {
+                            self.p2p().metrics().record_reconnect();
+                        }
+                        connected = true;
+
                         // Channel is now connected but not yet setup
 
                         // Remove pending lock since register_channel will add the channel to p2p
@@ -123,7 +123,10 @@ pub trait Session: Sync {
         channel.start(executor.clone());
 
         // Wait for handshake to finish.
-        handshake_task.await?;
+        if let Err(e) = handshake_task.await {
+            p2p.metrics().record_handshake_failure();
+            return Err(e)
+        }
 
         // Now the channel is ready
         debug!(target: "net", "Session handshake complete. Activating remaining protocols");
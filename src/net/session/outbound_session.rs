@@ -3,7 +3,6 @@ use std::fmt;
 use async_std::sync::{Arc, Mutex, Weak};
 use async_trait::async_trait;
 use log::{debug, error, info, warn};
-use rand::seq::SliceRandom;
 use serde_json::{json, Value};
 use smol::Executor;
 use url::Url;
@@ -211,6 +210,10 @@ impl OutboundSession {
                     self.p2p().remove_pending(&addr).await;
                     {
                         let info = &mut self.slot_info.lock().await[slot_number as usize];
+                        // The slot keeps its last channel once it's closed
+                        if info.channel.is_some() {
+                            self.p2p().metrics().record_reconnect();
+                        }
                         info.channel = Some(channel.clone());
                         info.state = OutboundState::Connected;
                     }
@@ -252,21 +255,25 @@ impl OutboundSession {
     /// Loops through host addresses to find a outbound address that we can
     /// connect to. Checks whether address is valid by making sure it isn't
     /// our own inbound address, then checks whether it is already connected
-    /// (exists) or connecting (pending). If no address was found, we try to
-    /// to discover new peers. Keeps looping until address is found that passes all checks.
+    /// (exists) or connecting (pending). Addresses in other network groups
+    /// than the ones of the other slots are tried first. If no address was
+    /// found, we try to to discover new peers. Keeps looping until address
+    /// is found that passes all checks.
     async fn load_address(&self, slot_number: u32) -> Result<Url> {
         loop {
             let p2p = self.p2p();
             let self_inbound_addr = p2p.settings().external_addr.clone();
 
-            let mut addrs;
-
-            {
-                let hosts = p2p.hosts().load_all().await;
-                addrs = hosts;
-            }
-
-            addrs.shuffle(&mut rand::thread_rng());
+            let connected: Vec<Url> = self
+                .slot_info
+                .lock()
+                .await
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != slot_number as usize)
+                .filter_map(|(_, info)| info.addr.clone())
+                .collect();
+            let addrs = p2p.hosts().load_outbound(&connected).await;
 
             for addr in addrs {
                 if p2p.exists(&addr).await? {
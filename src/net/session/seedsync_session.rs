@@ -35,6 +35,11 @@ impl SeedSyncSession {
         debug!(target: "net", "SeedSyncSession::start() [START]");
         let settings = self.p2p().settings();
 
+        // DNS seeds are resolved straight into the hosts
+        if !settings.dns_seeds.is_empty() {
+            self.p2p().hosts().store_dns_seeds(&settings.dns_seeds).await;
+        }
+
         if settings.seeds.is_empty() {
             warn!("Skipping seed sync process since no seeds are configured.");
             // Store external addresses in hosts explicitly
@@ -24,12 +24,16 @@ pub struct Settings {
     pub external_addr: Vec<Url>,
     pub peers: Vec<Url>,
     pub seeds: Vec<Url>,
+    pub dns_seeds: Vec<Url>,
     pub node_id: String,
     pub app_version: Option<String>,
     pub outbound_transports: Vec<TransportName>,
     pub localnet: bool,
+    pub no_resolve: bool,
     pub peer_discovery: bool,
     pub channel_log: bool,
+    pub compression: bool,
+    pub metrics_addr: Option<Url>,
 }
 
 impl Default for Settings {
@@ -46,12 +50,16 @@ impl Default for Settings {
             external_addr: Vec::new(),
             peers: Vec::new(),
             seeds: Vec::new(),
+            dns_seeds: Vec::new(),
             node_id: String::new(),
             app_version: Some(option_env!("CARGO_PKG_VERSION").unwrap_or("").to_string()),
             outbound_transports: get_outbound_transports(vec![]),
             localnet: false,
+            no_resolve: false,
             peer_discovery: true,
             channel_log: false,
+            compression: true,
+            metrics_addr: None,
         }
     }
 }
@@ -84,6 +92,11 @@ pub struct SettingsOpt {
     #[structopt(long)]
     pub seeds: Vec<Url>,
 
+    /// DNS seeds, hostnames resolving to the addresses of many nodes
+    #[serde(default)]
+    #[structopt(long)]
+    pub dns_seeds: Vec<Url>,
+
     #[structopt(skip)]
     pub manual_attempt_limit: Option<u32>,
     #[structopt(skip)]
@@ -115,6 +128,12 @@ pub struct SettingsOpt {
     #[structopt(long)]
     pub localnet: bool,
 
+    /// Never resolve hostnames, e.g. for Tor-only nodes. TCP peers given
+    /// by hostname are then never dialed.
+    #[serde(default)]
+    #[structopt(long)]
+    pub no_resolve: bool,
+
     /// Enable peer discovery
     #[serde(default = "default_as_true")]
     #[structopt(long)]
@@ -124,6 +143,16 @@ pub struct SettingsOpt {
     #[serde(default)]
     #[structopt(long)]
     pub channel_log: bool,
+
+    /// Enable compression of large payloads, for peers supporting it
+    #[serde(default = "default_as_true")]
+    #[structopt(long)]
+    pub compression: bool,
+
+    /// Address to serve Prometheus metrics on
+    #[serde(default)]
+    #[structopt(long)]
+    pub metrics_addr: Option<Url>,
 }
 
 impl From<SettingsOpt> for Settings {
@@ -140,12 +169,16 @@ impl From<SettingsOpt> for Settings {
             external_addr: settings_opt.external_addr,
             peers: settings_opt.peers,
             seeds: settings_opt.seeds,
+            dns_seeds: settings_opt.dns_seeds,
             node_id: settings_opt.node_id,
             app_version: settings_opt.app_version,
             outbound_transports: get_outbound_transports(settings_opt.outbound_transports),
             localnet: settings_opt.localnet,
+            no_resolve: settings_opt.no_resolve,
             peer_discovery: settings_opt.peer_discovery,
             channel_log: settings_opt.channel_log,
+            compression: settings_opt.compression,
+            metrics_addr: settings_opt.metrics_addr,
         }
     }
 }
@@ -0,0 +1,243 @@
+use std::future::Future;
+
+use async_std::sync::Arc;
+use log::debug;
+use smol::{future::yield_now, Executor};
+use url::Url;
+
+use crate::{util::async_util::msleep, Error, Result};
+
+use super::{
+    transport::{LinkConditions, MemoryNetwork, MemoryNetworkPtr, TransportName},
+    P2p, P2pPtr, Settings,
+};
+
+/// Port of the first simulated node. Node `i` listens on `FIRST_PORT + i`.
+const FIRST_PORT: u16 = 10000;
+
+/// Times a step yields to the nodes before delivering packets, so the
+/// messages they react with are sent in the same step.
+const SETTLE_YIELDS: usize = 32;
+
+/// Milliseconds a step sleeps when there is nothing in flight.
+const IDLE_MILLIS: u64 = 10;
+
+/// Runs a number of [`P2p`] instances in a single process, connected over
+/// an in-memory network with configurable latency, packet loss and
+/// partitions.
+///
+/// The simulation owns the clock of the network: packets only move when
+/// the simulation is stepped, see [`Simulation::step`] and
+/// [`Simulation::run_until`], and latencies are counted on that clock
+/// rather than waited for. The nodes' own timers (handshake timeouts,
+/// heartbeats, retries) still run on the wall clock.
+///
+/// By default, every node has a manual connection to all the nodes before
+/// it, so the nodes form a full mesh. Use [`Simulation::with_settings`] to
+/// build other topologies.
+pub struct Simulation {
+    network: MemoryNetworkPtr,
+    nodes: Vec<P2pPtr>,
+    executor: Arc<Executor<'static>>,
+}
+
+impl Simulation {
+    /// Create a simulation of `count` fully connected nodes. `seed` seeds the
+    /// RNG deciding packet loss, see [`MemoryNetwork`].
+    pub async fn new(count: usize, seed: u64, executor: Arc<Executor<'static>>) -> Self {
+        Self::with_settings(count, seed, executor, |_, _| {}).await
+    }
+
+    /// Create a simulation of `count` nodes, calling `configure` with each
+    /// node's index and default settings before creating it.
+    pub async fn with_settings(
+        count: usize,
+        seed: u64,
+        executor: Arc<Executor<'static>>,
+        configure: impl Fn(usize, &mut Settings),
+    ) -> Self {
+        let network = MemoryNetwork::new(seed);
+        let urls: Vec<Url> = (0..count).map(|i| network.url(FIRST_PORT + i as u16)).collect();
+
+        let mut nodes = Vec::with_capacity(count);
+        for (i, url) in urls.iter().enumerate() {
+            let mut settings = Settings {
+                inbound: vec![url.clone()],
+                peers: urls[..i].to_vec(),
+                node_id: format!("node{}", i),
+                outbound_transports: vec![TransportName::Memory],
+                localnet: true,
+                connect_timeout_seconds: 1,
+                channel_handshake_seconds: 2,
+                ..Default::default()
+            };
+            configure(i, &mut settings);
+            nodes.push(P2p::new(settings).await);
+        }
+
+        Self { network, nodes, executor }
+    }
+
+    /// Start all the nodes, and run them in the background.
+    pub async fn start(&self) -> Result<()> {
+        for node in &self.nodes {
+            node.clone().start(self.executor.clone()).await?;
+        }
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            let node = node.clone();
+            let executor = self.executor.clone();
+            self.executor
+                .spawn(async move {
+                    if let Err(e) = node.run(executor).await {
+                        debug!(target: "net", "Simulation: node {} stopped: {}", i, e);
+                    }
+                })
+                .detach();
+        }
+
+        Ok(())
+    }
+
+    /// Stop all the nodes.
+    pub async fn stop(&self) {
+        for node in &self.nodes {
+            node.stop().await;
+        }
+    }
+
+    /// Let the nodes handle everything they received, then deliver the next
+    /// packets in flight, moving the network's clock to their delivery
+    /// time. If nothing is in flight, the nodes are waiting on their own
+    /// timers, which run on the wall clock, so this sleeps for a bit.
+    pub async fn step(&self) {
+        for _ in 0..SETTLE_YIELDS {
+            yield_now().await;
+        }
+
+        if !self.network.step() {
+            msleep(IDLE_MILLIS).await;
+        }
+    }
+
+    /// Step the simulation until `condition` holds, failing after
+    /// `max_steps` steps.
+    pub async fn run_until<F, Fut>(&self, max_steps: usize, mut condition: F) -> Result<()>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        for _ in 0..max_steps {
+            if condition().await {
+                return Ok(())
+            }
+
+            self.step().await;
+        }
+
+        debug!(target: "net", "Simulation: condition not met after {} steps", max_steps);
+        Err(Error::NetworkOperationFailed)
+    }
+
+    /// Step the simulation until every node has at least `count` connected
+    /// channels, failing after `max_steps` steps.
+    pub async fn wait_for_channels(&self, count: usize, max_steps: usize) -> Result<()> {
+        self.run_until(max_steps, || async move {
+            for node in &self.nodes {
+                if node.channels().lock().await.len() < count {
+                    return false
+                }
+            }
+            true
+        })
+        .await
+    }
+
+    /// The in-memory network connecting the nodes.
+    pub fn network(&self) -> &MemoryNetworkPtr {
+        &self.network
+    }
+
+    /// All the simulated nodes, in order.
+    pub fn nodes(&self) -> &[P2pPtr] {
+        &self.nodes
+    }
+
+    /// The node at `index`.
+    pub fn node(&self, index: usize) -> P2pPtr {
+        self.nodes[index].clone()
+    }
+
+    /// Address the node at `index` listens on.
+    pub fn url(&self, index: usize) -> Url {
+        self.network.url(FIRST_PORT + index as u16)
+    }
+
+    /// Set the conditions of all links without conditions of their own.
+    pub fn set_default_link(&self, conditions: LinkConditions) {
+        self.network.set_default_link(conditions)
+    }
+
+    /// Set the conditions of the link between two nodes.
+    pub fn set_link(&self, a: usize, b: usize, conditions: LinkConditions) {
+        self.network.set_link(&self.url(a), &self.url(b), conditions)
+    }
+
+    /// Split the nodes into groups that can't reach each other.
+    pub fn partition(&self, groups: &[&[usize]]) {
+        let groups: Vec<Vec<Url>> =
+            groups.iter().map(|x| x.iter().map(|i| self.url(*i)).collect()).collect();
+        self.network.partition(&groups)
+    }
+
+    /// Remove all partitions.
+    pub fn heal(&self) {
+        self.network.heal()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::net::transport::MemoryTransport;
+
+    /// Steps the tests allow for a change to spread over the network
+    const MAX_STEPS: usize = 5000;
+
+    #[test]
+    fn simulation_test() {
+        let executor = Arc::new(Executor::new());
+        let ex = executor.clone();
+
+        smol::block_on(executor.run(async move {
+            let sim = Simulation::new(3, 42, ex).await;
+            let latency = Duration::from_millis(10);
+            sim.set_default_link(LinkConditions { latency, loss: 0.0 });
+            sim.start().await.unwrap();
+
+            // Every node connects to both others, with the handshake
+            // taking a few round trips on the network's clock.
+            sim.wait_for_channels(2, MAX_STEPS).await.unwrap();
+            assert!(sim.network().now() >= latency * 2);
+
+            // Partitioned nodes can't reach each other
+            sim.partition(&[&[0, 1], &[2]]);
+            let transport = MemoryTransport::new(Some(sim.url(2)));
+            assert!(transport.dial(sim.url(0)).await.is_err());
+            let channels: Vec<_> = sim.node(2).channels().lock().await.values().cloned().collect();
+            for channel in channels {
+                channel.stop().await;
+            }
+            let node = sim.node(2);
+            sim.run_until(MAX_STEPS, || async { node.channels().lock().await.is_empty() })
+                .await
+                .unwrap();
+
+            sim.heal();
+            sim.wait_for_channels(2, MAX_STEPS).await.unwrap();
+            sim.stop().await;
+        }));
+    }
+}
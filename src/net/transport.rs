@@ -19,6 +19,11 @@ pub use tor::TorTransport;
 mod unix;
 pub use unix::UnixTransport;
 
+mod memory;
+pub use memory::{
+    LinkConditions, MemoryListener, MemoryNetwork, MemoryNetworkPtr, MemoryStream, MemoryTransport,
+};
+
 /// A helper function to convert SocketAddr to Url and add scheme
 pub(crate) fn socket_addr_to_url(addr: SocketAddr, scheme: &str) -> Result<Url> {
     let url = Url::parse(&format!("{}://{}", scheme, addr))?;
@@ -40,6 +45,7 @@ pub enum TransportName {
     Tor(Option<String>),
     Nym(Option<String>),
     Unix,
+    Memory,
 }
 
 impl TransportName {
@@ -52,6 +58,7 @@ impl TransportName {
             Self::Nym(None) => "nym".into(),
             Self::Nym(Some(opt)) => format!("nym+{}", opt),
             Self::Unix => "unix".into(),
+            Self::Memory => "memory".into(),
         }
     }
 }
@@ -68,6 +75,7 @@ impl TryFrom<&str> for TransportName {
             "nym" => Self::Nym(None),
             "nym+tls" => Self::Nym(Some("tls".into())),
             "unix" => Self::Unix,
+            "memory" => Self::Memory,
             n => return Err(crate::Error::UnsupportedTransport(n.into())),
         };
         Ok(transport_name)
@@ -1,17 +1,23 @@
 use async_std::sync::{Arc, Mutex};
 use darkfi_sdk::crypto::{constants::MERKLE_DEPTH, MerkleNode};
+use darkfi_serial::serialize;
 use incrementalmerkletree::{bridgetree::BridgeTree, Tree};
 use lazy_init::Lazy;
 use log::{debug, error, info};
 use pasta_curves::group::ff::PrimeField;
+use rand::rngs::OsRng;
 
-use super::state::{state_transition, State};
+use super::{
+    coinselect::{coins_value, select_coins, CoinSelection},
+    state::{state_transition, State},
+};
 use crate::{
     crypto::{
         address::Address,
         coin::{Coin, OwnCoin},
+        keycache::KeyCache,
         keypair::{Keypair, PublicKey},
-        proof::ProvingKey,
+        proof::{ProvingKey, VerifyingKey},
         types::DrkTokenId,
     },
     tx::{
@@ -19,13 +25,38 @@ use crate::{
             TransactionBuilder, TransactionBuilderClearInputInfo, TransactionBuilderInputInfo,
             TransactionBuilderOutputInfo,
         },
+        unsigned::{UnsignedInputInfo, UnsignedTransaction},
         Transaction,
     },
-    wallet::walletdb::{Balance, Balances, WalletPtr},
+    util::time::Timestamp,
+    wallet::walletdb::{Balance, Balances, TxDirection, TxRecord, TxStatus, ViewedCoin, WalletPtr},
     zk::circuit::{BurnContract, MintContract},
     ClientFailed, ClientResult, Result,
 };
 
+/// A payment of `value` of a token to a recipient.
+#[derive(Clone, Debug)]
+pub struct Payment {
+    pub public: PublicKey,
+    /// Key to encrypt the note to, see [`TransactionBuilderOutputInfo`]
+    pub view_public: Option<PublicKey>,
+    pub value: u64,
+    pub token_id: DrkTokenId,
+}
+
+/// Planned inputs and outputs of a transaction moving a single token,
+/// before any proof is made. See [`Client::plan_transfer`].
+#[derive(Clone, Debug)]
+pub struct TransferPlan {
+    pub token_id: DrkTokenId,
+    /// Coins to spend
+    pub inputs: Vec<OwnCoin>,
+    /// Payments to the recipients
+    pub outputs: Vec<Payment>,
+    /// Fresh keypair receiving the change, and its value
+    pub change: Option<(Keypair, u64)>,
+}
+
 /// The Client structure, used for transaction operations.
 /// This includes, receiving, broadcasting, and building.
 pub struct Client {
@@ -58,91 +89,281 @@ impl Client {
         })
     }
 
-    // TODO: Better function name
-    async fn build_slab_from_tx(
+    /// Plan a transfer paying the given recipients, selecting the coins to
+    /// spend with `selection`. A transaction can only move a single token,
+    /// so one [`TransferPlan`] is returned for each token, in the order they
+    /// first appear in `payments`. Nothing is written to the wallet, so this
+    /// can be used as a dry run.
+    pub async fn plan_transfer(
         &self,
-        pubkey: PublicKey,
-        value: u64,
-        token_id: DrkTokenId,
-        clear_input: bool,
+        payments: &[Payment],
+        selection: CoinSelection,
+    ) -> ClientResult<Vec<TransferPlan>> {
+        let own_coins = self.wallet.get_own_coins().await?;
+
+        let mut plans = vec![];
+        for (token_id, outputs, value) in group_payments(payments)? {
+            let coins: Vec<OwnCoin> =
+                own_coins.iter().filter(|c| c.note.token_id == token_id).cloned().collect();
+            let inputs = select_coins(&coins, value, selection)?;
+            let inputs_value = coins_value(&inputs)?;
+
+            // Change goes to a fresh address, so it isn't linked to our others
+            let change = match inputs_value - value {
+                0 => None,
+                v => Some((Keypair::random(&mut OsRng), v)),
+            };
+
+            plans.push(TransferPlan { token_id, inputs, outputs, change });
+        }
+
+        Ok(plans)
+    }
+
+    /// Build the transaction of a [`TransferPlan`]. Nothing is written to
+    /// the wallet, see [`Client::record_transfer`].
+    pub async fn build_transfer(
+        &self,
+        plan: &TransferPlan,
         state: Arc<Mutex<State>>,
-    ) -> ClientResult<(Transaction, Vec<Coin>)> {
-        debug!("build_slab_from_tx(): Begin building slab from tx");
-        let mut clear_inputs = vec![];
-        let mut inputs = vec![];
-        let mut outputs = vec![];
-        let mut coins = vec![];
+    ) -> ClientResult<Transaction> {
+        debug!("build_transfer(): Building tx inputs");
+        // The wallet's tree witnesses our coins, see `WalletScanner`
+        let tree = self.wallet.get_tree().await?;
+        let root = tree.root(0).unwrap();
 
-        if clear_input {
-            debug!("build_slab_from_tx(): Building clear input");
-            let signature_secret = self.main_keypair.lock().await.secret;
-            let input = TransactionBuilderClearInputInfo { value, token_id, signature_secret };
-            clear_inputs.push(input);
-        } else {
-            debug!("build_slab_from_tx(): Building tx inputs");
-            let mut inputs_value = 0;
-            let state_m = state.lock().await;
-            let own_coins = self.wallet.get_own_coins().await?;
-
-            for own_coin in own_coins.iter() {
-                if inputs_value >= value {
-                    debug!("build_slab_from_tx(): inputs_value >= value");
-                    break
+        let mut inputs = vec![];
+        for own_coin in &plan.inputs {
+            let leaf_position = own_coin.leaf_position;
+            let merkle_path = match tree.authentication_path(leaf_position, &root) {
+                Some(v) => v,
+                None => {
+                    error!("build_transfer(): No merkle path for coin {:?}", own_coin.coin);
+                    return Err(ClientFailed::InternalError("Coin is not in the tree".into()))
                 }
+            };
+
+            inputs.push(TransactionBuilderInputInfo {
+                leaf_position,
+                merkle_path,
+                secret: own_coin.secret,
+                note: own_coin.note.clone(),
+            });
+        }
 
-                let leaf_position = own_coin.leaf_position;
-                let root = state_m.tree.root(0).unwrap();
-                let merkle_path = state_m.tree.authentication_path(leaf_position, &root).unwrap();
-                inputs_value += own_coin.note.value;
+        let mut outputs: Vec<TransactionBuilderOutputInfo> = plan
+            .outputs
+            .iter()
+            .map(|p| TransactionBuilderOutputInfo {
+                value: p.value,
+                token_id: p.token_id,
+                public: p.public,
+                view_public: p.view_public,
+            })
+            .collect();
+
+        if let Some((keypair, value)) = plan.change {
+            outputs.push(TransactionBuilderOutputInfo {
+                value,
+                token_id: plan.token_id,
+                public: keypair.public,
+                view_public: None,
+            });
+        }
 
-                let input = TransactionBuilderInputInfo {
-                    leaf_position,
-                    merkle_path,
-                    secret: own_coin.secret,
-                    note: own_coin.note.clone(),
-                };
+        let builder = TransactionBuilder { clear_inputs: vec![], inputs, outputs };
+        self.build_slab(builder, state).await
+    }
 
-                inputs.push(input);
-                coins.push(own_coin.coin);
-            }
-            // Release state lock
-            drop(state_m);
+    /// Record a transaction built from a [`TransferPlan`] before it is
+    /// broadcast, marking its inputs as spent. The change keypair is stored
+    /// in the wallet so the change can be found when scanning. The
+    /// transaction stays pending in the wallet until it is finalized or
+    /// reverted, see [`WalletScanner::update_pending`].
+    ///
+    /// [`WalletScanner::update_pending`]: super::scanner::WalletScanner::update_pending
+    pub async fn record_transfer(&self, plan: &TransferPlan, tx: &Transaction) -> Result<()> {
+        if let Some((keypair, _)) = plan.change {
+            self.wallet.put_keypair(&keypair).await?;
+        }
+
+        // The coins are reverted if the transaction never gets finalized
+        for own_coin in &plan.inputs {
+            self.wallet.confirm_spend_coin(&own_coin.coin).await?;
+        }
+
+        // The change output is the last one
+        let mut received_coins = vec![];
+        if plan.change.is_some() {
+            received_coins.push(tx.outputs.last().unwrap().revealed.coin);
+        }
+
+        let tx_bytes = serialize(tx);
+        let tx_hash = blake3::hash(&tx_bytes);
+        let record = TxRecord {
+            tx_hash,
+            direction: TxDirection::Sent,
+            value: plan.outputs.iter().map(|p| p.value).sum(),
+            token_id: plan.token_id,
+            counterparties: plan.outputs.iter().map(|p| p.public).collect(),
+            slot: None,
+            memo: vec![],
+            status: TxStatus::Pending,
+            timestamp: Timestamp::current_time(),
+            spent_coins: plan.inputs.iter().map(|c| c.coin).collect(),
+            received_coins,
+        };
+        self.wallet.put_tx_record(&record).await?;
+        self.wallet.put_pending_tx(&tx_hash, &tx_bytes).await
+    }
+
+    /// Revert a transaction recorded by [`Client::transfer`] which could
+    /// not be broadcast, making its inputs spendable again.
+    pub async fn revert_transfer(&self, tx: &Transaction) -> Result<()> {
+        // The transaction was never broadcast, so its inputs are unspent
+        self.wallet.revert_pending_tx(&blake3::hash(&serialize(tx)), &|_| Ok(false)).await
+    }
+
+    /// Pay the given recipients, returning one transaction per token.
+    /// See [`Client::plan_transfer`]. Every transaction is built before any
+    /// is recorded, so a failure leaves the wallet untouched. Transactions
+    /// which then fail to broadcast should be given to
+    /// [`Client::revert_transfer`].
+    pub async fn transfer(
+        &self,
+        payments: &[Payment],
+        selection: CoinSelection,
+        state: Arc<Mutex<State>>,
+    ) -> ClientResult<Vec<Transaction>> {
+        let plans = self.plan_transfer(payments, selection).await?;
+
+        let mut txs = vec![];
+        for plan in &plans {
+            txs.push(self.build_transfer(plan, state.clone()).await?);
+        }
+
+        for (plan, tx) in plans.iter().zip(&txs) {
+            self.record_transfer(plan, tx).await?;
+        }
+
+        Ok(txs)
+    }
+
+    /// Build unsigned transactions paying the given recipients from the
+    /// unspent coins of the wallet's view keys, one per token like
+    /// [`Client::plan_transfer`]. The change goes back to the owner of the
+    /// first spent coin. No spending key is needed: the owners prove and
+    /// sign their inputs, see [`UnsignedTransaction`]. The spent coins are
+    /// reserved, so they aren't selected again, until the transaction is
+    /// found finalized or reverted, see [`WalletScanner::update_pending`].
+    ///
+    /// [`WalletScanner::update_pending`]: super::scanner::WalletScanner::update_pending
+    pub async fn build_unsigned(
+        &self,
+        payments: &[Payment],
+        selection: CoinSelection,
+    ) -> ClientResult<Vec<UnsignedTransaction>> {
+        let reserved = self.wallet.get_reserved_viewed_coins().await?;
+        let viewed_coins: Vec<ViewedCoin> = self
+            .wallet
+            .get_viewed_coins()
+            .await?
+            .into_iter()
+            .filter(|c| !c.is_spent && !reserved.contains(&c.coin))
+            .collect();
+        let view_keys = self.wallet.get_view_keys().await?;
+
+        // The wallet's tree witnesses our viewed coins, see `WalletScanner`
+        let tree = self.wallet.get_tree().await?;
+        let root = tree.root(0).unwrap();
+        let mint_pk = self.mint_pk.get_or_create(Client::build_mint_pk);
+
+        let mut txs = vec![];
+        for (token_id, payments, value) in group_payments(payments)? {
+            let coins: Vec<ViewedCoin> =
+                viewed_coins.iter().filter(|c| c.note.token_id == token_id).cloned().collect();
+            let selected = select_coins(&coins, value, selection)?;
+
+            let mut inputs = vec![];
+            for coin in &selected {
+                let merkle_path = match tree.authentication_path(coin.leaf_position, &root) {
+                    Some(v) => v,
+                    None => {
+                        error!("build_unsigned(): No merkle path for coin {:?}", coin.coin);
+                        return Err(ClientFailed::InternalError("Coin is not in the tree".into()))
+                    }
+                };
 
-            if inputs_value < value {
-                error!("build_slab_from_tx(): Not enough value to build tx inputs");
-                return Err(ClientFailed::NotEnoughValue(inputs_value))
+                inputs.push(UnsignedInputInfo {
+                    public: coin.public,
+                    leaf_position: coin.leaf_position,
+                    merkle_path,
+                    note: coin.note.clone(),
+                });
             }
 
+            let mut outputs: Vec<TransactionBuilderOutputInfo> = payments
+                .iter()
+                .map(|p| TransactionBuilderOutputInfo {
+                    value: p.value,
+                    token_id: p.token_id,
+                    public: p.public,
+                    view_public: p.view_public,
+                })
+                .collect();
+
+            // The change is encrypted to the view key, so we see it too
+            let inputs_value = coins_value(&selected)?;
             if inputs_value > value {
-                let return_value = inputs_value - value;
+                let public = selected[0].public;
+                let view_key = view_keys.iter().find(|k| k.public == public);
                 outputs.push(TransactionBuilderOutputInfo {
-                    value: return_value,
+                    value: inputs_value - value,
                     token_id,
-                    public: self.main_keypair.lock().await.public,
+                    public,
+                    view_public: view_key.map(|k| k.view_public()),
                 });
             }
 
-            debug!("build_slab_from_tx(): Finished building inputs");
+            let tx = UnsignedTransaction::build(mint_pk, inputs, outputs)?;
+
+            // View keys can't compute nullifiers, so the spent coins are
+            // recognized by an output of the transaction instead. This
+            // also reserves them for this transaction.
+            let output = tx.outputs[0].output.revealed.coin;
+            for coin in &selected {
+                self.wallet.put_viewed_spend(&coin.coin, &output).await?;
+            }
+
+            txs.push(tx);
         }
 
-        outputs.push(TransactionBuilderOutputInfo { value, token_id, public: pubkey });
-        let builder = TransactionBuilder { clear_inputs, inputs, outputs };
+        Ok(txs)
+    }
 
+    /// Create the proofs of a transaction and check its state transition.
+    async fn build_slab(
+        &self,
+        builder: TransactionBuilder,
+        state: Arc<Mutex<State>>,
+    ) -> ClientResult<Transaction> {
         let mint_pk = self.mint_pk.get_or_create(Client::build_mint_pk);
         let burn_pk = self.burn_pk.get_or_create(Client::build_burn_pk);
         let tx = builder.build(mint_pk, burn_pk)?;
 
         // Check if state transition is valid before broadcasting
-        debug!("build_slab_from_tx(): Checking if state transition is valid");
+        debug!("build_slab(): Checking if state transition is valid");
         let state = &*state.lock().await;
-        debug!("build_slab_from_tx(): Got state lock");
+        debug!("build_slab(): Got state lock");
         state_transition(state, tx.clone())?;
-        debug!("build_slab_from_tx(): Successful state transition");
+        debug!("build_slab(): Successful state transition");
 
-        Ok((tx, coins))
+        Ok(tx)
     }
 
     /// Build a transaction given the required parameters and state machine.
+    /// With `clear_input`, the value is minted from the main keypair,
+    /// otherwise our coins are spent with the default [`CoinSelection`].
     pub async fn build_transaction(
         &self,
         pubkey: PublicKey,
@@ -161,17 +382,28 @@ impl Client {
             return Err(ClientFailed::InvalidAmount(0))
         }
 
-        if !self.wallet.token_id_exists(token_id).await? && !clear_input {
-            return Err(ClientFailed::NotEnoughValue(amount))
-        }
-
-        let (tx, coins) =
-            self.build_slab_from_tx(pubkey, amount, token_id, clear_input, state).await?;
-        for coin in coins.iter() {
-            // TODO: This should be more robust. In case our transaction is denied,
-            // we want to revert to be able to send again.
-            self.wallet.confirm_spend_coin(coin).await?;
-        }
+        let tx = if clear_input {
+            debug!("build_transaction(): Building clear input");
+            let signature_secret = self.main_keypair.lock().await.secret;
+            let builder = TransactionBuilder {
+                clear_inputs: vec![TransactionBuilderClearInputInfo {
+                    value: amount,
+                    token_id,
+                    signature_secret,
+                }],
+                inputs: vec![],
+                outputs: vec![TransactionBuilderOutputInfo {
+                    value: amount,
+                    token_id,
+                    public: pubkey,
+                    view_public: None,
+                }],
+            };
+            self.build_slab(builder, state).await?
+        } else {
+            let payment = Payment { public: pubkey, view_public: None, value: amount, token_id };
+            self.transfer(&[payment], CoinSelection::default(), state).await?.remove(0)
+        };
 
         debug!("send(): Sent {}", amount);
         Ok(tx)
@@ -237,11 +469,55 @@ impl Client {
 
     fn build_mint_pk() -> ProvingKey {
         debug!("Building proving key for MintContract");
-        ProvingKey::build(11, &MintContract::default())
+        let circuit_id = KeyCache::native_circuit_id::<MintContract>();
+        KeyCache::default().proving_key(&circuit_id, 11, &MintContract::default())
     }
 
-    fn build_burn_pk() -> ProvingKey {
+    pub fn build_burn_pk() -> ProvingKey {
         debug!("Building proving key for BurnContract");
-        ProvingKey::build(11, &BurnContract::default())
+        let circuit_id = KeyCache::native_circuit_id::<BurnContract>();
+        KeyCache::default().proving_key(&circuit_id, 11, &BurnContract::default())
+    }
+
+    pub fn build_mint_vk() -> VerifyingKey {
+        debug!("Building verifying key for MintContract");
+        let circuit_id = KeyCache::native_circuit_id::<MintContract>();
+        KeyCache::default().verifying_key(&circuit_id, 11, &MintContract::default())
+    }
+}
+
+/// Group payments by token, in the order the tokens first appear, along
+/// with the total value paid in each token.
+fn group_payments(payments: &[Payment]) -> ClientResult<Vec<(DrkTokenId, Vec<Payment>, u64)>> {
+    if payments.is_empty() {
+        return Err(ClientFailed::InvalidAmount(0))
     }
+
+    let mut token_ids: Vec<DrkTokenId> = vec![];
+    for payment in payments {
+        if payment.value == 0 {
+            return Err(ClientFailed::InvalidAmount(0))
+        }
+        if !token_ids.contains(&payment.token_id) {
+            token_ids.push(payment.token_id);
+        }
+    }
+
+    let mut groups = vec![];
+    for token_id in token_ids {
+        let outputs: Vec<Payment> =
+            payments.iter().filter(|p| p.token_id == token_id).cloned().collect();
+
+        let mut value = 0_u64;
+        for output in &outputs {
+            value = match value.checked_add(output.value) {
+                Some(v) => v,
+                None => return Err(ClientFailed::InvalidAmount(output.value)),
+            };
+        }
+
+        groups.push((token_id, outputs, value));
+    }
+
+    Ok(groups)
 }
@@ -0,0 +1,222 @@
+use std::{fmt, str::FromStr};
+
+use rand::{rngs::OsRng, seq::SliceRandom};
+
+use crate::{
+    crypto::coin::OwnCoin, wallet::walletdb::ViewedCoin, ClientFailed, ClientResult, Error,
+};
+
+/// A coin that can be picked by [`select_coins`].
+pub trait SelectableCoin: Clone {
+    fn value(&self) -> u64;
+}
+
+impl SelectableCoin for OwnCoin {
+    fn value(&self) -> u64 {
+        self.note.value
+    }
+}
+
+impl SelectableCoin for ViewedCoin {
+    fn value(&self) -> u64 {
+        self.note.value
+    }
+}
+
+/// Strategy used to pick the coins spent by a transaction.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CoinSelection {
+    /// Spend the largest coins first. Few inputs, but the amounts of the
+    /// spent coins can be guessed from the change.
+    #[default]
+    LargestFirst,
+    /// Spend as few coins as possible, preferring a single coin covering
+    /// the amount with the least change.
+    MinimizeInputs,
+    /// Spend coins in random order, so the selection does not leak the
+    /// wallet's coin amounts.
+    Random,
+}
+
+impl fmt::Display for CoinSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LargestFirst => write!(f, "largest-first"),
+            Self::MinimizeInputs => write!(f, "minimize-inputs"),
+            Self::Random => write!(f, "random"),
+        }
+    }
+}
+
+impl FromStr for CoinSelection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "largest-first" => Ok(Self::LargestFirst),
+            "minimize-inputs" => Ok(Self::MinimizeInputs),
+            "random" => Ok(Self::Random),
+            _ => Err(Error::ParseFailed("Unknown coin selection strategy")),
+        }
+    }
+}
+
+/// Select coins from `coins` worth at least `value` in total, using the
+/// given strategy. All coins should be unspent and of the same token.
+/// Returns [`ClientFailed::NotEnoughValue`] with the total available value
+/// if the coins can't cover `value`.
+pub fn select_coins<C: SelectableCoin>(
+    coins: &[C],
+    value: u64,
+    selection: CoinSelection,
+) -> ClientResult<Vec<C>> {
+    let total = coins.iter().fold(0_u64, |acc, c| acc.saturating_add(c.value()));
+    if total < value {
+        return Err(ClientFailed::NotEnoughValue(total))
+    }
+
+    if value == 0 {
+        return Ok(vec![])
+    }
+
+    let mut coins = coins.to_vec();
+    match selection {
+        CoinSelection::LargestFirst => {
+            coins.sort_by(|a, b| b.value().cmp(&a.value()));
+            Ok(take_until(coins, value))
+        }
+
+        CoinSelection::MinimizeInputs => {
+            // A single coin with the least change, if any covers the value
+            coins.sort_by_key(|c| c.value());
+            if let Some(coin) = coins.iter().find(|c| c.value() >= value) {
+                return Ok(vec![coin.clone()])
+            }
+
+            // Otherwise largest-first gives the fewest inputs, but its last
+            // coin can be swapped for the smallest one covering the rest.
+            coins.reverse();
+            let mut selected = take_until(coins.clone(), value);
+            selected.pop();
+            let sum = selected.iter().fold(0_u64, |acc, c| acc.saturating_add(c.value()));
+            let last = coins[selected.len()..]
+                .iter()
+                .rev()
+                .find(|c| sum.saturating_add(c.value()) >= value)
+                .unwrap();
+            selected.push(last.clone());
+            Ok(selected)
+        }
+
+        CoinSelection::Random => {
+            coins.shuffle(&mut OsRng);
+            Ok(take_until(coins, value))
+        }
+    }
+}
+
+/// Total value of the given coins, failing if it overflows.
+pub fn coins_value<C: SelectableCoin>(coins: &[C]) -> ClientResult<u64> {
+    match coins.iter().try_fold(0_u64, |acc, c| acc.checked_add(c.value())) {
+        Some(v) => Ok(v),
+        None => Err(ClientFailed::InternalError("Coins value overflows".into())),
+    }
+}
+
+/// Take coins in order until their total covers `value`.
+fn take_until<C: SelectableCoin>(coins: Vec<C>, value: u64) -> Vec<C> {
+    let mut sum = 0_u64;
+    let mut selected = vec![];
+    for coin in coins {
+        if sum >= value {
+            break
+        }
+        sum = sum.saturating_add(coin.value());
+        selected.push(coin);
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{
+        coin::Coin,
+        keypair::SecretKey,
+        note::Note,
+        types::{DrkCoinBlind, DrkSerial, DrkTokenId, DrkValueBlind},
+    };
+    use darkfi_sdk::crypto::Nullifier;
+    use pasta_curves::{group::ff::Field, pallas};
+
+    fn dummy_coin(value: u64) -> OwnCoin {
+        let note = Note {
+            serial: DrkSerial::random(&mut OsRng),
+            value,
+            token_id: DrkTokenId::from(42),
+            coin_blind: DrkCoinBlind::random(&mut OsRng),
+            value_blind: DrkValueBlind::random(&mut OsRng),
+            token_blind: DrkValueBlind::random(&mut OsRng),
+            memo: vec![],
+        };
+
+        OwnCoin {
+            coin: Coin(pallas::Base::random(&mut OsRng)),
+            note,
+            secret: SecretKey::random(&mut OsRng),
+            nullifier: Nullifier::from(pallas::Base::random(&mut OsRng)),
+            leaf_position: 0.into(),
+        }
+    }
+
+    fn values(coins: &[OwnCoin]) -> Vec<u64> {
+        coins.iter().map(|c| c.note.value).collect()
+    }
+
+    #[test]
+    fn test_select_coins() {
+        let coins: Vec<OwnCoin> = [5, 30, 10, 20, 1].into_iter().map(dummy_coin).collect();
+
+        let selected = select_coins(&coins, 35, CoinSelection::LargestFirst).unwrap();
+        assert_eq!(values(&selected), vec![30, 20]);
+
+        // A single coin covering the value is preferred
+        let selected = select_coins(&coins, 8, CoinSelection::MinimizeInputs).unwrap();
+        assert_eq!(values(&selected), vec![10]);
+        let selected = select_coins(&coins, 30, CoinSelection::MinimizeInputs).unwrap();
+        assert_eq!(values(&selected), vec![30]);
+
+        // The last input is the smallest coin covering the rest
+        let selected = select_coins(&coins, 35, CoinSelection::MinimizeInputs).unwrap();
+        assert_eq!(values(&selected), vec![30, 5]);
+        let selected = select_coins(&coins, 55, CoinSelection::MinimizeInputs).unwrap();
+        assert_eq!(values(&selected), vec![30, 20, 5]);
+        let selected = select_coins(&coins, 41, CoinSelection::MinimizeInputs).unwrap();
+        assert_eq!(values(&selected), vec![30, 20]);
+
+        let selected = select_coins(&coins, 60, CoinSelection::Random).unwrap();
+        assert!(values(&selected).iter().sum::<u64>() >= 60);
+
+        match select_coins(&coins, 67, CoinSelection::Random) {
+            Err(ClientFailed::NotEnoughValue(66)) => {}
+            _ => panic!("Expected NotEnoughValue(66)"),
+        }
+
+        assert_eq!(
+            "minimize-inputs".parse::<CoinSelection>().unwrap(),
+            CoinSelection::MinimizeInputs
+        );
+        assert!("smallest-first".parse::<CoinSelection>().is_err());
+
+        // Values close to u64::MAX don't overflow the sums
+        let coins: Vec<OwnCoin> = [u64::MAX, u64::MAX - 1, 3].into_iter().map(dummy_coin).collect();
+        for selection in [CoinSelection::LargestFirst, CoinSelection::MinimizeInputs] {
+            let selected = select_coins(&coins, u64::MAX, selection).unwrap();
+            assert_eq!(values(&selected), vec![u64::MAX]);
+        }
+        let selected = select_coins(&coins, u64::MAX, CoinSelection::Random).unwrap();
+        assert!(!selected.is_empty());
+        assert!(coins_value(&coins).is_err());
+        assert_eq!(coins_value(&coins[2..]).unwrap(), 3);
+    }
+}
@@ -1,8 +1,14 @@
 pub mod client;
-pub use client::Client;
+pub use client::{Client, Payment, TransferPlan};
+
+pub mod coinselect;
+pub use coinselect::CoinSelection;
 
 pub mod state;
 pub use state::State;
 
 pub mod memorystate;
 pub use memorystate::MemoryState;
+
+pub mod scanner;
+pub use scanner::WalletScanner;
@@ -0,0 +1,685 @@
+use async_trait::async_trait;
+use darkfi_sdk::crypto::{constants::MERKLE_DEPTH, MerkleNode, Nullifier};
+#[cfg(feature = "rpc")]
+use darkfi_serial::deserialize;
+use darkfi_serial::serialize;
+use incrementalmerkletree::{bridgetree::BridgeTree, Tree};
+use log::{debug, info, warn};
+
+use crate::{
+    blockchain::Blockchain,
+    consensus::BlockInfo,
+    crypto::{
+        coin::{Coin, OwnCoin},
+        keypair::{Keypair, PublicKey, SecretKey, ViewKey},
+        note::Note,
+        util::poseidon_hash,
+    },
+    tx::TransactionOutput,
+    wallet::walletdb::{PendingTx, TxDirection, TxRecord, TxStatus, ViewedCoin, WalletPtr},
+    Error, Result,
+};
+
+#[cfg(feature = "rpc")]
+use crate::rpc::{client::RpcClient, jsonrpc::JsonRequest};
+
+/// Number of blocks requested from a [`BlockSource`] at once
+const SCAN_BATCH_SIZE: u64 = 100;
+
+/// Source of finalized blocks for a [`WalletScanner`].
+#[async_trait]
+pub trait BlockSource: Sync {
+    /// Slot of the last finalized block.
+    async fn last_slot(&self) -> Result<u64>;
+    /// The finalized block at the given slot, if any.
+    async fn block_at(&self, slot: u64) -> Result<Option<BlockInfo>>;
+    /// Up to `n` finalized blocks after the given slot, in slot order.
+    async fn blocks_after(&self, slot: u64, n: u64) -> Result<Vec<BlockInfo>>;
+    /// Slot of the snapshot the source was synced from, if any. The source
+    /// doesn't have the blocks between genesis and that slot.
+    async fn snapshot_slot(&self) -> Result<Option<u64>>;
+}
+
+#[async_trait]
+impl BlockSource for Blockchain {
+    async fn last_slot(&self) -> Result<u64> {
+        Ok(self.last()?.0)
+    }
+
+    async fn block_at(&self, slot: u64) -> Result<Option<BlockInfo>> {
+        Ok(self.get_blocks_by_slot(&[slot])?.into_iter().next())
+    }
+
+    async fn blocks_after(&self, slot: u64, n: u64) -> Result<Vec<BlockInfo>> {
+        self.get_blocks_after(slot, n)
+    }
+
+    async fn snapshot_slot(&self) -> Result<Option<u64>> {
+        self.snapshots.get_synced_from()
+    }
+}
+
+/// [`BlockSource`] fetching the blocks from a `darkfid` node over JSON-RPC.
+#[cfg(feature = "rpc")]
+pub struct RpcBlockSource {
+    rpc: RpcClient,
+}
+
+#[cfg(feature = "rpc")]
+impl RpcBlockSource {
+    pub fn new(rpc: RpcClient) -> Self {
+        Self { rpc }
+    }
+}
+
+#[cfg(feature = "rpc")]
+#[async_trait]
+impl BlockSource for RpcBlockSource {
+    async fn last_slot(&self) -> Result<u64> {
+        let req = JsonRequest::new("blockchain.last_slot", serde_json::json!([]));
+        let rep = self.rpc.request(req).await?;
+        rep.as_u64().ok_or(Error::ParseFailed("Invalid last slot"))
+    }
+
+    async fn block_at(&self, slot: u64) -> Result<Option<BlockInfo>> {
+        let req = JsonRequest::new("blockchain.get_slot", serde_json::json!([slot]));
+        let rep = self.rpc.request(req).await?;
+        let encoded = rep.as_str().ok_or(Error::ParseFailed("Invalid block"))?;
+        let bytes = bs58::decode(encoded).into_vec()?;
+        Ok(Some(deserialize(&bytes)?))
+    }
+
+    async fn blocks_after(&self, slot: u64, n: u64) -> Result<Vec<BlockInfo>> {
+        let req = JsonRequest::new("blockchain.get_blocks_after", serde_json::json!([slot, n]));
+        let rep = self.rpc.request(req).await?;
+        let encoded = rep.as_str().ok_or(Error::ParseFailed("Invalid blocks"))?;
+        let bytes = bs58::decode(encoded).into_vec()?;
+        Ok(deserialize(&bytes)?)
+    }
+
+    async fn snapshot_slot(&self) -> Result<Option<u64>> {
+        let req = JsonRequest::new("blockchain.snapshot_slot", serde_json::json!([]));
+        let rep = self.rpc.request(req).await?;
+        if rep.is_null() {
+            return Ok(None)
+        }
+        Ok(Some(rep.as_u64().ok_or(Error::ParseFailed("Invalid snapshot slot"))?))
+    }
+}
+
+/// Coins found by a scan.
+#[derive(Clone, Debug, Default)]
+pub struct ScanResult {
+    /// Coins received with one of our spending keys
+    pub own_coins: Vec<OwnCoin>,
+    /// Coins received with one of our view keys
+    pub viewed_coins: Vec<ViewedCoin>,
+    /// Slot of the last scanned block
+    pub height: u64,
+}
+
+/// Finds the coins of a wallet in finalized blocks, independently of the
+/// validator state. Coins are trial-decrypted with the wallet's keypairs
+/// and their view keys, which makes them spendable, and with the view keys
+/// imported into the wallet, which only records them. The wallet keeps the
+/// Merkle tree witnessing its coins, and the slot of the last scanned block.
+pub struct WalletScanner {
+    wallet: WalletPtr,
+}
+
+impl WalletScanner {
+    pub fn new(wallet: WalletPtr) -> Self {
+        Self { wallet }
+    }
+
+    /// Slot of the last block scanned by the wallet.
+    pub async fn height(&self) -> Result<u64> {
+        Ok(self.wallet.get_scan_height().await?.unwrap_or(0))
+    }
+
+    /// Scan the blocks finalized since the last scan.
+    /// A source synced from a snapshot lacks the blocks before it, so a
+    /// wallet which hasn't scanned up to the snapshot is refused, since its
+    /// Merkle tree couldn't be built.
+    pub async fn scan(&self, source: &dyn BlockSource) -> Result<ScanResult> {
+        let tree = self.wallet.get_tree().await?;
+        let height = self.wallet.get_scan_height().await?;
+
+        // Wallets from before the scanner have a tree of unknown height
+        if height.is_none() && tree.current_position().is_some() {
+            warn!(target: "scanner", "Wallet has a tree but no scan height, rescanning");
+            return self.rescan(source, 0).await
+        }
+
+        if let Some(snapshot_slot) = source.snapshot_slot().await? {
+            if height.map_or(true, |h| h < snapshot_slot) {
+                return Err(Error::WalletScanBeforeSnapshot(height.unwrap_or(0), snapshot_slot))
+            }
+        }
+
+        self.scan_blocks(source, height, tree, height.unwrap_or(0), &[]).await
+    }
+
+    /// Scan the blocks again, looking for coins from the given slot. The
+    /// Merkle tree is rebuilt from the start of the chain, witnessing the
+    /// coins the wallet already has from before that slot, so the source
+    /// must have all the blocks since genesis and one synced from a
+    /// snapshot is refused.
+    pub async fn rescan(&self, source: &dyn BlockSource, slot: u64) -> Result<ScanResult> {
+        if let Some(snapshot_slot) = source.snapshot_slot().await? {
+            return Err(Error::WalletScanBeforeSnapshot(0, snapshot_slot))
+        }
+
+        info!(target: "scanner", "Rescanning wallet from slot {}", slot);
+        let known = self.wallet.get_all_coins().await?;
+        let tree = BridgeTree::<MerkleNode, MERKLE_DEPTH>::new(100);
+        self.scan_blocks(source, None, tree, slot, &known).await
+    }
+
+    /// Scan the blocks after slot `height` into `tree`, or all of them from
+    /// genesis when `height` is `None`. Notes are only decrypted in blocks
+    /// from slot `decrypt_from`, before it the coins in `known` are witnessed.
+    async fn scan_blocks(
+        &self,
+        source: &dyn BlockSource,
+        mut height: Option<u64>,
+        mut tree: BridgeTree<MerkleNode, MERKLE_DEPTH>,
+        decrypt_from: u64,
+        known: &[Coin],
+    ) -> Result<ScanResult> {
+        let keypairs = self.wallet.get_keypairs().await?;
+        let view_keys = self.wallet.get_view_keys().await?;
+
+        let mut result = ScanResult::default();
+        let last_slot = source.last_slot().await?;
+
+        while height.map_or(true, |h| h < last_slot) {
+            // Slot 0 isn't after any slot, so genesis is fetched on its own
+            let blocks = match height {
+                Some(h) => source.blocks_after(h, SCAN_BATCH_SIZE).await?,
+                None => source.block_at(0).await?.into_iter().collect(),
+            };
+            if blocks.is_empty() {
+                if height.is_none() {
+                    height = Some(0);
+                    continue
+                }
+                break
+            }
+
+            for block in &blocks {
+                let slot = block.header.slot;
+                for tx in &block.txs {
+                    let mut spent = vec![];
+                    let mut received = vec![];
+
+                    for input in &tx.inputs {
+                        let nullifier = &input.revealed.nullifier;
+                        if let Some(own_coin) =
+                            self.wallet.get_own_coin_by_nullifier(nullifier).await?
+                        {
+                            spent.push(own_coin);
+                        }
+                        self.wallet.confirm_spend_nullifier(nullifier).await?;
+                    }
+
+                    for output in &tx.outputs {
+                        let coin = output.revealed.coin;
+                        tree.append(&MerkleNode::from(coin.0));
+                        self.wallet.confirm_viewed_spend(&coin).await?;
+
+                        if slot < decrypt_from {
+                            if known.contains(&coin) {
+                                tree.witness();
+                            }
+                            continue
+                        }
+
+                        // Coins for our keypairs
+                        if let Some((keypair, note)) =
+                            self.try_decrypt_own(&keypairs, output).await?
+                        {
+                            let leaf_position = tree.witness().unwrap();
+                            let nullifier = Nullifier::from(poseidon_hash::<2>([
+                                keypair.secret.inner(),
+                                note.serial,
+                            ]));
+                            debug!(target: "scanner", "Received a coin: amount {}", note.value);
+                            let own_coin = OwnCoin {
+                                coin,
+                                note,
+                                secret: keypair.secret,
+                                nullifier,
+                                leaf_position,
+                            };
+                            self.wallet.put_own_coin(own_coin.clone()).await?;
+                            received.push(own_coin.clone());
+                            result.own_coins.push(own_coin);
+                            continue
+                        }
+
+                        // Coins for the view keys we imported
+                        for key in &view_keys {
+                            let note =
+                                match self.try_decrypt(key.secret, key.public, output).await? {
+                                    Some(v) => v,
+                                    None => continue,
+                                };
+
+                            let leaf_position = tree.witness().unwrap();
+                            debug!(target: "scanner", "Viewed a coin: amount {}", note.value);
+                            let viewed_coin = ViewedCoin {
+                                coin,
+                                public: key.public,
+                                note,
+                                slot,
+                                leaf_position,
+                                is_spent: false,
+                            };
+                            self.wallet.put_viewed_coin(&viewed_coin).await?;
+                            result.viewed_coins.push(viewed_coin);
+                            break
+                        }
+                    }
+
+                    if !spent.is_empty() || !received.is_empty() {
+                        let tx_hash = blake3::hash(&serialize(tx));
+                        self.record_tx(tx_hash, block, &spent, &received).await?;
+                    }
+                }
+
+                height = Some(slot);
+            }
+
+            let height = height.unwrap();
+            self.wallet.put_tree(&tree).await?;
+            self.wallet.put_scan_height(height).await?;
+            debug!(target: "scanner", "Scanned up to slot {}", height);
+        }
+
+        let height = height.unwrap_or(0);
+        result.height = height;
+        info!(
+            target: "scanner",
+            "Scanned up to slot {}: {} own coins, {} viewed coins",
+            height,
+            result.own_coins.len(),
+            result.viewed_coins.len()
+        );
+        Ok(result)
+    }
+
+    /// Handle our transactions which aren't finalized yet, after a scan.
+    /// A pending transaction is given the last scanned slot when first seen
+    /// here, and it is reverted once `revert_after` more slots got scanned
+    /// without finding it, making its coins spendable again. Transactions
+    /// still `in_mempool` may yet be finalized, so they stay pending.
+    /// The coins whose nullifier `is_spent` were spent by another
+    /// transaction and stay spent, see [`WalletDb::revert_pending_tx`].
+    /// The viewed coins reserved by unsigned transactions are released
+    /// the same way, see [`WalletDb::revert_viewed_spends`].
+    /// Returns the transactions still pending, which may be broadcast again.
+    ///
+    /// [`WalletDb::revert_pending_tx`]: crate::wallet::walletdb::WalletDb::revert_pending_tx
+    /// [`WalletDb::revert_viewed_spends`]: crate::wallet::walletdb::WalletDb::revert_viewed_spends
+    pub async fn update_pending(
+        &self,
+        revert_after: u64,
+        in_mempool: &(dyn Fn(&blake3::Hash) -> bool + Sync),
+        is_spent: &(dyn Fn(&Nullifier) -> Result<bool> + Sync),
+    ) -> Result<Vec<PendingTx>> {
+        let height = self.height().await?;
+        self.wallet.revert_viewed_spends(height, revert_after).await?;
+
+        let mut pending_txs = vec![];
+        for mut pending in self.wallet.get_pending_txs().await? {
+            let slot = match pending.slot {
+                Some(v) => v,
+                None => {
+                    self.wallet.set_pending_tx_slot(&pending.tx_hash, height).await?;
+                    pending.slot = Some(height);
+                    height
+                }
+            };
+
+            if height.saturating_sub(slot) >= revert_after && !in_mempool(&pending.tx_hash) {
+                warn!(
+                    target: "scanner",
+                    "Transaction {} not finalized after {} slots, reverting it",
+                    pending.tx_hash.to_hex(),
+                    revert_after
+                );
+                self.wallet.revert_pending_tx(&pending.tx_hash, is_spent).await?;
+                continue
+            }
+
+            pending_txs.push(pending);
+        }
+
+        Ok(pending_txs)
+    }
+
+    /// Record a finalized transaction spending or giving us coins in the
+    /// wallet's history. Transactions we sent are confirmed, and the others
+    /// are recorded from what the wallet can tell about them.
+    async fn record_tx(
+        &self,
+        tx_hash: blake3::Hash,
+        block: &BlockInfo,
+        spent: &[OwnCoin],
+        received: &[OwnCoin],
+    ) -> Result<()> {
+        let slot = block.header.slot;
+
+        if let Some(mut record) = self.wallet.get_tx_record(&tx_hash).await? {
+            record.slot = Some(slot);
+            record.status = TxStatus::Confirmed;
+            for own_coin in received {
+                if !record.received_coins.contains(&own_coin.coin) {
+                    record.received_coins.push(own_coin.coin);
+                }
+            }
+            self.wallet.remove_pending_tx(&tx_hash).await?;
+            return self.wallet.put_tx_record(&record).await
+        }
+
+        let received_value: u64 = received.iter().map(|c| c.note.value).sum();
+        let (direction, value, token_id, memo) = if spent.is_empty() {
+            let memo = received[0].note.memo.clone();
+            (TxDirection::Received, received_value, received[0].note.token_id, memo)
+        } else {
+            // Spent from elsewhere, e.g. by another wallet holding our keys.
+            // What didn't come back to us went to someone else.
+            let spent_value: u64 = spent.iter().map(|c| c.note.value).sum();
+            let value = spent_value.saturating_sub(received_value);
+            (TxDirection::Sent, value, spent[0].note.token_id, vec![])
+        };
+
+        let record = TxRecord {
+            tx_hash,
+            direction,
+            value,
+            token_id,
+            counterparties: vec![],
+            slot: Some(slot),
+            memo,
+            status: TxStatus::Confirmed,
+            timestamp: block.header.timestamp,
+            spent_coins: spent.iter().map(|c| c.coin).collect(),
+            received_coins: received.iter().map(|c| c.coin).collect(),
+        };
+
+        self.wallet.put_tx_record(&record).await
+    }
+
+    /// Try decrypting the note of a transaction output for one of our
+    /// keypairs. Notes are encrypted either to the spending key, or to its
+    /// view key when paid to a [`ViewableAddress`].
+    ///
+    /// [`ViewableAddress`]: crate::crypto::address::ViewableAddress
+    async fn try_decrypt_own(
+        &self,
+        keypairs: &[Keypair],
+        output: &TransactionOutput,
+    ) -> Result<Option<(Keypair, Note)>> {
+        for keypair in keypairs {
+            let secrets = [keypair.secret, ViewKey::from(*keypair).secret];
+            for secret in secrets {
+                if let Some(note) = self.try_decrypt(secret, keypair.public, output).await? {
+                    return Ok(Some((*keypair, note)))
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Try decrypting the note of a transaction output with `secret`, for
+    /// a coin minted to `public`.
+    /// Don't trust - verify: a note not opening its coin is recorded as
+    /// invalid, since we couldn't spend it.
+    async fn try_decrypt(
+        &self,
+        secret: SecretKey,
+        public: PublicKey,
+        output: &TransactionOutput,
+    ) -> Result<Option<Note>> {
+        let coin = output.revealed.coin;
+        let note = match output.enc_note.decrypt(&secret) {
+            Ok(v) => v,
+            Err(_) => return Ok(None),
+        };
+
+        if Coin::from_note(&public, &note) != coin {
+            warn!(target: "scanner", "Received a note not matching its coin: {:?}", coin);
+            self.wallet.put_invalid_note(&coin, &public, &note).await?;
+            return Ok(None)
+        }
+
+        Ok(Some(note))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pasta_curves::{
+        group::{ff::Field, Group},
+        pallas,
+    };
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{
+        crypto::{
+            burn_proof::BurnRevealedValues,
+            mint_proof::MintRevealedValues,
+            proof::Proof,
+            schnorr::SchnorrSecret,
+            types::{DrkCoinBlind, DrkSerial, DrkTokenId, DrkValueBlind},
+        },
+        tx::{Transaction, TransactionInput},
+        wallet::walletdb::WalletDb,
+    };
+
+    /// Finalized blocks kept in memory, and the slot of the snapshot they
+    /// were synced from.
+    struct MemorySource(Vec<BlockInfo>, Option<u64>);
+
+    #[async_trait]
+    impl BlockSource for MemorySource {
+        async fn last_slot(&self) -> Result<u64> {
+            Ok(self.0.last().map(|x| x.header.slot).unwrap_or(0))
+        }
+
+        async fn block_at(&self, slot: u64) -> Result<Option<BlockInfo>> {
+            Ok(self.0.iter().find(|x| x.header.slot == slot).cloned())
+        }
+
+        async fn blocks_after(&self, slot: u64, n: u64) -> Result<Vec<BlockInfo>> {
+            Ok(self.0.iter().filter(|x| x.header.slot > slot).take(n as usize).cloned().collect())
+        }
+
+        async fn snapshot_slot(&self) -> Result<Option<u64>> {
+            Ok(self.1)
+        }
+    }
+
+    fn note(value: u64, token_id: DrkTokenId) -> Note {
+        Note {
+            serial: DrkSerial::random(&mut OsRng),
+            value,
+            token_id,
+            coin_blind: DrkCoinBlind::random(&mut OsRng),
+            value_blind: DrkValueBlind::random(&mut OsRng),
+            token_blind: DrkValueBlind::random(&mut OsRng),
+            memo: vec![],
+        }
+    }
+
+    /// An output minting `note` to `public`, with the note encrypted to
+    /// `enc_public`. Proofs aren't checked by the scanner.
+    fn output(public: &PublicKey, enc_public: &PublicKey, note: &Note) -> TransactionOutput {
+        let revealed = MintRevealedValues {
+            value_commit: pallas::Point::identity(),
+            token_commit: pallas::Point::identity(),
+            coin: Coin::from_note(public, note),
+        };
+        let enc_note = note.encrypt(enc_public).unwrap();
+        TransactionOutput { mint_proof: Proof::default(), revealed, enc_note }
+    }
+
+    fn input(nullifier: Nullifier) -> TransactionInput {
+        let secret = SecretKey::random(&mut OsRng);
+        let revealed = BurnRevealedValues {
+            value_commit: pallas::Point::identity(),
+            token_commit: pallas::Point::identity(),
+            nullifier,
+            merkle_root: MerkleNode::from(pallas::Base::zero()),
+            spend_hook: pallas::Base::zero(),
+            user_data_enc: pallas::Base::zero(),
+            signature_public: PublicKey::from_secret(secret),
+        };
+        TransactionInput { burn_proof: Proof::default(), revealed, signature: secret.sign(&[]) }
+    }
+
+    fn block(slot: u64, txs: Vec<Transaction>) -> BlockInfo {
+        let mut block = BlockInfo { txs, ..Default::default() };
+        block.header.slot = slot;
+        block
+    }
+
+    async fn wallet() -> WalletPtr {
+        let wallet = WalletDb::new("sqlite::memory:", "darkfi").await.unwrap();
+        wallet.init_db().await.unwrap();
+        wallet.tree_gen().await.unwrap();
+        wallet
+    }
+
+    #[async_std::test]
+    async fn test_wallet_scanner() -> Result<()> {
+        let token_id = DrkTokenId::random(&mut OsRng);
+        let wallet = wallet().await;
+        let keypair = wallet.keygen().await?;
+        let own_view_key = ViewKey::from(keypair);
+
+        // Coins of someone else, whose view key we hold
+        let owner = Keypair::random(&mut OsRng);
+        let view_key = ViewKey::from(owner);
+        wallet.put_view_key(&view_key).await?;
+
+        // A note for us which doesn't open its coin
+        let stranger = Keypair::random(&mut OsRng);
+        let invalid = TransactionOutput {
+            enc_note: note(2, token_id).encrypt(&keypair.public)?,
+            ..output(&keypair.public, &keypair.public, &note(1, token_id))
+        };
+
+        let paid = Transaction {
+            clear_inputs: vec![],
+            inputs: vec![],
+            outputs: vec![
+                // Paid to our address, and to our viewable address
+                output(&keypair.public, &keypair.public, &note(10, token_id)),
+                output(&keypair.public, &own_view_key.view_public(), &note(5, token_id)),
+                // Paid to the owner's viewable address
+                output(&owner.public, &view_key.view_public(), &note(7, token_id)),
+                // Paid to the owner's address, which the view key can't see
+                output(&owner.public, &owner.public, &note(8, token_id)),
+                output(&stranger.public, &stranger.public, &note(3, token_id)),
+                invalid,
+            ],
+        };
+        let mut source = MemorySource(vec![block(1, vec![]), block(2, vec![paid])], None);
+
+        let scanner = WalletScanner::new(wallet.clone());
+        let result = scanner.scan(&source).await?;
+        assert_eq!(result.height, 2);
+        let mut values: Vec<u64> = result.own_coins.iter().map(|x| x.note.value).collect();
+        values.sort();
+        assert_eq!(values, vec![5, 10]);
+        assert_eq!(result.viewed_coins.len(), 1);
+        assert_eq!(result.viewed_coins[0].note.value, 7);
+        assert_eq!(result.viewed_coins[0].public, owner.public);
+        assert_eq!(wallet.get_invalid_notes().await?.len(), 1);
+        assert_eq!(wallet.get_own_coins().await?.len(), 2);
+
+        // Scanning again finds nothing new
+        let result = scanner.scan(&source).await?;
+        assert!(result.own_coins.is_empty() && result.viewed_coins.is_empty());
+
+        // Our spends are confirmed by their nullifiers, and the spends of
+        // viewed coins by an output of the transaction we built
+        let spent = wallet.get_own_coins().await?.into_iter().find(|x| x.note.value == 10);
+        let change = output(&stranger.public, &stranger.public, &note(4, token_id));
+        let viewed = wallet.get_viewed_coins().await?[0].coin;
+        wallet.put_viewed_spend(&viewed, &change.revealed.coin).await?;
+        let spend = Transaction {
+            clear_inputs: vec![],
+            inputs: vec![input(spent.unwrap().nullifier)],
+            outputs: vec![change],
+        };
+        source.0.push(block(3, vec![spend]));
+
+        let result = scanner.scan(&source).await?;
+        assert_eq!(result.height, 3);
+        let own_coins = wallet.get_own_coins().await?;
+        assert_eq!(own_coins.len(), 1);
+        assert_eq!(own_coins[0].note.value, 5);
+        assert!(wallet.get_viewed_coins().await?[0].is_spent);
+
+        // A rescan rebuilds the same tree and finds the same coins
+        let tree = wallet.get_tree().await?;
+        let result = scanner.rescan(&source, 0).await?;
+        assert_eq!(result.own_coins.len(), 2);
+        assert_eq!(result.viewed_coins.len(), 1);
+        assert_eq!(wallet.get_tree().await?.root(0), tree.root(0));
+
+        // A wallet from before the scanner has a tree but no scan height,
+        // it is rescanned from the start
+        let upgraded = wallet().await;
+        upgraded.put_keypair(&keypair).await?;
+        upgraded.put_tree(&tree).await?;
+        let result = WalletScanner::new(upgraded.clone()).scan(&source).await?;
+        assert_eq!(result.height, 3);
+        assert_eq!(result.own_coins.len(), 2);
+        assert_eq!(upgraded.get_own_coins().await?.len(), 1);
+        assert_eq!(upgraded.get_tree().await?.root(0), tree.root(0));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_wallet_scanner_genesis_and_snapshot() -> Result<()> {
+        let token_id = DrkTokenId::random(&mut OsRng);
+        let wallet = wallet().await;
+        let keypair = wallet.keygen().await?;
+
+        // Coins in the genesis block are found
+        let paid = Transaction {
+            clear_inputs: vec![],
+            inputs: vec![],
+            outputs: vec![output(&keypair.public, &keypair.public, &note(10, token_id))],
+        };
+        let source = MemorySource(vec![block(0, vec![paid]), block(1, vec![])], None);
+        let result = WalletScanner::new(wallet.clone()).scan(&source).await?;
+        assert_eq!(result.height, 1);
+        assert_eq!(result.own_coins.len(), 1);
+
+        // A source synced from a snapshot can't build the tree of a wallet
+        // which hasn't scanned up to the snapshot
+        let source = MemorySource(vec![block(5, vec![]), block(6, vec![])], Some(5));
+        let scanner = WalletScanner::new(wallet.clone());
+        assert!(matches!(scanner.scan(&source).await, Err(Error::WalletScanBeforeSnapshot(1, 5))));
+        assert!(matches!(
+            scanner.rescan(&source, 0).await,
+            Err(Error::WalletScanBeforeSnapshot(0, 5))
+        ));
+        assert_eq!(wallet.get_scan_height().await?, Some(1));
+
+        // Wallets seeded from the snapshot scan from it
+        wallet.put_scan_height(5).await?;
+        assert_eq!(scanner.scan(&source).await?.height, 6);
+
+        Ok(())
+    }
+}
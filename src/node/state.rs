@@ -7,14 +7,13 @@ use crate::{
     blockchain::{nfstore::NullifierStore, rootstore::RootStore, Blockchain},
     consensus::{TESTNET_GENESIS_HASH_BYTES, TESTNET_GENESIS_TIMESTAMP},
     crypto::{
-        coin::{Coin, OwnCoin},
+        coin::Coin,
+        keycache::KeyCache,
         keypair::{PublicKey, SecretKey},
         note::{EncryptedNote, Note},
         proof::VerifyingKey,
-        util::poseidon_hash,
     },
     tx::Transaction,
-    wallet::walletdb::WalletPtr,
     zk::circuit::{BurnContract, MintContract},
     Result, VerifyFailed, VerifyResult,
 };
@@ -49,6 +48,27 @@ pub struct StateUpdate {
 
 /// State transition function
 pub fn state_transition<S: ProgramState>(state: &S, tx: Transaction) -> VerifyResult<StateUpdate> {
+    let update = check_state_transition(state, &tx)?;
+
+    debug!(target: "state_transition", "Verifying zk proofs");
+    match tx.verify(state.mint_vk(), state.burn_vk()) {
+        Ok(()) => debug!(target: "state_transition", "Verified successfully"),
+        Err(e) => {
+            error!(target: "state_transition", "Failed verifying zk proofs: {}", e);
+            return Err(VerifyFailed::ProofVerifyFailed(e.to_string()))
+        }
+    }
+
+    Ok(update)
+}
+
+/// State transition checks against the given state, without verifying the
+/// transaction itself. The caller is responsible for verifying the transaction,
+/// e.g. with [`Transaction::verify_batch`] for all transactions of a block.
+pub fn check_state_transition<S: ProgramState>(
+    state: &S,
+    tx: &Transaction,
+) -> VerifyResult<StateUpdate> {
     // Check the public keys in the clear inputs to see if they're coming
     // from a valid cashier or faucet.
     debug!(target: "state_transition", "Iterate clear_inputs");
@@ -90,22 +110,13 @@ pub fn state_transition<S: ProgramState>(state: &S, tx: Transaction) -> VerifyRe
         nullifiers.push(input.revealed.nullifier);
     }
 
-    debug!(target: "state_transition", "Verifying zk proofs");
-    match tx.verify(state.mint_vk(), state.burn_vk()) {
-        Ok(()) => debug!(target: "state_transition", "Verified successfully"),
-        Err(e) => {
-            error!(target: "state_transition", "Failed verifying zk proofs: {}", e);
-            return Err(VerifyFailed::ProofVerifyFailed(e.to_string()))
-        }
-    }
-
     // Newly created coins for this transaction
     let mut coins = Vec::with_capacity(tx.outputs.len());
     let mut enc_notes = Vec::with_capacity(tx.outputs.len());
-    for output in tx.outputs {
+    for output in &tx.outputs {
         // Gather all the coins
         coins.push(output.revealed.coin);
-        enc_notes.push(output.enc_note);
+        enc_notes.push(output.enc_note.clone());
     }
 
     Ok(StateUpdate { nullifiers, coins, enc_notes })
@@ -148,21 +159,18 @@ impl State {
         })
     }
 
-    /// Apply a [`StateUpdate`] to some state.
-    pub async fn apply(
-        &mut self,
-        update: StateUpdate,
-        secret_keys: Vec<SecretKey>,
-        notify: Option<smol::channel::Sender<(PublicKey, u64)>>,
-        wallet: WalletPtr,
-    ) -> Result<()> {
+    /// Apply a [`StateUpdate`] to some state. Notes are not scanned here,
+    /// wallets find their coins with a [`WalletScanner`].
+    ///
+    /// [`WalletScanner`]: super::scanner::WalletScanner
+    pub fn apply(&mut self, update: StateUpdate) -> Result<()> {
         debug!(target: "state_apply", "Extend nullifier set");
         debug!("Existing nullifiers: {:#?}", self.nullifiers.get_all()?);
         debug!("Update's nullifiers: {:#?}", update.nullifiers);
         self.nullifiers.insert(&update.nullifiers)?;
 
-        debug!(target: "state_apply", "Update Merkle tree and witnesses");
-        for (coin, enc_note) in update.coins.into_iter().zip(update.enc_notes.iter()) {
+        debug!(target: "state_apply", "Update Merkle tree");
+        for coin in update.coins {
             // Add the new coins to the Merkle tree
             let node = MerkleNode::from(coin.0);
             debug!("Current merkle tree: {:#?}", self.tree);
@@ -173,38 +181,6 @@ impl State {
             debug!("Existing merkle roots: {:#?}", self.merkle_roots.get_all()?);
             debug!("New merkle root: {:#?}", self.tree.root(0).unwrap());
             self.merkle_roots.insert(&[self.tree.root(0).unwrap()])?;
-
-            for secret in secret_keys.iter() {
-                if let Some(note) = State::try_decrypt_note(enc_note, *secret) {
-                    debug!(target: "state_apply", "Received a coin: amount {}", note.value);
-                    let leaf_position = self.tree.witness().unwrap();
-                    let nullifier =
-                        Nullifier::from(poseidon_hash::<2>([secret.inner(), note.serial]));
-                    let own_coin = OwnCoin {
-                        coin,
-                        note: note.clone(),
-                        secret: *secret,
-                        nullifier,
-                        leaf_position,
-                    };
-
-                    // TODO: FIXME: BUG check values inside the note are correct
-                    // We need to hash them all and check them against the coin
-                    // for them to be accepted.
-                    // Don't trust - verify.
-
-                    wallet.put_own_coin(own_coin).await?;
-
-                    if let Some(ch) = notify.clone() {
-                        debug!(target: "state_apply", "Send a notification");
-                        let pubkey = PublicKey::from_secret(*secret);
-                        ch.send((pubkey, note.value)).await?;
-                    }
-                }
-            }
-
-            // Save updated merkle tree into the wallet.
-            wallet.put_tree(&self.tree).await?;
         }
 
         debug!(target: "state_apply", "Finished apply() successfully.");
@@ -259,10 +235,12 @@ impl ProgramState for State {
 
 fn build_mint_vk() -> VerifyingKey {
     debug!("Building verifying key for MintContract");
-    VerifyingKey::build(11, &MintContract::default())
+    let circuit_id = KeyCache::native_circuit_id::<MintContract>();
+    KeyCache::default().verifying_key(&circuit_id, 11, &MintContract::default())
 }
 
 fn build_burn_vk() -> VerifyingKey {
     debug!("Building verifying key for BurnContract");
-    VerifyingKey::build(11, &BurnContract::default())
+    let circuit_id = KeyCache::native_circuit_id::<BurnContract>();
+    KeyCache::default().verifying_key(&circuit_id, 11, &BurnContract::default())
 }
@@ -49,3 +49,106 @@ async fn p2p_send_loop(receiver: smol::channel::Receiver<NetMsg>, p2p: net::P2pP
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use fxhash::FxHashMap;
+    use smol::Executor;
+
+    use super::*;
+    use crate::net::{sim::Simulation, transport::LinkConditions};
+
+    /// Steps the test allows for an election and a commit
+    const MAX_STEPS: usize = 5000;
+
+    /// Steps after which an uncommitted message is sent again
+    const RETRY_STEPS: usize = 100;
+
+    #[test]
+    fn raft_simulation_test() {
+        let executor = Arc::new(Executor::new());
+        let ex = executor.clone();
+
+        smol::block_on(executor.run(async move {
+            let sim = Simulation::new(3, 42, ex.clone()).await;
+
+            let mut rafts = vec![];
+            for (i, node) in sim.nodes().iter().enumerate() {
+                let datastore_path = std::env::temp_dir().join(format!("darkfi_raft_sim_{}", i));
+                let _ = std::fs::remove_dir_all(&datastore_path);
+
+                // Election timeouts don't overlap, so the nodes don't keep
+                // splitting their votes.
+                let settings = RaftSettings {
+                    heartbeat_timeout: 100,
+                    timeout: i as u64 + 1,
+                    id_timeout: 1,
+                    datastore_path,
+                    ..RaftSettings::default()
+                };
+
+                let seen_msgs = Arc::new(Mutex::new(FxHashMap::default()));
+                let raft = Raft::<String>::new(settings, seen_msgs.clone()).unwrap();
+                let (p2p_send, p2p_recv) = smol::channel::unbounded::<NetMsg>();
+
+                let id = raft.id();
+                node.protocol_registry()
+                    .register(net::SESSION_ALL, move |channel, p2p| {
+                        let (id, sender, seen_msgs) =
+                            (id.clone(), p2p_send.clone(), seen_msgs.clone());
+                        async move { ProtocolRaft::init(id, channel, sender, p2p, seen_msgs).await }
+                    })
+                    .await;
+
+                rafts.push((raft, p2p_recv));
+            }
+
+            sim.start().await.unwrap();
+            sim.wait_for_channels(2, MAX_STEPS).await.unwrap();
+
+            // Elect a leader and replicate over slow links losing some messages
+            sim.set_default_link(LinkConditions { latency: Duration::from_millis(20), loss: 0.1 });
+
+            let (stop_send, stop_recv) = smol::channel::unbounded();
+            let mut senders = vec![];
+            let mut receivers = vec![];
+            for (i, (mut raft, p2p_recv)) in rafts.into_iter().enumerate() {
+                senders.push(raft.sender());
+                receivers.push(raft.receiver());
+
+                let (node, executor, stop_recv) = (sim.node(i), ex.clone(), stop_recv.clone());
+                ex.spawn(
+                    async move { raft.run(node, p2p_recv, executor, stop_recv).await.unwrap() },
+                )
+                .detach();
+            }
+
+            // A message sent by a follower goes to the leader, and is lost while
+            // there is none, or on the way, so it's sent again until it's
+            // committed, as a client of the cluster would.
+            let mut steps = 0;
+            sim.run_until(MAX_STEPS, || {
+                if steps % RETRY_STEPS == 0 {
+                    senders[2].try_send("hello".to_string()).unwrap();
+                }
+                steps += 1;
+                async { !receivers[2].is_empty() }
+            })
+            .await
+            .unwrap();
+
+            // Every node commits it first
+            sim.run_until(MAX_STEPS, || async { receivers.iter().all(|x| !x.is_empty()) })
+                .await
+                .unwrap();
+            for receiver in &receivers {
+                assert_eq!(receiver.recv().await.unwrap(), "hello");
+            }
+
+            stop_send.close();
+            sim.stop().await;
+        }));
+    }
+}
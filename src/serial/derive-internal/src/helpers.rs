@@ -1,11 +1,10 @@
 use quote::ToTokens;
-use syn::{Attribute, Meta};
-//use syn::{spanned::Spanned, Attribute, Error, Meta, NestedMeta, Path};
+use syn::{spanned::Spanned, Attribute, Error, Lit, Meta, NestedMeta, Path};
 
-pub fn contains_skip(attrs: &[Attribute]) -> bool {
+fn contains_path(attrs: &[Attribute], name: &str) -> bool {
     for attr in attrs.iter() {
         if let Ok(Meta::Path(path)) = attr.parse_meta() {
-            if path.to_token_stream().to_string().as_str() == "skip_serialize" {
+            if path.to_token_stream().to_string().as_str() == name {
                 return true
             }
         }
@@ -13,24 +12,46 @@ pub fn contains_skip(attrs: &[Attribute]) -> bool {
     false
 }
 
-/*
-pub fn contains_initialize_with(attrs: &[Attribute]) -> syn::Result<Option<Path>> {
+pub fn contains_skip(attrs: &[Attribute]) -> bool {
+    contains_path(attrs, "skip_serialize")
+}
+
+pub fn contains_default(attrs: &[Attribute]) -> bool {
+    contains_path(attrs, "default")
+}
+
+/// Find the single argument of a `#[name(arg)]` attribute.
+fn attr_arg(attrs: &[Attribute], name: &str) -> syn::Result<Option<NestedMeta>> {
     for attr in attrs.iter() {
         if let Ok(Meta::List(meta_list)) = attr.parse_meta() {
-            if meta_list.path.to_token_stream().to_string().as_str() == "init_serialize" {
+            if meta_list.path.to_token_stream().to_string().as_str() == name {
                 if meta_list.nested.len() != 1 {
                     return Err(Error::new(
                         meta_list.span(),
-                        "init_serialize requires exactly one initialization method.",
+                        format!("{} requires exactly one argument.", name),
                     ))
                 }
-                let nested_meta = meta_list.nested.iter().next().unwrap();
-                if let NestedMeta::Meta(Meta::Path(path)) = nested_meta {
-                    return Ok(Some(path.clone()))
-                }
+                return Ok(meta_list.nested.into_iter().next())
             }
         }
     }
     Ok(None)
 }
-*/
+
+/// Version given with `#[serial_version(N)]`
+pub fn contains_version(attrs: &[Attribute]) -> syn::Result<Option<u64>> {
+    match attr_arg(attrs, "serial_version")? {
+        Some(NestedMeta::Lit(Lit::Int(lit))) => Ok(Some(lit.base10_parse()?)),
+        Some(nested) => Err(Error::new(nested.span(), "serial_version requires an integer.")),
+        None => Ok(None),
+    }
+}
+
+/// Migration function given with `#[serial_migrate(path)]`
+pub fn contains_migrate(attrs: &[Attribute]) -> syn::Result<Option<Path>> {
+    match attr_arg(attrs, "serial_migrate")? {
+        Some(NestedMeta::Meta(Meta::Path(path))) => Ok(Some(path)),
+        Some(nested) => Err(Error::new(nested.span(), "serial_migrate requires a function path.")),
+        None => Ok(None),
+    }
+}
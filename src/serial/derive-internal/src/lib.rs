@@ -1,10 +1,119 @@
 //! Derive (de)serialization for structs, see src/serial/derive
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::quote;
-use syn::{Fields, Ident, Index, ItemEnum, ItemStruct, WhereClause};
+use syn::{
+    spanned::Spanned, Attribute, Error, Field, Fields, Ident, Index, ItemEnum, ItemStruct,
+    WhereClause,
+};
 
 mod helpers;
-use helpers::contains_skip;
+use helpers::{contains_default, contains_migrate, contains_skip, contains_version};
+
+/// Statements encoding the `#[serial_version(N)]` prefix, if any
+fn version_ser(attrs: &[Attribute], cratename: &Ident) -> syn::Result<TokenStream2> {
+    Ok(match contains_version(attrs)? {
+        Some(version) => quote! {
+            len += #cratename::VarInt(#version).encode(&mut s)?;
+        },
+        None => TokenStream2::new(),
+    })
+}
+
+/// Statements decoding and checking the `#[serial_version(N)]` prefix, if
+/// any. Data of other versions is handed to the `#[serial_migrate(path)]`
+/// function, called as `path(version, &mut d)`. Without a migration
+/// function, types with `#[default]` fields accept data of older versions,
+/// with those fields missing.
+fn version_de(
+    attrs: &[Attribute],
+    has_default: bool,
+    cratename: &Ident,
+) -> syn::Result<TokenStream2> {
+    let migrate = contains_migrate(attrs)?;
+
+    let version = match contains_version(attrs)? {
+        Some(v) => v,
+        None => {
+            if let Some(path) = migrate {
+                return Err(Error::new(path.span(), "serial_migrate requires serial_version."))
+            }
+            return Ok(TokenStream2::new())
+        }
+    };
+
+    let unsupported = quote! {
+        let msg = format!("Unsupported version: {}", version);
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, msg))
+    };
+
+    let mismatch = match migrate {
+        Some(path) => quote! {
+            return #path(version, &mut d)
+        },
+        None if has_default => quote! {
+            if version > #version {
+                #unsupported
+            }
+        },
+        None => unsupported,
+    };
+
+    Ok(quote! {
+        let version = <#cratename::VarInt as #cratename::Decodable>::decode(&mut d)?.0;
+        if version != #version {
+            #mismatch
+        }
+    })
+}
+
+/// Whether any of the fields is marked `#[default]`
+fn has_default(fields: &Fields) -> bool {
+    fields.iter().any(|field| contains_default(&field.attrs))
+}
+
+/// Expression decoding a field. `#[default]` fields are only present in
+/// data of the type's current `#[serial_version(N)]`, and take their
+/// default value when decoding data of older versions.
+fn field_de(
+    field: &Field,
+    cratename: &Ident,
+    version: Option<u64>,
+    where_clause: &mut WhereClause,
+) -> syn::Result<TokenStream2> {
+    if contains_skip(&field.attrs) {
+        return Ok(quote! { Default::default() })
+    }
+
+    let field_type = &field.ty;
+    where_clause.predicates.push(
+        syn::parse2(quote! {
+            #field_type: #cratename::Decodable
+        })
+        .unwrap(),
+    );
+
+    if contains_default(&field.attrs) {
+        let version = match version {
+            Some(v) => v,
+            None => {
+                return Err(Error::new(
+                    field.span(),
+                    "#[default] fields require serial_version on the type.",
+                ))
+            }
+        };
+
+        return Ok(quote! {
+            if version == #version {
+                #cratename::Decodable::decode(&mut d)?
+            } else {
+                Default::default()
+            }
+        })
+    }
+
+    Ok(quote! { #cratename::Decodable::decode(&mut d)? })
+}
 
 pub fn enum_ser(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream2> {
     let name = &input.ident;
@@ -26,7 +135,7 @@ pub fn enum_ser(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream2>
                 for field in &fields.named {
                     let field_name = field.ident.as_ref().unwrap();
                     if contains_skip(&field.attrs) {
-                        variant_header.extend(quote! { _ #field_name, }); // TODO: Test this
+                        variant_header.extend(quote! { #field_name: _, });
                         continue
                     } else {
                         let field_type = &field.ty;
@@ -39,7 +148,7 @@ pub fn enum_ser(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream2>
                         variant_header.extend(quote! { #field_name, });
                     }
                     variant_body.extend(quote! {
-                        len += self.#field_name.encode(&mut s);
+                        len += #field_name.encode(&mut s)?;
                     })
                 }
                 variant_header = quote! { { #variant_header } };
@@ -52,9 +161,7 @@ pub fn enum_ser(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream2>
                     let field_idx =
                         u32::try_from(field_idx).expect("up to 2^32 fields are supported");
                     if contains_skip(&field.attrs) {
-                        let field_ident =
-                            Ident::new(format!("_id{}", field_idx).as_str(), Span::call_site());
-                        variant_header.extend(quote! { #field_ident, });
+                        variant_header.extend(quote! { _, });
                         continue
                     } else {
                         let field_type = &field.ty;
@@ -69,7 +176,7 @@ pub fn enum_ser(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream2>
                             Ident::new(format!("id{}", field_idx).as_str(), Span::call_site());
                         variant_header.extend(quote! { #field_ident, });
                         variant_body.extend(quote! {
-                            len += self.#field_ident.encode(&mut s)?;
+                            len += #field_ident.encode(&mut s)?;
                         })
                     }
                 }
@@ -91,15 +198,20 @@ pub fn enum_ser(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream2>
         ))
     }
 
+    let version = version_ser(&input.attrs, &cratename)?;
+
     Ok(quote! {
         impl #impl_generics #cratename::Encodable for #name #ty_generics #where_clause {
             fn encode<S: std::io::Write>(&self, mut s: S) -> ::core::result::Result<usize, std::io::Error> {
+                let mut len = 0;
+                #version
+
                 let variant_idx: u8 = match self {
                     #variant_idx_body
                 };
 
                 s.write_all(&variant_idx.to_le_bytes())?;
-                let mut len = 1;
+                len += 1;
 
                 match self {
                     #fields_body
@@ -119,6 +231,7 @@ pub fn enum_de(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream2>
         Clone::clone,
     );
 
+    let version = contains_version(&input.attrs)?;
     let mut variant_arms = TokenStream2::new();
     for (variant_idx, variant) in input.variants.iter().enumerate() {
         let variant_idx = u8::try_from(variant_idx).expect("up to 256 enum variants are supported");
@@ -128,41 +241,15 @@ pub fn enum_de(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream2>
             Fields::Named(fields) => {
                 for field in &fields.named {
                     let field_name = field.ident.as_ref().unwrap();
-                    if contains_skip(&field.attrs) {
-                        variant_header.extend(quote! {
-                                #field_name: Default::default(),
-                        });
-                    } else {
-                        let field_type = &field.ty;
-                        where_clause.predicates.push(
-                            syn::parse2(quote! {
-                                #field_type: #cratename::Decodable
-                            })
-                            .unwrap(),
-                        );
-
-                        variant_header.extend(quote! {
-                            #field_name: #cratename::Decodable::decode(&mut d)?,
-                        });
-                    }
+                    let value = field_de(field, &cratename, version, &mut where_clause)?;
+                    variant_header.extend(quote! { #field_name: #value, });
                 }
                 variant_header = quote! { { #variant_header } };
             }
             Fields::Unnamed(fields) => {
                 for field in fields.unnamed.iter() {
-                    if contains_skip(&field.attrs) {
-                        variant_header.extend(quote! { Default::default(), });
-                    } else {
-                        let field_type = &field.ty;
-                        where_clause.predicates.push(
-                            syn::parse2(quote! {
-                                #field_type: #cratename::Decodable
-                            })
-                            .unwrap(),
-                        );
-
-                        variant_header.extend(quote! { #cratename::Decodable::decode(&mut d)?, });
-                    }
+                    let value = field_de(field, &cratename, version, &mut where_clause)?;
+                    variant_header.extend(quote! { #value, });
                 }
                 variant_header = quote! { ( #variant_header ) };
             }
@@ -178,16 +265,20 @@ pub fn enum_de(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream2>
         let variant_idx: u8 = #cratename::Decodable::decode(&mut d)?;
     };
 
+    let has_default = input.variants.iter().any(|variant| has_default(&variant.fields));
+    let version = version_de(&input.attrs, has_default, &cratename)?;
+
     Ok(quote! {
         impl #impl_generics #cratename::Decodable for #name #ty_generics #where_clause {
             fn decode<D: std::io::Read>(mut d: D) -> ::core::result::Result<Self, std::io::Error> {
+                #version
                 #variant_idx
 
                 let return_value = match variant_idx {
                     #variant_arms
                     _ => {
                         let msg = format!("Unexpected variant index: {:?}", variant_idx);
-                        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg));
+                        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg))
                     }
                 };
                 Ok(return_value)
@@ -229,7 +320,11 @@ pub fn struct_ser(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStre
             }
         }
         Fields::Unnamed(fields) => {
-            for field_idx in 0..fields.unnamed.len() {
+            for (field_idx, field) in fields.unnamed.iter().enumerate() {
+                if contains_skip(&field.attrs) {
+                    continue
+                }
+
                 let field_idx = Index {
                     index: u32::try_from(field_idx).expect("up to 2^32 fields are supported"),
                     span: Span::call_site(),
@@ -238,15 +333,26 @@ pub fn struct_ser(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStre
                     len += self.#field_idx.encode(&mut s)?;
                 };
                 body.extend(delta);
+
+                let field_type = &field.ty;
+                where_clause.predicates.push(
+                    syn::parse2(quote! {
+                        #field_type: #cratename::Encodable
+                    })
+                    .unwrap(),
+                );
             }
         }
         Fields::Unit => {}
     }
 
+    let version = version_ser(&input.attrs, &cratename)?;
+
     Ok(quote! {
         impl #impl_generics #cratename::Encodable for #name #ty_generics #where_clause {
             fn encode<S: std::io::Write>(&self, mut s: S) -> ::core::result::Result<usize, std::io::Error> {
                 let mut len = 0;
+                #version
                 #body
                 Ok(len)
             }
@@ -262,30 +368,16 @@ pub fn struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStrea
         Clone::clone,
     );
 
+    let version = contains_version(&input.attrs)?;
     let return_value = match &input.fields {
         Fields::Named(fields) => {
             let mut body = TokenStream2::new();
             for field in &fields.named {
                 let field_name = field.ident.as_ref().unwrap();
-
-                let delta: TokenStream2 = if contains_skip(&field.attrs) {
-                    quote! {
-                        #field_name: Default::default(),
-                    }
-                } else {
-                    let field_type = &field.ty;
-                    where_clause.predicates.push(
-                        syn::parse2(quote! {
-                            #field_type: #cratename::Decodable
-                        })
-                        .unwrap(),
-                    );
-
-                    quote! {
-                        #field_name: #cratename::Decodable::decode(&mut d)?,
-                    }
-                };
-                body.extend(delta);
+                let value = field_de(field, &cratename, version, &mut where_clause)?;
+                body.extend(quote! {
+                    #field_name: #value,
+                });
             }
             quote! {
                 Self { #body }
@@ -293,11 +385,11 @@ pub fn struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStrea
         }
         Fields::Unnamed(fields) => {
             let mut body = TokenStream2::new();
-            for _ in 0..fields.unnamed.len() {
-                let delta = quote! {
-                    #cratename::Decodable::decode(&mut d)?,
-                };
-                body.extend(delta);
+            for field in fields.unnamed.iter() {
+                let value = field_de(field, &cratename, version, &mut where_clause)?;
+                body.extend(quote! {
+                    #value,
+                });
             }
             quote! {
                 Self( #body )
@@ -310,9 +402,12 @@ pub fn struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStrea
         }
     };
 
+    let version = version_de(&input.attrs, has_default(&input.fields), &cratename)?;
+
     Ok(quote! {
         impl #impl_generics #cratename::Decodable for #name #ty_generics #where_clause {
             fn decode<D: std::io::Read>(mut d: D) -> ::core::result::Result<Self, std::io::Error> {
+                #version
                 Ok(#return_value)
             }
         }
@@ -6,7 +6,25 @@ use syn::{Ident, ItemEnum, ItemStruct, ItemUnion};
 
 use darkfi_derive_internal::{enum_de, enum_ser, struct_de, struct_ser};
 
-#[proc_macro_derive(SerialEncodable, attributes(skip_serialize))]
+/// Derive `Encodable`, encoding fields in the order they're declared.
+///
+/// * `#[skip_serialize]` on a field leaves it out of the encoding, and it
+///   is decoded as `Default::default()`.
+/// * `#[serial_version(N)]` on the type prefixes the encoding with a
+///   `VarInt` version. Data of other versions fails to decode, unless
+///   `#[serial_migrate(path)]` names a function `path(version, reader)`
+///   returning the decoded object.
+/// * `#[default]` on a field marks it as added in the current version.
+///   It requires `#[serial_version(N)]`, and data of older versions
+///   decodes with the field set to `Default::default()`. Only one version
+///   of fields can be added this way: once the version is bumped again,
+///   older data has to be decoded by a `#[serial_migrate(path)]` function.
+///   Fields can't be detected as missing from the end of the data, as a
+///   reader doesn't tell where an object ends.
+#[proc_macro_derive(
+    SerialEncodable,
+    attributes(skip_serialize, default, serial_version, serial_migrate)
+)]
 pub fn darkfi_serialize(input: TokenStream) -> TokenStream {
     let found_crate = crate_name("darkfi-serial").expect("darkfi-serial is found in Cargo.toml");
 
@@ -34,7 +52,11 @@ pub fn darkfi_serialize(input: TokenStream) -> TokenStream {
     })
 }
 
-#[proc_macro_derive(SerialDecodable, attributes(skip_serialize))]
+/// Derive `Decodable`, see [`SerialEncodable`] for the attributes.
+#[proc_macro_derive(
+    SerialDecodable,
+    attributes(skip_serialize, default, serial_version, serial_migrate)
+)]
 pub fn darkfi_deserialize(input: TokenStream) -> TokenStream {
     let found_crate = crate_name("darkfi-serial").expect("darkfi-serial is found in Cargo.toml");
 
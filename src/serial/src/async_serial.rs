@@ -4,7 +4,10 @@ use futures_lite::{
     AsyncRead, AsyncReadExt as AsyncReadExtFut, AsyncWrite, AsyncWriteExt as AsyncWriteExtFut,
 };
 
-use super::{endian, VarInt};
+use super::{deserialize_partial_with_limits, endian, Decodable, DecodeLimits, Encodable, VarInt};
+
+/// Size of the chunks data is streamed in
+const CHUNK_SIZE: usize = 64 * 1024;
 
 pub struct AsyncReadExt;
 pub struct AsyncWriteExt;
@@ -123,3 +126,378 @@ impl VarInt {
         }
     }
 }
+
+/// Streams a byte blob to a writer in chunks, encoded the same way as a
+/// `Vec<u8>`, so it never has to be held in memory as a whole.
+pub struct BlobEncoder<W> {
+    stream: W,
+    remaining: u64,
+    #[cfg(feature = "blake3")]
+    hasher: blake3::Hasher,
+}
+
+impl<W: AsyncWrite + Unpin> BlobEncoder<W> {
+    /// Write the length prefix of a blob of `len` bytes.
+    pub async fn new(mut stream: W, len: u64) -> Result<Self, Error> {
+        VarInt(len).encode_async(&mut stream).await?;
+        Ok(Self {
+            stream,
+            remaining: len,
+            #[cfg(feature = "blake3")]
+            hasher: blake3::Hasher::new(),
+        })
+    }
+
+    /// Write the next chunk of the blob.
+    pub async fn write(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        if chunk.len() as u64 > self.remaining {
+            return Err(Error::new(ErrorKind::InvalidInput, "Blob is longer than its length"))
+        }
+
+        self.stream.write_all(chunk).await?;
+        #[cfg(feature = "blake3")]
+        self.hasher.update(chunk);
+        self.remaining -= chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Copy the rest of the blob from a reader.
+    pub async fn copy_from<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> Result<(), Error> {
+        let mut buf = vec![0u8; self.remaining.min(CHUNK_SIZE as u64) as usize];
+        while self.remaining > 0 {
+            let n = self.remaining.min(buf.len() as u64) as usize;
+            reader.read_exact(&mut buf[..n]).await?;
+            self.write(&buf[..n]).await?;
+        }
+        Ok(())
+    }
+
+    /// Bytes of the blob left to write
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Hash of the blob bytes written so far
+    #[cfg(feature = "blake3")]
+    pub fn hash(&self) -> blake3::Hash {
+        self.hasher.finalize()
+    }
+
+    /// Flush the writer, failing if the blob wasn't written in full.
+    pub async fn finish(mut self) -> Result<W, Error> {
+        if self.remaining > 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "Blob is shorter than its length"))
+        }
+
+        self.stream.flush().await?;
+        Ok(self.stream)
+    }
+}
+
+/// Reads a byte blob encoded as a `Vec<u8>` from a reader in chunks.
+pub struct BlobDecoder<R> {
+    stream: R,
+    remaining: u64,
+    #[cfg(feature = "blake3")]
+    hasher: blake3::Hasher,
+}
+
+impl<R: AsyncRead + Unpin> BlobDecoder<R> {
+    /// Read the length prefix of a blob. The length is untrusted, so
+    /// callers should check [`BlobDecoder::remaining`] against their limits.
+    pub async fn new(mut stream: R) -> Result<Self, Error> {
+        let remaining = VarInt::decode_async(&mut stream).await?.0;
+        Ok(Self {
+            stream,
+            remaining,
+            #[cfg(feature = "blake3")]
+            hasher: blake3::Hasher::new(),
+        })
+    }
+
+    /// Read the next chunk of the blob into `buf`, returning its size.
+    /// Returns 0 once the whole blob was read.
+    pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let n = self.remaining.min(buf.len() as u64) as usize;
+        self.stream.read_exact(&mut buf[..n]).await?;
+        #[cfg(feature = "blake3")]
+        self.hasher.update(&buf[..n]);
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+
+    /// Read the rest of the blob into a vector. Memory is allocated as the
+    /// data arrives, rather than up front from the untrusted length.
+    pub async fn read_to_vec(&mut self) -> Result<Vec<u8>, Error> {
+        let mut data = vec![];
+        while self.remaining > 0 {
+            let start = data.len();
+            data.resize(start + self.remaining.min(CHUNK_SIZE as u64) as usize, 0);
+            self.read(&mut data[start..]).await?;
+        }
+        Ok(data)
+    }
+
+    /// Copy the rest of the blob to a writer.
+    pub async fn copy_to<W: AsyncWrite + Unpin>(&mut self, writer: &mut W) -> Result<(), Error> {
+        let mut buf = vec![0u8; self.remaining.min(CHUNK_SIZE as u64) as usize];
+        while self.remaining > 0 {
+            let n = self.read(&mut buf).await?;
+            writer.write_all(&buf[..n]).await?;
+        }
+        Ok(())
+    }
+
+    /// Bytes of the blob left to read
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Hash of the blob bytes read so far
+    #[cfg(feature = "blake3")]
+    pub fn hash(&self) -> blake3::Hash {
+        self.hasher.finalize()
+    }
+}
+
+/// Streams a `Vec<T>` to a writer one element at a time, encoded the
+/// same way as the `Vec<T>` itself.
+pub struct VecEncoder<W> {
+    stream: W,
+    remaining: u64,
+    buf: Vec<u8>,
+    #[cfg(feature = "blake3")]
+    hasher: blake3::Hasher,
+}
+
+impl<W: AsyncWrite + Unpin> VecEncoder<W> {
+    /// Write the length prefix of a vector of `len` elements.
+    pub async fn new(mut stream: W, len: u64) -> Result<Self, Error> {
+        VarInt(len).encode_async(&mut stream).await?;
+        Ok(Self {
+            stream,
+            remaining: len,
+            buf: vec![],
+            #[cfg(feature = "blake3")]
+            hasher: blake3::Hasher::new(),
+        })
+    }
+
+    /// Write the next element of the vector.
+    pub async fn write<T: Encodable>(&mut self, item: &T) -> Result<(), Error> {
+        if self.remaining == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Vector is longer than its length"))
+        }
+
+        self.buf.clear();
+        item.encode(&mut self.buf)?;
+        self.stream.write_all(&self.buf).await?;
+        #[cfg(feature = "blake3")]
+        self.hasher.update(&self.buf);
+        self.remaining -= 1;
+        Ok(())
+    }
+
+    /// Elements of the vector left to write
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Hash of the encoded elements written so far
+    #[cfg(feature = "blake3")]
+    pub fn hash(&self) -> blake3::Hash {
+        self.hasher.finalize()
+    }
+
+    /// Flush the writer, failing if the vector wasn't written in full.
+    pub async fn finish(mut self) -> Result<W, Error> {
+        if self.remaining > 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "Vector is shorter than its length"))
+        }
+
+        self.stream.flush().await?;
+        Ok(self.stream)
+    }
+}
+
+/// Reads a `Vec<T>` from a reader one element at a time.
+///
+/// The size of an element isn't known before decoding it, so data is
+/// read ahead in chunks and elements are decoded from the buffered data.
+/// Bytes read past the end of the vector are returned by
+/// [`VecDecoder::into_inner`].
+pub struct VecDecoder<R> {
+    stream: R,
+    remaining: u64,
+    limits: DecodeLimits,
+    buf: Vec<u8>,
+    pos: usize,
+    #[cfg(feature = "blake3")]
+    hasher: blake3::Hasher,
+}
+
+impl<R: AsyncRead + Unpin> VecDecoder<R> {
+    /// Read the length prefix of a vector. Each element is decoded with
+    /// the given limits, and may take at most `limits.max_bytes` bytes.
+    pub async fn new(mut stream: R, limits: DecodeLimits) -> Result<Self, Error> {
+        let remaining = VarInt::decode_async(&mut stream).await?.0;
+        if remaining > limits.max_len {
+            return Err(Error::new(ErrorKind::Other, "Collection length exceeds limit"))
+        }
+
+        Ok(Self {
+            stream,
+            remaining,
+            limits,
+            buf: vec![],
+            pos: 0,
+            #[cfg(feature = "blake3")]
+            hasher: blake3::Hasher::new(),
+        })
+    }
+
+    /// Decode the next element, or return `None` once all of them were read.
+    pub async fn next<T: Decodable>(&mut self) -> Result<Option<T>, Error> {
+        if self.remaining == 0 {
+            return Ok(None)
+        }
+
+        loop {
+            match deserialize_partial_with_limits(&self.buf[self.pos..], self.limits) {
+                Ok((item, n)) => {
+                    #[cfg(feature = "blake3")]
+                    self.hasher.update(&self.buf[self.pos..self.pos + n]);
+                    self.pos += n;
+                    self.remaining -= 1;
+                    return Ok(Some(item))
+                }
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => self.fill().await?,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Read another chunk of data into the buffer.
+    async fn fill(&mut self) -> Result<(), Error> {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+
+        if self.buf.len() as u64 >= self.limits.max_bytes {
+            return Err(Error::new(ErrorKind::Other, "Element size exceeds limit"))
+        }
+
+        let start = self.buf.len();
+        self.buf.resize(start + CHUNK_SIZE, 0);
+        let n = self.stream.read(&mut self.buf[start..]).await?;
+        self.buf.truncate(start + n);
+
+        if n == 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "Vector is shorter than its length"))
+        }
+
+        Ok(())
+    }
+
+    /// Elements of the vector left to read
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Hash of the encoded elements read so far
+    #[cfg(feature = "blake3")]
+    pub fn hash(&self) -> blake3::Hash {
+        self.hasher.finalize()
+    }
+
+    /// Return the reader, along with any bytes read past the end of the vector.
+    pub fn into_inner(mut self) -> (R, Vec<u8>) {
+        self.buf.drain(..self.pos);
+        (self.stream, self.buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{
+        future,
+        io::{self, Cursor},
+    };
+
+    use super::*;
+    use crate::serialize;
+
+    #[test]
+    fn stream_blob_test() {
+        future::block_on(async {
+            let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 1).map(|i| i as u8).collect();
+
+            let mut encoder = BlobEncoder::new(vec![], data.len() as u64).await.unwrap();
+            encoder.copy_from(&mut Cursor::new(&data)).await.unwrap();
+            assert!(encoder.write(&[0]).await.is_err());
+            let encoded = encoder.finish().await.unwrap();
+            assert_eq!(encoded, serialize(&data));
+
+            let mut decoder = BlobDecoder::new(Cursor::new(&encoded)).await.unwrap();
+            assert_eq!(decoder.remaining(), data.len() as u64);
+            assert_eq!(decoder.read_to_vec().await.unwrap(), data);
+            #[cfg(feature = "blake3")]
+            assert_eq!(decoder.hash(), blake3::hash(&data));
+            assert_eq!(decoder.read(&mut [0u8; 8]).await.unwrap(), 0);
+
+            // Truncated data fails instead of allocating the claimed length
+            let mut decoder = BlobDecoder::new(Cursor::new(&encoded[..100])).await.unwrap();
+            assert!(decoder.read_to_vec().await.is_err());
+
+            let encoder = BlobEncoder::new(vec![], 1).await.unwrap();
+            assert!(encoder.finish().await.is_err());
+        })
+    }
+
+    #[test]
+    fn stream_vec_test() {
+        future::block_on(async {
+            let data: Vec<String> = (0..10000).map(|i| format!("item {}", i)).collect();
+
+            let mut encoder = VecEncoder::new(vec![], data.len() as u64).await.unwrap();
+            for item in &data {
+                encoder.write(item).await.unwrap();
+            }
+            let mut encoded = encoder.finish().await.unwrap();
+            assert_eq!(encoded, serialize(&data));
+            encoded.extend_from_slice(&[1, 2, 3]);
+
+            let limits = DecodeLimits::default();
+            let mut decoder = VecDecoder::new(Cursor::new(&encoded), limits).await.unwrap();
+            let mut decoded: Vec<String> = vec![];
+            while let Some(item) = decoder.next().await.unwrap() {
+                decoded.push(item);
+            }
+            assert_eq!(decoded, data);
+            assert_eq!(decoder.into_inner().1, vec![1, 2, 3]);
+
+            // Elements over the size limit fail
+            let limits = DecodeLimits { max_bytes: 4, ..DecodeLimits::default() };
+            let mut decoder = VecDecoder::new(Cursor::new(&encoded), limits).await.unwrap();
+            assert!(decoder.next::<String>().await.is_err());
+        })
+    }
+    #[test]
+    fn stream_vec_bounded_test() {
+        future::block_on(async {
+            // 8 MiB of elements, produced as they are read
+            let len = 1u64 << 18;
+            let stream = Cursor::new(serialize(&VarInt(len))).chain(io::repeat(7).take(len * 32));
+
+            let mut decoder = VecDecoder::new(stream, DecodeLimits::default()).await.unwrap();
+            let mut decoded = 0;
+            while let Some(item) = decoder.next::<[u8; 32]>().await.unwrap() {
+                assert_eq!(item, [7; 32]);
+                decoded += 1;
+            }
+            assert_eq!(decoded, len);
+
+            // Only a chunk of the data was buffered at once
+            assert!(decoder.buf.capacity() <= 2 * CHUNK_SIZE);
+        })
+    }
+}
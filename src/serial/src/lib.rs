@@ -5,10 +5,15 @@ pub use darkfi_derive::{SerialDecodable, SerialEncodable};
 
 #[cfg(feature = "async")]
 mod async_serial;
+#[cfg(feature = "async")]
+pub use async_serial::{BlobDecoder, BlobEncoder, VecDecoder, VecEncoder};
 
 mod endian;
+mod limits;
 mod types;
 
+pub use limits::{decode_with_limits, DecodeLimits};
+
 /// Data which can be encoded in a consensus-consistent way.
 pub trait Encodable {
     /// Encode an object with a well-defined format.
@@ -33,8 +38,17 @@ pub fn serialize<T: Encodable + ?Sized>(data: &T) -> Vec<u8> {
 /// Deserialize an object from a vector, but do not error if the entire
 /// vector is not consumed.
 pub fn deserialize_partial<T: Decodable>(data: &[u8]) -> Result<(T, usize), Error> {
+    deserialize_partial_with_limits(data, DecodeLimits::default())
+}
+
+/// Deserialize an object from a vector with the given limits, but do not
+/// error if the entire vector is not consumed.
+pub fn deserialize_partial_with_limits<T: Decodable>(
+    data: &[u8],
+    limits: DecodeLimits,
+) -> Result<(T, usize), Error> {
     let mut decoder = Cursor::new(data);
-    let rv = Decodable::decode(&mut decoder)?;
+    let rv = decode_with_limits(&mut decoder, limits)?;
     let consumed = decoder.position() as usize;
 
     Ok((rv, consumed))
@@ -43,7 +57,16 @@ pub fn deserialize_partial<T: Decodable>(data: &[u8]) -> Result<(T, usize), Erro
 /// Deserialize an object from a vector.
 /// Will error if said deserialization doesn't consume the entore vector.
 pub fn deserialize<T: Decodable>(data: &[u8]) -> Result<T, Error> {
-    let (rv, consumed) = deserialize_partial(data)?;
+    deserialize_with_limits(data, DecodeLimits::default())
+}
+
+/// Deserialize an object from a vector with the given limits.
+/// Will error if said deserialization doesn't consume the entire vector.
+pub fn deserialize_with_limits<T: Decodable>(
+    data: &[u8],
+    limits: DecodeLimits,
+) -> Result<T, Error> {
+    let (rv, consumed) = deserialize_partial_with_limits(data, limits)?;
 
     // Fail if data is not consumed entirely.
     if consumed != data.len() {
@@ -53,6 +76,19 @@ pub fn deserialize<T: Decodable>(data: &[u8]) -> Result<T, Error> {
     Ok(rv)
 }
 
+/// Decode a byte slice, encoded the same way as a `Vec<u8>`, borrowing it
+/// from `data` rather than copying it. `data` is advanced past the slice.
+pub fn decode_slice<'a>(data: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    let len = VarInt::decode(&mut *data)?.0;
+    if len > data.len() as u64 {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Slice is longer than the data"))
+    }
+
+    let (slice, rest) = data.split_at(len as usize);
+    *data = rest;
+    Ok(slice)
+}
+
 /// Extensions of `Write` to encode data as per Bitcoin consensus.
 pub trait WriteExt {
     /// Output a platform-specific unsigned int (DANGEROUS AND UNDEFINED)
@@ -440,7 +476,8 @@ impl<T: Decodable> Decodable for Vec<T> {
     #[inline]
     fn decode<D: Read>(mut d: D) -> Result<Self, Error> {
         let len = VarInt::decode(&mut d)?.0;
-        let mut ret = Vec::with_capacity(len as usize);
+        let _collection = limits::Collection::enter::<T>(len)?;
+        let mut ret = Vec::with_capacity(limits::Collection::capacity::<T>(len));
         for _ in 0..len {
             ret.push(Decodable::decode(&mut d)?);
         }
@@ -757,6 +794,51 @@ mod tests {
         assert!((deserialize(&[4u8, 2, 3, 4, 5, 6]) as Result<Vec<u8>, Error>).is_err());
     }
 
+    #[test]
+    fn deserialize_limits_test() {
+        // A huge length prefix fails without allocating
+        let huge = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        assert!(deserialize::<Vec<u64>>(&huge).is_err());
+        assert!(deserialize::<String>(&huge).is_err());
+        assert!(deserialize::<Vec<(u64, u64)>>(&[0xfe, 0xff, 0xff, 0xff, 0x00]).is_err());
+
+        let limits = DecodeLimits { max_len: 3, max_bytes: 16, max_depth: 2 };
+        let data = serialize(&vec![vec![1u8, 2], vec![3]]);
+        assert!(deserialize_with_limits::<Vec<Vec<u8>>>(&data, limits).is_err());
+
+        let limits = DecodeLimits { max_len: 3, max_bytes: 64, max_depth: 2 };
+        assert_eq!(
+            deserialize_with_limits::<Vec<Vec<u8>>>(&data, limits).unwrap(),
+            vec![vec![1u8, 2], vec![3]]
+        );
+
+        let limits = DecodeLimits { max_len: 2, max_bytes: 64, max_depth: 2 };
+        let data = serialize(&vec![1u8, 2, 3]);
+        assert!(deserialize_with_limits::<Vec<u8>>(&data, limits).is_err());
+
+        let limits = DecodeLimits { max_len: 3, max_bytes: 64, max_depth: 1 };
+        let data = serialize(&vec![vec![vec![1u8]]]);
+        assert!(deserialize_with_limits::<Vec<Vec<Vec<u8>>>>(&data, limits).is_err());
+        assert!(deserialize::<Vec<Vec<Vec<u8>>>>(&data).is_ok());
+
+        // Direct decoding uses the default limits, which only bound what
+        // can't be represented
+        assert!(Vec::<u64>::decode(Cursor::new(huge)).is_err());
+        assert_eq!(Vec::<u8>::decode(Cursor::new([1, 42])).unwrap(), vec![42]);
+        let data = serialize(&vec![0u8; (1 << 24) + 1]);
+        assert_eq!(deserialize::<Vec<u8>>(&data).unwrap().len(), (1 << 24) + 1);
+    }
+
+    #[test]
+    fn decode_slice_test() {
+        let data = serialize(&(vec![1u8, 2, 3], vec![4u8]));
+        let mut rest = &data[..];
+        assert_eq!(decode_slice(&mut rest).unwrap(), &[1, 2, 3]);
+        assert_eq!(decode_slice(&mut rest).unwrap(), &[4]);
+        assert!(rest.is_empty());
+        assert!(decode_slice(&mut &[4u8, 1, 2][..]).is_err());
+    }
+
     #[test]
     fn deserialize_strbuf_test() {
         assert_eq!(
@@ -856,4 +938,98 @@ mod tests {
         assert_eq!(ts1, ts1_n);
         assert_eq!(ts1_n, TestStruct1(baz.clone()));
     }
+
+    #[derive(Debug, PartialEq, SerialEncodable, SerialDecodable)]
+    enum TestEnum2 {
+        First {
+            foo: u64,
+            #[skip_serialize]
+            bar: bool,
+        },
+        Second(String, #[skip_serialize] u32, u8),
+    }
+
+    #[derive(Debug, PartialEq, SerialEncodable, SerialDecodable)]
+    #[serial_version(2)]
+    struct TestStruct2 {
+        foo: u64,
+        #[skip_serialize]
+        bar: bool,
+        #[default]
+        baz: String,
+        qux: u8,
+        #[default]
+        quux: Vec<u8>,
+    }
+
+    #[test]
+    fn derive_serialize_deserialize_attributes() {
+        let first = TestEnum2::First { foo: 42, bar: true };
+        assert_eq!(serialize(&first), vec![0, 42, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            deserialize::<TestEnum2>(&serialize(&first)).unwrap(),
+            TestEnum2::First { foo: 42, bar: false }
+        );
+
+        let second = TestEnum2::Second("foo".to_string(), 3, 4);
+        assert_eq!(
+            deserialize::<TestEnum2>(&serialize(&second)).unwrap(),
+            TestEnum2::Second("foo".to_string(), 0, 4)
+        );
+
+        let ts2 = TestStruct2 { foo: 1, bar: true, baz: "foo".to_string(), qux: 2, quux: vec![3] };
+        let ts2_s = serialize(&ts2);
+        assert_eq!(deserialize::<TestStruct2>(&ts2_s).unwrap(), TestStruct2 { bar: false, ..ts2 });
+
+        // Data written by version 1, before the default fields were added
+        let v1 = serialize(&(VarInt(1), 1u64, 2u8));
+        assert_eq!(
+            deserialize::<TestStruct2>(&v1).unwrap(),
+            TestStruct2 { foo: 1, bar: false, baz: String::new(), qux: 2, quux: vec![] }
+        );
+
+        // Newer versions are unknown, and current data has to be complete
+        assert!(deserialize::<TestStruct2>(&serialize(&(VarInt(3), 1u64, 2u8))).is_err());
+        assert!(deserialize::<TestStruct2>(&ts2_s[..ts2_s.len() - 1]).is_err());
+    }
+
+    #[derive(Debug, PartialEq, SerialEncodable, SerialDecodable)]
+    #[serial_version(2)]
+    #[serial_migrate(TestStruct3::migrate)]
+    struct TestStruct3 {
+        foo: u64,
+        bar: u32,
+    }
+
+    impl TestStruct3 {
+        fn migrate<D: Read>(version: u64, mut d: D) -> Result<Self, Error> {
+            match version {
+                1 => Ok(Self { foo: Decodable::decode(&mut d)?, bar: 0 }),
+                _ => Err(Error::new(ErrorKind::InvalidData, "Unknown version")),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, SerialEncodable, SerialDecodable)]
+    #[serial_version(1)]
+    enum TestEnum3 {
+        First(u8),
+        Second,
+    }
+
+    #[test]
+    fn derive_serialize_deserialize_version() {
+        let ts3 = TestStruct3 { foo: 1, bar: 2 };
+        let ts3_s = serialize(&ts3);
+        assert_eq!(ts3_s, vec![2, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(deserialize::<TestStruct3>(&ts3_s).unwrap(), ts3);
+
+        let v1 = serialize(&(VarInt(1), 1u64));
+        assert_eq!(deserialize::<TestStruct3>(&v1).unwrap(), TestStruct3 { foo: 1, bar: 0 });
+        assert!(deserialize::<TestStruct3>(&serialize(&(VarInt(3), 1u64))).is_err());
+
+        assert_eq!(serialize(&TestEnum3::First(7)), vec![1, 0, 7]);
+        assert_eq!(deserialize::<TestEnum3>(&[1, 1]).unwrap(), TestEnum3::Second);
+        assert!(deserialize::<TestEnum3>(&[2, 1]).is_err());
+    }
 }
@@ -0,0 +1,126 @@
+//! Limits applied when decoding untrusted data
+use std::{
+    cell::RefCell,
+    io::{Error, ErrorKind, Read},
+    mem::size_of,
+};
+
+use crate::Decodable;
+
+/// Most bytes reserved up front for a collection. Larger collections
+/// grow as their elements are actually read.
+const MAX_PREALLOC_BYTES: usize = 64 * 1024;
+
+/// Limits on the resources used when decoding, so a crafted length
+/// prefix can't make us allocate or loop without bound.
+/// The default limits are unbounded, for data we wrote ourselves like
+/// the local databases. Data received from peers is decoded with
+/// [`decode_with_limits`] and tighter limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum number of elements in a single collection
+    pub max_len: u64,
+    /// Maximum number of bytes taken by all decoded collections together
+    pub max_bytes: u64,
+    /// Maximum nesting depth of collections
+    pub max_depth: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self { max_len: u64::MAX, max_bytes: u64::MAX, max_depth: usize::MAX }
+    }
+}
+
+/// Decoder context, kept for the duration of a single decode.
+struct DecoderContext {
+    limits: DecodeLimits,
+    depth: usize,
+    reserved: u64,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Option<DecoderContext>> = const { RefCell::new(None) };
+}
+
+/// Decode an object, applying the given limits to all the collections
+/// within it.
+pub fn decode_with_limits<T: Decodable, D: Read>(d: D, limits: DecodeLimits) -> Result<T, Error> {
+    let _scope = Scope::enter(limits);
+    T::decode(d)
+}
+
+/// Installs a fresh decoder context, restoring the previous one on drop.
+struct Scope {
+    prev: Option<DecoderContext>,
+}
+
+impl Scope {
+    fn enter(limits: DecodeLimits) -> Self {
+        let ctx = DecoderContext { limits, depth: 0, reserved: 0 };
+        Self { prev: CONTEXT.with(|c| c.replace(Some(ctx))) }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        CONTEXT.with(|c| *c.borrow_mut() = self.prev.take());
+    }
+}
+
+/// Guard held while decoding the elements of a collection.
+pub(crate) struct Collection {
+    _scope: Option<Scope>,
+}
+
+impl Collection {
+    /// Check a collection of `len` elements of `T` against the limits of
+    /// the current context, and account for it. Collections decoded
+    /// outside of [`decode_with_limits`] get the default limits.
+    pub(crate) fn enter<T>(len: u64) -> Result<Self, Error> {
+        let scope = match CONTEXT.with(|c| c.borrow().is_none()) {
+            true => Some(Scope::enter(DecodeLimits::default())),
+            false => None,
+        };
+
+        CONTEXT.with(|c| {
+            let mut c = c.borrow_mut();
+            let ctx = c.as_mut().unwrap();
+
+            if ctx.depth >= ctx.limits.max_depth {
+                return Err(Error::new(ErrorKind::Other, "Collection nesting too deep"))
+            }
+
+            if len > ctx.limits.max_len {
+                return Err(Error::new(ErrorKind::Other, "Collection length exceeds limit"))
+            }
+
+            let size = len.checked_mul(size_of::<T>().max(1) as u64);
+            match size.and_then(|x| x.checked_add(ctx.reserved)) {
+                Some(v) if v <= ctx.limits.max_bytes => ctx.reserved = v,
+                _ => return Err(Error::new(ErrorKind::Other, "Collection size exceeds limit")),
+            }
+
+            ctx.depth += 1;
+            Ok(())
+        })?;
+
+        Ok(Self { _scope: scope })
+    }
+
+    /// Capacity to reserve up front for a collection of `len` elements of `T`
+    pub(crate) fn capacity<T>(len: u64) -> usize {
+        let max = MAX_PREALLOC_BYTES / size_of::<T>().max(1);
+        len.min(max as u64) as usize
+    }
+}
+
+impl Drop for Collection {
+    fn drop(&mut self) {
+        CONTEXT.with(|c| {
+            if let Some(ctx) = c.borrow_mut().as_mut() {
+                ctx.depth -= 1;
+            }
+        });
+    }
+}
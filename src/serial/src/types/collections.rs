@@ -4,7 +4,7 @@ use std::{
     io::{Error, Read, Write},
 };
 
-use crate::{Decodable, Encodable, VarInt};
+use crate::{limits::Collection, Decodable, Encodable, VarInt};
 
 impl<T: Encodable> Encodable for HashSet<T> {
     fn encode<S: Write>(&self, mut s: S) -> Result<usize, Error> {
@@ -20,6 +20,7 @@ impl<T: Encodable> Encodable for HashSet<T> {
 impl<T: Decodable + std::cmp::Eq + std::hash::Hash> Decodable for HashSet<T> {
     fn decode<D: Read>(mut d: D) -> Result<Self, Error> {
         let len = VarInt::decode(&mut d)?.0;
+        let _collection = Collection::enter::<T>(len)?;
         let mut ret = HashSet::new();
         for _ in 0..len {
             let entry: T = Decodable::decode(&mut d)?;
@@ -44,6 +45,7 @@ impl<T: Encodable, U: Encodable> Encodable for BTreeMap<T, U> {
 impl<T: Decodable + std::cmp::Ord, U: Decodable> Decodable for BTreeMap<T, U> {
     fn decode<D: Read>(mut d: D) -> Result<Self, Error> {
         let len = VarInt::decode(&mut d)?.0;
+        let _collection = Collection::enter::<(T, U)>(len)?;
         let mut ret = BTreeMap::new();
         for _ in 0..len {
             let key: T = Decodable::decode(&mut d)?;
@@ -68,6 +70,7 @@ impl<T: Encodable> Encodable for BTreeSet<T> {
 impl<T: Decodable + std::cmp::Ord> Decodable for BTreeSet<T> {
     fn decode<D: Read>(mut d: D) -> Result<Self, Error> {
         let len = VarInt::decode(&mut d)?.0;
+        let _collection = Collection::enter::<T>(len)?;
         let mut ret = BTreeSet::new();
         for _ in 0..len {
             let key: T = Decodable::decode(&mut d)?;
@@ -93,6 +96,7 @@ impl<T: Encodable, U: Encodable> Encodable for fxhash::FxHashMap<T, U> {
 impl<T: Decodable + std::cmp::Eq + std::hash::Hash, U: Decodable> Decodable for HashMap<T, U> {
     fn decode<D: Read>(mut d: D) -> Result<Self, Error> {
         let len = VarInt::decode(&mut d)?.0;
+        let _collection = Collection::enter::<(T, U)>(len)?;
         let mut ret = HashMap::new();
         for _ in 0..len {
             let key: T = Decodable::decode(&mut d)?;
@@ -121,6 +125,7 @@ impl<T: Decodable + std::cmp::Eq + std::hash::Hash, U: Decodable> Decodable
 {
     fn decode<D: Read>(mut d: D) -> Result<Self, Error> {
         let len = VarInt::decode(&mut d)?.0;
+        let _collection = Collection::enter::<(T, U)>(len)?;
         let mut ret = fxhash::FxHashMap::default();
         for _ in 0..len {
             let key: T = Decodable::decode(&mut d)?;
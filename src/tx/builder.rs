@@ -46,6 +46,9 @@ pub struct TransactionBuilderOutputInfo {
     pub value: u64,
     pub token_id: DrkTokenId,
     pub public: PublicKey,
+    /// View public key to encrypt the note to, when paying a
+    /// [`ViewableAddress`](crate::crypto::address::ViewableAddress)
+    pub view_public: Option<PublicKey>,
 }
 
 impl TransactionBuilder {
@@ -172,7 +175,7 @@ impl TransactionBuilder {
                 memo: vec![],
             };
 
-            let encrypted_note = note.encrypt(&output.public)?;
+            let encrypted_note = note.encrypt(&output.view_public.unwrap_or(output.public))?;
 
             let output = TransactionOutput { mint_proof, revealed, enc_note: encrypted_note };
             outputs.push(output);
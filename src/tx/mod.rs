@@ -3,17 +3,16 @@ use std::io;
 use darkfi_serial::{Encodable, SerialDecodable, SerialEncodable, VarInt};
 use log::error;
 use pasta_curves::group::Group;
+use rayon::prelude::*;
 
 use crate::{
     crypto::{
-        burn_proof::verify_burn_proof,
         keypair::PublicKey,
-        mint_proof::verify_mint_proof,
         note::EncryptedNote,
         proof::VerifyingKey,
         schnorr,
         schnorr::SchnorrPublic,
-        types::{DrkTokenId, DrkValueBlind, DrkValueCommit},
+        types::{DrkCircuitField, DrkTokenId, DrkValueBlind, DrkValueCommit},
         util::{pedersen_commitment_base, pedersen_commitment_u64},
         BurnRevealedValues, MintRevealedValues, Proof,
     },
@@ -22,6 +21,7 @@ use crate::{
 
 pub mod builder;
 pub mod partial;
+pub mod unsigned;
 
 /// A DarkFi transaction
 #[derive(Debug, Clone, PartialEq, Eq, SerialEncodable, SerialDecodable)]
@@ -76,6 +76,42 @@ pub struct TransactionOutput {
 impl Transaction {
     /// Verify the transaction
     pub fn verify(&self, mint_vk: &VerifyingKey, burn_vk: &VerifyingKey) -> VerifyResult<()> {
+        self.verify_without_proofs()?;
+
+        // Errors are reported for the transaction index in the batch,
+        // which is always zero here.
+        match Self::verify_proofs(std::slice::from_ref(self), mint_vk, burn_vk) {
+            Err(VerifyFailed::Transaction(_, e)) => Err(*e),
+            r => r,
+        }
+    }
+
+    /// Verify a set of transactions, e.g. all the transactions of a block.
+    /// The checks not involving zk proofs are run for all transactions in
+    /// parallel, and then all the proofs are batch verified together.
+    /// On failure, the error of the lowest failing transaction index is
+    /// returned, so all nodes report the same error for the same set.
+    pub fn verify_batch(
+        txs: &[Transaction],
+        mint_vk: &VerifyingKey,
+        burn_vk: &VerifyingKey,
+    ) -> VerifyResult<()> {
+        let results: Vec<VerifyResult<()>> =
+            txs.par_iter().map(|tx| tx.verify_without_proofs()).collect();
+
+        let failed = results.into_iter().enumerate().find_map(|(i, r)| r.err().map(|e| (i, e)));
+        match failed {
+            Some((i, e)) => {
+                // Proof failures of the transactions before it come first
+                Self::verify_proofs(&txs[..i], mint_vk, burn_vk)?;
+                Err(VerifyFailed::Transaction(i, Box::new(e)))
+            }
+            None => Self::verify_proofs(txs, mint_vk, burn_vk),
+        }
+    }
+
+    /// Verify everything in the transaction except for the zk proofs.
+    fn verify_without_proofs(&self) -> VerifyResult<()> {
         // Transaction must have minimum 1 clear or anon input, and 1 output
         if self.clear_inputs.len() + self.inputs.len() == 0 {
             error!("tx::verify(): Missing inputs");
@@ -95,25 +131,13 @@ impl Transaction {
         }
 
         // Add values from the inputs
-        for (i, input) in self.inputs.iter().enumerate() {
-            match verify_burn_proof(burn_vk, &input.burn_proof, &input.revealed) {
-                Ok(()) => valcom_total += &input.revealed.value_commit,
-                Err(e) => {
-                    error!("tx::verify(): Failed to verify burn proof {}: {}", i, e);
-                    return Err(VerifyFailed::BurnProof(i))
-                }
-            }
+        for input in &self.inputs {
+            valcom_total += &input.revealed.value_commit;
         }
 
         // Subtract values from the outputs
-        for (i, output) in self.outputs.iter().enumerate() {
-            match verify_mint_proof(mint_vk, &output.mint_proof, &output.revealed) {
-                Ok(()) => valcom_total -= &output.revealed.value_commit,
-                Err(e) => {
-                    error!("tx::verify(): Failed to verify mint proof {}: {}", i, e);
-                    return Err(VerifyFailed::MintProof(i))
-                }
-            }
+        for output in &self.outputs {
+            valcom_total -= &output.revealed.value_commit;
         }
 
         // If the accumulator is not back in its initial state,
@@ -152,6 +176,53 @@ impl Transaction {
         Ok(())
     }
 
+    /// Verify all burn and mint proofs of the given transactions. Burn and
+    /// mint proofs are batch verified in parallel. If a batch fails, its
+    /// proofs are verified one by one to find the first failing one, and
+    /// the failure of the lowest transaction index is returned.
+    fn verify_proofs(
+        txs: &[Transaction],
+        mint_vk: &VerifyingKey,
+        burn_vk: &VerifyingKey,
+    ) -> VerifyResult<()> {
+        let mut burns = vec![];
+        let mut mints = vec![];
+        for (i, tx) in txs.iter().enumerate() {
+            for (j, input) in tx.inputs.iter().enumerate() {
+                burns.push((i, j, &input.burn_proof, input.revealed.make_outputs()));
+            }
+            for (j, output) in tx.outputs.iter().enumerate() {
+                mints.push((i, j, &output.mint_proof, output.revealed.make_outputs()));
+            }
+        }
+
+        let (burn_result, mint_result) = rayon::join(
+            || verify_proof_batch(burn_vk, &burns),
+            || verify_proof_batch(mint_vk, &mints),
+        );
+
+        // The failure of the lowest transaction index is reported. Within a
+        // transaction, burn proofs come before mint proofs, the same order
+        // as in the transaction itself.
+        let burn_first = match (burn_result, mint_result) {
+            (Some((bi, _)), Some((mi, _))) => bi <= mi,
+            (burn, _) => burn.is_some(),
+        };
+
+        if burn_first {
+            let (i, j) = burn_result.unwrap();
+            error!("tx::verify(): Failed to verify burn proof {} of tx {}", j, i);
+            return Err(VerifyFailed::Transaction(i, Box::new(VerifyFailed::BurnProof(j))))
+        }
+
+        if let Some((i, j)) = mint_result {
+            error!("tx::verify(): Failed to verify mint proof {} of tx {}", j, i);
+            return Err(VerifyFailed::Transaction(i, Box::new(VerifyFailed::MintProof(j))))
+        }
+
+        Ok(())
+    }
+
     pub fn encode_without_signature<S: io::Write>(&self, mut s: S) -> Result<usize> {
         let mut len = 0;
         len += self.clear_inputs.encode_without_signature(&mut s)?;
@@ -178,6 +249,37 @@ impl Transaction {
     }
 }
 
+/// Batch verify the given (tx index, proof index, proof, public inputs) tuples,
+/// returning the indexes of the first invalid proof, if any.
+fn verify_proof_batch(
+    vk: &VerifyingKey,
+    proofs: &[(usize, usize, &Proof, Vec<DrkCircuitField>)],
+) -> Option<(usize, usize)> {
+    if proofs.is_empty() {
+        return None
+    }
+
+    let batch: Vec<(&Proof, Vec<DrkCircuitField>)> =
+        proofs.iter().map(|(_, _, proof, instances)| (*proof, instances.clone())).collect();
+
+    if Proof::verify_batch(vk, &batch) {
+        return None
+    }
+
+    // `find_first` keeps the result deterministic regardless of the
+    // order in which the parallel verifications finish.
+    let failed = proofs
+        .par_iter()
+        .find_first(|(_, _, proof, instances)| proof.verify(vk, instances).is_err());
+
+    match failed {
+        Some((i, j, _, _)) => Some((*i, *j)),
+        // The batch failed but all proofs verify individually, which should
+        // not happen. Blame the first proof so the set is still rejected.
+        None => Some((proofs[0].0, proofs[0].1)),
+    }
+}
+
 impl TransactionClearInput {
     fn from_partial(
         partial: partial::PartialTransactionClearInput,
@@ -241,3 +343,68 @@ macro_rules! impl_vec_without_signature {
 }
 impl_vec_without_signature!(TransactionClearInput);
 impl_vec_without_signature!(TransactionInput);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::{
+            keypair::{Keypair, SecretKey},
+            proof::ProvingKey,
+        },
+        tx::builder::{
+            TransactionBuilder, TransactionBuilderClearInputInfo, TransactionBuilderOutputInfo,
+        },
+        zk::circuit::{BurnContract, MintContract},
+    };
+    use pasta_curves::group::ff::Field;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_verify_batch() -> Result<()> {
+        let mint_pk = ProvingKey::build(11, &MintContract::default());
+        let burn_pk = ProvingKey::build(11, &BurnContract::default());
+        let mint_vk = VerifyingKey::build(11, &MintContract::default());
+        let burn_vk = VerifyingKey::build(11, &BurnContract::default());
+
+        let token_id = DrkTokenId::random(&mut OsRng);
+        let recipient = Keypair::random(&mut OsRng);
+        let faucet = |value| TransactionBuilder {
+            clear_inputs: vec![TransactionBuilderClearInputInfo {
+                value,
+                token_id,
+                signature_secret: SecretKey::random(&mut OsRng),
+            }],
+            inputs: vec![],
+            outputs: vec![TransactionBuilderOutputInfo {
+                value,
+                token_id,
+                public: recipient.public,
+                view_public: None,
+            }],
+        };
+
+        let txs =
+            vec![faucet(10).build(&mint_pk, &burn_pk)?, faucet(20).build(&mint_pk, &burn_pk)?];
+        assert!(Transaction::verify_batch(&txs, &mint_vk, &burn_vk).is_ok());
+
+        // A bad proof in the first transaction is reported before a bad
+        // state in the second one, though proofs are checked last.
+        let mut bad = txs.clone();
+        bad[0].outputs[0].mint_proof = txs[1].outputs[0].mint_proof.clone();
+        bad[1].clear_inputs[0].value += 1;
+        let err = Transaction::verify_batch(&bad, &mint_vk, &burn_vk).unwrap_err();
+        assert!(
+            matches!(err, VerifyFailed::Transaction(0, e) if matches!(*e, VerifyFailed::MintProof(0)))
+        );
+
+        // Without the bad proof, the bad state is reported
+        bad[0] = txs[0].clone();
+        let err = Transaction::verify_batch(&bad, &mint_vk, &burn_vk).unwrap_err();
+        assert!(
+            matches!(err, VerifyFailed::Transaction(1, e) if matches!(*e, VerifyFailed::MissingFunds))
+        );
+
+        Ok(())
+    }
+}
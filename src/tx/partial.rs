@@ -23,7 +23,7 @@ pub struct PartialTransactionClearInput {
     pub signature_public: PublicKey,
 }
 
-#[derive(Clone, SerialEncodable, SerialDecodable)]
+#[derive(Clone, Debug, PartialEq, Eq, SerialEncodable, SerialDecodable)]
 pub struct PartialTransactionInput {
     pub burn_proof: Proof,
     pub revealed: BurnRevealedValues,
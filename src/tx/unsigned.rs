@@ -0,0 +1,544 @@
+use darkfi_sdk::crypto::{constants::MERKLE_DEPTH, MerkleNode};
+use darkfi_serial::{serialize, SerialDecodable, SerialEncodable};
+use pasta_curves::{group::ff::Field, pallas};
+use rand::rngs::OsRng;
+
+use super::{
+    builder::TransactionBuilderOutputInfo,
+    partial::{PartialTransaction, PartialTransactionInput},
+    Transaction, TransactionInput, TransactionOutput,
+};
+use crate::{
+    crypto::{
+        burn_proof::create_burn_proof,
+        keypair::{PublicKey, SecretKey},
+        mint_proof::{create_mint_proof, verify_mint_proof, MintRevealedValues},
+        note::Note,
+        proof::{ProvingKey, VerifyingKey},
+        schnorr,
+        schnorr::SchnorrSecret,
+        types::{
+            DrkCoinBlind, DrkSerial, DrkSpendHook, DrkUserData, DrkUserDataBlind, DrkValueBlind,
+        },
+        util::poseidon_hash,
+    },
+    Error, Result, VerifyFailed,
+};
+
+/// Domain separator of the input signature secrets, see
+/// [`UnsignedInput::signature_secret`]
+const SIGNATURE_SECRET_DOMAIN: u64 = 1;
+
+/// A transaction built without spending keys, to be proven and signed
+/// elsewhere, e.g. on an offline machine holding the keys.
+///
+/// Minting a coin needs no secret, so the outputs are complete. They carry
+/// their plaintext, so the owners can check what they sign, see
+/// [`UnsignedTransaction::check`]. Each input carries what its owner needs
+/// to spend it: the note and Merkle path of the coin, and the value blind
+/// making the transaction balance. Inputs of different owners are signed
+/// in two rounds, since the signatures cover all the proofs: every owner
+/// first proves their inputs, then signs them once all the inputs are
+/// proven. Copies signed by different owners can be combined.
+#[derive(Clone, Debug, PartialEq, Eq, SerialEncodable, SerialDecodable)]
+pub struct UnsignedTransaction {
+    pub inputs: Vec<UnsignedInput>,
+    pub outputs: Vec<UnsignedOutput>,
+    /// Blinding factor for the token ID, shared by all inputs and outputs
+    pub token_blind: DrkValueBlind,
+}
+
+/// An input of an [`UnsignedTransaction`].
+#[derive(Clone, Debug, PartialEq, Eq, SerialEncodable, SerialDecodable)]
+pub struct UnsignedInput {
+    /// Owner of the spent coin, whose secret key proves and signs the input
+    pub public: PublicKey,
+    pub leaf_position: incrementalmerkletree::Position,
+    pub merkle_path: Vec<MerkleNode>,
+    pub note: Note,
+    /// Blinding factor for the input's value
+    pub value_blind: DrkValueBlind,
+    /// Burn proof, made by the owner
+    pub proof: Option<PartialTransactionInput>,
+    /// Signature, made by the owner once all the inputs are proven
+    pub signature: Option<schnorr::Signature>,
+}
+
+/// An output of an [`UnsignedTransaction`], with its plaintext.
+#[derive(Clone, Debug, PartialEq, Eq, SerialEncodable, SerialDecodable)]
+pub struct UnsignedOutput {
+    /// Recipient of the minted coin
+    pub public: PublicKey,
+    /// Key the note is encrypted to, see [`TransactionBuilderOutputInfo`]
+    pub view_public: Option<PublicKey>,
+    /// Plaintext of the encrypted note
+    pub note: Note,
+    pub output: TransactionOutput,
+}
+
+/// A coin to spend in an [`UnsignedTransaction`].
+#[derive(Clone)]
+pub struct UnsignedInputInfo {
+    pub public: PublicKey,
+    pub leaf_position: incrementalmerkletree::Position,
+    pub merkle_path: Vec<MerkleNode>,
+    pub note: Note,
+}
+
+impl UnsignedTransaction {
+    /// Build an unsigned transaction spending `inputs` to `outputs`. All
+    /// inputs and outputs must be of the same token, and their values must
+    /// balance.
+    pub fn build(
+        mint_pk: &ProvingKey,
+        inputs: Vec<UnsignedInputInfo>,
+        outputs: Vec<TransactionBuilderOutputInfo>,
+    ) -> Result<Self> {
+        if inputs.is_empty() {
+            return Err(VerifyFailed::LackingInputs.into())
+        }
+        if outputs.is_empty() {
+            return Err(VerifyFailed::LackingOutputs.into())
+        }
+
+        // Nothing gets proven for a transaction which can't be valid
+        let token_id = inputs[0].note.token_id;
+        if inputs.iter().any(|x| x.note.token_id != token_id) ||
+            outputs.iter().any(|x| x.token_id != token_id)
+        {
+            return Err(VerifyFailed::TokenMismatch.into())
+        }
+
+        let inputs_value = checked_sum(inputs.iter().map(|x| x.note.value))?;
+        let outputs_value = checked_sum(outputs.iter().map(|x| x.value))?;
+        if inputs_value != outputs_value {
+            return Err(VerifyFailed::MissingFunds.into())
+        }
+
+        for (i, input) in inputs.iter().enumerate() {
+            if input.merkle_path.len() != MERKLE_DEPTH as usize {
+                return Err(Error::UnsignedTxMerklePath(i))
+            }
+        }
+
+        let token_blind = DrkValueBlind::random(&mut OsRng);
+
+        let mut total_blind = DrkValueBlind::zero();
+        let mut unsigned_inputs = vec![];
+        for input in inputs {
+            let value_blind = DrkValueBlind::random(&mut OsRng);
+            total_blind += value_blind;
+
+            unsigned_inputs.push(UnsignedInput {
+                public: input.public,
+                leaf_position: input.leaf_position,
+                merkle_path: input.merkle_path,
+                note: input.note,
+                value_blind,
+                proof: None,
+                signature: None,
+            });
+        }
+
+        let mut unsigned_outputs = vec![];
+        for (i, output) in outputs.iter().enumerate() {
+            // The last output's blind balances the value commitments
+            let value_blind = if i == outputs.len() - 1 {
+                total_blind
+            } else {
+                DrkValueBlind::random(&mut OsRng)
+            };
+            total_blind -= value_blind;
+
+            let serial = DrkSerial::random(&mut OsRng);
+            let coin_blind = DrkCoinBlind::random(&mut OsRng);
+
+            // Disable composability for this old obselete API
+            let spend_hook = DrkSpendHook::from(0);
+            let user_data = DrkUserData::from(0);
+
+            let (mint_proof, revealed) = create_mint_proof(
+                mint_pk,
+                output.value,
+                output.token_id,
+                value_blind,
+                token_blind,
+                serial,
+                spend_hook,
+                user_data,
+                coin_blind,
+                output.public,
+            )?;
+
+            let note = Note {
+                serial,
+                value: output.value,
+                token_id: output.token_id,
+                coin_blind,
+                value_blind,
+                token_blind,
+                memo: vec![],
+            };
+            let enc_note = note.encrypt(&output.view_public.unwrap_or(output.public))?;
+
+            unsigned_outputs.push(UnsignedOutput {
+                public: output.public,
+                view_public: output.view_public,
+                note,
+                output: TransactionOutput { mint_proof, revealed, enc_note },
+            });
+        }
+
+        Ok(Self { inputs: unsigned_inputs, outputs: unsigned_outputs, token_blind })
+    }
+
+    /// Check that the transaction mints the outputs given in plaintext with
+    /// valid proofs and balances, so the owners know what they sign. The
+    /// encrypted notes can't be checked, only their recipients can decrypt
+    /// them.
+    pub fn check(&self, mint_vk: &VerifyingKey) -> Result<()> {
+        if self.inputs.is_empty() {
+            return Err(VerifyFailed::LackingInputs.into())
+        }
+        if self.outputs.is_empty() {
+            return Err(VerifyFailed::LackingOutputs.into())
+        }
+
+        for (i, input) in self.inputs.iter().enumerate() {
+            if input.merkle_path.len() != MERKLE_DEPTH as usize {
+                return Err(Error::UnsignedTxMerklePath(i))
+            }
+        }
+
+        for (i, output) in self.outputs.iter().enumerate() {
+            let note = &output.note;
+            let revealed = MintRevealedValues::compute(
+                note.value,
+                note.token_id,
+                note.value_blind,
+                note.token_blind,
+                note.serial,
+                DrkSpendHook::from(0),
+                DrkUserData::from(0),
+                note.coin_blind,
+                output.public,
+            );
+
+            if revealed != output.output.revealed {
+                return Err(Error::UnsignedTxOutputMismatch(i))
+            }
+
+            if verify_mint_proof(mint_vk, &output.output.mint_proof, &revealed).is_err() {
+                return Err(VerifyFailed::MintProof(i).into())
+            }
+        }
+
+        let token_id = self.inputs[0].note.token_id;
+        if self.inputs.iter().any(|x| x.note.token_id != token_id) ||
+            self.outputs
+                .iter()
+                .any(|x| x.note.token_id != token_id || x.note.token_blind != self.token_blind)
+        {
+            return Err(VerifyFailed::TokenMismatch.into())
+        }
+
+        let inputs_value = checked_sum(self.inputs.iter().map(|x| x.note.value))?;
+        let outputs_value = checked_sum(self.outputs.iter().map(|x| x.note.value))?;
+        let zero = DrkValueBlind::zero();
+        let inputs_blind = self.inputs.iter().fold(zero, |acc, x| acc + x.value_blind);
+        let outputs_blind = self.outputs.iter().fold(zero, |acc, x| acc + x.note.value_blind);
+        if inputs_value != outputs_value || inputs_blind != outputs_blind {
+            return Err(VerifyFailed::MissingFunds.into())
+        }
+
+        Ok(())
+    }
+
+    /// Prove and sign the inputs owned by `secret`, after checking the
+    /// transaction, see [`UnsignedTransaction::check`]. Inputs are only
+    /// signed once all of them are proven, so with several owners, each of
+    /// them has to sign again after the copies were combined.
+    /// Returns the number of inputs proven or signed.
+    pub fn sign(
+        &mut self,
+        mint_vk: &VerifyingKey,
+        burn_pk: &ProvingKey,
+        secret: SecretKey,
+    ) -> Result<usize> {
+        self.check(mint_vk)?;
+
+        let public = PublicKey::from_secret(secret);
+        let mut changed = 0;
+
+        for input in self.inputs.iter_mut().filter(|x| x.public == public && x.proof.is_none()) {
+            // Disable composability for this old obselete API
+            let spend_hook = DrkSpendHook::from(0);
+            let user_data = DrkUserData::from(0);
+            let user_data_blind = DrkUserDataBlind::random(&mut OsRng);
+
+            let (burn_proof, revealed) = create_burn_proof(
+                burn_pk,
+                input.note.value,
+                input.note.token_id,
+                input.value_blind,
+                self.token_blind,
+                input.note.serial,
+                spend_hook,
+                user_data,
+                user_data_blind,
+                input.note.coin_blind,
+                secret,
+                input.leaf_position,
+                input.merkle_path.clone(),
+                input.signature_secret(secret),
+            )?;
+
+            input.proof = Some(PartialTransactionInput { burn_proof, revealed });
+            changed += 1;
+        }
+
+        let unsigned_tx_data = match self.partial() {
+            Some(partial_tx) => serialize(&partial_tx),
+            None => return Ok(changed),
+        };
+
+        for input in self.inputs.iter_mut().filter(|x| x.public == public && x.signature.is_none())
+        {
+            let signature = input.signature_secret(secret).sign(&unsigned_tx_data);
+            input.signature = Some(signature);
+            changed += 1;
+        }
+
+        Ok(changed)
+    }
+
+    /// Take the proofs and signatures of another copy of this transaction,
+    /// signed by other owners.
+    pub fn combine(&mut self, other: &Self) -> Result<()> {
+        let same_coins = self.outputs == other.outputs &&
+            self.token_blind == other.token_blind &&
+            self.inputs.len() == other.inputs.len() &&
+            self.inputs.iter().zip(other.inputs.iter()).all(|(a, b)| {
+                a.public == b.public && a.note == b.note && a.value_blind == b.value_blind
+            });
+
+        if !same_coins {
+            return Err(Error::UnsignedTxMismatch)
+        }
+
+        for (input, other) in self.inputs.iter_mut().zip(other.inputs.iter()) {
+            if input.proof.is_none() {
+                input.proof = other.proof.clone();
+            }
+            if input.signature.is_none() {
+                input.signature = other.signature.clone();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether all the inputs are proven and signed.
+    pub fn is_complete(&self) -> bool {
+        self.inputs.iter().all(|x| x.proof.is_some() && x.signature.is_some())
+    }
+
+    /// Turn a complete unsigned transaction into a [`Transaction`].
+    pub fn finalize(self) -> Result<Transaction> {
+        if !self.is_complete() {
+            return Err(Error::UnsignedTxIncomplete)
+        }
+
+        let inputs = self
+            .inputs
+            .into_iter()
+            .map(|x| TransactionInput::from_partial(x.proof.unwrap(), x.signature.unwrap()))
+            .collect();
+
+        let outputs = self.outputs.into_iter().map(|x| x.output).collect();
+        Ok(Transaction { clear_inputs: vec![], inputs, outputs })
+    }
+
+    /// The transaction data covered by the signatures, once all the inputs
+    /// are proven.
+    fn partial(&self) -> Option<PartialTransaction> {
+        let mut inputs = vec![];
+        for input in &self.inputs {
+            inputs.push(input.proof.clone()?);
+        }
+
+        let outputs = self.outputs.iter().map(|x| x.output.clone()).collect();
+        Some(PartialTransaction { clear_inputs: vec![], inputs, outputs })
+    }
+}
+
+impl UnsignedInput {
+    /// The secret key signing this input. It is derived from the owner's
+    /// secret key and the spent coin, so the owner can sign after the
+    /// inputs of others are proven without storing anything.
+    fn signature_secret(&self, secret: SecretKey) -> SecretKey {
+        let domain = pallas::Base::from(SIGNATURE_SECRET_DOMAIN);
+        SecretKey(poseidon_hash::<3>([secret.0, self.note.serial, domain]))
+    }
+}
+
+/// Sum of the given values, failing if it overflows.
+fn checked_sum(mut values: impl Iterator<Item = u64>) -> Result<u64> {
+    match values.try_fold(0_u64, |acc, x| acc.checked_add(x)) {
+        Some(v) => Ok(v),
+        None => Err(VerifyFailed::MissingFunds.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::{
+            coin::Coin,
+            keypair::Keypair,
+            proof::VerifyingKey,
+            types::{DrkTokenId, DrkValueBlind},
+        },
+        zk::circuit::{BurnContract, MintContract},
+    };
+    use incrementalmerkletree::{bridgetree::BridgeTree, Tree};
+
+    fn dummy_note(value: u64, token_id: DrkTokenId) -> Note {
+        Note {
+            serial: DrkSerial::random(&mut OsRng),
+            value,
+            token_id,
+            coin_blind: DrkCoinBlind::random(&mut OsRng),
+            value_blind: DrkValueBlind::random(&mut OsRng),
+            token_blind: DrkValueBlind::random(&mut OsRng),
+            memo: vec![],
+        }
+    }
+
+    #[test]
+    fn test_unsigned_transaction() -> Result<()> {
+        let mint_pk = ProvingKey::build(11, &MintContract::default());
+        let burn_pk = ProvingKey::build(11, &BurnContract::default());
+        let mint_vk = VerifyingKey::build(11, &MintContract::default());
+        let burn_vk = VerifyingKey::build(11, &BurnContract::default());
+
+        let token_id = DrkTokenId::random(&mut OsRng);
+        let alice = Keypair::random(&mut OsRng);
+        let bob = Keypair::random(&mut OsRng);
+        let recipient = Keypair::random(&mut OsRng);
+
+        // Alice and Bob each own a coin
+        let notes =
+            [(alice.public, dummy_note(30, token_id)), (bob.public, dummy_note(12, token_id))];
+        let mut tree = BridgeTree::<MerkleNode, MERKLE_DEPTH>::new(100);
+        let mut positions = vec![];
+        for (public, note) in &notes {
+            tree.append(&MerkleNode::from(Coin::from_note(public, note).0));
+            positions.push(tree.witness().unwrap());
+        }
+        let root = tree.root(0).unwrap();
+
+        let inputs: Vec<UnsignedInputInfo> = notes
+            .iter()
+            .zip(positions)
+            .map(|((public, note), leaf_position)| UnsignedInputInfo {
+                public: *public,
+                leaf_position,
+                merkle_path: tree.authentication_path(leaf_position, &root).unwrap(),
+                note: note.clone(),
+            })
+            .collect();
+
+        // Outputs paying the recipient, and some change to Alice
+        let outputs = |change: u64, change_token: DrkTokenId| {
+            vec![
+                TransactionBuilderOutputInfo {
+                    value: 40,
+                    token_id,
+                    public: recipient.public,
+                    view_public: None,
+                },
+                TransactionBuilderOutputInfo {
+                    value: change,
+                    token_id: change_token,
+                    public: alice.public,
+                    view_public: None,
+                },
+            ]
+        };
+
+        // Transactions which can't balance are refused before proving
+        let res = UnsignedTransaction::build(&mint_pk, inputs.clone(), outputs(3, token_id));
+        assert!(matches!(res, Err(Error::VerifyFailed(VerifyFailed::MissingFunds))));
+        let other_token = DrkTokenId::random(&mut OsRng);
+        let res = UnsignedTransaction::build(&mint_pk, inputs.clone(), outputs(2, other_token));
+        assert!(matches!(res, Err(Error::VerifyFailed(VerifyFailed::TokenMismatch))));
+
+        let unsigned = UnsignedTransaction::build(&mint_pk, inputs, outputs(2, token_id))?;
+        unsigned.check(&mint_vk)?;
+        assert_eq!(unsigned.outputs[0].public, recipient.public);
+        assert_eq!(unsigned.outputs[0].note.value, 40);
+
+        // Owners refuse to sign outputs which don't match their plaintext
+        let mut tampered = unsigned.clone();
+        tampered.outputs[0].public = bob.public;
+        let res = tampered.sign(&mint_vk, &burn_pk, alice.secret);
+        assert!(matches!(res, Err(Error::UnsignedTxOutputMismatch(0))));
+        let mut tampered = unsigned.clone();
+        tampered.outputs[0].note.value = 41;
+        tampered.outputs[1].note.value = 1;
+        let res = tampered.sign(&mint_vk, &burn_pk, alice.secret);
+        assert!(matches!(res, Err(Error::UnsignedTxOutputMismatch(0))));
+        let mut tampered = unsigned.clone();
+        tampered.inputs[1].merkle_path.pop();
+        let res = tampered.sign(&mint_vk, &burn_pk, alice.secret);
+        assert!(matches!(res, Err(Error::UnsignedTxMerklePath(1))));
+        let mut tampered = unsigned.clone();
+        tampered.outputs[0].output.mint_proof = unsigned.outputs[1].output.mint_proof.clone();
+        let res = tampered.sign(&mint_vk, &burn_pk, alice.secret);
+        assert!(matches!(res, Err(Error::VerifyFailed(VerifyFailed::MintProof(0)))));
+
+        // It survives the trip to the offline machines
+        let mut alice_tx: UnsignedTransaction = darkfi_serial::deserialize(&serialize(&unsigned))?;
+        let mut bob_tx = unsigned.clone();
+
+        // First round: each owner proves their input
+        assert_eq!(alice_tx.sign(&mint_vk, &burn_pk, alice.secret)?, 1);
+        assert_eq!(bob_tx.sign(&mint_vk, &burn_pk, bob.secret)?, 1);
+        assert!(!alice_tx.is_complete());
+        assert!(alice_tx.clone().finalize().is_err());
+
+        // Second round: once combined, each owner signs their input
+        alice_tx.combine(&bob_tx)?;
+        assert_eq!(alice_tx.sign(&mint_vk, &burn_pk, alice.secret)?, 1);
+        bob_tx.combine(&alice_tx)?;
+        assert_eq!(bob_tx.sign(&mint_vk, &burn_pk, bob.secret)?, 1);
+        alice_tx.combine(&bob_tx)?;
+        assert!(alice_tx.is_complete());
+
+        let tx = alice_tx.finalize()?;
+        tx.verify(&mint_vk, &burn_vk)?;
+        assert_eq!(tx.inputs[0].revealed.merkle_root, root);
+
+        // Copies of other transactions can't be combined
+        let other = UnsignedTransaction::build(
+            &mint_pk,
+            vec![UnsignedInputInfo {
+                public: alice.public,
+                leaf_position: 0.into(),
+                merkle_path: tree.authentication_path(0.into(), &root).unwrap(),
+                note: notes[0].1.clone(),
+            }],
+            vec![TransactionBuilderOutputInfo {
+                value: 30,
+                token_id,
+                public: bob.public,
+                view_public: None,
+            }],
+        )?;
+        assert!(bob_tx.combine(&other).is_err());
+
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use std::{fs::create_dir_all, path::Path, str::FromStr, time::Duration};
 use async_std::sync::Arc;
 use darkfi_sdk::crypto::{constants::MERKLE_DEPTH, MerkleNode, Nullifier};
 use darkfi_serial::{deserialize, serialize};
-use incrementalmerkletree::bridgetree::BridgeTree;
+use incrementalmerkletree::{bridgetree::BridgeTree, Position};
 use log::{debug, error, info, LevelFilter};
 use pasta_curves::group::ff::PrimeField;
 use rand::rngs::OsRng;
@@ -16,17 +16,21 @@ use crate::{
     crypto::{
         address::Address,
         coin::{Coin, OwnCoin},
-        keypair::{Keypair, PublicKey, SecretKey},
+        keypair::{Keypair, PublicKey, SecretKey, ViewKey},
         note::Note,
         types::DrkTokenId,
     },
-    util::path::expand_path,
-    Error::{WalletEmptyPassword, WalletTreeExists},
+    util::{path::expand_path, time::Timestamp},
+    Error::{self, WalletEmptyPassword, WalletTreeExists},
     Result,
 };
 
 pub type WalletPtr = Arc<WalletDb>;
 
+/// Version of the wallet's tables, kept in the database's `user_version`.
+/// Bump it when a table changes, with a migration in `WalletDb::migrate`.
+const WALLET_VERSION: i64 = 1;
+
 #[derive(Clone, Debug)]
 pub struct Balance {
     pub token_id: DrkTokenId,
@@ -39,6 +43,125 @@ pub struct Balances {
     pub list: Vec<Balance>,
 }
 
+/// A note that decrypted under one of our keys, but doesn't open the coin
+/// it came with, so the wallet didn't credit it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidNote {
+    pub coin: Coin,
+    pub public: PublicKey,
+    pub note: Note,
+}
+
+/// A coin found with one of our view keys. It can be seen, but not spent
+/// by the wallet. It is witnessed in the Merkle tree so that unsigned
+/// transactions spending it can be built for its owner to sign. Its
+/// nullifier can't be computed without the spending key, so it is only
+/// known to be spent once one of these transactions is finalized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViewedCoin {
+    pub coin: Coin,
+    pub public: PublicKey,
+    pub note: Note,
+    pub slot: u64,
+    pub leaf_position: Position,
+    pub is_spent: bool,
+}
+
+/// Direction of a transaction in the wallet's history.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TxDirection {
+    Received = 0,
+    Sent = 1,
+}
+
+impl TryFrom<i64> for TxDirection {
+    type Error = Error;
+
+    fn try_from(v: i64) -> Result<Self> {
+        match v {
+            0 => Ok(Self::Received),
+            1 => Ok(Self::Sent),
+            _ => Err(Error::ParseFailed("Invalid transaction direction")),
+        }
+    }
+}
+
+impl std::fmt::Display for TxDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Received => write!(f, "received"),
+            Self::Sent => write!(f, "sent"),
+        }
+    }
+}
+
+/// Confirmation status of a transaction in the wallet's history.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TxStatus {
+    /// Built or broadcasted, but not yet seen in a finalized block
+    Pending = 0,
+    /// Seen in a finalized block
+    Confirmed = 1,
+    /// Not finalized in time, so the coins it spent were made spendable again
+    Reverted = 2,
+}
+
+impl TryFrom<i64> for TxStatus {
+    type Error = Error;
+
+    fn try_from(v: i64) -> Result<Self> {
+        match v {
+            0 => Ok(Self::Pending),
+            1 => Ok(Self::Confirmed),
+            2 => Ok(Self::Reverted),
+            _ => Err(Error::ParseFailed("Invalid transaction status")),
+        }
+    }
+}
+
+impl std::fmt::Display for TxStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Confirmed => write!(f, "confirmed"),
+            Self::Reverted => write!(f, "reverted"),
+        }
+    }
+}
+
+/// A transaction in the wallet's history, linked to the coins of ours it
+/// spent and the coins it gave us.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxRecord {
+    pub tx_hash: blake3::Hash,
+    pub direction: TxDirection,
+    /// Value sent to others, or received by us
+    pub value: u64,
+    pub token_id: DrkTokenId,
+    /// Recipients of a sent transaction, if we built it
+    pub counterparties: Vec<PublicKey>,
+    /// Slot of the block including the transaction, once confirmed
+    pub slot: Option<u64>,
+    pub memo: Vec<u8>,
+    pub status: TxStatus,
+    pub timestamp: Timestamp,
+    /// Our coins spent by the transaction
+    pub spent_coins: Vec<Coin>,
+    /// Coins we received from the transaction, including change
+    pub received_coins: Vec<Coin>,
+}
+
+/// A transaction we built which isn't finalized yet. The coins it spends
+/// are marked as spent until it is, or until it is reverted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingTx {
+    pub tx_hash: blake3::Hash,
+    /// The serialized transaction, to broadcast it again
+    pub tx: Vec<u8>,
+    /// Last finalized slot when the wallet first saw the transaction pending
+    pub slot: Option<u64>,
+}
+
 pub struct WalletDb {
     pub conn: SqlitePool,
 }
@@ -85,6 +208,16 @@ impl WalletDb {
         let tree = include_str!("../../script/sql/tree.sql");
         let keys = include_str!("../../script/sql/keys.sql");
         let coins = include_str!("../../script/sql/coins.sql");
+        let invalid_notes = include_str!("../../script/sql/invalid_notes.sql");
+        let view_keys = include_str!("../../script/sql/view_keys.sql");
+        let viewed_coins = include_str!("../../script/sql/viewed_coins.sql");
+        let viewed_spends = include_str!("../../script/sql/viewed_spends.sql");
+        let scan_state = include_str!("../../script/sql/scan_state.sql");
+        let transactions = include_str!("../../script/sql/transactions.sql");
+        let tx_coins = include_str!("../../script/sql/tx_coins.sql");
+        let pending_txs = include_str!("../../script/sql/pending_txs.sql");
+
+        self.migrate().await?;
 
         let mut conn = self.conn.acquire().await?;
 
@@ -96,6 +229,63 @@ impl WalletDb {
 
         debug!("Initializing coins table");
         sqlx::query(coins).execute(&mut conn).await?;
+
+        debug!("Initializing invalid notes table");
+        sqlx::query(invalid_notes).execute(&mut conn).await?;
+
+        debug!("Initializing view keys table");
+        sqlx::query(view_keys).execute(&mut conn).await?;
+
+        debug!("Initializing viewed coins table");
+        sqlx::query(viewed_coins).execute(&mut conn).await?;
+
+        debug!("Initializing viewed spends table");
+        sqlx::query(viewed_spends).execute(&mut conn).await?;
+
+        debug!("Initializing scan state table");
+        sqlx::query(scan_state).execute(&mut conn).await?;
+
+        debug!("Initializing transactions table");
+        sqlx::query(transactions).execute(&mut conn).await?;
+
+        debug!("Initializing transaction coins table");
+        sqlx::query(tx_coins).execute(&mut conn).await?;
+
+        debug!("Initializing pending transactions table");
+        sqlx::query(pending_txs).execute(&mut conn).await?;
+        Ok(())
+    }
+
+    /// Update the tables of a wallet made by an older version, before
+    /// `init_db` creates the missing ones.
+    async fn migrate(&self) -> Result<()> {
+        let mut conn = self.conn.acquire().await?;
+        let version: i64 = sqlx::query("PRAGMA user_version;").fetch_one(&mut conn).await?.get(0);
+
+        if version < 1 {
+            // The viewed coins table changed columns before the wallet had a
+            // version. Viewed coins are only found by scanning, so they are
+            // dropped and the wallet scanned again from the start.
+            let viewed_coins = sqlx::query(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'viewed_coins';",
+            )
+            .fetch_optional(&mut conn)
+            .await?;
+
+            if viewed_coins.is_some() {
+                info!("Migrating viewed coins, the wallet will be scanned again");
+                sqlx::query("DROP TABLE viewed_coins;").execute(&mut conn).await?;
+                sqlx::query("DROP TABLE IF EXISTS scan_state;").execute(&mut conn).await?;
+            }
+        }
+
+        if version < WALLET_VERSION {
+            debug!("Setting wallet version to {}", WALLET_VERSION);
+            sqlx::query(&format!("PRAGMA user_version = {};", WALLET_VERSION))
+                .execute(&mut conn)
+                .await?;
+        }
+
         Ok(())
     }
 
@@ -381,6 +571,526 @@ impl WalletDb {
         Ok(())
     }
 
+    /// Record a note received for `public` that doesn't match its coin.
+    pub async fn put_invalid_note(
+        &self,
+        coin: &Coin,
+        public: &PublicKey,
+        note: &Note,
+    ) -> Result<()> {
+        debug!("Putting invalid note into wallet database");
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO invalid_notes (coin, public, note) VALUES (?1, ?2, ?3);",
+        )
+        .bind(serialize(coin))
+        .bind(serialize(public))
+        .bind(serialize(note))
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_invalid_notes(&self) -> Result<Vec<InvalidNote>> {
+        debug!("Finding invalid notes");
+
+        let mut conn = self.conn.acquire().await?;
+        let rows = sqlx::query("SELECT * FROM invalid_notes;").fetch_all(&mut conn).await?;
+
+        let mut notes = vec![];
+        for row in rows {
+            let coin = deserialize(row.get("coin"))?;
+            let public = deserialize(row.get("public"))?;
+            let note = deserialize(row.get("note"))?;
+            notes.push(InvalidNote { coin, public, note });
+        }
+
+        Ok(notes)
+    }
+
+    /// Get the coins of all our coins and viewed coins, spent or not.
+    pub async fn get_all_coins(&self) -> Result<Vec<Coin>> {
+        debug!("Finding all own coins");
+
+        let mut conn = self.conn.acquire().await?;
+        let rows = sqlx::query("SELECT coin FROM coins;").fetch_all(&mut conn).await?;
+
+        let mut coins = vec![];
+        for row in rows {
+            coins.push(deserialize(row.get("coin"))?);
+        }
+
+        let rows = sqlx::query("SELECT coin FROM viewed_coins;").fetch_all(&mut conn).await?;
+        for row in rows {
+            let coin = deserialize(row.get("coin"))?;
+            if !coins.contains(&coin) {
+                coins.push(coin);
+            }
+        }
+
+        Ok(coins)
+    }
+
+    pub async fn get_own_coin_by_nullifier(
+        &self,
+        nullifier: &Nullifier,
+    ) -> Result<Option<OwnCoin>> {
+        debug!("Finding own coin by nullifier");
+
+        let mut conn = self.conn.acquire().await?;
+        let row = sqlx::query("SELECT * FROM coins WHERE nullifier = ?1;")
+            .bind(serialize(nullifier))
+            .fetch_optional(&mut conn)
+            .await?;
+
+        let row = match row {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let coin = deserialize(row.get("coin"))?;
+
+        // Note
+        let serial = deserialize(row.get("serial"))?;
+        let coin_blind = deserialize(row.get("coin_blind"))?;
+        let value_blind = deserialize(row.get("valcom_blind"))?;
+        let value = deserialize(row.get("value"))?;
+        let token_id = deserialize(row.get("token_id"))?;
+        let token_blind = deserialize(row.get("token_blind"))?;
+        let memo = deserialize(row.get("memo"))?;
+        let note = Note { serial, value, token_id, coin_blind, value_blind, token_blind, memo };
+
+        let secret = deserialize(row.get("secret"))?;
+        let nullifier = deserialize(row.get("nullifier"))?;
+        let leaf_position = deserialize(row.get("leaf_position"))?;
+
+        Ok(Some(OwnCoin { coin, note, secret, nullifier, leaf_position }))
+    }
+
+    /// Mark the coin with the given nullifier as spent, if it's ours.
+    pub async fn confirm_spend_nullifier(&self, nullifier: &Nullifier) -> Result<()> {
+        debug!("Confirm spend nullifier");
+        let is_spent = 1;
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query("UPDATE coins SET is_spent = ?1 WHERE nullifier = ?2;")
+            .bind(is_spent)
+            .bind(serialize(nullifier))
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn put_view_key(&self, view_key: &ViewKey) -> Result<()> {
+        debug!("Writing view key into the wallet database");
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query("INSERT OR REPLACE INTO view_keys (public, secret) VALUES (?1, ?2);")
+            .bind(serialize(&view_key.public))
+            .bind(serialize(&view_key.secret))
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_view_keys(&self) -> Result<Vec<ViewKey>> {
+        debug!("Returning view keys");
+
+        let mut conn = self.conn.acquire().await?;
+        let rows = sqlx::query("SELECT * FROM view_keys;").fetch_all(&mut conn).await?;
+
+        let mut view_keys = vec![];
+        for row in rows {
+            let secret = deserialize(row.get("secret"))?;
+            let public = deserialize(row.get("public"))?;
+            view_keys.push(ViewKey { secret, public });
+        }
+
+        Ok(view_keys)
+    }
+
+    pub async fn put_viewed_coin(&self, viewed_coin: &ViewedCoin) -> Result<()> {
+        debug!("Putting viewed coin into wallet database");
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO viewed_coins
+             (coin, public, note, slot, leaf_position, is_spent)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+        )
+        .bind(serialize(&viewed_coin.coin))
+        .bind(serialize(&viewed_coin.public))
+        .bind(serialize(&viewed_coin.note))
+        .bind(viewed_coin.slot as i64)
+        .bind(serialize(&viewed_coin.leaf_position))
+        .bind(viewed_coin.is_spent)
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_viewed_coins(&self) -> Result<Vec<ViewedCoin>> {
+        debug!("Finding viewed coins");
+
+        let mut conn = self.conn.acquire().await?;
+        let rows = sqlx::query("SELECT * FROM viewed_coins;").fetch_all(&mut conn).await?;
+
+        let mut viewed_coins = vec![];
+        for row in rows {
+            let coin = deserialize(row.get("coin"))?;
+            let public = deserialize(row.get("public"))?;
+            let note = deserialize(row.get("note"))?;
+            let slot: i64 = row.get("slot");
+            let leaf_position = deserialize(row.get("leaf_position"))?;
+            let is_spent: bool = row.get("is_spent");
+            viewed_coins.push(ViewedCoin {
+                coin,
+                public,
+                note,
+                slot: slot as u64,
+                leaf_position,
+                is_spent,
+            });
+        }
+
+        Ok(viewed_coins)
+    }
+
+    /// Record that a transaction with the given output spends a viewed coin.
+    /// The coin is reserved for that transaction until it is reverted, see
+    /// [`WalletDb::revert_viewed_spends`].
+    pub async fn put_viewed_spend(&self, coin: &Coin, output: &Coin) -> Result<()> {
+        debug!("Putting viewed spend into wallet database");
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO viewed_spends (coin, output, slot, is_reverted)
+             VALUES (?1, ?2, NULL, 0);",
+        )
+        .bind(serialize(coin))
+        .bind(serialize(output))
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the viewed coins reserved by a transaction which isn't reverted,
+    /// see [`WalletDb::put_viewed_spend`].
+    pub async fn get_reserved_viewed_coins(&self) -> Result<Vec<Coin>> {
+        debug!("Finding reserved viewed coins");
+
+        let mut conn = self.conn.acquire().await?;
+        let rows = sqlx::query("SELECT DISTINCT coin FROM viewed_spends WHERE is_reverted = 0;")
+            .fetch_all(&mut conn)
+            .await?;
+
+        let mut coins = vec![];
+        for row in rows {
+            let coin = row.get("coin");
+            coins.push(deserialize(coin)?);
+        }
+
+        Ok(coins)
+    }
+
+    /// Release the viewed coins reserved by transactions which weren't
+    /// finalized within `revert_after` slots. Like pending transactions, a
+    /// viewed spend is given the last scanned slot `height` when first seen
+    /// here. The spends are kept, so a late transaction still marks its
+    /// coins as spent once finalized.
+    pub async fn revert_viewed_spends(&self, height: u64, revert_after: u64) -> Result<()> {
+        debug!("Reverting viewed spends older than {} slots", revert_after);
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query("UPDATE viewed_spends SET slot = ?1 WHERE slot IS NULL;")
+            .bind(height as i64)
+            .execute(&mut conn)
+            .await?;
+
+        sqlx::query(
+            "UPDATE viewed_spends SET is_reverted = 1
+             WHERE is_reverted = 0 AND slot + ?2 <= ?1;",
+        )
+        .bind(height as i64)
+        .bind(revert_after as i64)
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Mark the viewed coins spent by the transaction with the given output
+    /// as spent, see [`WalletDb::put_viewed_spend`].
+    pub async fn confirm_viewed_spend(&self, output: &Coin) -> Result<()> {
+        debug!("Confirm viewed spend");
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query(
+            "UPDATE viewed_coins SET is_spent = 1
+             WHERE coin IN (SELECT coin FROM viewed_spends WHERE output = ?1);",
+        )
+        .bind(serialize(output))
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the slot of the last block scanned by the wallet, if any.
+    pub async fn get_scan_height(&self) -> Result<Option<u64>> {
+        debug!("Getting scan height");
+        let mut conn = self.conn.acquire().await?;
+
+        let row = sqlx::query("SELECT height FROM scan_state;").fetch_optional(&mut conn).await?;
+        Ok(row.map(|x| x.get::<i64, _>("height") as u64))
+    }
+
+    pub async fn put_scan_height(&self, height: u64) -> Result<()> {
+        debug!("Putting scan height {}", height);
+        let mut conn = self.conn.acquire().await?;
+
+        sqlx::query("DELETE FROM scan_state;").execute(&mut conn).await?;
+        sqlx::query("INSERT INTO scan_state (height) VALUES (?1);")
+            .bind(height as i64)
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Insert or replace a transaction in the history, and link its coins.
+    pub async fn put_tx_record(&self, record: &TxRecord) -> Result<()> {
+        debug!("Putting transaction {} into wallet history", record.tx_hash.to_hex());
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO transactions
+             (tx_hash, direction, value, token_id, counterparties, slot, memo, status, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);",
+        )
+        .bind(serialize(&record.tx_hash))
+        .bind(record.direction as i64)
+        .bind(serialize(&record.value))
+        .bind(serialize(&record.token_id))
+        .bind(serialize(&record.counterparties))
+        .bind(record.slot.map(|x| x as i64))
+        .bind(serialize(&record.memo))
+        .bind(record.status as i64)
+        .bind(record.timestamp.0)
+        .execute(&mut conn)
+        .await?;
+
+        for (coins, is_spent) in [(&record.spent_coins, true), (&record.received_coins, false)] {
+            for coin in coins {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO tx_coins (tx_hash, coin, is_spent) VALUES (?1, ?2, ?3);",
+                )
+                .bind(serialize(&record.tx_hash))
+                .bind(serialize(coin))
+                .bind(is_spent)
+                .execute(&mut conn)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_tx_record(&self, tx_hash: &blake3::Hash) -> Result<Option<TxRecord>> {
+        debug!("Finding transaction {} in wallet history", tx_hash.to_hex());
+
+        let mut conn = self.conn.acquire().await?;
+        let row = sqlx::query("SELECT * FROM transactions WHERE tx_hash = ?1;")
+            .bind(serialize(tx_hash))
+            .fetch_optional(&mut conn)
+            .await?;
+        drop(conn);
+
+        match row {
+            Some(row) => Ok(Some(self.tx_record_from_row(row).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the wallet's transaction history, most recent first.
+    pub async fn get_tx_history(&self) -> Result<Vec<TxRecord>> {
+        debug!("Returning wallet history");
+
+        let mut conn = self.conn.acquire().await?;
+        let rows = sqlx::query("SELECT * FROM transactions ORDER BY timestamp DESC;")
+            .fetch_all(&mut conn)
+            .await?;
+        drop(conn);
+
+        let mut records = vec![];
+        for row in rows {
+            records.push(self.tx_record_from_row(row).await?);
+        }
+
+        Ok(records)
+    }
+
+    /// Mark a transaction of the history as confirmed in the given slot.
+    pub async fn confirm_tx(&self, tx_hash: &blake3::Hash, slot: u64) -> Result<()> {
+        debug!("Confirm transaction {} in slot {}", tx_hash.to_hex(), slot);
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query("UPDATE transactions SET status = ?1, slot = ?2 WHERE tx_hash = ?3;")
+            .bind(TxStatus::Confirmed as i64)
+            .bind(slot as i64)
+            .bind(serialize(tx_hash))
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn put_pending_tx(&self, tx_hash: &blake3::Hash, tx: &[u8]) -> Result<()> {
+        debug!("Putting pending transaction {} into wallet database", tx_hash.to_hex());
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO pending_txs (tx_hash, tx, slot) VALUES (?1, ?2, NULL);",
+        )
+        .bind(serialize(tx_hash))
+        .bind(tx)
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_pending_txs(&self) -> Result<Vec<PendingTx>> {
+        debug!("Returning pending transactions");
+
+        let mut conn = self.conn.acquire().await?;
+        let rows = sqlx::query("SELECT * FROM pending_txs;").fetch_all(&mut conn).await?;
+
+        let mut pending_txs = vec![];
+        for row in rows {
+            let tx_hash = deserialize(row.get("tx_hash"))?;
+            let tx = row.get("tx");
+            let slot: Option<i64> = row.get("slot");
+            pending_txs.push(PendingTx { tx_hash, tx, slot: slot.map(|x| x as u64) });
+        }
+
+        Ok(pending_txs)
+    }
+
+    pub async fn set_pending_tx_slot(&self, tx_hash: &blake3::Hash, slot: u64) -> Result<()> {
+        debug!("Set pending transaction {} slot to {}", tx_hash.to_hex(), slot);
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query("UPDATE pending_txs SET slot = ?1 WHERE tx_hash = ?2;")
+            .bind(slot as i64)
+            .bind(serialize(tx_hash))
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_pending_tx(&self, tx_hash: &blake3::Hash) -> Result<()> {
+        debug!("Removing pending transaction {}", tx_hash.to_hex());
+
+        let mut conn = self.conn.acquire().await?;
+        sqlx::query("DELETE FROM pending_txs WHERE tx_hash = ?1;")
+            .bind(serialize(tx_hash))
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Revert a pending transaction which didn't get finalized: its history
+    /// record is marked as reverted, and the coins it spent become spendable
+    /// again, unless `is_spent` tells their nullifier was seen elsewhere,
+    /// e.g. in a finalized block or in the mempool.
+    pub async fn revert_pending_tx(
+        &self,
+        tx_hash: &blake3::Hash,
+        is_spent: &(dyn Fn(&Nullifier) -> Result<bool> + Sync),
+    ) -> Result<()> {
+        debug!("Reverting pending transaction {}", tx_hash.to_hex());
+
+        if let Some(record) = self.get_tx_record(tx_hash).await? {
+            for coin in &record.spent_coins {
+                if let Some(nullifier) = self.get_coin_nullifier(coin).await? {
+                    if is_spent(&nullifier)? {
+                        debug!("Coin {:?} was spent elsewhere, not reverting it", coin);
+                        continue
+                    }
+                }
+
+                self.revert_spend_coin(coin).await?;
+            }
+
+            let mut conn = self.conn.acquire().await?;
+            sqlx::query("UPDATE transactions SET status = ?1 WHERE tx_hash = ?2;")
+                .bind(TxStatus::Reverted as i64)
+                .bind(serialize(tx_hash))
+                .execute(&mut conn)
+                .await?;
+        }
+
+        self.remove_pending_tx(tx_hash).await
+    }
+
+    /// Nullifier of one of our own coins.
+    async fn get_coin_nullifier(&self, coin: &Coin) -> Result<Option<Nullifier>> {
+        let mut conn = self.conn.acquire().await?;
+        let row = sqlx::query("SELECT nullifier FROM coins WHERE coin = ?1;")
+            .bind(serialize(coin))
+            .fetch_optional(&mut conn)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(deserialize(row.get("nullifier"))?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn tx_record_from_row(&self, row: sqlx::sqlite::SqliteRow) -> Result<TxRecord> {
+        let tx_hash: blake3::Hash = deserialize(row.get("tx_hash"))?;
+        let slot: Option<i64> = row.get("slot");
+
+        let mut conn = self.conn.acquire().await?;
+        let coin_rows = sqlx::query("SELECT coin, is_spent FROM tx_coins WHERE tx_hash = ?1;")
+            .bind(serialize(&tx_hash))
+            .fetch_all(&mut conn)
+            .await?;
+
+        let mut spent_coins = vec![];
+        let mut received_coins = vec![];
+        for coin_row in coin_rows {
+            let coin = deserialize(coin_row.get("coin"))?;
+            match coin_row.get("is_spent") {
+                true => spent_coins.push(coin),
+                false => received_coins.push(coin),
+            }
+        }
+
+        Ok(TxRecord {
+            tx_hash,
+            direction: TxDirection::try_from(row.get::<i64, _>("direction"))?,
+            value: deserialize(row.get("value"))?,
+            token_id: deserialize(row.get("token_id"))?,
+            counterparties: deserialize(row.get("counterparties"))?,
+            slot: slot.map(|x| x as u64),
+            memo: deserialize(row.get("memo"))?,
+            status: TxStatus::try_from(row.get::<i64, _>("status"))?,
+            timestamp: Timestamp(row.get("timestamp")),
+            spent_coins,
+            received_coins,
+        })
+    }
+
     pub async fn remove_own_coins(&self) -> Result<()> {
         debug!("Removing own coins from wallet database");
         let mut conn = self.conn.acquire().await?;
@@ -546,6 +1256,38 @@ mod tests {
         OwnCoin { coin, note, secret: *s, nullifier, leaf_position }
     }
 
+    #[async_std::test]
+    async fn test_walletdb_migrate() -> Result<()> {
+        let wallet = WalletDb::new("sqlite::memory:", WPASS).await?;
+
+        // A viewed coin and scan height of a wallet from before versioning
+        let mut conn = wallet.conn.acquire().await?;
+        sqlx::query(
+            "CREATE TABLE viewed_coins(coin BLOB PRIMARY KEY NOT NULL, public BLOB NOT NULL,
+             note BLOB NOT NULL, slot INTEGER NOT NULL);",
+        )
+        .execute(&mut conn)
+        .await?;
+        sqlx::query("INSERT INTO viewed_coins VALUES (x'00', x'00', x'00', 1);")
+            .execute(&mut conn)
+            .await?;
+        sqlx::query("CREATE TABLE scan_state(height INTEGER NOT NULL);").execute(&mut conn).await?;
+        sqlx::query("INSERT INTO scan_state VALUES (12);").execute(&mut conn).await?;
+        drop(conn);
+
+        // The wallet is scanned again for its viewed coins
+        wallet.init_db().await?;
+        assert!(wallet.get_viewed_coins().await?.is_empty());
+        assert_eq!(wallet.get_scan_height().await?, None);
+
+        // Up to date wallets keep their state
+        wallet.put_scan_height(12).await?;
+        wallet.init_db().await?;
+        assert_eq!(wallet.get_scan_height().await?, Some(12));
+
+        Ok(())
+    }
+
     #[async_std::test]
     async fn test_walletdb() -> Result<()> {
         let wallet = WalletDb::new("sqlite::memory:", WPASS).await?;
@@ -644,6 +1386,141 @@ mod tests {
         let root3 = tree3.root(0).unwrap();
         assert_eq!(root2, root3);
 
+        // confirm_spend_nullifier()
+        wallet.confirm_spend_nullifier(&c1.nullifier).await?;
+        assert_eq!(wallet.get_own_coins().await?.len(), 3);
+        assert_eq!(wallet.get_all_coins().await?.len(), 4);
+
+        // put_view_key()
+        let view_key = ViewKey::from(Keypair::random(&mut OsRng));
+        wallet.put_view_key(&view_key).await?;
+        assert_eq!(wallet.get_view_keys().await?, vec![view_key]);
+
+        // put_viewed_coin()
+        let c5 = dummy_coin(&view_key.secret, 3, &token_id);
+        let mut viewed_coin = ViewedCoin {
+            coin: c5.coin,
+            public: view_key.public,
+            note: c5.note.clone(),
+            slot: 7,
+            leaf_position: c5.leaf_position,
+            is_spent: false,
+        };
+        wallet.put_viewed_coin(&viewed_coin).await?;
+        assert_eq!(wallet.get_viewed_coins().await?, vec![viewed_coin.clone()]);
+        assert_eq!(wallet.get_all_coins().await?.len(), 5);
+
+        // put_viewed_spend()
+        let output = Coin(pallas::Base::random(&mut OsRng));
+        wallet.put_viewed_spend(&c5.coin, &output).await?;
+        assert_eq!(wallet.get_reserved_viewed_coins().await?, vec![c5.coin]);
+
+        // revert_viewed_spends()
+        wallet.revert_viewed_spends(20, 5).await?;
+        wallet.revert_viewed_spends(24, 5).await?;
+        assert_eq!(wallet.get_reserved_viewed_coins().await?, vec![c5.coin]);
+        wallet.revert_viewed_spends(25, 5).await?;
+        assert!(wallet.get_reserved_viewed_coins().await?.is_empty());
+
+        // confirm_viewed_spend()
+        wallet.confirm_viewed_spend(&c5.coin).await?;
+        wallet.confirm_spend_nullifier(&c5.nullifier).await?;
+        assert_eq!(wallet.get_viewed_coins().await?, vec![viewed_coin.clone()]);
+        wallet.confirm_viewed_spend(&output).await?;
+        viewed_coin.is_spent = true;
+        assert_eq!(wallet.get_viewed_coins().await?, vec![viewed_coin]);
+
+        // put_scan_height()
+        assert_eq!(wallet.get_scan_height().await?, None);
+        wallet.put_scan_height(10).await?;
+        wallet.put_scan_height(12).await?;
+        assert_eq!(wallet.get_scan_height().await?, Some(12));
+
+        // get_own_coin_by_nullifier()
+        assert_eq!(wallet.get_own_coin_by_nullifier(&c3.nullifier).await?, Some(c3.clone()));
+        let nullifier = Nullifier::from(pallas::Base::random(&mut OsRng));
+        assert_eq!(wallet.get_own_coin_by_nullifier(&nullifier).await?, None);
+
+        // put_tx_record()
+        let sent = TxRecord {
+            tx_hash: blake3::hash(b"sent"),
+            direction: TxDirection::Sent,
+            value: 400,
+            token_id,
+            counterparties: vec![keypair2.public],
+            slot: None,
+            memo: vec![],
+            status: TxStatus::Pending,
+            timestamp: Timestamp(1),
+            spent_coins: vec![c1.coin],
+            received_coins: vec![c3.coin],
+        };
+        let received = TxRecord {
+            tx_hash: blake3::hash(b"received"),
+            direction: TxDirection::Received,
+            value: 42,
+            token_id,
+            counterparties: vec![],
+            slot: Some(3),
+            memo: b"hello".to_vec(),
+            status: TxStatus::Confirmed,
+            timestamp: Timestamp(2),
+            spent_coins: vec![],
+            received_coins: vec![c2.coin],
+        };
+        wallet.put_tx_record(&sent).await?;
+        wallet.put_tx_record(&received).await?;
+        assert_eq!(wallet.get_tx_record(&sent.tx_hash).await?, Some(sent.clone()));
+        assert_eq!(wallet.get_tx_history().await?, vec![received.clone(), sent.clone()]);
+
+        // confirm_tx()
+        wallet.confirm_tx(&sent.tx_hash, 5).await?;
+        let confirmed = wallet.get_tx_record(&sent.tx_hash).await?.unwrap();
+        assert_eq!(confirmed.status, TxStatus::Confirmed);
+        assert_eq!(confirmed.slot, Some(5));
+        assert_eq!(confirmed.spent_coins, vec![c1.coin]);
+
+        // put_pending_tx()
+        wallet.put_pending_tx(&sent.tx_hash, b"tx").await?;
+        let pending = PendingTx { tx_hash: sent.tx_hash, tx: b"tx".to_vec(), slot: None };
+        assert_eq!(wallet.get_pending_txs().await?, vec![pending]);
+
+        // set_pending_tx_slot()
+        wallet.set_pending_tx_slot(&sent.tx_hash, 6).await?;
+        assert_eq!(wallet.get_pending_txs().await?[0].slot, Some(6));
+
+        // revert_pending_tx()
+        wallet.revert_pending_tx(&sent.tx_hash, &|_| Ok(false)).await?;
+        assert!(wallet.get_pending_txs().await?.is_empty());
+        assert_eq!(wallet.get_own_coins().await?.len(), 4);
+        let reverted = wallet.get_tx_record(&sent.tx_hash).await?.unwrap();
+        assert_eq!(reverted.status, TxStatus::Reverted);
+
+        // Coins whose nullifier was seen elsewhere stay spent
+        let stale = TxRecord {
+            tx_hash: blake3::hash(b"stale"),
+            spent_coins: vec![c2.coin],
+            received_coins: vec![],
+            ..sent.clone()
+        };
+        wallet.put_tx_record(&stale).await?;
+        wallet.put_pending_tx(&stale.tx_hash, b"tx").await?;
+        wallet.confirm_spend_coin(&c2.coin).await?;
+        wallet.revert_pending_tx(&stale.tx_hash, &|nf| Ok(*nf == c2.nullifier)).await?;
+        assert!(wallet.get_pending_txs().await?.is_empty());
+        assert_eq!(wallet.get_own_coins().await?.len(), 3);
+        let reverted = wallet.get_tx_record(&stale.tx_hash).await?.unwrap();
+        assert_eq!(reverted.status, TxStatus::Reverted);
+
+        // put_invalid_note()
+        assert!(wallet.get_invalid_notes().await?.is_empty());
+        wallet.put_invalid_note(&c0.coin, &keypair.public, &c0.note).await?;
+        let invalid_notes = wallet.get_invalid_notes().await?;
+        assert_eq!(
+            invalid_notes,
+            vec![InvalidNote { coin: c0.coin, public: keypair.public, note: c0.note }]
+        );
+
         Ok(())
     }
 }
@@ -0,0 +1,197 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter},
+    pasta::pallas,
+    plonk,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Expression, Selector},
+    poly::Rotation,
+};
+
+#[derive(Clone, Debug)]
+pub struct CondSelectConfig {
+    q_select: Selector,
+    cond: Column<Advice>,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    out: Column<Advice>,
+}
+
+/// Chip selecting between two values given a boolean condition,
+/// i.e. `out = cond ? a : b`.
+pub struct CondSelectChip {
+    config: CondSelectConfig,
+}
+
+impl Chip<pallas::Base> for CondSelectChip {
+    type Config = CondSelectConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl CondSelectChip {
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        cond: Column<Advice>,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        out: Column<Advice>,
+    ) -> CondSelectConfig {
+        let q_select = meta.selector();
+
+        meta.create_gate("Conditional selection: out = cond ? a : b", |meta| {
+            let q_select = meta.query_selector(q_select);
+            let cond = meta.query_advice(cond, Rotation::cur());
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+
+            let one = Expression::Constant(pallas::Base::one());
+
+            Constraints::with_selector(
+                q_select,
+                [
+                    ("cond is boolean", cond.clone() * (one - cond.clone())),
+                    ("out = cond * (a - b) + b", cond * (a - b.clone()) + b - out),
+                ],
+            )
+        });
+
+        CondSelectConfig { q_select, cond, a, b, out }
+    }
+
+    pub fn construct(config: CondSelectConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn cond_select(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        cond: &AssignedCell<pallas::Base, pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        b: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, plonk::Error> {
+        layouter.assign_region(
+            || "out = cond ? a : b",
+            |mut region| {
+                self.config.q_select.enable(&mut region, 0)?;
+
+                cond.copy_advice(|| "copy cond", &mut region, self.config.cond, 0)?;
+                a.copy_advice(|| "copy a", &mut region, self.config.a, 0)?;
+                b.copy_advice(|| "copy b", &mut region, self.config.b, 0)?;
+
+                let out = cond.value().zip(a.value()).zip(b.value()).map(|((cond, a), b)| {
+                    if *cond == pallas::Base::one() {
+                        *a
+                    } else {
+                        *b
+                    }
+                });
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zk::assign_free_advice;
+    use halo2_proofs::{
+        circuit::{floor_planner, Value},
+        dev::MockProver,
+        plonk::{Circuit, Instance as InstanceColumn},
+    };
+
+    #[derive(Clone)]
+    struct CondSelectCircuitConfig {
+        primary: Column<InstanceColumn>,
+        advices: [Column<Advice>; 4],
+        cond_select_config: CondSelectConfig,
+    }
+
+    #[derive(Default)]
+    struct CondSelectCircuit {
+        cond: Value<pallas::Base>,
+        a: Value<pallas::Base>,
+        b: Value<pallas::Base>,
+    }
+
+    impl Circuit<pallas::Base> for CondSelectCircuit {
+        type Config = CondSelectCircuitConfig;
+        type FloorPlanner = floor_planner::V1;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+
+            let primary = meta.instance_column();
+            meta.enable_equality(primary);
+
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+
+            let cond_select_config =
+                CondSelectChip::configure(meta, advices[0], advices[1], advices[2], advices[3]);
+
+            CondSelectCircuitConfig { primary, advices, cond_select_config }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let chip = CondSelectChip::construct(config.cond_select_config.clone());
+
+            let cond =
+                assign_free_advice(layouter.namespace(|| "cond"), config.advices[0], self.cond)?;
+            let a = assign_free_advice(layouter.namespace(|| "a"), config.advices[1], self.a)?;
+            let b = assign_free_advice(layouter.namespace(|| "b"), config.advices[2], self.b)?;
+
+            let out = chip.cond_select(layouter.namespace(|| "cond_select"), &cond, &a, &b)?;
+            layouter.constrain_instance(out.cell(), config.primary, 0)?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn cond_select() {
+        let a = pallas::Base::from(42);
+        let b = pallas::Base::from(69);
+
+        for (cond, expected) in [(1, a), (0, b)] {
+            let circuit = CondSelectCircuit {
+                cond: Value::known(pallas::Base::from(cond)),
+                a: Value::known(a),
+                b: Value::known(b),
+            };
+            let prover = MockProver::run(4, &circuit, vec![vec![expected]]).unwrap();
+            prover.assert_satisfied();
+        }
+
+        // A non-boolean condition must fail
+        let circuit = CondSelectCircuit {
+            cond: Value::known(pallas::Base::from(2)),
+            a: Value::known(a),
+            b: Value::known(b),
+        };
+        let prover = MockProver::run(4, &circuit, vec![vec![b]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
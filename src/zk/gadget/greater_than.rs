@@ -0,0 +1,257 @@
+use std::cmp::Ordering;
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter},
+    pasta::{group::ff::PrimeField, pallas},
+    plonk,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Expression, Selector},
+    poly::Rotation,
+};
+
+use super::native_range_check::{NativeRangeCheckChip, NativeRangeCheckConfig};
+
+#[derive(Clone, Debug)]
+pub struct GreaterThanConfig<
+    const WINDOW_SIZE: usize,
+    const NUM_OF_BITS: usize,
+    const NUM_OF_WINDOWS: usize,
+> {
+    q_gt: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    gt: Column<Advice>,
+    diff: Column<Advice>,
+    range_diff_config: NativeRangeCheckConfig<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>,
+}
+
+/// Chip comparing two field elements, outputting `1` if `a > b` and `0`
+/// otherwise. Both `a` and `b` must be smaller than `2^NUM_OF_BITS`, and
+/// `NUM_OF_BITS` must be smaller than the field size in bits.
+///
+/// The output `gt` is constrained to be boolean, and the difference
+/// `gt ? a - b - 1 : b - a` is range checked to `NUM_OF_BITS`, which only
+/// holds for the correct `gt`. The range check is done with an existing
+/// [`NativeRangeCheckConfig`], so the chip doesn't allocate any columns.
+pub struct GreaterThanChip<
+    const WINDOW_SIZE: usize,
+    const NUM_OF_BITS: usize,
+    const NUM_OF_WINDOWS: usize,
+> {
+    config: GreaterThanConfig<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>,
+}
+
+impl<const WINDOW_SIZE: usize, const NUM_OF_BITS: usize, const NUM_OF_WINDOWS: usize>
+    Chip<pallas::Base> for GreaterThanChip<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>
+{
+    type Config = GreaterThanConfig<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<const WINDOW_SIZE: usize, const NUM_OF_BITS: usize, const NUM_OF_WINDOWS: usize>
+    GreaterThanChip<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>
+{
+    pub fn construct(config: GreaterThanConfig<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        gt: Column<Advice>,
+        diff: Column<Advice>,
+        range_diff_config: NativeRangeCheckConfig<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>,
+    ) -> GreaterThanConfig<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS> {
+        let q_gt = meta.selector();
+
+        meta.enable_equality(a);
+        meta.enable_equality(b);
+        meta.enable_equality(gt);
+        meta.enable_equality(diff);
+
+        meta.create_gate("a > b", |meta| {
+            let q_gt = meta.query_selector(q_gt);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let gt = meta.query_advice(gt, Rotation::cur());
+            let diff = meta.query_advice(diff, Rotation::cur());
+
+            let one = Expression::Constant(pallas::Base::one());
+            let expected = gt.clone() * (a.clone() - b.clone() - one.clone()) +
+                (one.clone() - gt.clone()) * (b - a);
+
+            Constraints::with_selector(
+                q_gt,
+                [("gt is boolean", gt.clone() * (one - gt)), ("diff", expected - diff)],
+            )
+        });
+
+        GreaterThanConfig { q_gt, a, b, gt, diff, range_diff_config }
+    }
+
+    pub fn greater_than(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        b: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, plonk::Error> {
+        let (gt, diff) = layouter.assign_region(
+            || "a > b",
+            |mut region| {
+                self.config.q_gt.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "copy a", &mut region, self.config.a, 0)?;
+                b.copy_advice(|| "copy b", &mut region, self.config.b, 0)?;
+
+                let is_gt = a.value().zip(b.value()).map(|(a, b)| cmp(a, b) == Ordering::Greater);
+
+                let gt_val =
+                    is_gt.map(
+                        |is_gt| if is_gt { pallas::Base::one() } else { pallas::Base::zero() },
+                    );
+                let gt = region.assign_advice(|| "gt", self.config.gt, 0, || gt_val)?;
+
+                let diff_val = is_gt.zip(a.value()).zip(b.value()).map(|((is_gt, a), b)| {
+                    if is_gt {
+                        a - b - pallas::Base::one()
+                    } else {
+                        b - a
+                    }
+                });
+                let diff = region.assign_advice(|| "diff", self.config.diff, 0, || diff_val)?;
+
+                Ok((gt, diff))
+            },
+        )?;
+
+        let range_diff_chip =
+            NativeRangeCheckChip::<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>::construct(
+                self.config.range_diff_config.clone(),
+            );
+        range_diff_chip.copy_range_check(layouter.namespace(|| "diff range check"), diff, true)?;
+
+        Ok(gt)
+    }
+}
+
+/// Compare two field elements as integers.
+fn cmp(a: &pallas::Base, b: &pallas::Base) -> Ordering {
+    // The representation is little-endian
+    a.to_repr().iter().rev().cmp(b.to_repr().iter().rev())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zk::assign_free_advice;
+    use halo2_proofs::{
+        circuit::{floor_planner, Value},
+        dev::MockProver,
+        plonk::{Circuit, Instance as InstanceColumn},
+    };
+
+    const WINDOW_SIZE: usize = 3;
+    const NUM_OF_BITS: usize = 253;
+    const NUM_OF_WINDOWS: usize = 85;
+
+    #[derive(Clone)]
+    struct GreaterThanCircuitConfig {
+        primary: Column<InstanceColumn>,
+        advices: [Column<Advice>; 4],
+        gt_config: GreaterThanConfig<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>,
+    }
+
+    #[derive(Default)]
+    struct GreaterThanCircuit {
+        a: Value<pallas::Base>,
+        b: Value<pallas::Base>,
+    }
+
+    impl Circuit<pallas::Base> for GreaterThanCircuit {
+        type Config = GreaterThanCircuitConfig;
+        type FloorPlanner = floor_planner::V1;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+
+            let primary = meta.instance_column();
+            meta.enable_equality(primary);
+
+            let k_values_table = meta.lookup_table_column();
+            let range_config =
+                NativeRangeCheckChip::<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>::configure(
+                    meta,
+                    advices[3],
+                    k_values_table,
+                );
+            let gt_config = GreaterThanChip::configure(
+                meta,
+                advices[0],
+                advices[1],
+                advices[2],
+                advices[3],
+                range_config,
+            );
+
+            GreaterThanCircuitConfig { primary, advices, gt_config }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            NativeRangeCheckChip::<WINDOW_SIZE, NUM_OF_BITS, NUM_OF_WINDOWS>::load_k_table(
+                &mut layouter,
+                config.gt_config.range_diff_config.k_values_table,
+            )?;
+
+            let chip = GreaterThanChip::construct(config.gt_config.clone());
+
+            let a = assign_free_advice(layouter.namespace(|| "a"), config.advices[0], self.a)?;
+            let b = assign_free_advice(layouter.namespace(|| "b"), config.advices[1], self.b)?;
+
+            let gt = chip.greater_than(layouter.namespace(|| "a > b"), &a, &b)?;
+            layouter.constrain_instance(gt.cell(), config.primary, 0)?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn greater_than() {
+        let cases: [(u64, u64, u64); 4] =
+            [(69, 42, 1), (42, 69, 0), (42, 42, 0), (u64::MAX, u64::MAX - 1, 1)];
+
+        for (a, b, expected) in cases {
+            let circuit = GreaterThanCircuit {
+                a: Value::known(pallas::Base::from(a)),
+                b: Value::known(pallas::Base::from(b)),
+            };
+            let public = vec![pallas::Base::from(expected)];
+            let prover = MockProver::run(11, &circuit, vec![public]).unwrap();
+            prover.assert_satisfied();
+
+            let public = vec![pallas::Base::from(1 - expected)];
+            let prover = MockProver::run(11, &circuit, vec![public]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+}
@@ -12,3 +12,12 @@ pub mod less_than;
 
 /// is_zero comparison gadget
 pub mod is_zero;
+
+/// Field-native greater than comparison gadget, outputting a boolean
+pub mod greater_than;
+
+/// Conditional selection gadget
+pub mod cond_select;
+
+/// Zero condition gadget, outputting zero if a value is zero
+pub mod zero_cond;
@@ -0,0 +1,188 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter},
+    pasta::pallas,
+    plonk,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Expression, Selector},
+    poly::Rotation,
+};
+
+use super::is_zero::{IsZeroChip, IsZeroConfig};
+
+#[derive(Clone, Debug)]
+pub struct ZeroCondConfig {
+    q_zero_cond: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    out: Column<Advice>,
+    is_zero: IsZeroConfig<pallas::Base>,
+}
+
+/// Chip outputting zero if `a` is zero, and `b` otherwise,
+/// i.e. `out = a == 0 ? 0 : b`.
+pub struct ZeroCondChip {
+    config: ZeroCondConfig,
+}
+
+impl Chip<pallas::Base> for ZeroCondChip {
+    type Config = ZeroCondConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl ZeroCondChip {
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        a_inv: Column<Advice>,
+        out: Column<Advice>,
+    ) -> ZeroCondConfig {
+        let q_zero_cond = meta.selector();
+
+        let is_zero = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_zero_cond),
+            |meta| meta.query_advice(a, Rotation::cur()),
+            a_inv,
+        );
+
+        meta.create_gate("Zero condition: out = a == 0 ? 0 : b", |meta| {
+            let q_zero_cond = meta.query_selector(q_zero_cond);
+            let b = meta.query_advice(b, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+
+            let one = Expression::Constant(pallas::Base::one());
+
+            Constraints::with_selector(q_zero_cond, Some((one - is_zero.expr()) * b - out))
+        });
+
+        ZeroCondConfig { q_zero_cond, a, b, out, is_zero }
+    }
+
+    pub fn construct(config: ZeroCondConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn zero_cond(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        b: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, plonk::Error> {
+        let is_zero_chip = IsZeroChip::construct(self.config.is_zero.clone());
+
+        layouter.assign_region(
+            || "out = a == 0 ? 0 : b",
+            |mut region| {
+                self.config.q_zero_cond.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "copy a", &mut region, self.config.a, 0)?;
+                b.copy_advice(|| "copy b", &mut region, self.config.b, 0)?;
+                is_zero_chip.assign(&mut region, 0, a.value().copied())?;
+
+                let out = a.value().zip(b.value()).map(|(a, b)| {
+                    if *a == pallas::Base::zero() {
+                        pallas::Base::zero()
+                    } else {
+                        *b
+                    }
+                });
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zk::assign_free_advice;
+    use halo2_proofs::{
+        circuit::{floor_planner, Value},
+        dev::MockProver,
+        plonk::{Circuit, Instance as InstanceColumn},
+    };
+
+    #[derive(Clone)]
+    struct ZeroCondCircuitConfig {
+        primary: Column<InstanceColumn>,
+        advices: [Column<Advice>; 4],
+        zero_cond_config: ZeroCondConfig,
+    }
+
+    #[derive(Default)]
+    struct ZeroCondCircuit {
+        a: Value<pallas::Base>,
+        b: Value<pallas::Base>,
+    }
+
+    impl Circuit<pallas::Base> for ZeroCondCircuit {
+        type Config = ZeroCondCircuitConfig;
+        type FloorPlanner = floor_planner::V1;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+
+            let primary = meta.instance_column();
+            meta.enable_equality(primary);
+
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+
+            let zero_cond_config =
+                ZeroCondChip::configure(meta, advices[0], advices[1], advices[2], advices[3]);
+
+            ZeroCondCircuitConfig { primary, advices, zero_cond_config }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let chip = ZeroCondChip::construct(config.zero_cond_config.clone());
+
+            let a = assign_free_advice(layouter.namespace(|| "a"), config.advices[0], self.a)?;
+            let b = assign_free_advice(layouter.namespace(|| "b"), config.advices[1], self.b)?;
+
+            let out = chip.zero_cond(layouter.namespace(|| "zero_cond"), &a, &b)?;
+            layouter.constrain_instance(out.cell(), config.primary, 0)?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn zero_cond() {
+        let b = pallas::Base::from(69);
+
+        for (a, expected) in [(0, pallas::Base::zero()), (42, b)] {
+            let circuit =
+                ZeroCondCircuit { a: Value::known(pallas::Base::from(a)), b: Value::known(b) };
+            let prover = MockProver::run(4, &circuit, vec![vec![expected]]).unwrap();
+            prover.assert_satisfied();
+        }
+
+        // Claiming the wrong output must fail
+        let circuit = ZeroCondCircuit { a: Value::known(pallas::Base::zero()), b: Value::known(b) };
+        let prover = MockProver::run(4, &circuit, vec![vec![b]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -2,6 +2,10 @@
 pub mod vm;
 pub mod vm_stack;
 
+/// Circuit size measurement
+pub mod stats;
+pub use stats::CircuitStats;
+
 /// ZK circuits
 pub mod circuit;
 
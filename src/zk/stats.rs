@@ -0,0 +1,265 @@
+use std::collections::HashSet;
+
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::Value,
+    pasta::pallas,
+    plonk,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Fixed, FloorPlanner,
+        Instance, Selector,
+    },
+};
+
+use super::{vm::ZkCircuit, vm_stack::empty_witnesses};
+use crate::{
+    zkas::{Opcode, ZkBinary},
+    Result,
+};
+
+/// Rows and advice columns used by a single opcode in the circuit layout.
+#[derive(Clone, Debug)]
+pub struct OpcodeStats {
+    pub opcode: Opcode,
+    /// Number of rows the opcode assigns cells in
+    pub rows: usize,
+    /// Number of advice columns the opcode assigns cells in
+    pub advices: usize,
+    /// Number of copy constraints the opcode adds
+    pub copies: usize,
+}
+
+/// Circuit size of a compiled zkas circuit.
+///
+/// The circuit is laid out by the same floor planner the prover uses,
+/// without any witness values, and the assigned cells are recorded.
+/// Regions using disjoint columns can share rows, so the opcode rows
+/// don't necessarily add up to the rows of the circuit.
+#[derive(Clone, Debug)]
+pub struct CircuitStats {
+    /// Rows used by the lookup tables, global constants and witnesses
+    pub setup_rows: usize,
+    /// Per-opcode statistics, in order of execution
+    pub opcodes: Vec<OpcodeStats>,
+    /// Number of rows used by the circuit
+    pub rows: usize,
+    /// Rows halo2 reserves at the end of the circuit for blinding factors
+    pub reserved_rows: usize,
+    /// Number of advice columns used by the circuit
+    pub advices: usize,
+    /// Number of copy constraints in the circuit
+    pub copies: usize,
+    /// Smallest `k` such that the circuit fits in `2^k` rows
+    pub k: u32,
+}
+
+impl CircuitStats {
+    /// Measure the circuit of the given binary.
+    pub fn measure(zkbin: &ZkBinary) -> Result<Self> {
+        let circuit = ZkCircuit::new(empty_witnesses(zkbin), zkbin.clone());
+
+        let mut cs = ConstraintSystem::<pallas::Base>::default();
+        let config = ZkCircuit::configure(&mut cs);
+        let constants = config.constants();
+
+        let mut layout = LayoutRecorder::default();
+        <ZkCircuit as Circuit<pallas::Base>>::FloorPlanner::synthesize(
+            &mut layout,
+            &circuit,
+            config,
+            constants,
+        )?;
+
+        // Every opcode is synthesized in its own namespace, after the ones
+        // loading the witnesses.
+        if layout.namespaces.len() < zkbin.opcodes.len() {
+            return Err(plonk::Error::Synthesis.into())
+        }
+        let opcode_usage =
+            layout.namespaces.split_off(layout.namespaces.len() - zkbin.opcodes.len());
+
+        let mut setup = layout.root;
+        for usage in &layout.namespaces {
+            setup.merge(usage);
+        }
+
+        let mut total = Usage::default();
+        total.merge(&setup);
+
+        let mut opcodes = Vec::with_capacity(zkbin.opcodes.len());
+        for ((opcode, _), usage) in zkbin.opcodes.iter().zip(opcode_usage.iter()) {
+            opcodes.push(OpcodeStats {
+                opcode: *opcode,
+                rows: usage.rows.len(),
+                advices: usage.advices.len(),
+                copies: usage.copies,
+            });
+            total.merge(usage);
+        }
+
+        let rows = total.rows.iter().max().map_or(0, |x| x + 1);
+
+        // The last usable row is followed by the blinding factors, and
+        // halo2 needs a minimum number of rows for its arguments.
+        let reserved_rows = cs.blinding_factors() + 1;
+        let min_rows = (rows + reserved_rows).max(cs.minimum_rows());
+
+        let mut k = 1;
+        while (1 << k) < min_rows {
+            k += 1;
+        }
+
+        Ok(Self {
+            setup_rows: setup.rows.len(),
+            opcodes,
+            rows,
+            reserved_rows,
+            advices: total.advices.len(),
+            copies: total.copies,
+            k,
+        })
+    }
+}
+
+/// Cells and copy constraints assigned by a part of the circuit
+#[derive(Default)]
+struct Usage {
+    rows: HashSet<usize>,
+    advices: HashSet<Column<Advice>>,
+    copies: usize,
+}
+
+impl Usage {
+    fn merge(&mut self, other: &Usage) {
+        self.rows.extend(&other.rows);
+        self.advices.extend(&other.advices);
+        self.copies += other.copies;
+    }
+}
+
+/// Records the layout of a circuit, attributing assignments to the
+/// top-level namespace they're made in.
+#[derive(Default)]
+struct LayoutRecorder {
+    /// Assignments made outside of any namespace, like the lookup
+    /// tables and the global constants placed by the floor planner
+    root: Usage,
+    /// Assignments made in every top-level namespace, in order
+    namespaces: Vec<Usage>,
+    /// Depth of the current namespace
+    depth: usize,
+}
+
+impl LayoutRecorder {
+    fn usage(&mut self) -> &mut Usage {
+        match self.namespaces.last_mut() {
+            Some(usage) if self.depth > 0 => usage,
+            _ => &mut self.root,
+        }
+    }
+}
+
+impl<F: Field> Assignment<F> for LayoutRecorder {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn exit_region(&mut self) {}
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        _: A,
+        _: &Selector,
+        row: usize,
+    ) -> std::result::Result<(), plonk::Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.usage().rows.insert(row);
+        Ok(())
+    }
+
+    fn query_instance(
+        &self,
+        _: Column<Instance>,
+        _: usize,
+    ) -> std::result::Result<Value<F>, plonk::Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Advice>,
+        row: usize,
+        _: V,
+    ) -> std::result::Result<(), plonk::Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let usage = self.usage();
+        usage.rows.insert(row);
+        usage.advices.insert(column);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        row: usize,
+        _: V,
+    ) -> std::result::Result<(), plonk::Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.usage().rows.insert(row);
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        _: Column<Any>,
+        _: usize,
+        _: Column<Any>,
+        _: usize,
+    ) -> std::result::Result<(), plonk::Error> {
+        self.usage().copies += 1;
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<F>>,
+    ) -> std::result::Result<(), plonk::Error> {
+        // Only pads the lookup tables up to the usable rows
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        if self.depth == 0 {
+            self.namespaces.push(Usage::default());
+        }
+        self.depth += 1;
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {
+        self.depth -= 1;
+    }
+}
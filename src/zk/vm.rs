@@ -25,7 +25,7 @@ use halo2_proofs::{
     circuit::{floor_planner, AssignedCell, Layouter, Value},
     pasta::{group::Curve, pallas, Fp},
     plonk,
-    plonk::{Advice, Circuit, Column, ConstraintSystem, Instance as InstanceColumn},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Fixed, Instance as InstanceColumn},
 };
 use log::{debug, error};
 
@@ -34,9 +34,12 @@ use super::{
     assign_free_advice,
     gadget::{
         arithmetic::{ArithChip, ArithConfig, ArithInstruction},
+        cond_select::{CondSelectChip, CondSelectConfig},
+        greater_than::{GreaterThanChip, GreaterThanConfig},
         less_than::{LessThanChip, LessThanConfig},
         native_range_check::{NativeRangeCheckChip, NativeRangeCheckConfig},
         small_range_check::{SmallRangeCheckChip, SmallRangeCheckConfig},
+        zero_cond::{ZeroCondChip, ZeroCondConfig},
     },
 };
 use crate::zkas::{
@@ -48,6 +51,7 @@ use crate::zkas::{
 pub struct VmConfig {
     primary: Column<InstanceColumn>,
     advices: [Column<Advice>; 10],
+    constants: Column<Fixed>,
     ecc_config: EccConfig<OrchardFixedBases>,
     merkle_cfg1: MerkleConfig<OrchardHashDomains, OrchardCommitDomains, OrchardFixedBases>,
     merkle_cfg2: MerkleConfig<OrchardHashDomains, OrchardCommitDomains, OrchardFixedBases>,
@@ -59,10 +63,18 @@ pub struct VmConfig {
     native_64_range_check_config: NativeRangeCheckConfig<3, 64, 22>,
     native_253_range_check_config: NativeRangeCheckConfig<3, 253, 85>,
     lessthan_config: LessThanConfig<3, 253, 85>,
+    greaterthan_config: GreaterThanConfig<3, 253, 85>,
     boolcheck_config: SmallRangeCheckConfig,
+    condselect_config: CondSelectConfig,
+    zerocond_config: ZeroCondConfig,
 }
 
 impl VmConfig {
+    /// Fixed columns the floor planner places global constants in
+    pub(super) fn constants(&self) -> Vec<Column<Fixed>> {
+        vec![self.constants]
+    }
+
     fn ecc_chip(&self) -> EccChip<OrchardFixedBases> {
         EccChip::construct(self.ecc_config.clone())
     }
@@ -233,13 +245,31 @@ impl Circuit<pallas::Base> for ZkCircuit {
             k_values_table_253,
         );
 
+        let greaterthan_config = GreaterThanChip::<3, 253, 85>::configure(
+            meta,
+            advices[6],
+            advices[7],
+            advices[8],
+            advices[9],
+            native_253_range_check_config.clone(),
+        );
+
         // Configuration for boolean checks, it uses the small_range_check
         // chip with a range of 2, which enforces one bit, i.e. 0 or 1.
         let boolcheck_config = SmallRangeCheckChip::configure(meta, advices[9], 2);
 
+        // Configuration for conditional selection
+        let condselect_config =
+            CondSelectChip::configure(meta, advices[1], advices[2], advices[3], advices[4]);
+
+        // Configuration for zero conditions
+        let zerocond_config =
+            ZeroCondChip::configure(meta, advices[1], advices[2], advices[3], advices[4]);
+
         VmConfig {
             primary,
             advices,
+            constants: lagrange_coeffs[0],
             ecc_config,
             merkle_cfg1,
             merkle_cfg2,
@@ -250,7 +280,10 @@ impl Circuit<pallas::Base> for ZkCircuit {
             native_64_range_check_config,
             native_253_range_check_config,
             lessthan_config,
+            greaterthan_config,
             boolcheck_config,
+            condselect_config,
+            zerocond_config,
         }
     }
 
@@ -295,6 +328,8 @@ impl Circuit<pallas::Base> for ZkCircuit {
             config.native_253_range_check_config.clone(),
         );
         let lessthan_chip = LessThanChip::<3, 253, 85>::construct(config.lessthan_config.clone());
+        let greaterthan_chip =
+            GreaterThanChip::<3, 253, 85>::construct(config.greaterthan_config.clone());
         NativeRangeCheckChip::<3, 253, 85>::load_k_table(
             &mut layouter,
             config.native_253_range_check_config.k_values_table,
@@ -309,6 +344,10 @@ impl Circuit<pallas::Base> for ZkCircuit {
         // Construct the boolean check chip.
         let boolcheck_chip = SmallRangeCheckChip::construct(config.boolcheck_config.clone());
 
+        // Construct the conditional selection chips.
+        let condselect_chip = CondSelectChip::construct(config.condselect_config.clone());
+        let zerocond_chip = ZeroCondChip::construct(config.zerocond_config.clone());
+
         // ==========================
         // Constants setup
         // ==========================
@@ -429,7 +468,10 @@ impl Circuit<pallas::Base> for ZkCircuit {
         // And now, work through opcodes
         // =============================
         // TODO: Copy constraints
-        for opcode in &self.opcodes {
+        // Every opcode is synthesized in its own namespace, so its part of
+        // the layout can be told apart when measuring the circuit.
+        for (i, opcode) in self.opcodes.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("{}: {:?}", i, opcode.0));
             match opcode.0 {
                 Opcode::EcAdd => {
                     debug!("Executing `EcAdd{:?}` opcode", opcode.1);
@@ -716,6 +758,79 @@ impl Circuit<pallas::Base> for ZkCircuit {
                         .small_range_check(layouter.namespace(|| "copy boolean check"), w)?;
                 }
 
+                Opcode::GreaterThan => {
+                    debug!("Executing `GreaterThan{:?}` opcode", opcode.1);
+                    let args = &opcode.1;
+
+                    let a = stack[args[0].1].clone().into();
+                    let b = stack[args[1].1].clone().into();
+
+                    let gt =
+                        greaterthan_chip.greater_than(layouter.namespace(|| "a > b"), &a, &b)?;
+
+                    debug!("Pushing comparison result to stack index {}", stack.len());
+                    stack.push(StackVar::Base(gt));
+                }
+
+                Opcode::CondSelect => {
+                    debug!("Executing `CondSelect{:?}` opcode", opcode.1);
+                    let args = &opcode.1;
+
+                    let cond = stack[args[0].1].clone().into();
+                    let a = stack[args[1].1].clone().into();
+                    let b = stack[args[2].1].clone().into();
+
+                    let out = condselect_chip.cond_select(
+                        layouter.namespace(|| "cond_select()"),
+                        &cond,
+                        &a,
+                        &b,
+                    )?;
+
+                    debug!("Pushing selection to stack index {}", stack.len());
+                    stack.push(StackVar::Base(out));
+                }
+
+                Opcode::ZeroCondSelect => {
+                    debug!("Executing `ZeroCondSelect{:?}` opcode", opcode.1);
+                    let args = &opcode.1;
+
+                    let a = stack[args[0].1].clone().into();
+                    let b = stack[args[1].1].clone().into();
+
+                    let out =
+                        zerocond_chip.zero_cond(layouter.namespace(|| "zero_cond()"), &a, &b)?;
+
+                    debug!("Pushing selection to stack index {}", stack.len());
+                    stack.push(StackVar::Base(out));
+                }
+
+                Opcode::ConstrainEqualBase => {
+                    debug!("Executing `ConstrainEqualBase{:?}` opcode", opcode.1);
+                    let args = &opcode.1;
+
+                    let lhs: AssignedCell<Fp, Fp> = stack[args[0].1].clone().into();
+                    let rhs: AssignedCell<Fp, Fp> = stack[args[1].1].clone().into();
+
+                    layouter.assign_region(
+                        || "constrain_equal_base()",
+                        |mut region| region.constrain_equal(lhs.cell(), rhs.cell()),
+                    )?;
+                }
+
+                Opcode::ConstrainEqualPoint => {
+                    debug!("Executing `ConstrainEqualPoint{:?}` opcode", opcode.1);
+                    let args = &opcode.1;
+
+                    let lhs: Point<pallas::Affine, EccChip<OrchardFixedBases>> =
+                        stack[args[0].1].clone().into();
+
+                    let rhs: Point<pallas::Affine, EccChip<OrchardFixedBases>> =
+                        stack[args[1].1].clone().into();
+
+                    lhs.constrain_equal(layouter.namespace(|| "constrain_equal_point()"), &rhs)?;
+                }
+
                 Opcode::ConstrainInstance => {
                     debug!("Executing `ConstrainInstance{:?}` opcode", opcode.1);
                     let args = &opcode.1;
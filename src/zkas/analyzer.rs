@@ -5,7 +5,7 @@ use std::{
 
 use super::{
     ast::{Arg, Constant, Literal, Statement, StatementType, Var, Variable, Witness},
-    error::ErrorEmitter,
+    error::{Diagnostic, DiagnosticResult, ErrorEmitter},
     Opcode, VarType,
 };
 
@@ -34,7 +34,12 @@ impl Analyzer {
         Self { constants, witnesses, statements, literals: vec![], stack: vec![], error }
     }
 
-    pub fn analyze_types(&mut self) {
+    /// Diagnostics emitted while analyzing, including warnings.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.error.diagnostics()
+    }
+
+    pub fn analyze_types(&mut self) -> DiagnosticResult<()> {
         // To work around the pedantic safety, we'll make new vectors and then
         // replace the `statements` and `stack` vectors from the `Analyzer`
         // object when we are done.
@@ -49,10 +54,13 @@ impl Analyzer {
             let mut rhs = vec![];
 
             // This handling is kinda limiting, but it'll do for now.
-            if !(arg_types[0] == VarType::BaseArray || arg_types[0] == VarType::ScalarArray) {
+            let valid_args = if !matches!(
+                arg_types.first(),
+                Some(VarType::BaseArray) | Some(VarType::ScalarArray)
+            ) {
                 // Check that number of args is correct
                 if statement.rhs.len() != arg_types.len() {
-                    self.error.abort(
+                    self.error.error(
                         &format!(
                             "Incorrect number of arguments for statement. Expected {}, got {}.",
                             arg_types.len(),
@@ -61,16 +69,34 @@ impl Analyzer {
                         statement.line,
                         1,
                     );
+                    false
+                } else {
+                    true
                 }
             } else {
                 // In case of arrays, check there's at least one element.
                 if statement.rhs.is_empty() {
-                    self.error.abort(
+                    self.error.error(
                         "Expected at least one element for statement using arrays.",
                         statement.line,
                         1,
                     );
+                    false
+                } else {
+                    true
                 }
+            };
+
+            // The arguments can't be checked, but we still push the result
+            // on the stack so the following statements can be analyzed.
+            if !valid_args {
+                if statement.typ == StatementType::Assign {
+                    let mut var = statement.lhs.clone().unwrap();
+                    var.typ = return_types.first().copied().unwrap_or(VarType::Dummy);
+                    stack.push(var);
+                    self.stack = stack.clone();
+                }
+                continue
             }
 
             // Edge-cases for some opcodes
@@ -79,14 +105,14 @@ impl Analyzer {
                 Opcode::RangeCheck => {
                     if let Arg::Lit(arg0) = &statement.rhs[0] {
                         if &arg0.name != "64" && &arg0.name != "253" {
-                            self.error.abort(
+                            self.error.error(
                                 "Supported range checks are only 64 and 253 bits.",
                                 arg0.line,
                                 arg0.column,
                             );
                         }
                     } else {
-                        self.error.abort(
+                        self.error.error(
                             "Invalid argument for range_check opcode.",
                             statement.line,
                             0,
@@ -109,7 +135,7 @@ impl Analyzer {
                 if let Arg::Func(func) = arg {
                     let (f_return_types, f_arg_types) = func.opcode.arg_types();
                     if f_return_types.is_empty() {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "Used a function argument which doesn't have a return value: {:?}",
                                 func.opcode
@@ -117,6 +143,7 @@ impl Analyzer {
                             statement.line,
                             1,
                         );
+                        continue
                     }
 
                     let v = Variable {
@@ -129,7 +156,7 @@ impl Analyzer {
                     // FIXME: Needs better *Array handling.
                     if arg_types[0] == VarType::BaseArray {
                         if f_return_types[0] != VarType::Base {
-                            self.error.abort(
+                            self.error.error(
                                 &format!(
                                     "Function passed as argument returns wrong type. Expected `{:?}`, got `{:?}`.",
                                     VarType::Base,
@@ -141,7 +168,7 @@ impl Analyzer {
                         }
                     } else if arg_types[0] == VarType::ScalarArray {
                         if f_return_types[0] != VarType::Scalar {
-                            self.error.abort(
+                            self.error.error(
                                 &format!(
                                     "Function passed as argument returns wrong type. Expected `{:?}`, got `{:?}`.",
                                     VarType::Scalar,
@@ -152,7 +179,7 @@ impl Analyzer {
                             );
                         }
                     } else if f_return_types[0] != arg_types[idx] {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "Function passed as argument returns wrong type. Expected `{:?}`, got `{:?}`.",
                                 arg_types[idx],
@@ -177,15 +204,24 @@ impl Analyzer {
                                     Var::Variable(c) => (c.typ, c.line, c.column),
                                 };
 
-                                if var_type != f_arg_types[inner_idx] {
-                                    self.error.abort(
+                                match f_arg_types.get(inner_idx) {
+                                    Some(t) if *t == var_type => {}
+                                    Some(t) => self.error.error(
                                         &format!(
                                             "Incorrect argument type. Expected `{:?}`, got `{:?}`.",
-                                            f_arg_types[inner_idx], var_type
+                                            t, var_type
                                         ),
                                         ln,
                                         col,
-                                    );
+                                    ),
+                                    None => self.error.error(
+                                        &format!(
+                                            "Too many arguments for function. Expected {}.",
+                                            f_arg_types.len()
+                                        ),
+                                        v.line,
+                                        v.column,
+                                    ),
                                 }
 
                                 // Apply the proper type.
@@ -196,13 +232,17 @@ impl Analyzer {
                                 continue
                             }
 
-                            self.error.abort(
+                            self.error.error(
                                 &format!("Unknown variable reference `{}`.", v.name),
                                 v.line,
                                 v.column,
                             );
                         } else {
-                            unimplemented!()
+                            self.error.error(
+                                "Only variables are supported as arguments of nested functions.",
+                                func.line,
+                                1,
+                            );
                         }
                     }
 
@@ -238,7 +278,7 @@ impl Analyzer {
                     // type checking.
                     let var_type = v.typ.to_vartype();
                     if var_type != arg_types[idx] {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "Incorrect argument type. Expected `{:?}`, got `{:?}`.",
                                 arg_types[idx], var_type
@@ -265,7 +305,7 @@ impl Analyzer {
                         // FIXME: Better array handling
                         if arg_types[0] == VarType::BaseArray {
                             if var_type != VarType::Base {
-                                self.error.abort(
+                                self.error.error(
                                     &format!(
                                         "Incorrect argument type. Expected `{:?}`, got `{:?}`.",
                                         VarType::Base,
@@ -277,7 +317,7 @@ impl Analyzer {
                             }
                         } else if arg_types[0] == VarType::ScalarArray {
                             if var_type != VarType::Scalar {
-                                self.error.abort(
+                                self.error.error(
                                     &format!(
                                         "Incorrect argument type. Expected `{:?}`, got `{:?}`.",
                                         VarType::Scalar,
@@ -288,7 +328,7 @@ impl Analyzer {
                                 );
                             }
                         } else if var_type != arg_types[idx] {
-                            self.error.abort(
+                            self.error.error(
                                 &format!(
                                     "Incorrect argument type. Expected `{:?}`, got `{:?}`.",
                                     arg_types[idx], var_type
@@ -305,7 +345,7 @@ impl Analyzer {
                         continue
                     }
 
-                    self.error.abort(
+                    self.error.error(
                         &format!("Unknown variable reference `{}`.", v.name),
                         v.line,
                         v.column,
@@ -340,6 +380,8 @@ impl Analyzer {
         //println!("=================STATEMENTS===============\n{:#?}", self.statements);
         //println!("===================STACK==================\n{:#?}", self.stack);
         //println!("==================LITERALS================\n{:#?}", self.literals);
+
+        self.error.result(())
     }
 
     fn lookup_var(&self, name: &str) -> Option<Var> {
@@ -388,7 +430,7 @@ impl Analyzer {
         None
     }
 
-    pub fn analyze_semantic(&mut self) {
+    pub fn analyze_semantic(&mut self) -> DiagnosticResult<()> {
         let mut stack = vec![];
 
         println!("Loading constants...\n-----");
@@ -427,7 +469,7 @@ impl Analyzer {
                     if let Some(index) = stack.iter().position(|&r| r == &arg.name) {
                         println!("Found at stack index {}", index);
                     } else {
-                        self.error.abort(
+                        self.error.error(
                             &format!("Could not find `{}` on the stack", arg.name),
                             arg.line,
                             arg.column,
@@ -454,6 +496,8 @@ impl Analyzer {
                 _ => unreachable!(),
             }
         }
+
+        self.error.result(())
     }
 
     fn pause() {
@@ -68,3 +68,108 @@ impl Default for Statement {
         Self { typ: StatementType::Noop, opcode: Opcode::Noop, lhs: None, rhs: vec![], line: 0 }
     }
 }
+
+/// A user-defined function, which is expanded inline wherever it's
+/// called, so the compiled circuit only ever contains opcodes.
+#[derive(Clone, Debug)]
+pub struct Function {
+    pub name: String,
+    /// Parameters, substituted with the call arguments on expansion
+    pub params: Vec<Variable>,
+    /// Function body, with calls to other functions already expanded
+    pub statements: Vec<Statement>,
+    /// Local variable holding the return value, if any
+    pub ret: Option<Variable>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Function {
+    /// Names of the variables assigned in the function body
+    pub fn locals(&self) -> Vec<String> {
+        fn collect(stmt: &Statement, locals: &mut Vec<String>) {
+            if let Some(lhs) = &stmt.lhs {
+                locals.push(lhs.name.clone());
+            }
+
+            for arg in &stmt.rhs {
+                if let Arg::Func(inner) = arg {
+                    collect(inner, locals);
+                }
+            }
+        }
+
+        let mut locals = vec![];
+        for stmt in &self.statements {
+            collect(stmt, &mut locals);
+        }
+
+        locals
+    }
+
+    /// Expand a call to the function into a list of statements.
+    ///
+    /// `args` holds a variable or literal for each parameter, and `lhs` is
+    /// the variable the return value gets assigned to. Local variables are
+    /// renamed with the given prefix so several calls don't clash, and all
+    /// positions are set to the call site.
+    pub fn expand(
+        &self,
+        args: &[Arg],
+        lhs: Option<&Variable>,
+        prefix: &str,
+        line: usize,
+        column: usize,
+    ) -> Vec<Statement> {
+        let expansion =
+            Expansion { function: self, args, lhs, locals: self.locals(), prefix, line, column };
+
+        self.statements.iter().map(|x| expansion.statement(x)).collect()
+    }
+}
+
+struct Expansion<'a> {
+    function: &'a Function,
+    args: &'a [Arg],
+    lhs: Option<&'a Variable>,
+    locals: Vec<String>,
+    prefix: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl Expansion<'_> {
+    fn variable(&self, var: &Variable) -> Variable {
+        let is_ret = self.function.ret.as_ref().map_or(false, |x| x.name == var.name);
+
+        let name = match self.lhs {
+            Some(lhs) if is_ret => lhs.name.clone(),
+            _ if self.locals.contains(&var.name) => format!("{}{}", self.prefix, var.name),
+            // Constants and witnesses are left as they are
+            _ => var.name.clone(),
+        };
+
+        Variable { name, typ: var.typ, line: self.line, column: self.column }
+    }
+
+    fn arg(&self, arg: &Arg) -> Arg {
+        match arg {
+            Arg::Var(v) => match self.function.params.iter().position(|x| x.name == v.name) {
+                Some(idx) => self.args[idx].clone(),
+                None => Arg::Var(self.variable(v)),
+            },
+            Arg::Lit(v) => Arg::Lit(Literal { line: self.line, column: self.column, ..v.clone() }),
+            Arg::Func(v) => Arg::Func(self.statement(v)),
+        }
+    }
+
+    fn statement(&self, stmt: &Statement) -> Statement {
+        Statement {
+            typ: stmt.typ,
+            opcode: stmt.opcode,
+            lhs: stmt.lhs.as_ref().map(|x| self.variable(x)),
+            rhs: stmt.rhs.iter().map(|x| self.arg(x)).collect(),
+            line: self.line,
+        }
+    }
+}
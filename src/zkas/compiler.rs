@@ -4,12 +4,12 @@ use darkfi_serial::{serialize, VarInt};
 
 use super::{
     ast::{Arg, Constant, Literal, Statement, StatementType, Witness},
-    error::ErrorEmitter,
+    error::{Diagnostic, DiagnosticResult, ErrorEmitter},
     types::StackType,
 };
 
 /// Version of the binary
-pub const BINARY_VERSION: u8 = 2;
+pub const BINARY_VERSION: u8 = 3;
 /// Magic bytes prepended to the binary
 pub const MAGIC_BYTES: [u8; 4] = [0x0b, 0x01, 0xb1, 0x35];
 
@@ -42,13 +42,22 @@ impl Compiler {
         Self { namespace, constants, witnesses, statements, literals, debug_info, error }
     }
 
-    pub fn compile(&self) -> Vec<u8> {
+    /// Diagnostics emitted while compiling, including warnings.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.error.diagnostics()
+    }
+
+    pub fn compile(&self) -> DiagnosticResult<Vec<u8>> {
         let mut bincode = vec![];
 
         // Write the magic bytes and version
         bincode.extend_from_slice(&MAGIC_BYTES);
         bincode.push(BINARY_VERSION);
 
+        // Reserve space for the circuit's k. It is measured by laying out
+        // the compiled circuit, and written with `Compiler::write_k()`.
+        bincode.extend_from_slice(&[0u8; 4]);
+
         // Write the circuit's namespace
         bincode.extend_from_slice(&serialize(&self.namespace));
 
@@ -87,8 +96,11 @@ impl Compiler {
                 StatementType::Assign => tmp_stack.push(&i.lhs.as_ref().unwrap().name),
                 // In case of a simple call, we don't append anything to the stack
                 StatementType::Call => {}
-                // TODO: FIXME: unreachable is reached with missing semicolons in the code
-                _ => unreachable!(),
+                // This is reached with missing semicolons in the code
+                _ => {
+                    self.error.error("Invalid statement, missing semicolon?", i.line, 1);
+                    continue
+                }
             }
 
             bincode.push(i.opcode as u8);
@@ -103,7 +115,7 @@ impl Compiler {
                             continue
                         }
 
-                        self.error.abort(
+                        self.error.error(
                             &format!("Failed finding a stack reference for `{}`", arg.name),
                             arg.line,
                             arg.column,
@@ -116,7 +128,7 @@ impl Compiler {
                             continue
                         }
 
-                        self.error.abort(
+                        self.error.error(
                             &format!("Failed finding literal `{}`", lit.name),
                             lit.line,
                             lit.column,
@@ -127,14 +139,22 @@ impl Compiler {
             }
         }
 
+        // Bail out if any references could not be resolved
+        self.error.result(())?;
+
         // If we're not doing debug info, we're done here and can return.
         if !self.debug_info {
-            return bincode
+            return Ok(bincode)
         }
 
         // TODO: Otherwise, we proceed appending debug info.
 
-        bincode
+        Ok(bincode)
+    }
+
+    /// Write the circuit's `k` into the header of a compiled binary.
+    pub fn write_k(bincode: &mut [u8], k: u32) {
+        bincode[5..9].copy_from_slice(&k.to_le_bytes());
     }
 
     fn lookup_stack(stack: &[&str], name: &str) -> Option<usize> {
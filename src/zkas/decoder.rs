@@ -8,6 +8,9 @@ use crate::{Error::ZkasDecoderError as ZkasErr, Result};
 #[derive(Clone, Debug)]
 pub struct ZkBinary {
     pub namespace: String,
+    /// Number of rows of the circuit is `2^k`, or 0 if the binary
+    /// doesn't carry it
+    pub k: u32,
     pub constants: Vec<(VarType, String)>,
     pub literals: Vec<(LitType, String)>,
     pub witnesses: Vec<VarType>,
@@ -21,15 +24,29 @@ fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
 
 impl ZkBinary {
     pub fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 5 {
+            return Err(ZkasErr("Binary is too short.".to_string()))
+        }
+
         let magic_bytes = &bytes[0..4];
         if magic_bytes != MAGIC_BYTES {
             return Err(ZkasErr("Magic bytes are incorrect.".to_string()))
         }
 
-        let _binary_version = &bytes[4];
+        let binary_version = bytes[4];
 
-        // After the binary version, we're supposed to have the contract namespace
-        let (namespace, _) = deserialize_partial(&bytes[5..])?;
+        // Since version 3, the binary version is followed by the circuit's k.
+        let (k, namespace_offset) = match binary_version {
+            2 => (0, 5),
+            3 => match bytes.get(5..9) {
+                Some(k) => (u32::from_le_bytes(k.try_into().unwrap()), 9),
+                None => return Err(ZkasErr("Binary is missing the circuit's k.".to_string())),
+            },
+            v => return Err(ZkasErr(format!("Unsupported binary version {}", v))),
+        };
+
+        // After that, we're supposed to have the contract namespace
+        let (namespace, _) = deserialize_partial(&bytes[namespace_offset..])?;
 
         let constants_offset = match find_subslice(bytes, b".constant") {
             Some(v) => v,
@@ -84,7 +101,7 @@ impl ZkBinary {
 
         // TODO: Debug info
 
-        Ok(Self { namespace, constants, literals, witnesses, opcodes })
+        Ok(Self { namespace, k, constants, literals, witnesses, opcodes })
     }
 
     fn parse_constants(bytes: &[u8]) -> Result<Vec<(VarType, String)>> {
@@ -202,3 +219,18 @@ impl ZkBinary {
         Ok(opcodes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_binary() {
+        assert!(ZkBinary::decode(&[]).is_err());
+        assert!(ZkBinary::decode(&MAGIC_BYTES).is_err());
+
+        let mut bytes = MAGIC_BYTES.to_vec();
+        bytes.extend_from_slice(&[3, 11, 0]);
+        assert!(ZkBinary::decode(&bytes).is_err());
+    }
+}
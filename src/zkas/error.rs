@@ -1,73 +1,214 @@
-use std::{io, io::Write, process};
+use std::{cell::RefCell, fs::read_to_string, io, io::Write};
 
 use termion::{color, style};
 
+/// Severity of a [`Diagnostic`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A diagnostic emitted by one of the zkas stages.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Name of the stage that emitted the diagnostic
+    pub namespace: String,
+    /// Source file name
+    pub file: String,
+    pub message: String,
+    /// Source line, starting from 1, or 0 if the diagnostic
+    /// does not refer to a specific position.
+    pub line: usize,
+    /// Source column, starting from 1
+    pub column: usize,
+    /// Number of characters the diagnostic refers to, starting at `column`
+    pub span: usize,
+}
+
+impl Diagnostic {
+    /// Format the diagnostic along with the source line it refers to.
+    pub fn fmt(&self, lines: &[String]) -> String {
+        if self.line == 0 || self.line > lines.len() {
+            return format!("{}\n\n\n", self.message)
+        }
+
+        let err_msg = format!("{} (line {}, column {})", self.message, self.line, self.column);
+        let dbg_msg =
+            format!("{}:{}:{}: {}", self.file, self.line, self.column, lines[self.line - 1]);
+        let pad = dbg_msg.split(": ").next().unwrap().len() + self.column + 1;
+        let caret = format!("{:width$}^", "", width = pad);
+        format!("{}\n{}\n{}\n", err_msg, dbg_msg, caret)
+    }
+}
+
+/// Result of a zkas stage. On failure, it holds all the diagnostics
+/// emitted by the stage, including warnings.
+pub type DiagnosticResult<T> = std::result::Result<T, Vec<Diagnostic>>;
+
+/// Print diagnostics to stderr in a human readable way, given the
+/// source code of the file they were emitted for. Diagnostics from
+/// imported files are shown using the file found on disk.
+pub fn emit_diagnostics(filename: &str, source: &str, diagnostics: &[Diagnostic]) {
+    let lines: Vec<String> = source.lines().map(|x| x.to_string()).collect();
+
+    let stderr = io::stderr();
+    let mut handle = stderr.lock();
+
+    for diagnostic in diagnostics {
+        let imported: Vec<String>;
+        let lines = if diagnostic.file == filename {
+            &lines
+        } else {
+            let source = read_to_string(&diagnostic.file).unwrap_or_default();
+            imported = source.lines().map(|x| x.to_string()).collect();
+            &imported
+        };
+
+        let (typ, fg) = match diagnostic.severity {
+            Severity::Error => ("error", color::Fg(color::Red).to_string()),
+            Severity::Warning => ("warning", color::Fg(color::Yellow).to_string()),
+        };
+
+        write!(
+            handle,
+            "{}{}{} {}:{} {}",
+            style::Bold,
+            fg,
+            diagnostic.namespace,
+            typ,
+            style::Reset,
+            diagnostic.fmt(lines)
+        )
+        .unwrap();
+    }
+
+    handle.flush().unwrap();
+}
+
 pub(super) struct ErrorEmitter {
     namespace: String,
     file: String,
     lines: Vec<String>,
+    diagnostics: RefCell<Vec<Diagnostic>>,
 }
 
 impl ErrorEmitter {
     pub fn new(namespace: &str, file: &str, lines: Vec<String>) -> Self {
-        Self { namespace: namespace.to_string(), file: file.to_string(), lines }
+        Self {
+            namespace: namespace.to_string(),
+            file: file.to_string(),
+            lines,
+            diagnostics: RefCell::new(vec![]),
+        }
     }
 
-    fn fmt(&self, msg: String, ln: usize, col: usize) -> String {
-        let (err_msg, dbg_msg, caret) = match ln {
-            0 => (msg, "".to_string(), "".to_string()),
-            _ => {
-                let err_msg = format!("{} (line {}, column {})", msg, ln, col);
-                let dbg_msg = format!("{}:{}:{}: {}", self.file, ln, col, self.lines[ln - 1]);
-                let pad = dbg_msg.split(": ").next().unwrap().len() + col + 1;
-                let caret = format!("{:width$}^", "", width = pad);
-                (err_msg, dbg_msg, caret)
-            }
+    /// Length of the token found at the given position in the source.
+    fn span(&self, ln: usize, col: usize) -> usize {
+        let line = match ln.checked_sub(1).and_then(|i| self.lines.get(i)) {
+            Some(v) => v,
+            None => return 0,
         };
-        format!("{}\n{}\n{}\n", err_msg, dbg_msg, caret)
+
+        let token = line
+            .chars()
+            .skip(col.saturating_sub(1))
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .count();
+
+        token.max(1)
+    }
+
+    fn push(&self, severity: Severity, msg: &str, ln: usize, col: usize) {
+        self.diagnostics.borrow_mut().push(Diagnostic {
+            severity,
+            namespace: self.namespace.clone(),
+            file: self.file.clone(),
+            message: msg.to_string(),
+            line: ln,
+            column: col,
+            span: self.span(ln, col),
+        });
     }
 
-    pub fn abort(&self, msg: &str, ln: usize, col: usize) {
-        let m = self.fmt(msg.to_string(), ln, col);
-        self.emit("error", &m);
-        process::exit(1);
+    /// Record an error and keep going, so further errors can be found.
+    pub fn error(&self, msg: &str, ln: usize, col: usize) {
+        self.push(Severity::Error, msg, ln, col);
+    }
+
+    /// Record an error the stage can't recover from, and return all the
+    /// diagnostics emitted so far.
+    pub fn abort(&self, msg: &str, ln: usize, col: usize) -> Vec<Diagnostic> {
+        self.error(msg, ln, col);
+        self.diagnostics()
     }
 
     pub fn warn(&self, msg: &str, ln: usize, col: usize) {
-        let m = self.fmt(msg.to_string(), ln, col);
-        self.emit("warning", &m);
+        self.push(Severity::Warning, msg, ln, col);
     }
 
-    pub fn emit(&self, typ: &str, msg: &str) {
-        let stderr = io::stderr();
-        let mut handle = stderr.lock();
-
-        match typ {
-            "error" => write!(
-                handle,
-                "{}{}{} error:{} {}",
-                style::Bold,
-                color::Fg(color::Red),
-                self.namespace,
-                style::Reset,
-                msg
-            )
-            .unwrap(),
-
-            "warning" => write!(
-                handle,
-                "{}{}{} warning:{} {}",
-                style::Bold,
-                color::Fg(color::Yellow),
-                self.namespace,
-                style::Reset,
-                msg
-            )
-            .unwrap(),
-
-            _ => unreachable!(),
-        };
+    /// Record diagnostics emitted by another emitter, e.g. for an
+    /// imported file.
+    pub fn extend(&self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics.borrow_mut().extend(diagnostics);
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.borrow().clone()
+    }
+
+    /// Return the given value, unless any errors were recorded.
+    pub fn result<T>(&self, value: T) -> DiagnosticResult<T> {
+        if self.diagnostics.borrow().iter().any(|x| x.severity == Severity::Error) {
+            return Err(self.diagnostics())
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zkas::{Analyzer, Lexer, Parser};
+
+    #[test]
+    fn test_diagnostics() {
+        // Lexer errors are collected instead of aborting on the first one
+        let source = "constant \"Test\" {}\ncircuit \"Test\" { a = $; b = ?; }\n";
+        let lexer = Lexer::new("test.zk", source.chars());
+        let diagnostics = lexer.lex().unwrap_err();
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|x| x.severity == Severity::Error && x.line == 2));
+
+        // Type errors in separate statements are all reported
+        let source = r#"
+constant "Test" {}
+contract "Test" { Base a, Scalar b, }
+circuit "Test" {
+    c = base_add(a, b);
+    d = base_mul(c, x);
+    constrain_instance(d);
+}
+"#;
+        let lexer = Lexer::new("test.zk", source.chars());
+        let tokens = lexer.lex().unwrap();
+        let parser = Parser::new("test.zk", source.chars(), tokens);
+        let (_, constants, witnesses, statements) = parser.parse().unwrap();
+        assert_eq!(parser.diagnostics().len(), 1);
+        assert_eq!(parser.diagnostics()[0].severity, Severity::Warning);
+
+        let mut analyzer =
+            Analyzer::new("test.zk", source.chars(), constants, witnesses, statements);
+        let diagnostics = analyzer.analyze_types().unwrap_err();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column, diagnostics[0].span), (5, 21, 1));
+        assert_eq!((diagnostics[1].line, diagnostics[1].column, diagnostics[1].span), (6, 21, 1));
 
-        handle.flush().unwrap();
+        // Spans count characters, not bytes
+        let emitter = ErrorEmitter::new("Test", "test.zk", vec!["a = ñandú + b;".to_string()]);
+        emitter.error("Unknown variable", 1, 5);
+        assert_eq!(emitter.diagnostics()[0].span, 5);
     }
 }
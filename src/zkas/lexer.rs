@@ -1,6 +1,6 @@
 use std::str::Chars;
 
-use super::error::ErrorEmitter;
+use super::error::{Diagnostic, DiagnosticResult, ErrorEmitter};
 
 const SPECIAL_CHARS: [char; 7] = ['{', '}', '(', ')', ',', ';', '='];
 
@@ -55,7 +55,12 @@ impl<'a> Lexer<'a> {
         Self { source, error }
     }
 
-    pub fn lex(&self) -> Vec<Token> {
+    /// Diagnostics emitted while lexing, including warnings.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.error.diagnostics()
+    }
+
+    pub fn lex(&self) -> DiagnosticResult<Vec<Token>> {
         let mut tokens = vec![];
         let mut lineno = 1;
         let mut column = 0;
@@ -102,11 +107,15 @@ impl<'a> Lexer<'a> {
                 }
 
                 if in_string {
-                    self.error.abort("Strings can't contain newlines", lineno, column);
+                    self.error.error("Strings can't contain newlines", lineno, column);
+                    in_string = false;
+                    buf = String::new();
                 }
 
                 if in_number {
-                    self.error.abort("Numbers can't contain newlines", lineno, column);
+                    self.error.error("Numbers can't contain newlines", lineno, column);
+                    in_number = false;
+                    buf = String::new();
                 }
 
                 in_comment = false;
@@ -183,10 +192,18 @@ impl<'a> Lexer<'a> {
                 continue
             }
 
+            // Paths used in imports
+            if in_string && ['.', '/', '-'].contains(&c) {
+                buf.push(c);
+                continue
+            }
+
             if in_string && c == '"' {
                 // " I need to fix my vis lexer
                 if buf.is_empty() {
-                    self.error.abort("String cannot be empty", lineno, column);
+                    self.error.error("String cannot be empty", lineno, column);
+                    in_string = false;
+                    continue
                 }
                 new_string!();
                 continue
@@ -234,14 +251,18 @@ impl<'a> Lexer<'a> {
                         tokens.push(Token::new("=", TokenType::Assign, lineno, column));
                         continue
                     }
-                    _ => self.error.abort(&format!("Invalid token `{}`", c), lineno, column - 1),
+                    _ => self.error.error(&format!("Invalid token `{}`", c), lineno, column - 1),
                 }
                 continue
             }
 
-            self.error.abort(&format!("Invalid token `{}`", c), lineno, column - 1);
+            self.error.error(&format!("Invalid token `{}`", c), lineno, column - 1);
+        }
+
+        if in_string {
+            self.error.error("Unterminated string", lineno, column);
         }
 
-        tokens
+        self.error.result(tokens)
     }
 }
@@ -1,9 +1,10 @@
 //! `src/zkas` is the library holding the zkas toolchain, consisting of a
-//! lexer, parser, static/semantic analyzers, a binary compiler, and a
-//! binary decoder.
+//! lexer, parser, static/semantic analyzers, a binary compiler, a
+//! binary decoder, and circuit size estimation.
 
 /// Error emitter
-mod error;
+pub mod error;
+pub use error::{Diagnostic, DiagnosticResult};
 
 /// Language opcodes
 pub mod opcode;
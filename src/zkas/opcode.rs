@@ -52,6 +52,21 @@ pub enum Opcode {
     /// Check if a field element fits in a boolean (Either 0 or 1)
     BoolCheck = 0x52,
 
+    /// Compare two Base field elements, returning 1 if a is greater than b, else 0
+    GreaterThan = 0x53,
+
+    /// Select between two Base field elements given a boolean condition
+    CondSelect = 0x60,
+
+    /// Return 0 if the first Base field element is 0, else the second one
+    ZeroCondSelect = 0x61,
+
+    /// Constrain equality of two Base field elements
+    ConstrainEqualBase = 0xe0,
+
+    /// Constrain equality of two elliptic curve points
+    ConstrainEqualPoint = 0xe1,
+
     /// Constrain a Base field element to a circuit's public input
     ConstrainInstance = 0xf0,
 
@@ -77,12 +92,46 @@ impl Opcode {
             "range_check" => Some(Self::RangeCheck),
             "less_than" => Some(Self::LessThan),
             "bool_check" => Some(Self::BoolCheck),
+            "greater_than" => Some(Self::GreaterThan),
+            "cond_select" => Some(Self::CondSelect),
+            "zero_cond" => Some(Self::ZeroCondSelect),
+            "constrain_equal_base" => Some(Self::ConstrainEqualBase),
+            "constrain_equal_point" => Some(Self::ConstrainEqualPoint),
             "constrain_instance" => Some(Self::ConstrainInstance),
             "debug" => Some(Self::DebugPrint),
             _ => None,
         }
     }
 
+    /// Return the name of the opcode as used in zkas source code.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Noop => "noop",
+            Self::EcAdd => "ec_add",
+            Self::EcMul => "ec_mul",
+            Self::EcMulBase => "ec_mul_base",
+            Self::EcMulShort => "ec_mul_short",
+            Self::EcGetX => "ec_get_x",
+            Self::EcGetY => "ec_get_y",
+            Self::PoseidonHash => "poseidon_hash",
+            Self::MerkleRoot => "merkle_root",
+            Self::BaseAdd => "base_add",
+            Self::BaseMul => "base_mul",
+            Self::BaseSub => "base_sub",
+            Self::WitnessBase => "witness_base",
+            Self::RangeCheck => "range_check",
+            Self::LessThan => "less_than",
+            Self::BoolCheck => "bool_check",
+            Self::GreaterThan => "greater_than",
+            Self::CondSelect => "cond_select",
+            Self::ZeroCondSelect => "zero_cond",
+            Self::ConstrainEqualBase => "constrain_equal_base",
+            Self::ConstrainEqualPoint => "constrain_equal_point",
+            Self::ConstrainInstance => "constrain_instance",
+            Self::DebugPrint => "debug",
+        }
+    }
+
     pub fn from_repr(b: u8) -> Option<Self> {
         match b {
             0x01 => Some(Self::EcAdd),
@@ -100,6 +149,11 @@ impl Opcode {
             0x50 => Some(Self::RangeCheck),
             0x51 => Some(Self::LessThan),
             0x52 => Some(Self::BoolCheck),
+            0x53 => Some(Self::GreaterThan),
+            0x60 => Some(Self::CondSelect),
+            0x61 => Some(Self::ZeroCondSelect),
+            0xe0 => Some(Self::ConstrainEqualBase),
+            0xe1 => Some(Self::ConstrainEqualPoint),
             0xf0 => Some(Self::ConstrainInstance),
             0xff => Some(Self::DebugPrint),
             _ => None,
@@ -148,6 +202,18 @@ impl Opcode {
 
             Opcode::BoolCheck => (vec![], vec![VarType::Base]),
 
+            Opcode::GreaterThan => (vec![VarType::Base], vec![VarType::Base, VarType::Base]),
+
+            Opcode::CondSelect => {
+                (vec![VarType::Base], vec![VarType::Base, VarType::Base, VarType::Base])
+            }
+
+            Opcode::ZeroCondSelect => (vec![VarType::Base], vec![VarType::Base, VarType::Base]),
+
+            Opcode::ConstrainEqualBase => (vec![], vec![VarType::Base, VarType::Base]),
+
+            Opcode::ConstrainEqualPoint => (vec![], vec![VarType::EcPoint, VarType::EcPoint]),
+
             Opcode::ConstrainInstance => (vec![], vec![VarType::Base]),
 
             Opcode::DebugPrint => (vec![], vec![]),
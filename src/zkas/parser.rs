@@ -1,18 +1,24 @@
-use std::{iter::Peekable, str::Chars};
+use std::{
+    cell::RefCell,
+    fs::read_to_string,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+};
 
 use indexmap::IndexMap;
 use itertools::Itertools;
 
 use super::{
-    ast::{Arg, Constant, Literal, Statement, StatementType, Variable, Witness},
-    error::ErrorEmitter,
-    lexer::{Token, TokenType},
+    ast::{Arg, Constant, Function, Literal, Statement, StatementType, Variable, Witness},
+    error::{Diagnostic, DiagnosticResult, ErrorEmitter},
+    lexer::{Lexer, Token, TokenType},
     LitType, Opcode, VarType,
 };
 
 /// zkas language builtin keywords.
 /// These can not be used anywhere except where they are expected.
-const KEYWORDS: [&str; 3] = ["constant", "contract", "circuit"];
+const KEYWORDS: [&str; 6] = ["constant", "contract", "circuit", "import", "function", "return"];
 
 /// Forbidden namespaces
 const NOPE_NS: [&str; 4] = [".constant", ".literal", ".contract", ".circuit"];
@@ -29,6 +35,12 @@ const VALID_ECFIXEDPOINTBASE: [&str; 1] = ["NULLIFIER_K"];
 pub struct Parser {
     tokens: Vec<Token>,
     error: ErrorEmitter,
+    /// Path of the source file, imports are resolved relative to it
+    path: PathBuf,
+    /// Files currently being imported, used to detect import cycles
+    import_chain: Vec<PathBuf>,
+    /// User-defined functions, including the imported ones
+    functions: RefCell<IndexMap<String, Function>>,
 }
 
 impl Parser {
@@ -38,10 +50,19 @@ impl Parser {
         let lines: Vec<String> = source.as_str().lines().map(|x| x.to_string()).collect();
         let error = ErrorEmitter::new("Parser", filename, lines);
 
-        Self { tokens, error }
+        let path = PathBuf::from(filename);
+        let import_chain = vec![path.canonicalize().unwrap_or_else(|_| path.clone())];
+
+        Self { tokens, error, path, import_chain, functions: RefCell::new(IndexMap::new()) }
+    }
+
+    /// Diagnostics emitted while parsing, including warnings.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.error.diagnostics()
     }
 
-    pub fn parse(&self) -> (String, Vec<Constant>, Vec<Witness>, Vec<Statement>) {
+    #[allow(clippy::type_complexity)]
+    pub fn parse(&self) -> DiagnosticResult<(String, Vec<Constant>, Vec<Witness>, Vec<Statement>)> {
         // We use these to keep state while parsing.
         let mut namespace = None;
         let (mut declaring_constant, mut declared_constant) = (false, false);
@@ -61,12 +82,12 @@ impl Parser {
         let mut ast_inner = IndexMap::new();
         let mut ast = IndexMap::new();
 
-        if self.tokens[0].token_type != TokenType::Symbol {
-            self.error.abort(
+        if self.tokens.is_empty() || self.tokens[0].token_type != TokenType::Symbol {
+            return Err(self.error.abort(
                 "Source file does not start with a section. Expected `constant/contract/circuit`.",
                 0,
                 0,
-            );
+            ))
         }
 
         let mut iter = self.tokens.iter();
@@ -89,7 +110,7 @@ impl Parser {
                             if KEYWORDS.contains(&inner.token.as_str()) &&
                                 inner.token_type == TokenType::Symbol
                             {
-                                self.error.abort(
+                                self.error.error(
                                     &format!("Keyword '{}' used in improper place.", inner.token),
                                     inner.line,
                                     inner.column,
@@ -105,6 +126,14 @@ impl Parser {
                 }
 
                 match t.token.as_str() {
+                    "import" => {
+                        self.parse_import(t, &mut iter)?;
+                        continue
+                    }
+                    "function" => {
+                        self.parse_function(t, &mut iter)?;
+                        continue
+                    }
                     "constant" => {
                         declaring_constant = true;
                         absorb_inner_tokens!(constant_tokens);
@@ -118,11 +147,13 @@ impl Parser {
                         absorb_inner_tokens!(circuit_tokens);
                     }
 
-                    x => self.error.abort(
-                        &format!("Section `{}` is not a valid section", x),
-                        t.line,
-                        t.column,
-                    ),
+                    x => {
+                        return Err(self.error.abort(
+                            &format!("Section `{}` is not a valid section", x),
+                            t.line,
+                            t.column,
+                        ))
+                    }
                 }
             }
 
@@ -132,7 +163,7 @@ impl Parser {
                 ($t:ident) => {
                     if let Some(ns) = namespace.clone() {
                         if ns != $t[0].token {
-                            self.error.abort(
+                            self.error.error(
                                 &format!("Found '{}' namespace, expected '{}'.", $t[0].token, ns),
                                 $t[0].line,
                                 $t[0].column,
@@ -140,7 +171,7 @@ impl Parser {
                         }
                     } else {
                         if NOPE_NS.contains(&$t[0].token.as_str()) {
-                            self.error.abort(
+                            self.error.error(
                                 &format!("'{}' cannot be a namespace.", $t[0].token),
                                 $t[0].line,
                                 $t[0].column,
@@ -154,10 +185,10 @@ impl Parser {
             // Parse the constant section into the AST.
             if declaring_constant {
                 if declared_constant {
-                    self.error.abort("Duplicate `constant` section found.", t.line, t.column);
+                    self.error.error("Duplicate `constant` section found.", t.line, t.column);
                 }
 
-                self.check_section_structure("constant", constant_tokens.clone());
+                self.check_section_structure("constant", constant_tokens.clone())?;
                 check_namespace!(constant_tokens);
 
                 let mut constants_map = IndexMap::new();
@@ -165,12 +196,12 @@ impl Parser {
                 let mut constant_inner = constant_tokens[2..constant_tokens.len() - 1].iter();
                 while let Some((typ, name, comma)) = constant_inner.next_tuple() {
                     if comma.token_type != TokenType::Comma {
-                        self.error.abort("Separator is not a comma.", comma.line, comma.column);
+                        self.error.error("Separator is not a comma.", comma.line, comma.column);
                     }
 
                     // No variable shadowing
                     if constants_map.contains_key(name.token.as_str()) {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "Section `constant` already contains the token `{}`.",
                                 &name.token
@@ -184,7 +215,11 @@ impl Parser {
                 }
 
                 if constant_inner.next().is_some() {
-                    self.error.abort("Internal error, leftovers in 'constant' iterator", 0, 0);
+                    return Err(self.error.abort(
+                        "Internal error, leftovers in 'constant' iterator",
+                        0,
+                        0,
+                    ))
                 }
 
                 ast_inner.insert("constant".to_string(), constants_map);
@@ -195,10 +230,10 @@ impl Parser {
             // Parse the contract section into the AST.
             if declaring_contract {
                 if declared_contract {
-                    self.error.abort("Duplicate `contract` section found.", t.line, t.column);
+                    self.error.error("Duplicate `contract` section found.", t.line, t.column);
                 }
 
-                self.check_section_structure("contract", contract_tokens.clone());
+                self.check_section_structure("contract", contract_tokens.clone())?;
                 check_namespace!(contract_tokens);
 
                 let mut witnesses_map = IndexMap::new();
@@ -206,12 +241,12 @@ impl Parser {
                 let mut contract_inner = contract_tokens[2..contract_tokens.len() - 1].iter();
                 while let Some((typ, name, comma)) = contract_inner.next_tuple() {
                     if comma.token_type != TokenType::Comma {
-                        self.error.abort("Separator is not a comma.", comma.line, comma.column);
+                        self.error.error("Separator is not a comma.", comma.line, comma.column);
                     }
 
                     // No variable shadowing
                     if witnesses_map.contains_key(name.token.as_str()) {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "Section `contract` already contains the token `{}`.",
                                 &name.token
@@ -225,7 +260,11 @@ impl Parser {
                 }
 
                 if contract_inner.next().is_some() {
-                    self.error.abort("Internal error, leftovers in 'contract' iterator", 0, 0);
+                    return Err(self.error.abort(
+                        "Internal error, leftovers in 'contract' iterator",
+                        0,
+                        0,
+                    ))
                 }
 
                 ast_inner.insert("contract".to_string(), witnesses_map);
@@ -236,10 +275,10 @@ impl Parser {
             // Parse the circuit section into the AST.
             if declaring_circuit {
                 if declared_circuit {
-                    self.error.abort("Duplicate `circuit` section found.", t.line, t.column);
+                    self.error.error("Duplicate `circuit` section found.", t.line, t.column);
                 }
 
-                self.check_section_structure("circuit", circuit_tokens.clone());
+                self.check_section_structure("circuit", circuit_tokens.clone())?;
                 check_namespace!(circuit_tokens);
 
                 // Grab tokens for each statement
@@ -260,15 +299,17 @@ impl Parser {
 
         // Tokens have been processed and ast is complete
 
-        let ns = namespace.unwrap();
+        let ns = match namespace {
+            Some(v) => v,
+            None => return Err(self.error.abort("Missing namespace in .zk source.", 0, 0)),
+        };
         ast.insert(ns.clone(), ast_inner);
 
         let constants = {
             let c = match ast.get(&ns).unwrap().get("constant") {
                 Some(c) => c,
                 None => {
-                    self.error.abort("Missing `constant` section in .zk source.", 0, 0);
-                    unreachable!();
+                    return Err(self.error.abort("Missing `constant` section in .zk source.", 0, 0))
                 }
             };
             self.parse_ast_constants(c)
@@ -278,8 +319,7 @@ impl Parser {
             let c = match ast.get(&ns).unwrap().get("contract") {
                 Some(c) => c,
                 None => {
-                    self.error.abort("Missing `contract` section in .zk source.", 0, 0);
-                    unreachable!();
+                    return Err(self.error.abort("Missing `contract` section in .zk source.", 0, 0))
                 }
             };
             self.parse_ast_contract(c)
@@ -287,36 +327,446 @@ impl Parser {
 
         let statements = self.parse_ast_circuit(circuit_stmts);
         if statements.is_empty() {
-            self.error.abort("Circuit section is empty.", 0, 0);
+            self.error.error("Circuit section is empty.", 0, 0);
+        }
+
+        self.error.result((ns, constants, witnesses, statements))
+    }
+
+    /// Parse an imported file, which may only hold imports and functions.
+    fn parse_library(&self) -> DiagnosticResult<()> {
+        let mut iter = self.tokens.iter();
+        while let Some(t) = iter.next() {
+            match t.token.as_str() {
+                "import" => self.parse_import(t, &mut iter)?,
+                "function" => self.parse_function(t, &mut iter)?,
+                x => {
+                    return Err(self.error.abort(
+                        &format!("Imported files can only contain functions, found `{}`.", x),
+                        t.line,
+                        t.column,
+                    ))
+                }
+            }
+        }
+
+        self.error.result(())
+    }
+
+    /// Parse `import "path/to/file.zk";` and make the functions defined
+    /// in that file available to this one.
+    fn parse_import(
+        &self,
+        t: &Token,
+        iter: &mut std::slice::Iter<'_, Token>,
+    ) -> DiagnosticResult<()> {
+        let path = match (iter.next(), iter.next()) {
+            (Some(path), Some(semicolon))
+                if path.token_type == TokenType::String &&
+                    semicolon.token_type == TokenType::Semicolon =>
+            {
+                path
+            }
+            _ => {
+                return Err(self.error.abort(
+                    "Imports must be declared as `import \"path/to/file.zk\";`.",
+                    t.line,
+                    t.column,
+                ))
+            }
+        };
+
+        let file = self.path.parent().unwrap_or_else(|| Path::new("")).join(&path.token);
+        let canonical = file.canonicalize().unwrap_or_else(|_| file.clone());
+        if self.import_chain.contains(&canonical) {
+            self.error.error(
+                &format!("Import cycle detected with \"{}\".", path.token),
+                path.line,
+                path.column,
+            );
+            return Ok(())
+        }
+
+        let source = match read_to_string(&file) {
+            Ok(v) => v,
+            Err(e) => {
+                self.error.error(
+                    &format!("Failed reading from \"{}\": {}", file.display(), e),
+                    path.line,
+                    path.column,
+                );
+                return Ok(())
+            }
+        };
+
+        // The imported file gets its own emitter, so diagnostics point
+        // to the file they were found in.
+        let filename = file.to_string_lossy();
+        let lexer = Lexer::new(&filename, source.chars());
+        let tokens = match lexer.lex() {
+            Ok(v) => v,
+            Err(e) => {
+                self.error.extend(e);
+                return Ok(())
+            }
+        };
+        self.error.extend(lexer.diagnostics());
+
+        let mut parser = Parser::new(&filename, source.chars(), tokens);
+        parser.import_chain = self.import_chain.clone();
+        parser.import_chain.push(canonical);
+
+        let result = parser.parse_library();
+        self.error.extend(parser.diagnostics());
+        if result.is_err() {
+            return Ok(())
+        }
+
+        for (name, function) in parser.functions.into_inner() {
+            if self.function(&name).is_some() {
+                self.error.error(
+                    &format!("Function `{}` is already defined.", name),
+                    path.line,
+                    path.column,
+                );
+                continue
+            }
+
+            self.functions.borrow_mut().insert(name, function);
+        }
+
+        Ok(())
+    }
+
+    /// Parse a function declaration:
+    ///
+    /// ```text
+    /// function pedersen_commitment(value, blind) {
+    ///     vcv = ec_mul_short(value, VALUE_COMMIT_VALUE);
+    ///     vcr = ec_mul(blind, VALUE_COMMIT_RANDOM);
+    ///     return ec_add(vcv, vcr);
+    /// }
+    /// ```
+    ///
+    /// The body is parsed like the circuit section, so functions may call
+    /// opcodes and any function declared before them.
+    fn parse_function(
+        &self,
+        t: &Token,
+        iter: &mut std::slice::Iter<'_, Token>,
+    ) -> DiagnosticResult<()> {
+        let name = match iter.next() {
+            Some(name) if name.token_type == TokenType::Symbol => name,
+            _ => {
+                return Err(self.error.abort(
+                    "Expected a function name after `function`.",
+                    t.line,
+                    t.column,
+                ))
+            }
+        };
+
+        if KEYWORDS.contains(&name.token.as_str()) || Opcode::from_name(&name.token).is_some() {
+            self.error.error(
+                &format!("`{}` is reserved and can't be used as a function name.", name.token),
+                name.line,
+                name.column,
+            );
+        } else if self.function(&name.token).is_some() {
+            self.error.error(
+                &format!("Function `{}` is already defined.", name.token),
+                name.line,
+                name.column,
+            );
+        }
+
+        match iter.next() {
+            Some(paren) if paren.token_type == TokenType::LeftParen => {}
+            _ => {
+                return Err(self.error.abort(
+                    "Function parameters must be opened with a '('.",
+                    name.line,
+                    name.column,
+                ))
+            }
+        }
+
+        let mut params: Vec<Variable> = vec![];
+        loop {
+            let param = match iter.next() {
+                Some(p) if p.token_type == TokenType::RightParen && params.is_empty() => break,
+                Some(p) if p.token_type == TokenType::Symbol => p,
+                Some(p) => {
+                    return Err(self.error.abort(
+                        &format!("Unexpected token `{}` in function parameters.", p.token),
+                        p.line,
+                        p.column,
+                    ))
+                }
+                None => {
+                    return Err(self.error.abort(
+                        "Premature ending of function declaration.",
+                        name.line,
+                        name.column,
+                    ))
+                }
+            };
+
+            if params.iter().any(|x| x.name == param.token) {
+                self.error.error(
+                    &format!("Duplicate function parameter `{}`.", param.token),
+                    param.line,
+                    param.column,
+                );
+            }
+
+            params.push(Variable {
+                name: param.token.clone(),
+                typ: VarType::Dummy,
+                line: param.line,
+                column: param.column,
+            });
+
+            match iter.next() {
+                Some(sep) if sep.token_type == TokenType::Comma => continue,
+                Some(sep) if sep.token_type == TokenType::RightParen => break,
+                _ => {
+                    return Err(self.error.abort(
+                        "Function parameters must be separated with a comma ','.",
+                        param.line,
+                        param.column,
+                    ))
+                }
+            }
+        }
+
+        match iter.next() {
+            Some(brace) if brace.token_type == TokenType::LeftBrace => {}
+            _ => {
+                return Err(self.error.abort(
+                    "Function body must be opened with a left brace '{'",
+                    name.line,
+                    name.column,
+                ))
+            }
+        }
+
+        // Grab tokens for each statement of the body
+        let mut statements = vec![];
+        let mut statement = vec![];
+        let mut closed = false;
+        for inner in iter.by_ref() {
+            match inner.token_type {
+                TokenType::RightBrace => {
+                    closed = true;
+                    break
+                }
+                TokenType::Semicolon => {
+                    statements.push(statement);
+                    statement = vec![];
+                    continue
+                }
+                _ => {}
+            }
+
+            if KEYWORDS.contains(&inner.token.as_str()) &&
+                inner.token != "return" &&
+                inner.token_type == TokenType::Symbol
+            {
+                self.error.error(
+                    &format!("Keyword '{}' used in improper place.", inner.token),
+                    inner.line,
+                    inner.column,
+                );
+            }
+
+            statement.push(inner.clone());
+        }
+
+        if !closed {
+            return Err(self.error.abort(
+                "Function body must be closed with a right brace '}'",
+                name.line,
+                name.column,
+            ))
+        }
+
+        if !statement.is_empty() {
+            return Err(self.error.abort(
+                "Function body does not end with a semicolon.",
+                statement[0].line,
+                statement[0].column,
+            ))
+        }
+
+        // A `return` may only be found as the last statement. Returning a
+        // call is handled by assigning it to a local variable first.
+        let mut ret = None;
+        if let Some(last) = statements.last_mut() {
+            if last.first().map_or(false, |x| x.token == "return") {
+                let keyword = last.remove(0);
+                match last.len() {
+                    0 => {
+                        self.error.error("Missing return value.", keyword.line, keyword.column);
+                        statements.pop();
+                    }
+                    1 => {
+                        ret = Some(last[0].clone());
+                        statements.pop();
+                    }
+                    _ => {
+                        let var = Token {
+                            token: "_return".to_string(),
+                            token_type: TokenType::Symbol,
+                            line: keyword.line,
+                            column: keyword.column,
+                        };
+                        let assign = Token {
+                            token: "=".to_string(),
+                            token_type: TokenType::Assign,
+                            line: keyword.line,
+                            column: keyword.column,
+                        };
+                        last.splice(0..0, [var.clone(), assign]);
+                        ret = Some(var);
+                    }
+                }
+            }
+        }
+
+        for statement in &statements {
+            if let Some(t) = statement.iter().find(|x| x.token == "return") {
+                self.error.error(
+                    "`return` must be the last statement of a function.",
+                    t.line,
+                    t.column,
+                );
+            }
+        }
+
+        let mut function = Function {
+            name: name.token.clone(),
+            params,
+            statements: self.parse_ast_circuit(statements),
+            ret: None,
+            line: name.line,
+            column: name.column,
+        };
+
+        let locals = function.locals();
+        for param in &function.params {
+            if locals.contains(&param.name) {
+                self.error.error(
+                    &format!("Function parameter `{}` can't be reassigned.", param.name),
+                    param.line,
+                    param.column,
+                );
+            }
+        }
+
+        if let Some(ret) = ret {
+            if !locals.contains(&ret.token) {
+                self.error.error(
+                    "Functions can only return variables assigned in their body.",
+                    ret.line,
+                    ret.column,
+                );
+            }
+
+            function.ret = Some(Variable {
+                name: ret.token,
+                typ: VarType::Dummy,
+                line: ret.line,
+                column: ret.column,
+            });
+        }
+
+        self.functions.borrow_mut().insert(function.name.clone(), function);
+        Ok(())
+    }
+
+    fn function(&self, name: &str) -> Option<Function> {
+        self.functions.borrow().get(name).cloned()
+    }
+
+    /// Expand a call to a user-defined function into statements. Nested
+    /// calls in the arguments are pushed to `prelude` so they are only
+    /// computed once, even if the parameter is used several times.
+    fn expand_call(
+        &self,
+        function: &Function,
+        token: &Token,
+        args: Vec<Arg>,
+        lhs: Option<&Variable>,
+        prelude: &mut Vec<Statement>,
+    ) -> Option<Vec<Statement>> {
+        if args.len() != function.params.len() {
+            self.error.error(
+                &format!(
+                    "Function `{}` takes {} arguments, got {}.",
+                    function.name,
+                    function.params.len(),
+                    args.len()
+                ),
+                token.line,
+                token.column,
+            );
+            return None
         }
 
-        (ns, constants, witnesses, statements)
+        if lhs.is_some() && function.ret.is_none() {
+            self.error.error(
+                &format!("Function `{}` does not return a value.", function.name),
+                token.line,
+                token.column,
+            );
+            return None
+        }
+
+        let args: Vec<Arg> = args
+            .into_iter()
+            .map(|arg| match arg {
+                Arg::Func(inner) => {
+                    let var = inner.lhs.clone().unwrap();
+                    prelude.push(inner);
+                    Arg::Var(var)
+                }
+                arg => arg,
+            })
+            .collect();
+
+        let prefix = format!("_fn_{}_{}_{}_", function.name, token.line, token.column);
+        Some(function.expand(&args, lhs, &prefix, token.line, token.column))
     }
 
     /// Routine checks on section structure
-    fn check_section_structure(&self, section: &str, tokens: Vec<Token>) {
+    fn check_section_structure(&self, section: &str, tokens: Vec<Token>) -> DiagnosticResult<()> {
+        if tokens.len() < 3 {
+            return Err(self.error.abort(&format!("Incomplete `{}` section.", section), 0, 0))
+        }
+
         if tokens[0].token_type != TokenType::String {
-            self.error.abort(
+            return Err(self.error.abort(
                 "Section declaration must start with a naming string.",
                 tokens[0].line,
                 tokens[0].column,
-            );
+            ))
         }
 
         if tokens[1].token_type != TokenType::LeftBrace {
-            self.error.abort(
+            return Err(self.error.abort(
                 "Section must be opened with a left brace '{'",
                 tokens[0].line,
                 tokens[0].column,
-            );
+            ))
         }
 
         if tokens.last().unwrap().token_type != TokenType::RightBrace {
-            self.error.abort(
+            return Err(self.error.abort(
                 "Section must be closed with a right brace '}'",
                 tokens[0].line,
                 tokens[0].column,
-            );
+            ))
         }
 
         match section {
@@ -326,28 +776,30 @@ impl Parser {
                 }
 
                 if tokens[2..tokens.len() - 1].len() % 3 != 0 {
-                    self.error.abort(
+                    return Err(self.error.abort(
                         &format!("Invalid number of elements in '{}' section. Must be pairs of '<Type> <name>' separated with a comma ','.", section),
                         tokens[0].line,
                         tokens[0].column
-                    );
+                    ))
                 }
             }
             "circuit" => {
                 if tokens.len() == 3 {
-                    self.error.abort("circuit section is empty.", 0, 0);
+                    return Err(self.error.abort("circuit section is empty.", 0, 0))
                 }
 
                 if tokens[tokens.len() - 2].token_type != TokenType::Semicolon {
-                    self.error.abort(
+                    return Err(self.error.abort(
                         "Circuit section does not end with a semicolon. Would never finish parsing.",
                         tokens[tokens.len()-2].line,
                         tokens[tokens.len()-2].column,
-                    );
+                    ))
                 }
             }
             _ => unreachable!(),
         };
+
+        Ok(())
     }
 
     fn parse_ast_constants(&self, ast: &IndexMap<String, (Token, Token)>) -> Vec<Constant> {
@@ -357,27 +809,30 @@ impl Parser {
         // v = (name, type)
         for (k, v) in ast {
             if &v.0.token != k {
-                self.error.abort(
+                self.error.error(
                     &format!("Constant name `{}` doesn't match token `{}`.", v.0.token, k),
                     v.0.line,
                     v.0.column,
                 );
+                continue
             }
 
             if v.0.token_type != TokenType::Symbol {
-                self.error.abort(
+                self.error.error(
                     &format!("Constant name `{}` is not a symbol.", v.0.token),
                     v.0.line,
                     v.0.column,
                 );
+                continue
             }
 
             if v.1.token_type != TokenType::Symbol {
-                self.error.abort(
+                self.error.error(
                     &format!("Constant type `{}` is not a symbol.", v.1.token),
                     v.1.line,
                     v.1.column,
                 );
+                continue
             }
 
             // Valid constant types, these are the constants/generators supported
@@ -385,7 +840,7 @@ impl Parser {
             match v.1.token.as_str() {
                 "EcFixedPoint" => {
                     if !VALID_ECFIXEDPOINT.contains(&v.0.token.as_str()) {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "`{}` is not a valid EcFixedPoint constant. Supported: {:?}",
                                 v.0.token.as_str(),
@@ -394,6 +849,7 @@ impl Parser {
                             v.0.line,
                             v.0.column,
                         );
+                        continue
                     }
 
                     ret.push(Constant {
@@ -406,7 +862,7 @@ impl Parser {
 
                 "EcFixedPointShort" => {
                     if !VALID_ECFIXEDPOINTSHORT.contains(&v.0.token.as_str()) {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "`{}` is not a valid EcFixedPointShort constant. Supported: {:?}",
                                 v.0.token.as_str(),
@@ -415,6 +871,7 @@ impl Parser {
                             v.0.line,
                             v.0.column,
                         );
+                        continue
                     }
 
                     ret.push(Constant {
@@ -427,7 +884,7 @@ impl Parser {
 
                 "EcFixedPointBase" => {
                     if !VALID_ECFIXEDPOINTBASE.contains(&v.0.token.as_str()) {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "`{}` is not a valid EcFixedPointBase constant. Supported: {:?}",
                                 v.0.token.as_str(),
@@ -436,6 +893,7 @@ impl Parser {
                             v.0.line,
                             v.0.column,
                         );
+                        continue
                     }
 
                     ret.push(Constant {
@@ -447,11 +905,12 @@ impl Parser {
                 }
 
                 x => {
-                    self.error.abort(
+                    self.error.error(
                         &format!("`{}` is an unsupported constant type.", x),
                         v.1.line,
                         v.1.column,
                     );
+                    continue
                 }
             }
         }
@@ -466,27 +925,30 @@ impl Parser {
         // v = (name, type)
         for (k, v) in ast {
             if &v.0.token != k {
-                self.error.abort(
+                self.error.error(
                     &format!("Witness name `{}` doesn't match token `{}`.", v.0.token, k),
                     v.0.line,
                     v.0.column,
                 );
+                continue
             }
 
             if v.0.token_type != TokenType::Symbol {
-                self.error.abort(
+                self.error.error(
                     &format!("Witness name `{}` is not a symbol.", v.0.token),
                     v.0.line,
                     v.0.column,
                 );
+                continue
             }
 
             if v.1.token_type != TokenType::Symbol {
-                self.error.abort(
+                self.error.error(
                     &format!("Witness type `{}` is not a symbol.", v.1.token),
                     v.1.line,
                     v.1.column,
                 );
+                continue
             }
 
             // Valid witness types
@@ -537,11 +999,12 @@ impl Parser {
                 }
 
                 x => {
-                    self.error.abort(
+                    self.error.error(
                         &format!("`{}` is an unsupported witness type.", x),
                         v.1.line,
                         v.1.column,
                     );
+                    continue
                 }
             }
         }
@@ -618,11 +1081,12 @@ impl Parser {
             }
 
             if left_paren != right_paren || (left_paren == 0 || right_paren == 0) {
-                self.error.abort(
+                self.error.error(
                     "Incorrect number of left and right parenthesis for statement.",
                     statement[0].line,
                     statement[0].column,
                 );
+                continue
             }
 
             // Peekable iterator so we can see tokens in advance
@@ -666,11 +1130,12 @@ impl Parser {
                         }
 
                         if !parsing {
-                            self.error.abort(
+                            self.error.error(
                                 &format!("Illegal token `{}`.", next_token.token),
                                 next_token.line,
                                 next_token.column,
                             );
+                            break
                         }
                     }
                 }
@@ -689,20 +1154,46 @@ impl Parser {
                 // call, so we check if it's legit and start digging.
                 let func_name = token.token.as_str();
 
+                // Calls to user-defined functions found in the arguments
+                // are expanded into statements preceding this one.
+                let mut prelude = vec![];
+
                 // TODO: MAKE SURE IT'S A SYMBOL
                 if let Some(op) = Opcode::from_name(func_name) {
-                    let rhs = self.parse_function_call(token, &mut iter);
+                    let rhs = match self.parse_function_call(token, &mut iter, &mut prelude) {
+                        Some(v) => v,
+                        None => break,
+                    };
                     stmt.opcode = op;
                     stmt.rhs = rhs;
+                    ret.extend(prelude);
+                    ret.push(stmt);
+                } else if let Some(function) = self.function(func_name) {
+                    let args = match self.parse_function_call(token, &mut iter, &mut prelude) {
+                        Some(v) => v,
+                        None => break,
+                    };
+                    let expanded = match self.expand_call(
+                        &function,
+                        token,
+                        args,
+                        stmt.lhs.as_ref(),
+                        &mut prelude,
+                    ) {
+                        Some(v) => v,
+                        None => break,
+                    };
+                    ret.extend(prelude);
+                    ret.extend(expanded);
                 } else {
-                    self.error.abort(
-                        &format!("Unimplemented opcode `{}`.", func_name),
+                    self.error.error(
+                        &format!("Unimplemented opcode or function `{}`.", func_name),
                         token.line,
                         token.column,
                     );
+                    break
                 }
 
-                ret.push(stmt);
                 stmt = Statement::default();
             }
         }
@@ -710,23 +1201,29 @@ impl Parser {
         ret
     }
 
+    /// Parse the arguments of a function call. Returns `None` if the call
+    /// is malformed, in which case the error is recorded in the emitter.
+    /// Calls to user-defined functions are expanded into `prelude`.
     fn parse_function_call(
         &self,
         token: &Token,
         iter: &mut Peekable<std::slice::Iter<'_, Token>>,
-    ) -> Vec<Arg> {
+        prelude: &mut Vec<Statement>,
+    ) -> Option<Vec<Arg>> {
         if let Some(next_token) = iter.peek() {
             if next_token.token_type != TokenType::LeftParen {
-                self.error.abort(
+                self.error.error(
                     "Invalid function call opening. Must start with a '('.",
                     next_token.line,
                     next_token.column,
                 );
+                return None
             }
             // Skip the opening parenthesis
             iter.next();
         } else {
-            self.error.abort("Premature ending of statement.", token.line, token.column);
+            self.error.error("Premature ending of statement.", token.line, token.column);
+            return None
         }
 
         let mut ret = vec![];
@@ -742,15 +1239,16 @@ impl Parser {
             if let Some(op_inner) = Opcode::from_name(&arg.token) {
                 if let Some(paren) = iter.peek() {
                     if paren.token_type != TokenType::LeftParen {
-                        self.error.abort(
+                        self.error.error(
                             "Invalid function call opening. Must start with a '('.",
                             paren.line,
                             paren.column,
                         );
+                        return None
                     }
 
                     // Recurse this function to get the params of the nested one.
-                    let args = self.parse_function_call(arg, iter);
+                    let args = self.parse_function_call(arg, iter, prelude)?;
 
                     // Then we assign a "fake" variable that serves as a stack
                     // reference.
@@ -773,11 +1271,35 @@ impl Parser {
                     continue
                 }
 
-                self.error.abort(
+                self.error.error(
                     "Missing tokens in statement, there's a syntax error here.",
                     arg.line,
                     arg.column,
                 );
+                return None
+            }
+
+            // ==========================================
+            // Parse a nested user-defined function call
+            // ==========================================
+            if let Some(function) = self.function(&arg.token) {
+                if iter.peek().map_or(false, |x| x.token_type == TokenType::LeftParen) {
+                    let args = self.parse_function_call(arg, iter, prelude)?;
+
+                    // The function is expanded before this statement, and its
+                    // return value is referenced through a "fake" variable.
+                    let var = Variable {
+                        name: format!("_op_inner_{}_{}", arg.line, arg.column),
+                        typ: VarType::Dummy,
+                        line: arg.line,
+                        column: arg.column,
+                    };
+
+                    let expanded = self.expand_call(&function, arg, args, Some(&var), prelude)?;
+                    prelude.extend(expanded);
+                    ret.push(Arg::Var(var));
+                    continue
+                }
             }
 
             // ==========================================
@@ -798,11 +1320,12 @@ impl Parser {
                         match arg.token.parse::<u64>() {
                             Ok(_) => {}
                             Err(e) => {
-                                self.error.abort(
+                                self.error.error(
                                     &format!("Failed to convert literal into u64: {}", e),
                                     arg.line,
                                     arg.column,
                                 );
+                                return None
                             }
                         };
 
@@ -823,7 +1346,14 @@ impl Parser {
                         break
                     }
 
-                    x => unimplemented!("{:#?}", x),
+                    _ => {
+                        self.error.error(
+                            &format!("Unexpected token `{}` in arguments.", arg.token),
+                            arg.line,
+                            arg.column,
+                        );
+                        return None
+                    }
                 };
 
                 if sep.token_type == TokenType::RightParen {
@@ -837,15 +1367,149 @@ impl Parser {
                 }
 
                 if sep.token_type != TokenType::Comma {
-                    self.error.abort(
+                    self.error.error(
                         "Argument separator is not a comma (`,`)",
                         sep.line,
                         sep.column,
                     );
+                    return None
                 }
             }
         }
 
-        ret
+        Some(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+    use crate::zkas::Analyzer;
+
+    fn parse(filename: &str, source: &str) -> DiagnosticResult<Vec<Statement>> {
+        let tokens = Lexer::new(filename, source.chars()).lex()?;
+        let parser = Parser::new(filename, source.chars(), tokens);
+        let (_, constants, witnesses, statements) = parser.parse()?;
+
+        let mut analyzer =
+            Analyzer::new(filename, source.chars(), constants, witnesses, statements);
+        analyzer.analyze_types()?;
+        Ok(analyzer.statements)
+    }
+
+    #[test]
+    fn test_functions() {
+        let dir = std::env::temp_dir().join("zkas_test_functions");
+        create_dir_all(dir.join("lib")).unwrap();
+        write(
+            dir.join("lib/commit.zk"),
+            r#"
+function commit(value, blind) {
+    vcv = ec_mul_short(value, VALUE_COMMIT_VALUE);
+    vcr = ec_mul(blind, VALUE_COMMIT_RANDOM);
+    return ec_add(vcv, vcr);
+}
+"#,
+        )
+        .unwrap();
+
+        let source = r#"
+import "lib/commit.zk";
+
+function constrain_point(point) {
+    constrain_instance(ec_get_x(point));
+    constrain_instance(ec_get_y(point));
+}
+
+function sum(a, b) {
+    c = base_add(a, b);
+    return c;
+}
+
+constant "Test" {
+    EcFixedPointShort VALUE_COMMIT_VALUE,
+    EcFixedPoint VALUE_COMMIT_RANDOM,
+}
+
+contract "Test" {
+    Base value,
+    Scalar blind_a,
+    Scalar blind_b,
+}
+
+circuit "Test" {
+    a = commit(value, blind_a);
+    b = commit(sum(value, value), blind_b);
+    constrain_point(a);
+    constrain_instance(sum(value, ec_get_x(b)));
+}
+"#;
+
+        let filename = dir.join("test.zk");
+        let statements = parse(filename.to_str().unwrap(), source).unwrap();
+        let opcodes: Vec<&str> = statements.iter().map(|x| x.opcode.name()).collect();
+        assert_eq!(
+            opcodes,
+            vec![
+                "ec_mul_short",
+                "ec_mul",
+                "ec_add",
+                "base_add",
+                "ec_mul_short",
+                "ec_mul",
+                "ec_add",
+                "ec_get_x",
+                "constrain_instance",
+                "ec_get_y",
+                "constrain_instance",
+                "ec_get_x",
+                "base_add",
+                "constrain_instance",
+            ]
+        );
+
+        // The return value is assigned to the caller's variable, and the
+        // locals of each call don't clash.
+        assert_eq!(statements[2].lhs.as_ref().unwrap().name, "a");
+        assert_eq!(statements[6].lhs.as_ref().unwrap().name, "b");
+        assert_ne!(
+            statements[0].lhs.as_ref().unwrap().name,
+            statements[4].lhs.as_ref().unwrap().name
+        );
+
+        // Wrong calls are reported at the call site
+        let source = r#"
+function sum(a, b) {
+    return base_add(a, b);
+}
+
+function noop(a) {
+    constrain_instance(a);
+}
+
+constant "Test" {}
+contract "Test" { Base value, }
+circuit "Test" {
+    a = sum(value);
+    b = noop(value);
+    c = unknown(value);
+    constrain_instance(value);
+}
+"#;
+        let diagnostics = parse("test.zk", source).unwrap_err();
+        let errors: Vec<(usize, usize)> = diagnostics
+            .iter()
+            .filter(|x| x.severity == super::super::error::Severity::Error)
+            .map(|x| (x.line, x.column))
+            .collect();
+        assert_eq!(errors, vec![(13, 9), (14, 9), (15, 9)]);
+
+        // Import cycles are detected
+        write(dir.join("lib/cycle.zk"), "import \"cycle.zk\";\n").unwrap();
+        let source = "import \"lib/cycle.zk\";\nconstant \"Test\" {}\n";
+        let diagnostics = parse(filename.to_str().unwrap(), source).unwrap_err();
+        assert!(diagnostics[0].message.starts_with("Import cycle"));
     }
 }
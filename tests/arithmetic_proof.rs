@@ -10,7 +10,7 @@ use darkfi::{
     zkas::decoder::ZkBinary,
     Result,
 };
-use halo2_proofs::circuit::Value;
+use halo2_proofs::{circuit::Value, dev::MockProver};
 use pasta_curves::pallas;
 use rand::rngs::OsRng;
 
@@ -24,6 +24,7 @@ fn arithmetic_proof() -> Result<()> {
     /* ANCHOR: main */
     let bincode = include_bytes!("../proof/arithmetic.zk.bin");
     let zkbin = ZkBinary::decode(bincode)?;
+    let k = zkbin.k;
 
     // ======
     // Prover
@@ -47,7 +48,11 @@ fn arithmetic_proof() -> Result<()> {
     // Create the circuit
     let circuit = ZkCircuit::new(prover_witnesses, zkbin.clone());
 
-    let proving_key = ProvingKey::build(13, &circuit);
+    // The circuit must fit in the k embedded in the binary
+    let prover = MockProver::run(k, &circuit, vec![public_inputs.clone()])?;
+    prover.assert_satisfied();
+
+    let proving_key = ProvingKey::build(k, &circuit);
     let proof = Proof::create(&proving_key, &[circuit], &public_inputs, &mut OsRng)?;
 
     // ========
@@ -60,7 +65,7 @@ fn arithmetic_proof() -> Result<()> {
     // Create the circuit
     let circuit = ZkCircuit::new(verifier_witnesses, zkbin);
 
-    let verifying_key = VerifyingKey::build(13, &circuit);
+    let verifying_key = VerifyingKey::build(k, &circuit);
     proof.verify(&verifying_key, &public_inputs)?;
     /* ANCHOR_END: main */
 
@@ -14,7 +14,7 @@ use darkfi::{
 };
 use darkfi_sdk::crypto::{MerkleNode, Nullifier};
 use halo2_gadgets::poseidon::primitives as poseidon;
-use halo2_proofs::circuit::Value;
+use halo2_proofs::{circuit::Value, dev::MockProver};
 use incrementalmerkletree::{bridgetree::BridgeTree, Tree};
 use pasta_curves::{
     arithmetic::CurveAffine,
@@ -28,6 +28,7 @@ fn burn_proof() -> Result<()> {
     /* ANCHOR: main */
     let bincode = include_bytes!("../proof/burn.zk.bin");
     let zkbin = ZkBinary::decode(bincode)?;
+    let k = zkbin.k;
 
     // ======
     // Prover
@@ -113,7 +114,11 @@ fn burn_proof() -> Result<()> {
     // Create the circuit
     let circuit = ZkCircuit::new(prover_witnesses, zkbin.clone());
 
-    let proving_key = ProvingKey::build(13, &circuit);
+    // The circuit must fit in the k embedded in the binary
+    let prover = MockProver::run(k, &circuit, vec![public_inputs.clone()])?;
+    prover.assert_satisfied();
+
+    let proving_key = ProvingKey::build(k, &circuit);
     let proof = Proof::create(&proving_key, &[circuit], &public_inputs, &mut OsRng)?;
 
     // ========
@@ -126,7 +131,7 @@ fn burn_proof() -> Result<()> {
     // Create the circuit
     let circuit = ZkCircuit::new(verifier_witnesses, zkbin);
 
-    let verifying_key = VerifyingKey::build(13, &circuit);
+    let verifying_key = VerifyingKey::build(k, &circuit);
     proof.verify(&verifying_key, &public_inputs)?;
     /* ANCHOR_END: main */
 
@@ -0,0 +1,394 @@
+use darkfi::{
+    crypto::{
+        keypair::{PublicKey, SecretKey},
+        util::{pedersen_commitment_u64, poseidon_hash},
+    },
+    zk::{
+        vm::{Witness, ZkCircuit},
+        CircuitStats,
+    },
+    zkas::decoder::ZkBinary,
+    Result,
+};
+use darkfi_sdk::crypto::MerkleNode;
+use halo2_proofs::{circuit::Value, dev::MockProver};
+use incrementalmerkletree::{bridgetree::BridgeTree, Tree};
+use pasta_curves::{
+    arithmetic::CurveAffine,
+    group::{ff::Field, Curve},
+    pallas,
+};
+use rand::rngs::OsRng;
+
+/// Check the circuit at the k embedded in the binary, which must be the
+/// smallest k the circuit fits in. `daod` ships the same circuits as the
+/// example, so both binaries are checked.
+fn mock(name: &str, witnesses: Vec<Witness>, public_inputs: Vec<pallas::Base>) -> Result<()> {
+    let bincodes = [
+        std::fs::read(format!("example/dao/proof/{}.zk.bin", name))?,
+        std::fs::read(format!("bin/dao/daod/proof/{}.zk.bin", name))?,
+    ];
+
+    for bincode in bincodes {
+        let zkbin = ZkBinary::decode(&bincode)?;
+        let k = zkbin.k;
+        assert_eq!(CircuitStats::measure(&zkbin)?.k, k);
+
+        let circuit = ZkCircuit::new(witnesses.clone(), zkbin);
+        let prover = MockProver::run(k, &circuit, vec![public_inputs.clone()])?;
+        prover.assert_satisfied();
+
+        assert!(MockProver::run(k - 1, &circuit, vec![public_inputs.clone()]).is_err());
+    }
+
+    Ok(())
+}
+
+/// DAO parameters, in the order they're hashed into the DAO bulla
+struct Dao {
+    proposer_limit: u64,
+    quorum: u64,
+    approval_ratio_quot: u64,
+    approval_ratio_base: u64,
+    gov_token_id: pallas::Base,
+    public: PublicKey,
+    bulla_blind: pallas::Base,
+}
+
+impl Dao {
+    fn random() -> Self {
+        Self {
+            proposer_limit: 110,
+            quorum: 110,
+            approval_ratio_quot: 1,
+            approval_ratio_base: 2,
+            gov_token_id: pallas::Base::random(&mut OsRng),
+            public: PublicKey::from_secret(SecretKey::random(&mut OsRng)),
+            bulla_blind: pallas::Base::random(&mut OsRng),
+        }
+    }
+
+    fn public_coords(&self) -> (pallas::Base, pallas::Base) {
+        let coords = self.public.0.to_affine().coordinates().unwrap();
+        (*coords.x(), *coords.y())
+    }
+
+    fn witnesses(&self) -> Vec<Witness> {
+        let (public_x, public_y) = self.public_coords();
+        vec![
+            Witness::Base(Value::known(pallas::Base::from(self.proposer_limit))),
+            Witness::Base(Value::known(pallas::Base::from(self.quorum))),
+            Witness::Base(Value::known(pallas::Base::from(self.approval_ratio_quot))),
+            Witness::Base(Value::known(pallas::Base::from(self.approval_ratio_base))),
+            Witness::Base(Value::known(self.gov_token_id)),
+            Witness::Base(Value::known(public_x)),
+            Witness::Base(Value::known(public_y)),
+            Witness::Base(Value::known(self.bulla_blind)),
+        ]
+    }
+
+    fn bulla(&self) -> pallas::Base {
+        let (public_x, public_y) = self.public_coords();
+        poseidon_hash::<8>([
+            pallas::Base::from(self.proposer_limit),
+            pallas::Base::from(self.quorum),
+            pallas::Base::from(self.approval_ratio_quot),
+            pallas::Base::from(self.approval_ratio_base),
+            self.gov_token_id,
+            public_x,
+            public_y,
+            self.bulla_blind,
+        ])
+    }
+}
+
+/// Proposal parameters, in the order they're hashed into the proposal bulla
+struct Proposal {
+    dest: PublicKey,
+    amount: u64,
+    serial: pallas::Base,
+    token_id: pallas::Base,
+    blind: pallas::Base,
+}
+
+impl Proposal {
+    fn random() -> Self {
+        Self {
+            dest: PublicKey::from_secret(SecretKey::random(&mut OsRng)),
+            amount: 1000,
+            serial: pallas::Base::random(&mut OsRng),
+            token_id: pallas::Base::random(&mut OsRng),
+            blind: pallas::Base::random(&mut OsRng),
+        }
+    }
+
+    fn dest_coords(&self) -> (pallas::Base, pallas::Base) {
+        let coords = self.dest.0.to_affine().coordinates().unwrap();
+        (*coords.x(), *coords.y())
+    }
+
+    fn witnesses(&self) -> Vec<Witness> {
+        let (dest_x, dest_y) = self.dest_coords();
+        vec![
+            Witness::Base(Value::known(dest_x)),
+            Witness::Base(Value::known(dest_y)),
+            Witness::Base(Value::known(pallas::Base::from(self.amount))),
+            Witness::Base(Value::known(self.serial)),
+            Witness::Base(Value::known(self.token_id)),
+            Witness::Base(Value::known(self.blind)),
+        ]
+    }
+
+    fn bulla(&self, dao: &Dao) -> pallas::Base {
+        let (dest_x, dest_y) = self.dest_coords();
+        poseidon_hash::<8>([
+            dest_x,
+            dest_y,
+            pallas::Base::from(self.amount),
+            self.serial,
+            self.token_id,
+            dao.bulla(),
+            self.blind,
+            self.blind,
+        ])
+    }
+}
+
+/// Coordinates of a Pedersen commitment to `value`
+fn value_commit(value: u64, blind: pallas::Scalar) -> [pallas::Base; 2] {
+    let coords = pedersen_commitment_u64(value, blind).to_affine().coordinates().unwrap();
+    [*coords.x(), *coords.y()]
+}
+
+/// Coordinates of the public key of `secret`
+fn public_coords(secret: pallas::Base) -> [pallas::Base; 2] {
+    let public = PublicKey::from_secret(SecretKey::from(secret));
+    let coords = public.0.to_affine().coordinates().unwrap();
+    [*coords.x(), *coords.y()]
+}
+
+/// Build a Merkle tree holding `leaf` among random leaves, and return
+/// its root along with the leaf's position and authentication path.
+fn merkle_tree(leaf: pallas::Base) -> (pallas::Base, Witness, Witness) {
+    let mut tree = BridgeTree::<MerkleNode, 32>::new(100);
+    tree.append(&MerkleNode::from(pallas::Base::random(&mut OsRng)));
+    tree.witness();
+    tree.append(&MerkleNode::from(leaf));
+    let leaf_pos = tree.witness().unwrap();
+    tree.append(&MerkleNode::from(pallas::Base::random(&mut OsRng)));
+
+    let root = tree.root(0).unwrap();
+    let merkle_path = tree.authentication_path(leaf_pos, &root).unwrap();
+    let leaf_pos: u64 = leaf_pos.into();
+
+    (
+        root.inner(),
+        Witness::Uint32(Value::known(leaf_pos.try_into().unwrap())),
+        Witness::MerklePath(Value::known(merkle_path.try_into().unwrap())),
+    )
+}
+
+#[test]
+fn dao_mint_proof() -> Result<()> {
+    let dao = Dao::random();
+    mock("dao-mint", dao.witnesses(), vec![dao.bulla()])
+}
+
+#[test]
+fn dao_foo_proof() -> Result<()> {
+    let a = pallas::Base::from(110);
+    let b = pallas::Base::from(4);
+
+    let witnesses = vec![Witness::Base(Value::known(a)), Witness::Base(Value::known(b))];
+    mock("foo", witnesses, vec![a + b])
+}
+
+/// Check the input burn proofs used when proposing and voting,
+/// which only differ by the nullifier.
+fn dao_input_proof(name: &str, nullifier: bool) -> Result<()> {
+    let secret = pallas::Base::random(&mut OsRng);
+    let serial = pallas::Base::random(&mut OsRng);
+    let spend_hook = pallas::Base::random(&mut OsRng);
+    let user_data = pallas::Base::random(&mut OsRng);
+    let value = 110;
+    let token = pallas::Base::random(&mut OsRng);
+    let coin_blind = pallas::Base::random(&mut OsRng);
+    let value_blind = pallas::Scalar::random(&mut OsRng);
+    let token_blind = pallas::Base::random(&mut OsRng);
+    let signature_secret = pallas::Base::random(&mut OsRng);
+
+    let [pub_x, pub_y] = public_coords(secret);
+    let coin = poseidon_hash::<8>([
+        pub_x,
+        pub_y,
+        pallas::Base::from(value),
+        token,
+        serial,
+        spend_hook,
+        user_data,
+        coin_blind,
+    ]);
+    let (root, leaf_pos, path) = merkle_tree(coin);
+
+    let witnesses = vec![
+        Witness::Base(Value::known(secret)),
+        Witness::Base(Value::known(serial)),
+        Witness::Base(Value::known(spend_hook)),
+        Witness::Base(Value::known(user_data)),
+        Witness::Base(Value::known(pallas::Base::from(value))),
+        Witness::Base(Value::known(token)),
+        Witness::Base(Value::known(coin_blind)),
+        Witness::Scalar(Value::known(value_blind)),
+        Witness::Base(Value::known(token_blind)),
+        leaf_pos,
+        path,
+        Witness::Base(Value::known(signature_secret)),
+    ];
+
+    let mut public_inputs = vec![];
+    if nullifier {
+        public_inputs.push(poseidon_hash::<2>([secret, serial]));
+    }
+    public_inputs.extend(value_commit(value, value_blind));
+    public_inputs.push(poseidon_hash::<2>([token, token_blind]));
+    public_inputs.push(root);
+    public_inputs.extend(public_coords(signature_secret));
+
+    mock(name, witnesses, public_inputs)
+}
+
+#[test]
+fn dao_propose_burn_proof() -> Result<()> {
+    dao_input_proof("dao-propose-burn", false)
+}
+
+#[test]
+fn dao_vote_burn_proof() -> Result<()> {
+    dao_input_proof("dao-vote-burn", true)
+}
+
+#[test]
+fn dao_propose_main_proof() -> Result<()> {
+    let dao = Dao::random();
+    let proposal = Proposal::random();
+    let total_funds = 110;
+    let total_funds_blind = pallas::Scalar::random(&mut OsRng);
+    let gov_token_blind = pallas::Base::random(&mut OsRng);
+
+    let (dao_root, dao_leaf_pos, dao_path) = merkle_tree(dao.bulla());
+
+    let mut witnesses = vec![
+        Witness::Base(Value::known(pallas::Base::from(total_funds))),
+        Witness::Scalar(Value::known(total_funds_blind)),
+        Witness::Base(Value::known(gov_token_blind)),
+    ];
+    witnesses.extend(proposal.witnesses());
+    witnesses.extend(dao.witnesses());
+    witnesses.push(dao_leaf_pos);
+    witnesses.push(dao_path);
+
+    let mut public_inputs = vec![
+        poseidon_hash::<2>([dao.gov_token_id, gov_token_blind]),
+        dao_root,
+        proposal.bulla(&dao),
+    ];
+    public_inputs.extend(value_commit(total_funds, total_funds_blind));
+
+    mock("dao-propose-main", witnesses, public_inputs)
+}
+
+#[test]
+fn dao_vote_main_proof() -> Result<()> {
+    let dao = Dao::random();
+    let proposal = Proposal::random();
+    let vote_option = 1;
+    let yes_vote_blind = pallas::Scalar::random(&mut OsRng);
+    let all_votes_value = 110;
+    let all_votes_blind = pallas::Scalar::random(&mut OsRng);
+    let gov_token_blind = pallas::Base::random(&mut OsRng);
+
+    let mut witnesses = proposal.witnesses();
+    witnesses.extend(dao.witnesses());
+    witnesses.extend([
+        Witness::Base(Value::known(pallas::Base::from(vote_option))),
+        Witness::Scalar(Value::known(yes_vote_blind)),
+        Witness::Base(Value::known(pallas::Base::from(all_votes_value))),
+        Witness::Scalar(Value::known(all_votes_blind)),
+        Witness::Base(Value::known(gov_token_blind)),
+    ]);
+
+    let mut public_inputs =
+        vec![poseidon_hash::<2>([dao.gov_token_id, gov_token_blind]), proposal.bulla(&dao)];
+    public_inputs.extend(value_commit(vote_option * all_votes_value, yes_vote_blind));
+    public_inputs.extend(value_commit(all_votes_value, all_votes_blind));
+
+    mock("dao-vote-main", witnesses, public_inputs)
+}
+
+#[test]
+fn dao_exec_proof() -> Result<()> {
+    let dao = Dao::random();
+    let proposal = Proposal::random();
+    let yes_votes_value = 110;
+    let all_votes_value = 200;
+    let yes_votes_blind = pallas::Scalar::random(&mut OsRng);
+    let all_votes_blind = pallas::Scalar::random(&mut OsRng);
+    let user_serial = pallas::Base::random(&mut OsRng);
+    let user_coin_blind = pallas::Base::random(&mut OsRng);
+    let dao_serial = pallas::Base::random(&mut OsRng);
+    let dao_coin_blind = pallas::Base::random(&mut OsRng);
+    let input_value = 5000;
+    let input_value_blind = pallas::Scalar::random(&mut OsRng);
+    let dao_spend_hook = pallas::Base::random(&mut OsRng);
+    let user_spend_hook = pallas::Base::random(&mut OsRng);
+    let user_data = pallas::Base::random(&mut OsRng);
+
+    let mut witnesses = proposal.witnesses();
+    witnesses.extend(dao.witnesses());
+    witnesses.extend([
+        Witness::Base(Value::known(pallas::Base::from(yes_votes_value))),
+        Witness::Base(Value::known(pallas::Base::from(all_votes_value))),
+        Witness::Scalar(Value::known(yes_votes_blind)),
+        Witness::Scalar(Value::known(all_votes_blind)),
+        Witness::Base(Value::known(user_serial)),
+        Witness::Base(Value::known(user_coin_blind)),
+        Witness::Base(Value::known(dao_serial)),
+        Witness::Base(Value::known(dao_coin_blind)),
+        Witness::Base(Value::known(pallas::Base::from(input_value))),
+        Witness::Scalar(Value::known(input_value_blind)),
+        Witness::Base(Value::known(dao_spend_hook)),
+        Witness::Base(Value::known(user_spend_hook)),
+        Witness::Base(Value::known(user_data)),
+    ]);
+
+    let (dest_x, dest_y) = proposal.dest_coords();
+    let (dao_public_x, dao_public_y) = dao.public_coords();
+    let coin_0 = poseidon_hash::<8>([
+        dest_x,
+        dest_y,
+        pallas::Base::from(proposal.amount),
+        proposal.token_id,
+        proposal.serial,
+        user_spend_hook,
+        user_data,
+        proposal.blind,
+    ]);
+    let coin_1 = poseidon_hash::<8>([
+        dao_public_x,
+        dao_public_y,
+        pallas::Base::from(input_value - proposal.amount),
+        proposal.token_id,
+        dao_serial,
+        dao_spend_hook,
+        dao.bulla(),
+        dao_coin_blind,
+    ]);
+
+    let mut public_inputs = vec![proposal.bulla(&dao), coin_0, coin_1];
+    public_inputs.extend(value_commit(yes_votes_value, yes_votes_blind));
+    public_inputs.extend(value_commit(all_votes_value, all_votes_blind));
+    public_inputs.extend(value_commit(input_value, input_value_blind));
+    public_inputs.extend([dao_spend_hook, user_spend_hook, user_data]);
+
+    mock("dao-exec", witnesses, public_inputs)
+}
@@ -0,0 +1,39 @@
+use darkfi::{
+    crypto::{
+        keypair::{PublicKey, SecretKey},
+        util::poseidon_hash,
+    },
+    zk::vm::{Witness, ZkCircuit},
+    zkas::decoder::ZkBinary,
+    Result,
+};
+use halo2_proofs::{circuit::Value, dev::MockProver};
+use pasta_curves::{arithmetic::CurveAffine, group::Curve, pallas};
+use rand::rngs::OsRng;
+
+#[test]
+fn deploy_contract_proof() -> Result<()> {
+    let bincode = include_bytes!("../proof/deploy_contract.zk.bin");
+    let zkbin = ZkBinary::decode(bincode)?;
+    let k = zkbin.k;
+
+    // Witness values
+    let bytes = pallas::Base::from(1024);
+    let deploy_key = SecretKey::random(&mut OsRng);
+
+    let prover_witnesses =
+        vec![Witness::Base(Value::known(bytes)), Witness::Base(Value::known(deploy_key.inner()))];
+
+    // Create the public inputs
+    let coords = PublicKey::from_secret(deploy_key).0.to_affine().coordinates().unwrap();
+    let address = poseidon_hash::<2>([*coords.x(), *coords.y()]);
+
+    let public_inputs = vec![*coords.x(), *coords.y(), address, bytes];
+
+    // The circuit must fit in the k embedded in the binary
+    let circuit = ZkCircuit::new(prover_witnesses, zkbin);
+    let prover = MockProver::run(k, &circuit, vec![public_inputs])?;
+    prover.assert_satisfied();
+
+    Ok(())
+}
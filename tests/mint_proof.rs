@@ -13,7 +13,7 @@ use darkfi::{
     Result,
 };
 use halo2_gadgets::poseidon::primitives as poseidon;
-use halo2_proofs::circuit::Value;
+use halo2_proofs::{circuit::Value, dev::MockProver};
 use pasta_curves::{
     arithmetic::CurveAffine,
     group::{ff::Field, Curve},
@@ -26,6 +26,7 @@ fn mint_proof() -> Result<()> {
     /* ANCHOR: main */
     let bincode = include_bytes!("../proof/mint.zk.bin");
     let zkbin = ZkBinary::decode(bincode)?;
+    let k = zkbin.k;
 
     // ======
     // Prover
@@ -69,7 +70,11 @@ fn mint_proof() -> Result<()> {
     // Create the circuit
     let circuit = ZkCircuit::new(prover_witnesses, zkbin.clone());
 
-    let proving_key = ProvingKey::build(13, &circuit);
+    // The circuit must fit in the k embedded in the binary
+    let prover = MockProver::run(k, &circuit, vec![public_inputs.clone()])?;
+    prover.assert_satisfied();
+
+    let proving_key = ProvingKey::build(k, &circuit);
     let proof = Proof::create(&proving_key, &[circuit], &public_inputs, &mut OsRng)?;
 
     // ========
@@ -82,7 +87,7 @@ fn mint_proof() -> Result<()> {
     // Create the circuit
     let circuit = ZkCircuit::new(verifier_witnesses, zkbin);
 
-    let verifying_key = VerifyingKey::build(13, &circuit);
+    let verifying_key = VerifyingKey::build(k, &circuit);
     proof.verify(&verifying_key, &public_inputs)?;
     /* ANCHOR_END: main */
 
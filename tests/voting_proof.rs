@@ -0,0 +1,73 @@
+use darkfi::{
+    crypto::{
+        keypair::{PublicKey, SecretKey},
+        util::{pedersen_commitment_u64, poseidon_hash},
+    },
+    zk::vm::{Witness, ZkCircuit},
+    zkas::decoder::ZkBinary,
+    Result,
+};
+use darkfi_sdk::crypto::MerkleNode;
+use halo2_proofs::{circuit::Value, dev::MockProver};
+use incrementalmerkletree::{bridgetree::BridgeTree, Tree};
+use pasta_curves::{
+    arithmetic::CurveAffine,
+    group::{ff::Field, Curve},
+    pallas,
+};
+use rand::rngs::OsRng;
+
+#[test]
+fn voting_proof() -> Result<()> {
+    let bincode = include_bytes!("../proof/voting.zk.bin");
+    let zkbin = ZkBinary::decode(bincode)?;
+    let k = zkbin.k;
+
+    // Witness values
+    let process_id_0 = pallas::Base::random(&mut OsRng);
+    let process_id_1 = pallas::Base::random(&mut OsRng);
+    let secret = SecretKey::random(&mut OsRng);
+    let vote = 1;
+    let vote_blind = pallas::Scalar::random(&mut OsRng);
+
+    // The voter's public key hash is a leaf of the Merkle tree
+    let coords = PublicKey::from_secret(secret).0.to_affine().coordinates().unwrap();
+    let pk_hash = poseidon_hash::<2>([*coords.x(), *coords.y()]);
+
+    let mut tree = BridgeTree::<MerkleNode, 32>::new(100);
+    tree.append(&MerkleNode::from(pallas::Base::random(&mut OsRng)));
+    tree.witness();
+    tree.append(&MerkleNode::from(pk_hash));
+    let leaf_pos = tree.witness().unwrap();
+    tree.append(&MerkleNode::from(pallas::Base::random(&mut OsRng)));
+
+    let root = tree.root(0).unwrap();
+    let merkle_path = tree.authentication_path(leaf_pos, &root).unwrap();
+    let leaf_pos: u64 = leaf_pos.into();
+
+    let prover_witnesses = vec![
+        Witness::Base(Value::known(process_id_0)),
+        Witness::Base(Value::known(process_id_1)),
+        Witness::Base(Value::known(secret.inner())),
+        Witness::Base(Value::known(pallas::Base::from(vote))),
+        Witness::Scalar(Value::known(vote_blind)),
+        Witness::Uint32(Value::known(leaf_pos.try_into().unwrap())),
+        Witness::MerklePath(Value::known(merkle_path.try_into().unwrap())),
+    ];
+
+    // Create the public inputs
+    let process_id = poseidon_hash::<2>([process_id_0, process_id_1]);
+    let nullifier = poseidon_hash::<2>([secret.inner(), process_id]);
+
+    let vote_commit = pedersen_commitment_u64(vote, vote_blind);
+    let vote_coords = vote_commit.to_affine().coordinates().unwrap();
+
+    let public_inputs = vec![nullifier, root.inner(), *vote_coords.x(), *vote_coords.y()];
+
+    // The circuit must fit in the k embedded in the binary
+    let circuit = ZkCircuit::new(prover_witnesses, zkbin);
+    let prover = MockProver::run(k, &circuit, vec![public_inputs])?;
+    prover.assert_satisfied();
+
+    Ok(())
+}
@@ -6,6 +6,7 @@ use halo2_gadgets::poseidon::{
 use halo2_proofs::{
     arithmetic::{CurveAffine, Field},
     circuit::Value,
+    dev::MockProver,
     pasta::{group::Curve, pallas},
 };
 use incrementalmerkletree::{bridgetree::BridgeTree, Tree};
@@ -34,6 +35,7 @@ fn zkvm_opcodes() -> Result<()> {
 
     let bincode = include_bytes!("../proof/opcodes.zk.bin");
     let zkbin = ZkBinary::decode(bincode)?;
+    let k = zkbin.k;
 
     // Values for the proof
     let value = 666_u64;
@@ -92,15 +94,21 @@ fn zkvm_opcodes() -> Result<()> {
         root.inner(),
         *public_coords.x(),
         *public_coords.y(),
+        b,
     ];
 
     let circuit = ZkCircuit::new(prover_witnesses, zkbin.clone());
-    let proving_key = ProvingKey::build(13, &circuit);
+
+    // The circuit must fit in the k embedded in the binary
+    let prover = MockProver::run(k, &circuit, vec![public_inputs.clone()])?;
+    prover.assert_satisfied();
+
+    let proving_key = ProvingKey::build(k, &circuit);
     let proof = Proof::create(&proving_key, &[circuit], &public_inputs, &mut OsRng)?;
 
     let verifier_witnesses = empty_witnesses(&zkbin);
     let circuit = ZkCircuit::new(verifier_witnesses, zkbin);
-    let verifying_key = VerifyingKey::build(13, &circuit);
+    let verifying_key = VerifyingKey::build(k, &circuit);
     proof.verify(&verifying_key, &public_inputs)?;
 
     Ok(())